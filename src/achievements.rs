@@ -0,0 +1,227 @@
+use irc::client::prelude::Client;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use crate::database::Database;
+
+/// Something a player did that achievements count. Commands and games
+/// publish events on the [`EventBus`]; the main loop passes each one to
+/// [`evaluate`] and announces what it unlocked.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// Any command, counted towards daily streaks.
+    Command,
+    /// Guessed the last letter or solved the word, with the game's wrong guesses so far.
+    HangmanSolved { wrong: u32 },
+    TriviaAnswered,
+    ScrambleSolved,
+    /// Won a head-to-head game of this kind.
+    BoardGameWon(&'static str),
+    /// Stored a key with `!set`, leaving this many.
+    KeysStored(usize),
+    /// A wallet changed, leaving this balance.
+    ChipBalance(i64),
+}
+
+/// A change an event makes to one of a player's stats.
+#[derive(Clone, Debug, PartialEq)]
+enum Update {
+    Add(&'static str, i64),
+    /// Raise the stat to this value if it is lower. Used for levels, such as
+    /// keys stored, that can fall again without taking back an achievement.
+    Max(&'static str, i64),
+    /// Days in a row with at least one command.
+    Streak(&'static str),
+}
+
+impl Event {
+    fn updates(&self) -> Vec<Update> {
+        match self {
+            Event::Command => vec![Update::Streak(STREAK)],
+            Event::HangmanSolved { wrong } => {
+                let mut updates = vec![Update::Add("hangman.solves", 1)];
+                if *wrong == 0 {
+                    updates.push(Update::Add("hangman.flawless", 1));
+                }
+                updates
+            }
+            Event::TriviaAnswered => vec![Update::Add("trivia.answers", 1)],
+            Event::ScrambleSolved => vec![Update::Add("scramble.solves", 1)],
+            Event::BoardGameWon(kind) => {
+                let mut updates = vec![Update::Add("board.wins", 1)];
+                if *kind == "chess" {
+                    updates.push(Update::Add("chess.wins", 1));
+                }
+                updates
+            }
+            Event::KeysStored(keys) => vec![Update::Max("data.keys", *keys as i64)],
+            Event::ChipBalance(balance) => vec![Update::Max("chips.peak", *balance)],
+        }
+    }
+}
+
+const STREAK: &str = "streak";
+
+/// An achievement is unlocked once a player's `stat` reaches `goal`.
+pub struct Achievement {
+    pub id: &'static str,
+    pub badge: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub stat: &'static str,
+    pub goal: i64,
+}
+
+/// Every achievement. Add new ones here; `id` is what is stored, so don't change it.
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement { id: "first_solve", badge: "🔤", name: "Wordsmith", description: "Solve a hangman word", stat: "hangman.solves", goal: 1 },
+    Achievement { id: "flawless", badge: "💎", name: "Flawless", description: "Solve a hangman word with no wrong guesses", stat: "hangman.flawless", goal: 1 },
+    Achievement { id: "hangman_25", badge: "🪢", name: "Executioner", description: "Solve 25 hangman words", stat: "hangman.solves", goal: 25 },
+    Achievement { id: "trivia_50", badge: "🧠", name: "Know-it-all", description: "Answer 50 trivia questions", stat: "trivia.answers", goal: 50 },
+    Achievement { id: "scramble_10", badge: "🔀", name: "Unscrambler", description: "Unscramble 10 words", stat: "scramble.solves", goal: 10 },
+    Achievement { id: "board_win", badge: "🎲", name: "Challenger", description: "Win a head-to-head board game", stat: "board.wins", goal: 1 },
+    Achievement { id: "checkmate", badge: "♟️", name: "Checkmate", description: "Win a game of chess", stat: "chess.wins", goal: 1 },
+    Achievement { id: "keys_100", badge: "🗄️", name: "Hoarder", description: "Store 100 keys with !set", stat: "data.keys", goal: 100 },
+    Achievement { id: "streak_7", badge: "📅", name: "Regular", description: "Use the bot 7 days in a row", stat: STREAK, goal: 7 },
+    Achievement { id: "chips_5000", badge: "💰", name: "High roller", description: "Hold 5000 chips", stat: "chips.peak", goal: 5000 },
+];
+
+pub fn find(id: &str) -> Option<&'static Achievement> {
+    ACHIEVEMENTS.iter().find(|a| a.id == id)
+}
+
+/// Applies `event` to `username`'s stats and returns the achievements it
+/// unlocked. `now` is a unix time, used for streaks.
+pub fn evaluate(db: &Database, username: &str, event: &Event, now: i64) -> Result<Vec<&'static Achievement>, rusqlite::Error> {
+    let mut unlocked = Vec::new();
+    for update in event.updates() {
+        let (stat, value) = match update {
+            Update::Add(stat, n) => (stat, db.add_stat(username, stat, n)?),
+            Update::Max(stat, n) => (stat, db.raise_stat(username, stat, n)?),
+            Update::Streak(stat) => (stat, db.record_streak(username, stat, now)?),
+        };
+        for achievement in ACHIEVEMENTS.iter().filter(|a| a.stat == stat && value >= a.goal) {
+            if db.unlock_achievement(username, achievement.id)? {
+                unlocked.push(achievement);
+            }
+        }
+    }
+    Ok(unlocked)
+}
+
+/// Evaluates a published event and announces anything it unlocked in the
+/// channel it happened in.
+pub fn announce(client: &Client, db: &Database, (channel, username, event): Published) -> irc::error::Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    match evaluate(db, &username, &event, now) {
+        Ok(unlocked) => {
+            for achievement in unlocked {
+                client.send_privmsg(&channel, format!(
+                    "🏅 {} unlocked {} {}: {}",
+                    username, achievement.badge, achievement.name, achievement.description
+                ))?;
+            }
+        }
+        Err(e) => eprintln!("Failed to evaluate achievements for {}: {}", username, e),
+    }
+    Ok(())
+}
+
+/// An event, the channel it happened in and the player it counts for.
+pub type Published = (String, String, Event);
+
+/// Carries events from commands and games to the main loop. A default bus
+/// is not connected and drops what it is given, which suits tests.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    sender: Option<UnboundedSender<Published>>,
+}
+
+impl EventBus {
+    /// A bus and the receiving end for the main loop.
+    pub fn new() -> (Self, UnboundedReceiver<Published>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (EventBus { sender: Some(sender) }, receiver)
+    }
+
+    pub fn publish(&self, channel: &str, username: &str, event: Event) {
+        if let Some(sender) = &self.sender {
+            // Only fails once the main loop has exited
+            let _ = sender.send((channel.to_string(), username.to_string(), event));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    fn ids(unlocked: Vec<&Achievement>) -> Vec<&str> {
+        unlocked.iter().map(|a| a.id).collect()
+    }
+
+    #[test]
+    fn test_ids_are_unique_and_known() {
+        for (i, achievement) in ACHIEVEMENTS.iter().enumerate() {
+            assert!(ACHIEVEMENTS[..i].iter().all(|a| a.id != achievement.id), "duplicate {}", achievement.id);
+        }
+        assert_eq!(find("flawless").unwrap().name, "Flawless");
+        assert!(find("nope").is_none());
+    }
+
+    #[test]
+    fn test_unlocks_once() {
+        let db = create_test_db();
+        let solved = Event::HangmanSolved { wrong: 0 };
+        assert_eq!(ids(evaluate(&db, "alice", &solved, 0).unwrap()), vec!["first_solve", "flawless"]);
+        assert!(evaluate(&db, "alice", &solved, 0).unwrap().is_empty());
+        assert!(evaluate(&db, "bob", &Event::HangmanSolved { wrong: 2 }, 0).unwrap().iter().all(|a| a.id != "flawless"));
+    }
+
+    #[test]
+    fn test_counts_build_up() {
+        let db = create_test_db();
+        for _ in 0..49 {
+            assert!(evaluate(&db, "alice", &Event::TriviaAnswered, 0).unwrap().is_empty());
+        }
+        assert_eq!(ids(evaluate(&db, "alice", &Event::TriviaAnswered, 0).unwrap()), vec!["trivia_50"]);
+    }
+
+    #[test]
+    fn test_levels_keep_their_peak() {
+        let db = create_test_db();
+        assert!(evaluate(&db, "alice", &Event::KeysStored(99), 0).unwrap().is_empty());
+        assert!(evaluate(&db, "alice", &Event::KeysStored(3), 0).unwrap().is_empty());
+        assert_eq!(ids(evaluate(&db, "alice", &Event::KeysStored(100), 0).unwrap()), vec!["keys_100"]);
+        assert_eq!(ids(evaluate(&db, "bob", &Event::BoardGameWon("chess"), 0).unwrap()), vec!["board_win", "checkmate"]);
+    }
+
+    #[test]
+    fn test_streak() {
+        let db = create_test_db();
+        for day in 0..6 {
+            assert!(evaluate(&db, "alice", &Event::Command, day * DAY + 100).unwrap().is_empty());
+            // More commands the same day don't count twice
+            assert!(evaluate(&db, "alice", &Event::Command, day * DAY + 200).unwrap().is_empty());
+        }
+        // A missed day starts the streak again
+        let mut day = 7;
+        for _ in 0..6 {
+            assert!(evaluate(&db, "alice", &Event::Command, day * DAY).unwrap().is_empty());
+            day += 1;
+        }
+        assert_eq!(ids(evaluate(&db, "alice", &Event::Command, day * DAY).unwrap()), vec!["streak_7"]);
+    }
+
+    #[test]
+    fn test_disconnected_bus_drops_events() {
+        EventBus::default().publish("#test", "alice", Event::Command);
+        let (bus, mut receiver) = EventBus::new();
+        bus.publish("#test", "alice", Event::TriviaAnswered);
+        assert_eq!(receiver.try_recv().unwrap(), ("#test".to_string(), "alice".to_string(), Event::TriviaAnswered));
+    }
+}
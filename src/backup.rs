@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use crate::database::Database;
+use crate::settings::BackupSettings;
+
+const PREFIX: &str = "bot_data-";
+const SUFFIX: &str = ".db";
+const STAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// A snapshot file in the backup directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub name: String,
+    pub taken_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+/// File name for a snapshot taken at `time`, e.g. `bot_data-20240105-201500.db`.
+pub fn snapshot_name(time: DateTime<Utc>) -> String {
+    format!("{}{}{}", PREFIX, time.format(STAMP_FORMAT), SUFFIX)
+}
+
+/// Recovers the snapshot time from a file name made by [`snapshot_name`].
+pub fn parse_snapshot_name(name: &str) -> Option<DateTime<Utc>> {
+    let stamp = name.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?;
+    NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT).ok().map(|t| t.and_utc())
+}
+
+/// Lists snapshots in the backup directory, newest first.
+pub fn list_snapshots(settings: &BackupSettings) -> std::io::Result<Vec<Snapshot>> {
+    let entries = match fs::read_dir(&settings.dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(taken_at) = parse_snapshot_name(&name) {
+            snapshots.push(Snapshot { name, taken_at, size: entry.metadata()?.len() });
+        }
+    }
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.taken_at));
+    Ok(snapshots)
+}
+
+pub fn snapshot_path(settings: &BackupSettings, name: &str) -> PathBuf {
+    Path::new(&settings.dir).join(name)
+}
+
+/// Takes a snapshot now, then prunes old ones according to the retention settings.
+/// Returns the new snapshot and the names of any pruned snapshots.
+pub fn take_snapshot(db: &Database, settings: &BackupSettings) -> Result<(Snapshot, Vec<String>), String> {
+    fs::create_dir_all(&settings.dir).map_err(|e| format!("cannot create {}: {}", settings.dir, e))?;
+
+    let taken_at = Utc::now();
+    let name = snapshot_name(taken_at);
+    let path = snapshot_path(settings, &name);
+    db.backup_to(&path).map_err(|e| format!("backup failed: {}", e))?;
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+
+    let snapshots = list_snapshots(settings).map_err(|e| format!("cannot list backups: {}", e))?;
+    let expired = expired_snapshots(&snapshots, settings.keep_daily, settings.keep_weekly);
+    for old in &expired {
+        fs::remove_file(snapshot_path(settings, old)).map_err(|e| format!("cannot remove {}: {}", old, e))?;
+    }
+
+    Ok((Snapshot { name, taken_at, size }, expired))
+}
+
+/// Picks the snapshots to delete so that only the newest snapshot of each of
+/// the last `keep_daily` days and of each of the last `keep_weekly` ISO weeks
+/// remain. `snapshots` must be sorted newest first.
+pub fn expired_snapshots(snapshots: &[Snapshot], keep_daily: usize, keep_weekly: usize) -> Vec<String> {
+    let mut keep = HashSet::new();
+
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    for snapshot in snapshots {
+        let day = snapshot.taken_at.date_naive();
+        if days.len() < keep_daily && days.insert(day) {
+            keep.insert(snapshot.name.clone());
+        }
+        let week = day.iso_week();
+        if weeks.len() < keep_weekly && weeks.insert((week.year(), week.week())) {
+            keep.insert(snapshot.name.clone());
+        }
+    }
+
+    snapshots
+        .iter()
+        .filter(|s| !keep.contains(&s.name))
+        .map(|s| s.name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn snapshot(y: i32, m: u32, d: u32, h: u32) -> Snapshot {
+        let taken_at = Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap();
+        Snapshot { name: snapshot_name(taken_at), taken_at, size: 0 }
+    }
+
+    #[test]
+    fn test_snapshot_name_round_trip() {
+        let time = Utc.with_ymd_and_hms(2024, 1, 5, 20, 15, 0).unwrap();
+        let name = snapshot_name(time);
+        assert_eq!(name, "bot_data-20240105-201500.db");
+        assert_eq!(parse_snapshot_name(&name), Some(time));
+    }
+
+    #[test]
+    fn test_parse_snapshot_name_rejects_other_files() {
+        assert_eq!(parse_snapshot_name("bot_data.db"), None);
+        assert_eq!(parse_snapshot_name("bot_data-notadate.db"), None);
+        assert_eq!(parse_snapshot_name("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_expired_keeps_newest_per_day() {
+        let snapshots = vec![
+            snapshot(2024, 1, 10, 18),
+            snapshot(2024, 1, 10, 6),
+            snapshot(2024, 1, 9, 18),
+            snapshot(2024, 1, 8, 18),
+        ];
+
+        let expired = expired_snapshots(&snapshots, 2, 0);
+        assert_eq!(expired, vec![snapshots[1].name.clone(), snapshots[3].name.clone()]);
+    }
+
+    #[test]
+    fn test_expired_keeps_weekly_beyond_daily() {
+        // Wednesdays three weeks running, plus a Tuesday in the middle week
+        let snapshots = vec![
+            snapshot(2024, 1, 17, 12),
+            snapshot(2024, 1, 10, 12),
+            snapshot(2024, 1, 9, 12),
+            snapshot(2024, 1, 3, 12),
+        ];
+
+        let expired = expired_snapshots(&snapshots, 1, 2);
+        assert_eq!(expired, vec![snapshots[2].name.clone(), snapshots[3].name.clone()]);
+    }
+
+    #[test]
+    fn test_expired_keep_nothing() {
+        let snapshots = vec![snapshot(2024, 1, 10, 18)];
+        assert_eq!(expired_snapshots(&snapshots, 0, 0), vec![snapshots[0].name.clone()]);
+        assert!(expired_snapshots(&[], 3, 3).is_empty());
+    }
+}
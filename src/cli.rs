@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+use chrono::Utc;
+use crate::backup;
+use crate::crypto::{Cipher, KEY_ENV_VAR};
+use crate::database::{Database, ImportMode, table_rows, validate_snapshot};
+use crate::settings::Settings;
+
+const USAGE: &str = "Usage: rustirc [export <file> | import <file> [--replace] [--dry-run] | restore <snapshot> \
+                     | keygen | rekey <new-key|--plaintext>]";
+
+/// Runs an offline maintenance command, e.g. `rustirc export backup.json`,
+/// instead of connecting to IRC.
+pub fn run(db: &mut Database, settings: &Settings, args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("export") => {
+            let path = args.get(1).ok_or(USAGE)?;
+            let doc = db.export_json().map_err(|e| format!("Export failed: {}", e))?;
+            let text = serde_json::to_string_pretty(&doc).map_err(|e| format!("Export failed: {}", e))?;
+            fs::write(path, text).map_err(|e| format!("Could not write {}: {}", path, e))?;
+
+            for (table, rows) in table_rows(&doc) {
+                println!("{}: {} rows", table, rows);
+            }
+            println!("Exported to {}", path);
+            Ok(())
+        }
+        Some("import") => {
+            let path = args.get(1).ok_or(USAGE)?;
+            let flags = &args[2..];
+            if let Some(unknown) = flags.iter().find(|f| *f != "--replace" && *f != "--dry-run") {
+                return Err(format!("Unknown option {}\n{}", unknown, USAGE));
+            }
+            let mode = if flags.iter().any(|f| f == "--replace") { ImportMode::Replace } else { ImportMode::Merge };
+            let dry_run = flags.iter().any(|f| f == "--dry-run");
+
+            let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+            let doc = serde_json::from_str(&text).map_err(|e| format!("Could not parse {}: {}", path, e))?;
+            let report = db.import_json(&doc, mode, dry_run).map_err(|e| format!("Import failed: {}", e))?;
+
+            for conflict in &report.conflicts {
+                println!("conflict: {}", conflict);
+            }
+            let result = format!("{}{}", if dry_run { "Dry run: " } else { "" }, report);
+            println!("{}", result);
+            // Logged like `!import`, so offline imports leave a trace too
+            if let Err(e) = db.record_audit("cli", None, "import", &args[1..].join(" "), "-", &result) {
+                eprintln!("Failed to write audit log: {}", e);
+            }
+            Ok(())
+        }
+        Some("restore") => {
+            let path = Path::new(args.get(1).ok_or(USAGE)?);
+            validate_snapshot(path).map_err(|e| format!("Refusing to restore {}: {}", path.display(), e))?;
+
+            // Keep a copy of the current state in case the restore was a mistake
+            fs::create_dir_all(&settings.backup.dir).map_err(|e| format!("Could not create {}: {}", settings.backup.dir, e))?;
+            let safety = backup::snapshot_path(&settings.backup, &backup::snapshot_name(Utc::now()));
+            db.backup_to(&safety).map_err(|e| format!("Could not save current database: {}", e))?;
+            println!("Current database saved to {}", safety.display());
+
+            db.restore_from(path).map_err(|e| format!("Restore failed: {}", e))?;
+            println!("Restored {}", path.display());
+            Ok(())
+        }
+        Some("keygen") => {
+            println!("{}", Cipher::generate_key());
+            Ok(())
+        }
+        Some("rekey") => {
+            // The current key comes from Settings or the environment, like when the bot runs
+            let old = settings.data_key()
+                .map(|key| Cipher::from_hex(&key).map_err(|e| format!("Current key: {}", e)))
+                .transpose()?;
+            let new = match args.get(1).map(String::as_str) {
+                Some("--plaintext") => None,
+                Some(key) => Some(Cipher::from_hex(key).map_err(|e| format!("New key: {}", e))?),
+                None => return Err(USAGE.to_string()),
+            };
+
+            let (encrypted, plaintext) = db.encryption_status().map_err(|e| e.to_string())?;
+            println!("Before: {} encrypted, {} plaintext values", encrypted, plaintext);
+            let rewritten = db.reencrypt_user_data(old.as_ref(), new.as_ref())
+                .map_err(|e| format!("Re-encryption failed, nothing was changed: {}", e))?;
+            println!("Rewrote {} values", rewritten);
+            match new {
+                Some(_) => println!("Now set {} (or Settings::encryption_key) to the new key", KEY_ENV_VAR),
+                None => println!("Values are now plaintext; remove the configured key"),
+            }
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can, parse_audit_filter};
+use crate::roles::AUDIT_VIEW;
+
+const MAX_SHOWN: usize = 10;
+
+pub fn cmd_audit(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    if let Some(username) = &msg.author {
+        if !can(db, username, AUDIT_VIEW) {
+            client.send_privmsg(&msg.channel, "Permission denied. Only moderators and above can read the audit log")?;
+            return audit(client, db, settings, msg, "denied");
+        }
+    } else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+        return Ok(());
+    }
+
+    let Some(filter) = parse_audit_filter(&msg.args) else {
+        client.send_privmsg(&msg.channel, "Usage: !audit [user:<nick>] [cmd:<command>] [since, e.g. 2h or 7d]")?;
+        return audit(client, db, settings, msg, "usage");
+    };
+    // Periods too long to subtract cover the whole log
+    let since = filter.since
+        .and_then(|duration| i64::try_from(duration.as_secs()).ok())
+        .and_then(|seconds| Utc::now().timestamp().checked_sub(seconds));
+
+    match db.audit_entries(filter.actor, filter.command, since, MAX_SHOWN) {
+        Ok(entries) if entries.is_empty() => {
+            client.send_privmsg(&msg.channel, "No matching audit entries")?;
+        }
+        Ok(entries) => {
+            for entry in &entries {
+                let when = DateTime::<Utc>::from_timestamp(entry.created_at, 0)
+                    .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| entry.created_at.to_string());
+                let who = entry.hostmask.as_deref().unwrap_or(&entry.actor);
+                client.send_privmsg(&msg.channel, format!(
+                    "{} {} !{} {} in {}: {}",
+                    when, who, entry.command, entry.arguments, entry.channel, entry.result
+                ))?;
+            }
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error reading audit log: {}", e))?;
+            return audit(client, db, settings, msg, &format!("error: {}", e));
+        }
+    }
+    audit(client, db, settings, msg, "ok")
+}
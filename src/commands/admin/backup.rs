@@ -0,0 +1,78 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::{Database, validate_snapshot};
+use crate::settings::Settings;
+use crate::backup;
+use crate::commands::utils::{audit, can, safe_file_name};
+use crate::roles::BACKUP_MANAGE;
+
+const MAX_LISTED: usize = 10;
+
+pub fn cmd_backup(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    if let Some(username) = &msg.author {
+        if !can(db, username, BACKUP_MANAGE) {
+            client.send_privmsg(&msg.channel, "Permission denied. Only admins can manage backups")?;
+            return audit(client, db, settings, msg, "denied");
+        }
+    } else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+        return Ok(());
+    }
+
+    let result = match msg.args.first().map(String::as_str) {
+        Some("now") => match backup::take_snapshot(db, &settings.backup) {
+            Ok((snapshot, pruned)) => {
+                client.send_privmsg(&msg.channel, format!(
+                    "Backup written to {} ({} bytes, {} old snapshots pruned)",
+                    snapshot.name, snapshot.size, pruned.len()
+                ))?;
+                format!("wrote {}", snapshot.name)
+            }
+            Err(e) => {
+                client.send_privmsg(&msg.channel, format!("Error taking backup: {}", e))?;
+                format!("error: {}", e)
+            }
+        },
+        Some("list") => match backup::list_snapshots(&settings.backup) {
+            Ok(snapshots) if snapshots.is_empty() => {
+                client.send_privmsg(&msg.channel, "No backups yet")?;
+                "ok".to_string()
+            }
+            Ok(snapshots) => {
+                client.send_privmsg(&msg.channel, format!("{} backups in {}:", snapshots.len(), settings.backup.dir))?;
+                for snapshot in snapshots.iter().take(MAX_LISTED) {
+                    client.send_privmsg(&msg.channel, format!(
+                        "  {} - {} ({} bytes)",
+                        snapshot.name, snapshot.taken_at.format("%Y-%m-%d %H:%M UTC"), snapshot.size
+                    ))?;
+                }
+                "ok".to_string()
+            }
+            Err(e) => {
+                client.send_privmsg(&msg.channel, format!("Error listing backups: {}", e))?;
+                format!("error: {}", e)
+            }
+        },
+        Some("verify") => {
+            let Some(name) = msg.args.get(1).and_then(|n| safe_file_name(n)) else {
+                client.send_privmsg(&msg.channel, "Usage: !backup verify <snapshot>")?;
+                return audit(client, db, settings, msg, "usage");
+            };
+            match validate_snapshot(&backup::snapshot_path(&settings.backup, name)) {
+                Ok(()) => {
+                    client.send_privmsg(&msg.channel, format!("{} is valid and can be restored", name))?;
+                    "valid".to_string()
+                }
+                Err(e) => {
+                    client.send_privmsg(&msg.channel, format!("{} is not usable: {}", name, e))?;
+                    format!("invalid: {}", e)
+                }
+            }
+        }
+        _ => {
+            client.send_privmsg(&msg.channel, "Usage: !backup <now|list|verify <snapshot>>")?;
+            "usage".to_string()
+        }
+    };
+    audit(client, db, settings, msg, &result)
+}
@@ -0,0 +1,53 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can, forget_summary, guard_grant_change, stored_row_count};
+use crate::roles::DATA_FORGET;
+
+pub fn cmd_forget(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    if let Some(username) = &msg.author {
+        if !can(db, username, DATA_FORGET) {
+            client.send_privmsg(&msg.channel, "Permission denied. Only admins can delete other users' data")?;
+            return audit(client, db, settings, msg, "denied");
+        }
+    } else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+        return Ok(());
+    }
+
+    let Some(target) = msg.args.first() else {
+        client.send_privmsg(&msg.channel, "Usage: !forget <user> [confirm]")?;
+        return audit(client, db, settings, msg, "usage");
+    };
+
+    // Forgetting a user also removes their grant
+    if !guard_grant_change(client, db, settings, msg, target, None)? {
+        return Ok(());
+    }
+
+    if msg.args.get(1).map(String::as_str) != Some("confirm") {
+        match stored_row_count(db, target) {
+            Ok(0) => client.send_privmsg(&msg.channel, format!("Nothing is stored about {}", target))?,
+            Ok(rows) => client.send_privmsg(&msg.channel, format!(
+                "This permanently deletes all {} records stored about {}. Type !forget {} confirm to proceed",
+                rows, target, target
+            ))?,
+            Err(e) => client.send_privmsg(&msg.channel, format!("Error checking stored data: {}", e))?,
+        }
+        return audit(client, db, settings, msg, "confirmation requested");
+    }
+
+    match db.forget_user(target) {
+        Ok(removed) => {
+            let summary = forget_summary(&removed);
+            client.send_privmsg(&msg.channel, format!("Deleted all data for {} ({})", target, summary))?;
+            audit(client, db, settings, msg, &format!("deleted {}", summary))?;
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error deleting data: {}", e))?;
+            audit(client, db, settings, msg, &format!("error: {}", e))?;
+        }
+    }
+    Ok(())
+}
@@ -1,40 +1,91 @@
+use std::time::Duration;
+use chrono::Utc;
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
 use crate::database::Database;
-use crate::commands::utils::is_admin;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can, guard_grant_change, format_remaining, parse_duration};
+use crate::roles::{find_role, role_for_level, PERMS_GRANT};
 
-pub fn cmd_grant(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
-    if let Some(username) = &msg.author {
-        if !is_admin(db, username) {
-            client.send_privmsg(&msg.channel, "Permission denied. Only admins can grant permissions")?;
-            return Ok(());
-        }
-    } else {
+/// The longest a `--for` grant can last. Longer grants should be permanent.
+const MAX_GRANT_DURATION: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+pub fn cmd_grant(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
         client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
         return Ok(());
+    };
+    if !can(db, username, PERMS_GRANT) {
+        client.send_privmsg(&msg.channel, "Permission denied. Only admins can grant permissions")?;
+        return audit(client, db, settings, msg, "denied");
     }
 
-    if msg.args.len() < 2 {
-        client.send_privmsg(&msg.channel, "Usage: !grant <user> <level>")?;
-    } else {
-        let target_user = &msg.args[0];
-        let level_str = &msg.args[1];
-        
-        match level_str.parse::<i32>() {
-            Ok(level) => {
-                match db.grant_permission(target_user, level) {
-                    Ok(_) => {
-                        client.send_privmsg(&msg.channel, &format!("Granted permission level {} to {}", level, target_user))?;
-                    }
-                    Err(e) => {
-                        client.send_privmsg(&msg.channel, &format!("Error granting permission: {}", e))?;
-                    }
-                }
-            }
-            Err(_) => {
-                client.send_privmsg(&msg.channel, "Permission level must be a number")?;
-            }
+    let usage = "Usage: !grant <user> <role|level> [--for <duration>, e.g. 8h]";
+    let (target_user, role_name, duration) = match msg.args.as_slice() {
+        [user, role] => (user, role, None),
+        [user, role, flag, duration] if flag == "--for" => (user, role, Some(duration)),
+        _ => {
+            client.send_privmsg(&msg.channel, usage)?;
+            return audit(client, db, settings, msg, "usage");
+        }
+    };
+
+    let roles = match db.roles() {
+        Ok(roles) => roles,
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error loading roles: {}", e))?;
+            return audit(client, db, settings, msg, &format!("error: {}", e));
+        }
+    };
+    let requested = match role_name.parse::<i32>() {
+        Ok(level) => Some(level),
+        Err(_) => find_role(&roles, role_name).map(|role| role.level),
+    };
+    let Some(requested) = requested else {
+        let names: Vec<&str> = roles.iter().map(|r| r.name.as_str()).collect();
+        client.send_privmsg(&msg.channel, format!("Unknown role. Roles: {}", names.join(", ")))?;
+        return audit(client, db, settings, msg, "invalid role");
+    };
+
+    if !guard_grant_change(client, db, settings, msg, target_user, Some(requested))? {
+        return Ok(());
+    }
+
+    // Numeric levels are rounded down to the nearest role, which never raises them past the check above
+    let Some(role) = role_for_level(&roles, requested) else {
+        client.send_privmsg(&msg.channel, format!("No role at or below level {}", requested))?;
+        return audit(client, db, settings, msg, "invalid role");
+    };
+    let level = role.level;
+
+    let result = match duration {
+        None => db.grant_permission(target_user, level)
+            .map(|_| format!("Granted {} (level {}) to {}", role.name, level, target_user)),
+        Some(duration) => {
+            let Some(duration) = parse_duration(duration).filter(|d| d.as_secs() > 0) else {
+                client.send_privmsg(&msg.channel, "Duration must look like 30m, 8h or 7d")?;
+                return audit(client, db, settings, msg, "invalid duration");
+            };
+            let expiry = i64::try_from(duration.as_secs()).ok()
+                .filter(|_| duration <= MAX_GRANT_DURATION)
+                .and_then(|seconds| Some((seconds, Utc::now().timestamp().checked_add(seconds)?)));
+            let Some((seconds, expires_at)) = expiry else {
+                client.send_privmsg(&msg.channel, format!("{}. Temporary grants last at most 365d", usage))?;
+                return audit(client, db, settings, msg, "invalid duration");
+            };
+            db.grant_permission_until(target_user, level, expires_at)
+                .map(|_| format!("Granted {} (level {}) to {} for {}", role.name, level, target_user, format_remaining(seconds)))
+        }
+    };
+
+    match result {
+        Ok(reply) => {
+            client.send_privmsg(&msg.channel, &reply)?;
+            audit(client, db, settings, msg, "ok")
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error granting permission: {}", e))?;
+            audit(client, db, settings, msg, &format!("error: {}", e))
         }
     }
-    Ok(())
 }
@@ -0,0 +1,113 @@
+use std::fs;
+use std::path::Path;
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can, safe_file_name};
+use crate::games::hangman::{normalize_word, Difficulty};
+use crate::roles::GAMES_MANAGE;
+
+const MAX_WORDS_SHOWN: usize = 5;
+
+/// `!hangman words add|del|import`, for editing the word lists.
+pub fn cmd_hangman_words(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    if let Some(username) = &msg.author {
+        if !can(db, username, GAMES_MANAGE) {
+            client.send_privmsg(&msg.channel, "Permission denied. Only admins can edit the word lists")?;
+            return audit(client, db, settings, msg, "denied");
+        }
+    } else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+        return Ok(());
+    }
+
+    let usage = "Usage: !hangman words add <category> <word or phrase>[, ...] | del <word or phrase> | import <category> <file>";
+    let result = match &msg.args[1..] {
+        [action, category, words @ ..] if action == "add" && !words.is_empty() => {
+            let words = words.join(" ");
+            add_words(db, category, words.split(',').map(str::trim).filter(|w| !w.is_empty()))
+        }
+        [action, words @ ..] if action == "del" && !words.is_empty() => {
+            let word = words.join(" ").to_lowercase();
+            db.delete_hangman_word(&word)
+                .map(|deleted| if deleted { format!("Deleted '{}'", word) } else { format!("'{}' is not in any word list", word) })
+                .map_err(|e| e.to_string())
+        }
+        [action, category, file] if action == "import" => {
+            let Some(name) = safe_file_name(file) else {
+                client.send_privmsg(&msg.channel, "File names may only contain letters, digits, '.', '_' and '-'")?;
+                return audit(client, db, settings, msg, "invalid file name");
+            };
+            let path = Path::new(&settings.export_dir).join(name);
+            fs::read_to_string(&path)
+                .map_err(|e| format!("could not read {}: {}", path.display(), e))
+                .and_then(|text| add_words(db, category, word_lines(&text)))
+        }
+        _ => {
+            client.send_privmsg(&msg.channel, usage)?;
+            return audit(client, db, settings, msg, "usage");
+        }
+    };
+
+    match result {
+        Ok(reply) => {
+            client.send_privmsg(&msg.channel, &reply)?;
+            audit(client, db, settings, msg, "ok")
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error updating word list: {}", e))?;
+            audit(client, db, settings, msg, &format!("error: {}", e))
+        }
+    }
+}
+
+/// The words in an import file: one per line, blank lines and `#` comments skipped.
+fn word_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Adds words to `category` and describes what happened, naming a few of the
+/// words that were rejected or already listed.
+fn add_words<'a>(db: &Database, category: &str, words: impl Iterator<Item = &'a str>) -> Result<String, String> {
+    let category = category.to_lowercase();
+    let mut valid = Vec::new();
+    let mut invalid = Vec::new();
+    for word in words {
+        match normalize_word(word) {
+            Some(word) if !valid.contains(&word) => valid.push(word),
+            Some(_) => {}
+            None => invalid.push(word),
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for word in &valid {
+        if let Some(existing) = db.hangman_word_category(word).map_err(|e| e.to_string())? {
+            duplicates.push(format!("{} (in {})", word, existing));
+        }
+    }
+    let rows: Vec<(&str, &str)> = valid.iter()
+        .map(|word| (word.as_str(), Difficulty::of(word).as_str()))
+        .collect();
+    let added = db.add_hangman_words(&category, &rows).map_err(|e| e.to_string())?;
+
+    let mut reply = format!("Added {} word(s) to {}", added, category);
+    if !duplicates.is_empty() {
+        reply.push_str(&format!("; {} already listed: {}", duplicates.len(), shown(&duplicates)));
+    }
+    if !invalid.is_empty() {
+        reply.push_str(&format!("; {} rejected (letters and simple punctuation, at least 3 letters, at most 30 characters): {}", invalid.len(), shown(&invalid)));
+    }
+    Ok(reply)
+}
+
+fn shown<S: AsRef<str>>(words: &[S]) -> String {
+    let mut list: Vec<&str> = words.iter().take(MAX_WORDS_SHOWN).map(AsRef::as_ref).collect();
+    if words.len() > MAX_WORDS_SHOWN {
+        list.push("...");
+    }
+    list.join(", ")
+}
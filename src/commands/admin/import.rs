@@ -0,0 +1,83 @@
+use std::fs;
+use std::path::Path;
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::{privileged_tables, Database, ImportMode};
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can, safe_file_name};
+use crate::roles::{DATA_IMPORT, ROLES_MANAGE};
+
+const MAX_CONFLICTS_SHOWN: usize = 5;
+
+pub fn cmd_import(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+        return Ok(());
+    };
+    if !can(db, username, DATA_IMPORT) {
+        client.send_privmsg(&msg.channel, "Permission denied. Only admins can import data")?;
+        return audit(client, db, settings, msg, "denied");
+    }
+
+    let usage = "Usage: !import <file> [merge|replace] [--dry-run]";
+    let Some(file) = msg.args.first() else {
+        client.send_privmsg(&msg.channel, usage)?;
+        return audit(client, db, settings, msg, "usage");
+    };
+
+    let mut mode = ImportMode::Merge;
+    let mut dry_run = false;
+    for option in &msg.args[1..] {
+        match option.as_str() {
+            "merge" => mode = ImportMode::Merge,
+            "replace" => mode = ImportMode::Replace,
+            "--dry-run" => dry_run = true,
+            _ => {
+                client.send_privmsg(&msg.channel, usage)?;
+                return audit(client, db, settings, msg, "usage");
+            }
+        }
+    }
+
+    let Some(name) = safe_file_name(file) else {
+        client.send_privmsg(&msg.channel, "File names may only contain letters, digits, '.', '_' and '-'")?;
+        return audit(client, db, settings, msg, "invalid file name");
+    };
+
+    let path = Path::new(&settings.export_dir).join(name);
+    let doc = fs::read_to_string(&path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))
+        .and_then(|text| serde_json::from_str(&text).map_err(|e| format!("invalid JSON: {}", e)));
+
+    // Grants and roles would skip the checks !grant, !revoke and !roles make
+    if let Ok(doc) = &doc {
+        let privileged = privileged_tables(doc);
+        if !privileged.is_empty() && !can(db, username, ROLES_MANAGE) {
+            client.send_privmsg(&msg.channel, format!(
+                "Permission denied. Only owners can import {}; remove them from the file to import the rest",
+                privileged.join(", ")
+            ))?;
+            return audit(client, db, settings, msg, "denied privileged");
+        }
+    }
+    let result = doc.and_then(|doc| db.import_json(&doc, mode, dry_run).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(report) => {
+            let prefix = if dry_run { "Dry run: " } else { "Imported: " };
+            client.send_privmsg(&msg.channel, format!("{}{}", prefix, report))?;
+            for conflict in report.conflicts.iter().take(MAX_CONFLICTS_SHOWN) {
+                client.send_privmsg(&msg.channel, format!("  conflict {}", conflict))?;
+            }
+            if report.conflicts.len() > MAX_CONFLICTS_SHOWN {
+                client.send_privmsg(&msg.channel, format!("  ... and {} more", report.conflicts.len() - MAX_CONFLICTS_SHOWN))?;
+            }
+            audit(client, db, settings, msg, &format!("{}{}", prefix, report))?;
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error importing data: {}", e))?;
+            audit(client, db, settings, msg, &format!("error: {}", e))?;
+        }
+    }
+    Ok(())
+}
@@ -1,7 +1,19 @@
 mod grant;
 mod revoke;
 mod perms;
+mod import;
+mod backup;
+mod forget;
+mod audit;
+mod roles;
+mod hangman_words;
 
 pub use grant::cmd_grant;
 pub use revoke::cmd_revoke;
 pub use perms::cmd_perms;
+pub use import::cmd_import;
+pub use backup::cmd_backup;
+pub use forget::cmd_forget;
+pub use audit::cmd_audit;
+pub use roles::cmd_roles;
+pub use hangman_words::cmd_hangman_words;
@@ -1,13 +1,16 @@
+use chrono::Utc;
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
 use crate::database::Database;
-use crate::commands::utils::is_admin;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can, format_remaining};
+use crate::roles::{role_for_level, PERMS_VIEW};
 
-pub fn cmd_perms(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+pub fn cmd_perms(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
     if let Some(username) = &msg.author {
-        if !is_admin(db, username) {
-            client.send_privmsg(&msg.channel, "Permission denied. Only admins can view permissions")?;
-            return Ok(());
+        if !can(db, username, PERMS_VIEW) {
+            client.send_privmsg(&msg.channel, "Permission denied. Only moderators and above can view permissions")?;
+            return audit(client, db, settings, msg, "denied");
         }
     } else {
         client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
@@ -20,14 +23,23 @@ pub fn cmd_perms(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::er
                 client.send_privmsg(&msg.channel, "No users with permissions")?;
             } else {
                 client.send_privmsg(&msg.channel, "Users with permissions:")?;
-                for (user, level) in users {
-                    client.send_privmsg(&msg.channel, &format!("  {} - Level {}", user, level))?;
+                let roles = db.roles().unwrap_or_default();
+                let now = Utc::now().timestamp();
+                for (user, level, expires_at) in users {
+                    let role = role_for_level(&roles, level).map_or("no role", |r| r.name.as_str());
+                    match expires_at {
+                        Some(expires_at) => client.send_privmsg(&msg.channel, format!(
+                            "  {} - {} (level {}, expires in {})", user, role, level, format_remaining(expires_at - now)
+                        ))?,
+                        None => client.send_privmsg(&msg.channel, format!("  {} - {} (level {})", user, role, level))?,
+                    }
                 }
             }
         }
         Err(e) => {
-            client.send_privmsg(&msg.channel, &format!("Error listing permissions: {}", e))?;
+            client.send_privmsg(&msg.channel, format!("Error listing permissions: {}", e))?;
+            return audit(client, db, settings, msg, &format!("error: {}", e));
         }
     }
-    Ok(())
+    audit(client, db, settings, msg, "ok")
 }
@@ -1,13 +1,15 @@
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
 use crate::database::Database;
-use crate::commands::utils::is_admin;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can, guard_grant_change};
+use crate::roles::PERMS_GRANT;
 
-pub fn cmd_revoke(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+pub fn cmd_revoke(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
     if let Some(username) = &msg.author {
-        if !is_admin(db, username) {
+        if !can(db, username, PERMS_GRANT) {
             client.send_privmsg(&msg.channel, "Permission denied. Only admins can revoke permissions")?;
-            return Ok(());
+            return audit(client, db, settings, msg, "denied");
         }
     } else {
         client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
@@ -16,18 +18,25 @@ pub fn cmd_revoke(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::e
 
     if msg.args.is_empty() {
         client.send_privmsg(&msg.channel, "Usage: !revoke <user>")?;
+        audit(client, db, settings, msg, "usage")?;
     } else {
         let target_user = &msg.args[0];
+        if !guard_grant_change(client, db, settings, msg, target_user, None)? {
+            return Ok(());
+        }
         
         match db.revoke_permission(target_user) {
             Ok(true) => {
-                client.send_privmsg(&msg.channel, &format!("Revoked permissions for {}", target_user))?;
+                client.send_privmsg(&msg.channel, format!("Revoked permissions for {}", target_user))?;
+                audit(client, db, settings, msg, "ok")?;
             }
             Ok(false) => {
-                client.send_privmsg(&msg.channel, &format!("User {} has no permissions", target_user))?;
+                client.send_privmsg(&msg.channel, format!("User {} has no permissions", target_user))?;
+                audit(client, db, settings, msg, "no permissions")?;
             }
             Err(e) => {
-                client.send_privmsg(&msg.channel, &format!("Error revoking permission: {}", e))?;
+                client.send_privmsg(&msg.channel, format!("Error revoking permission: {}", e))?;
+                audit(client, db, settings, msg, &format!("error: {}", e))?;
             }
         }
     }
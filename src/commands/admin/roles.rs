@@ -0,0 +1,82 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can};
+use crate::roles::{find_role, CAPABILITIES, PERMS_VIEW, ROLES_MANAGE};
+
+pub fn cmd_roles(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+        return Ok(());
+    };
+
+    // Listing needs the same capability as !perms; changing roles needs roles.manage
+    let needed = if msg.args.is_empty() { PERMS_VIEW } else { ROLES_MANAGE };
+    if !can(db, username, needed) {
+        client.send_privmsg(&msg.channel, "Permission denied. You don't have permission to use this command")?;
+        return audit(client, db, settings, msg, "denied");
+    }
+
+    let roles = match db.roles() {
+        Ok(roles) => roles,
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error loading roles: {}", e))?;
+            return audit(client, db, settings, msg, &format!("error: {}", e));
+        }
+    };
+
+    let usage = "Usage: !roles [<role> allow|deny <capability>]";
+    let (role_name, action, capability) = match msg.args.as_slice() {
+        [] => {
+            client.send_privmsg(&msg.channel, "Roles (each also has the capabilities of the roles above it in this list):")?;
+            for role in &roles {
+                let capabilities = if role.capabilities.is_empty() {
+                    "-".to_string()
+                } else {
+                    role.capabilities.join(", ")
+                };
+                client.send_privmsg(&msg.channel, format!("  {} (level {}): {}", role.name, role.level, capabilities))?;
+            }
+            return audit(client, db, settings, msg, "ok");
+        }
+        [role, action, capability] => (role, action.as_str(), capability),
+        _ => {
+            client.send_privmsg(&msg.channel, usage)?;
+            return audit(client, db, settings, msg, "usage");
+        }
+    };
+
+    let Some(role) = find_role(&roles, role_name) else {
+        client.send_privmsg(&msg.channel, format!("Unknown role: {}", role_name))?;
+        return audit(client, db, settings, msg, "invalid role");
+    };
+    if !CAPABILITIES.contains(&capability.as_str()) {
+        client.send_privmsg(&msg.channel, format!("Unknown capability. Capabilities: {}", CAPABILITIES.join(", ")))?;
+        return audit(client, db, settings, msg, "invalid capability");
+    }
+    let allowed = match action {
+        "allow" => true,
+        "deny" => false,
+        _ => {
+            client.send_privmsg(&msg.channel, usage)?;
+            return audit(client, db, settings, msg, "usage");
+        }
+    };
+
+    match db.set_role_capability(&role.name, capability, allowed) {
+        Ok(true) => {
+            let verb = if allowed { "now has" } else { "no longer has" };
+            client.send_privmsg(&msg.channel, format!("{} {} {}", role.name, verb, capability))?;
+            audit(client, db, settings, msg, "ok")
+        }
+        Ok(false) => {
+            client.send_privmsg(&msg.channel, format!("No change to {}", role.name))?;
+            audit(client, db, settings, msg, "unchanged")
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error updating role: {}", e))?;
+            audit(client, db, settings, msg, &format!("error: {}", e))
+        }
+    }
+}
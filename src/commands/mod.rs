@@ -1,4 +1,4 @@
-mod utils;
+pub(crate) mod utils;
 pub mod public;
 pub mod restricted;
 pub mod admin;
@@ -6,31 +6,75 @@ pub mod admin;
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
 use crate::database::Database;
-use crate::games::GameManager;
+use crate::achievements::{Event, EventBus};
+use crate::games::{self, GameManager};
+use crate::settings::Settings;
+
+pub async fn handle_command(client: &Client, msg: &ParsedMessage, db: &Database, game_manager: &GameManager, events: &EventBus, settings: &Settings) -> irc::error::Result<()> {
+    if let Some(author) = &msg.author {
+        events.publish(&msg.channel, author, Event::Command);
+    }
 
-pub async fn handle_command(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager) -> irc::error::Result<()> {
     match msg.command.as_str() {
         // Public commands
         "ping" => public::cmd_ping(client, msg),
         "hello" => public::cmd_hello(client, msg),
         "echo" => public::cmd_echo(client, msg),
-        "hangman" => public::cmd_hangman(client, msg, games).await,
         "help" => public::cmd_help(client, msg),
+        "roll" => public::cmd_roll(client, msg, db, settings),
+        "balance" => public::cmd_balance(client, msg, db),
+        "give" => public::cmd_give(client, msg, db, events, settings),
+        "daily" => public::cmd_daily(client, msg, db, events, settings),
+        "achievements" => public::cmd_achievements(client, msg, db),
         
         // Restricted commands
-        "join" => restricted::cmd_join(client, msg, db),
-        "leave" => restricted::cmd_leave(client, msg, db),
-        "set" => restricted::cmd_set(client, msg, db),
-        "get" => restricted::cmd_get(client, msg, db),
-        "del" => restricted::cmd_del(client, msg, db),
-        "list" => restricted::cmd_list(client, msg, db),
+        "join" => restricted::cmd_join(client, msg, db, settings),
+        "leave" => restricted::cmd_leave(client, msg, db, settings),
+        "set" => restricted::cmd_set(client, msg, db, events, settings),
+        "get" => restricted::cmd_get(client, msg, db, settings),
+        "del" => restricted::cmd_del(client, msg, db, settings),
+        "list" => restricted::cmd_list(client, msg, db, settings),
+        "find" => restricted::cmd_find(client, msg, db, settings),
+        "quota" => restricted::cmd_quota(client, msg, db, settings),
+        "export" => restricted::cmd_export(client, msg, db, settings),
+        "mydata" => restricted::cmd_mydata(client, msg, db, settings),
+        "forgetme" => restricted::cmd_forgetme(client, msg, db, settings),
         
         // Admin commands
-        "grant" => admin::cmd_grant(client, msg, db),
-        "revoke" => admin::cmd_revoke(client, msg, db),
-        "perms" => admin::cmd_perms(client, msg, db),
+        "grant" => admin::cmd_grant(client, msg, db, settings),
+        "revoke" => admin::cmd_revoke(client, msg, db, settings),
+        "perms" => admin::cmd_perms(client, msg, db, settings),
+        "import" => admin::cmd_import(client, msg, db, settings),
+        "backup" => admin::cmd_backup(client, msg, db, settings),
+        "forget" => admin::cmd_forget(client, msg, db, settings),
+        "audit" => admin::cmd_audit(client, msg, db, settings),
+        "roles" => admin::cmd_roles(client, msg, db, settings),
         
+        // Games
+        "hangman" => public::cmd_hangman(client, msg, db, game_manager, settings).await,
+        "trivia" => public::cmd_trivia(client, msg, db, game_manager, settings).await,
+        "scramble" => public::cmd_scramble(client, msg, db, game_manager, settings).await,
+        "blackjack" | "poker" => public::cmd_cards(client, msg, db, game_manager, settings).await,
+        "ttt" | "c4" | "chess" => public::cmd_board(client, msg, db, game_manager, settings).await,
+        "tournament" => public::cmd_tournament(client, msg, db, game_manager, settings).await,
+        kind if games::KINDS.contains(&kind) => public::cmd_game(client, msg, db, game_manager, settings).await,
+
         // Unknown command
         _ => public::cmd_unknown(client, msg),
     }
 }
+
+/// Offers a channel message that isn't a command to the channel's games,
+/// e.g. as a trivia answer.
+pub async fn handle_chat(client: &Client, msg: &ParsedMessage, text: &str, db: &Database, game_manager: &GameManager) -> irc::error::Result<()> {
+    let Some(player) = &msg.author else {
+        return Ok(());
+    };
+    if !msg.channel.starts_with('#') {
+        return Ok(());
+    }
+    for line in game_manager.chat(db, &msg.channel, player, text).await {
+        client.send_privmsg(&msg.channel, &line)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::achievements::{self, ACHIEVEMENTS};
+use crate::commands::utils::prefixed_lines;
+
+/// `!achievements [nick]`: the badges a player has earned, and their
+/// progress towards the rest.
+pub fn cmd_achievements(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    let Some(nick) = msg.args.first().or(msg.author.as_ref()) else {
+        return Ok(());
+    };
+    let unlocked = match db.achievements(nick) {
+        Ok(unlocked) => unlocked,
+        Err(e) => return client.send_privmsg(&msg.channel, format!("Error loading achievements: {}", e)),
+    };
+
+    let earned: Vec<String> = unlocked.iter()
+        .filter_map(|id| achievements::find(id))
+        .map(|a| format!("{} {}", a.badge, a.name))
+        .collect();
+    if earned.is_empty() {
+        client.send_privmsg(&msg.channel, format!("{} has no achievements yet (0/{})", nick, ACHIEVEMENTS.len()))?;
+    } else {
+        let prefix = format!("🏅 {} ({}/{}): ", nick, earned.len(), ACHIEVEMENTS.len());
+        for line in prefixed_lines(&prefix, earned, ", ") {
+            client.send_privmsg(&msg.channel, line)?;
+        }
+    }
+
+    let mut remaining = Vec::new();
+    for achievement in ACHIEVEMENTS.iter().filter(|a| !unlocked.iter().any(|id| id == a.id)) {
+        let progress = db.stat(nick, achievement.stat).unwrap_or(0).min(achievement.goal);
+        remaining.push(format!("{} ({}, {}/{})", achievement.name, achievement.description, progress, achievement.goal));
+    }
+    for line in prefixed_lines("Still to earn: ", remaining, ", ") {
+        client.send_privmsg(&msg.channel, line)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::games::{self, GameManager};
+use crate::commands::utils::prefixed_lines;
+use super::cmd_game;
+
+/// `!ttt`, `!c4` and `!chess`: `challenge <nick>` starts a game between two
+/// players; everything else is handled like any other game.
+pub async fn cmd_board(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    match msg.args.first().map(String::as_str) {
+        Some("challenge") => challenge(client, msg, db, games).await,
+        Some("start") => {
+            client.send_privmsg(&msg.channel, format!("Challenge someone with !{} challenge <nick>", msg.command))?;
+            Ok(())
+        }
+        _ => {
+            let player = msg.author.as_deref().unwrap_or("someone");
+            if games.status(&msg.channel, &msg.command, player).await.is_none() {
+                return not_playing(client, msg, games).await;
+            }
+            cmd_game(client, msg, db, games, settings).await
+        }
+    }
+}
+
+/// Tells someone who isn't in a game which pairs are playing, if any.
+async fn not_playing(client: &Client, msg: &ParsedMessage, games: &GameManager) -> irc::error::Result<()> {
+    let kind = msg.command.as_str();
+    let pairs = games.pairs(&msg.channel, kind).await;
+    if pairs.is_empty() {
+        client.send_privmsg(&msg.channel, format!("No {} games are being played here. Use !{} challenge <nick>", kind, kind))?;
+        return Ok(());
+    }
+    for line in prefixed_lines(&format!("You're not in a {} game. Playing here: ", kind), pairs, ", ") {
+        client.send_privmsg(&msg.channel, line)?;
+    }
+    Ok(())
+}
+
+async fn challenge(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager) -> irc::error::Result<()> {
+    let kind = msg.command.as_str();
+    let Some(challenger) = msg.author.as_deref() else {
+        return Ok(());
+    };
+    if !msg.channel.starts_with('#') {
+        client.send_privmsg(&msg.channel, "Challenges are made in a channel")?;
+        return Ok(());
+    }
+    let Some(opponent) = msg.args.get(1) else {
+        client.send_privmsg(&msg.channel, format!("Usage: !{} challenge <nick>", kind))?;
+        return Ok(());
+    };
+    if opponent.eq_ignore_ascii_case(challenger) {
+        client.send_privmsg(&msg.channel, "You can't challenge yourself")?;
+        return Ok(());
+    }
+    let Some(game) = games::new_board_game(kind, challenger, opponent) else {
+        return Ok(());
+    };
+
+    let lines = match games.start(db, &msg.channel, game).await {
+        Ok(lines) => lines,
+        Err(_) => vec![format!(
+            "{} or {} is already in a {} game here. Finish it with !{} resign first.",
+            challenger, opponent, kind, kind
+        )],
+    };
+    for line in lines {
+        client.send_privmsg(&msg.channel, &line)?;
+    }
+    Ok(())
+}
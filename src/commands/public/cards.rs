@@ -0,0 +1,16 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::games::GameManager;
+use super::{cmd_game, wallet};
+
+/// `!blackjack` and `!poker`: `chips [nick]` and `top` are shortcuts for
+/// `!balance`; everything else is handled like any other game.
+pub async fn cmd_cards(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    match msg.args.first().map(String::as_str) {
+        Some("chips") => wallet::send_balance(client, msg, db, msg.args.get(1).map(String::as_str)),
+        Some("top") => wallet::send_top(client, msg, db),
+        _ => cmd_game(client, msg, db, games, settings).await,
+    }
+}
@@ -0,0 +1,90 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::games::{self, Game, GameManager, Reply};
+use crate::games::leaderboard::ranked;
+use crate::commands::utils::can;
+use crate::roles::GAMES_MANAGE;
+
+/// Routes `!<kind> <subcommand>` to the game of that kind in the channel.
+pub async fn cmd_game(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    let kind = msg.command.as_str();
+    let Some(subcommand) = msg.args.first() else {
+        client.send_privmsg(&msg.channel, format!("Usage: !{} <{}>", kind, games::subcommands(kind)))?;
+        return Ok(());
+    };
+
+    let player = msg.author.as_deref().unwrap_or("someone");
+    let reply = match subcommand.as_str() {
+        "start" => {
+            return match games::new_game(db, settings, kind) {
+                Some(Ok(game)) => start_game(client, msg, db, games, game).await,
+                Some(Err(e)) => client.send_privmsg(&msg.channel, e),
+                None => Ok(()),
+            };
+        }
+        "status" => games.status(&msg.channel, kind, player).await.map(Reply::new),
+        "quit" => {
+            let manage = msg.author.as_deref().is_some_and(|username| can(db, username, GAMES_MANAGE));
+            games.quit(db, &msg.channel, kind, player, manage).await
+        }
+        _ => games.input(db, &msg.channel, kind, player, &msg.args).await,
+    };
+
+    match reply {
+        Some(reply) => {
+            for line in reply.lines {
+                client.send_privmsg(&msg.channel, &line)?;
+            }
+            // Private lines such as hole cards go to the player alone
+            for (player, line) in reply.notices {
+                client.send_notice(&player, &line)?;
+            }
+        }
+        None => {
+            client.send_privmsg(&msg.channel, format!("No game is running in this channel. Use !{} start", kind))?;
+        }
+    }
+    Ok(())
+}
+
+/// Starts `game` in the channel unless another game is in the way.
+pub async fn start_game(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, game: Box<dyn Game>) -> irc::error::Result<()> {
+    let lines = match games.start(db, &msg.channel, game).await {
+        Ok(lines) => lines,
+        Err(running) => vec![format!(
+            "A {} game is already running in this channel! Use !{} quit to end it.",
+            running, running
+        )],
+    };
+    for line in lines {
+        client.send_privmsg(&msg.channel, &line)?;
+    }
+    Ok(())
+}
+
+/// Posts a game's leaderboard, e.g. "🏆 Trivia top (#rust): 1. alice (12), 2. bob (4)".
+pub fn send_leaderboard(
+    client: &Client,
+    msg: &ParsedMessage,
+    game: &str,
+    scope: &str,
+    scores: Result<Vec<(String, i64)>, rusqlite::Error>,
+) -> irc::error::Result<()> {
+    match scores {
+        Ok(scores) if scores.is_empty() => {
+            client.send_privmsg(&msg.channel, format!("No {} scores yet ({})", game.to_lowercase(), scope))?;
+        }
+        Ok(scores) => {
+            let entries: Vec<String> = ranked(&scores).into_iter()
+                .map(|(rank, nick, points)| format!("{}. {} ({})", rank, nick, points))
+                .collect();
+            client.send_privmsg(&msg.channel, format!("🏆 {} top ({}): {}", game, scope, entries.join(", ")))?;
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error loading leaderboard: {}", e))?;
+        }
+    }
+    Ok(())
+}
@@ -1,128 +1,196 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+use chrono::Utc;
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::admin::cmd_hangman_words;
 use crate::games::GameManager;
-use crate::games::hangman::GuessResult;
+use crate::games::hangman::{new_hangman_game, normalize_word, Difficulty, HangmanGame, HangmanOptions};
+use super::{cmd_game, send_leaderboard, start_game};
 
-pub async fn cmd_hangman(client: &Client, msg: &ParsedMessage, games: &GameManager) -> irc::error::Result<()> {
-    if msg.args.is_empty() {
-        client.send_privmsg(&msg.channel, "Usage: !hangman <start|guess|status|quit>")?;
-        return Ok(());
-    }
-
-    let subcommand = msg.args[0].as_str();
+const TOP_SIZE: usize = 10;
+const WEEK: i64 = 7 * 24 * 60 * 60;
+/// Seconds per guess `!hangman start speed <seconds>` accepts.
+const SPEED_LIMITS: RangeInclusive<u64> = 5..=300;
 
-    match subcommand {
-        "start" => hangman_start(client, msg, games).await,
-        "guess" => hangman_guess(client, msg, games).await,
-        "status" => hangman_status(client, msg, games).await,
-        "quit" => hangman_quit(client, msg, games).await,
-        _ => {
-            client.send_privmsg(&msg.channel, "Unknown hangman subcommand. Use: start, guess, status, quit")?;
-            Ok(())
-        }
+/// `!hangman start`, `categories`, `words`, `stats` and `top`; everything
+/// else is handled like any other game.
+pub async fn cmd_hangman(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    match msg.args.first().map(String::as_str) {
+        Some("start") => hangman_start(client, msg, db, games, settings).await,
+        Some("set") => hangman_set(client, msg, db, games, settings).await,
+        Some("categories") => hangman_categories(client, msg, db),
+        Some("words") => cmd_hangman_words(client, msg, db, settings),
+        Some("stats") => hangman_stats(client, msg, db),
+        Some("top") => hangman_top(client, msg, db),
+        _ => cmd_game(client, msg, db, games, settings).await,
     }
 }
 
-async fn hangman_start(
-    client: &Client,
-    msg: &ParsedMessage,
-    games: &GameManager,
-) -> irc::error::Result<()> {
-    // Check if a game is already running
-    if games.hangman_game(&msg.channel).await.is_some() {
-        client.send_privmsg(&msg.channel, "A hangman game is already running in this channel! Use !hangman quit to end it.")?;
-        return Ok(());
+/// `!hangman start [category] [easy|hard] [speed [seconds]] [turns]`, in any order.
+async fn hangman_start(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    let usage = "Usage: !hangman start [category] [easy|hard] [speed [seconds]] [turns]";
+    let mut category = None;
+    let mut difficulty = None;
+    let mut speed_limit = None;
+    let mut turns = false;
+    let mut args = msg.args[1..].iter().peekable();
+    while let Some(arg) = args.next() {
+        if arg == "turns" && !turns {
+            turns = true;
+            continue;
+        }
+        if arg == "speed" && speed_limit.is_none() {
+            let seconds = args.peek().and_then(|next| next.parse::<u64>().ok());
+            if seconds.is_some() {
+                args.next();
+            }
+            let limit = seconds.map_or(settings.hangman_speed_limit, Duration::from_secs);
+            if !SPEED_LIMITS.contains(&limit.as_secs()) {
+                client.send_privmsg(&msg.channel, format!(
+                    "Speed mode allows {} to {} seconds per guess", SPEED_LIMITS.start(), SPEED_LIMITS.end()
+                ))?;
+                return Ok(());
+            }
+            speed_limit = Some(limit);
+            continue;
+        }
+        match Difficulty::parse(arg) {
+            Some(d) if difficulty.is_none() => difficulty = Some(d),
+            None if category.is_none() => category = Some(arg.to_lowercase()),
+            _ => {
+                client.send_privmsg(&msg.channel, usage)?;
+                return Ok(());
+            }
+        }
     }
 
-    games.new_hangman(&msg.channel).await;
-    if let Some(game) = games.hangman_game(&msg.channel).await {
-        client.send_privmsg(&msg.channel, "🎮 Hangman game started! Everyone can play along!")?;
-        client.send_privmsg(&msg.channel, &format!("Word: {}", game.display()))?;
-        client.send_privmsg(&msg.channel, &format!("Remaining guesses: {}", game.remaining()))?;
-        client.send_privmsg(&msg.channel, "Use !hangman guess <letter> to guess a letter")?;
+    let host = match (turns, &msg.author) {
+        (false, _) => None,
+        (true, Some(host)) => Some(host.clone()),
+        (true, None) => {
+            client.send_privmsg(&msg.channel, "You must be logged in to host a turn-based game")?;
+            return Ok(());
+        }
+    };
+    let options = HangmanOptions { category, difficulty, speed_limit, host };
+    match new_hangman_game(db, settings, &options) {
+        Ok(game) => start_game(client, msg, db, games, Box::new(game)).await,
+        Err(e) => client.send_privmsg(&msg.channel, e),
     }
-    Ok(())
 }
 
-async fn hangman_guess(
-    client: &Client,
-    msg: &ParsedMessage,
-    games: &GameManager,
-) -> irc::error::Result<()> {
-    if msg.args.len() < 2 {
-        client.send_privmsg(&msg.channel, "Usage: !hangman guess <letter>")?;
+/// `!hangman set <#channel> <word or phrase>`, sent privately: starts a game
+/// in the channel with that word, which the setter can't guess.
+async fn hangman_set(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    let Some(setter) = &msg.author else {
+        client.send_privmsg(&msg.channel, "You must be logged in to set a word")?;
         return Ok(());
-    }
-
-    let letter_str = &msg.args[1];
-    if letter_str.len() != 1 {
-        client.send_privmsg(&msg.channel, "Please guess a single letter")?;
+    };
+    if msg.channel.starts_with('#') {
+        client.send_privmsg(&msg.channel, "Send !hangman set <#channel> <word> to me in a private message, so nobody sees the word")?;
         return Ok(());
     }
-
-    let letter = letter_str.chars().next().unwrap();
-    let author = msg.author.as_ref().map(|s| s.as_str()).unwrap_or("someone");
-
-    match games.hangman_guess(&msg.channel, letter).await {
-        None => {
-            client.send_privmsg(&msg.channel, "No game is running in this channel. Use !hangman start")?;
-        }
-        Some(GuessResult::AlreadyGuessed) => {
-            client.send_privmsg(&msg.channel, &format!("{} already guessed '{}' 🤔", author, letter))?;
+    let (channel, words) = match msg.args.as_slice() {
+        [_, channel, words @ ..] if channel.starts_with('#') && !words.is_empty() => (channel, words),
+        _ => {
+            client.send_privmsg(&msg.channel, "Usage: !hangman set <#channel> <word or phrase>")?;
+            return Ok(());
         }
-        Some(GuessResult::Correct) => {
-            if let Some(game) = games.hangman_game(&msg.channel).await {
-                client.send_privmsg(&msg.channel, &format!("{} guessed '{}' - ✓ Correct! Word: {}", author, letter, game.display()))?;
-                client.send_privmsg(&msg.channel, &format!("Remaining guesses: {}", game.remaining()))?;
+    };
+    // Use the channel's name as the server spelled it, which is how its games are keyed
+    let joined = client.list_channels().unwrap_or_default();
+    let Some(channel) = joined.iter().find(|c| c.eq_ignore_ascii_case(channel)) else {
+        client.send_privmsg(&msg.channel, format!("I'm not in {}", channel))?;
+        return Ok(());
+    };
+    let Some(word) = normalize_word(&words.join(" ")) else {
+        client.send_privmsg(&msg.channel, "Words may only use letters and simple punctuation, with at least 3 letters and at most 30 characters")?;
+        return Ok(());
+    };
+
+    let game = HangmanGame::with_word(&word, None)
+        .with_solve_penalty(settings.hangman_solve_penalty)
+        .with_setter(setter);
+    match games.start(db, channel, Box::new(game)).await {
+        Ok(lines) => {
+            for line in lines {
+                client.send_privmsg(channel, &line)?;
             }
+            client.send_privmsg(&msg.channel, format!("Your word is up in {}", channel))?;
         }
-        Some(GuessResult::Wrong) => {
-            if let Some(game) = games.hangman_game(&msg.channel).await {
-                client.send_privmsg(&msg.channel, &format!("{} guessed '{}' - ✗ Wrong! Word: {}", author, letter, game.display()))?;
-                client.send_privmsg(&msg.channel, &format!("Remaining guesses: {}", game.remaining()))?;
-            }
+        Err(running) => {
+            client.send_privmsg(&msg.channel, format!("A {} game is already running in {}", running, channel))?;
         }
-        Some(GuessResult::Won) => {
-            if let Some(game) = games.hangman_game(&msg.channel).await {
-                client.send_privmsg(&msg.channel, &format!("🎉 {} solved it! The word was: {}", author, game.word()))?;
-                games.hangman_quit(&msg.channel).await;
-            }
+    }
+    Ok(())
+}
+
+fn hangman_categories(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    match db.hangman_categories() {
+        Ok(categories) if categories.is_empty() => {
+            client.send_privmsg(&msg.channel, "The word lists are empty")?;
+        }
+        Ok(categories) => {
+            let entries: Vec<String> = categories.iter()
+                .map(|(category, count)| format!("{} ({})", category, count))
+                .collect();
+            client.send_privmsg(&msg.channel, format!("Categories: {}", entries.join(", ")))?;
         }
-        Some(GuessResult::Lost(word)) => {
-            client.send_privmsg(&msg.channel, &format!("☠️ Game Over! The word was: {} 😢", word))?;
-            games.hangman_quit(&msg.channel).await;
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error listing categories: {}", e))?;
         }
     }
     Ok(())
 }
 
-async fn hangman_status(
-    client: &Client,
-    msg: &ParsedMessage,
-    games: &GameManager,
-) -> irc::error::Result<()> {
-    match games.hangman_game(&msg.channel).await {
-        None => {
-            client.send_privmsg(&msg.channel, "No game is running in this channel. Use !hangman start")?;
+fn hangman_stats(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    let Some(nick) = msg.args.get(1).or(msg.author.as_ref()) else {
+        client.send_privmsg(&msg.channel, "Usage: !hangman stats [nick]")?;
+        return Ok(());
+    };
+
+    match db.hangman_stats(nick) {
+        Ok(stats) if stats.games == 0 => {
+            client.send_privmsg(&msg.channel, format!("{} hasn't played hangman yet", nick))?;
+        }
+        Ok(stats) => {
+            client.send_privmsg(&msg.channel, format!(
+                "{}: {} points, {} games, {} solves, {} correct and {} wrong letters",
+                nick, stats.points, stats.games, stats.solves, stats.correct, stats.wrong
+            ))?;
         }
-        Some(game) => {
-            client.send_privmsg(&msg.channel, &format!("Word: {}", game.display()))?;
-            client.send_privmsg(&msg.channel, &format!("Guessed: {}", game.guessed()))?;
-            client.send_privmsg(&msg.channel, &format!("Wrong: {}/{}", game.wrong_count(), 6))?;
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error loading stats: {}", e))?;
         }
     }
     Ok(())
 }
 
-async fn hangman_quit(
-    client: &Client,
-    msg: &ParsedMessage,
-    games: &GameManager,
-) -> irc::error::Result<()> {
-    if games.hangman_quit(&msg.channel).await {
-        client.send_privmsg(&msg.channel, "Hangman game ended.")?;
-    } else {
-        client.send_privmsg(&msg.channel, "No game is running in this channel")?;
+fn hangman_top(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    // Defaults to this channel's all-time board, or the global one in private messages
+    let mut channel = msg.channel.starts_with('#').then_some(msg.channel.as_str());
+    let mut since = None;
+    for arg in &msg.args[1..] {
+        match arg.as_str() {
+            "channel" => channel = Some(msg.channel.as_str()),
+            "global" => channel = None,
+            "week" => since = Some(Utc::now().timestamp() - WEEK),
+            "all" => since = None,
+            _ => {
+                client.send_privmsg(&msg.channel, "Usage: !hangman top [channel|global] [week|all]")?;
+                return Ok(());
+            }
+        }
     }
-    Ok(())
+
+    let scope = match (channel, since) {
+        (Some(channel), Some(_)) => format!("{} this week", channel),
+        (Some(channel), None) => channel.to_string(),
+        (None, Some(_)) => "everywhere this week".to_string(),
+        (None, None) => "everywhere".to_string(),
+    };
+    send_leaderboard(client, msg, "Hangman", &scope, db.hangman_leaderboard(channel, since, TOP_SIZE))
 }
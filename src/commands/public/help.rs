@@ -1,9 +1,14 @@
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
+use crate::games;
 
 pub fn cmd_help(client: &Client, msg: &ParsedMessage) -> irc::error::Result<()> {
-    client.send_privmsg(&msg.channel, "Public: !ping, !hello, !echo <msg>, !help")?;
-    client.send_privmsg(&msg.channel, "Restricted: !join <#ch>, !leave <#ch>, !set <k> <v>, !get <k>, !del <k>, !list")?;
-    client.send_privmsg(&msg.channel, "Admin: !grant <user> <level>, !revoke <user>, !perms")?;
+    client.send_privmsg(&msg.channel, "Public: !ping, !hello, !echo <msg>, !help, !roll <dice|save|del|macros>, !balance [nick|top|history], !give <nick> <n>, !daily, !achievements [nick]")?;
+    let games: Vec<String> = games::KINDS.iter()
+        .map(|kind| format!("!{} <{}>", kind, games::subcommands(kind)))
+        .collect();
+    client.send_privmsg(&msg.channel, format!("Games: {}, !tournament <create|join|start|cancel|standings>", games.join(", ")))?;
+    client.send_privmsg(&msg.channel, "Restricted: !join <#ch>, !leave <#ch>, !set <k> <v>, !get <k>, !del <k>, !list [pattern] [page], !find <text>, !quota, !export, !mydata, !forgetme")?;
+    client.send_privmsg(&msg.channel, "Admin: !grant <user> <role> [--for 8h], !revoke <user>, !perms, !export all [file], !import <file> [merge|replace] [--dry-run], !backup <now|list|verify>, !mydata <user>, !forget <user>, !audit [user|command] [since], !roles [<role> allow|deny <capability>]")?;
     Ok(())
 }
@@ -3,11 +3,29 @@ mod hello;
 mod echo;
 mod help;
 mod unknown;
+mod game;
 mod hangman;
+mod trivia;
+mod scramble;
+mod cards;
+mod board;
+mod roll;
+mod wallet;
+mod achievements;
+mod tournament;
 
 pub use ping::cmd_ping;
 pub use hello::cmd_hello;
 pub use echo::cmd_echo;
 pub use help::cmd_help;
 pub use unknown::cmd_unknown;
+pub use game::{cmd_game, send_leaderboard, start_game};
 pub use hangman::cmd_hangman;
+pub use trivia::cmd_trivia;
+pub use scramble::cmd_scramble;
+pub use cards::cmd_cards;
+pub use board::cmd_board;
+pub use roll::cmd_roll;
+pub use wallet::{cmd_balance, cmd_daily, cmd_give};
+pub use achievements::cmd_achievements;
+pub use tournament::cmd_tournament;
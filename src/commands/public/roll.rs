@@ -0,0 +1,151 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, check_quota, prefixed_lines};
+use crate::dice;
+
+/// Prefix of the `users_data` keys that hold saved macros.
+const MACRO_PREFIX: &str = "dice.";
+const MAX_MACRO_NAME: usize = 20;
+/// Macros shown by `!roll macros`.
+const MAX_LISTED: usize = 30;
+
+const USAGE: &str = "Usage: !roll <dice> [xN] (e.g. 4d6kh3+2, 2d20adv, 3d6!), !roll save <name> <dice>, !roll del <name>, !roll macros";
+
+pub fn cmd_roll(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    match msg.args.first().map(|s| s.as_str()) {
+        None => client.send_privmsg(&msg.channel, USAGE),
+        Some("save") => save_macro(client, msg, db, settings),
+        Some("del") => delete_macro(client, msg, db, settings),
+        Some("macros") => list_macros(client, msg, db),
+        Some(_) => roll(client, msg, db),
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_MACRO_NAME
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        && !matches!(name, "save" | "del" | "macros")
+}
+
+fn roll(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    let who = msg.author.as_deref().unwrap_or("You");
+    let text = msg.args.join(" ");
+    let (label, expr) = match dice::parse(&text) {
+        Ok(expr) => (text.clone(), expr),
+        Err(e) => {
+            // Not dice notation: maybe one of the user's macros, with any repeat after it
+            let saved = match &msg.author {
+                Some(username) => db.get_user_data(username, &format!("{}{}", MACRO_PREFIX, msg.args[0].to_lowercase())),
+                None => Ok(None),
+            };
+            match saved {
+                Ok(Some(saved)) => {
+                    let rest = msg.args[1..].join(" ");
+                    match dice::parse(&format!("{} {}", saved, rest)) {
+                        Ok(expr) => (format!("{} ({})", msg.args[0], saved), expr),
+                        Err(e) => {
+                            return client.send_privmsg(&msg.channel, format!("Error rolling {}: {}", msg.args[0], e));
+                        }
+                    }
+                }
+                Ok(None) => return client.send_privmsg(&msg.channel, format!("Error rolling: {}. {}", e, USAGE)),
+                Err(e) => return client.send_privmsg(&msg.channel, format!("Error loading macro: {}", e)),
+            }
+        }
+    };
+
+    let rolls = dice::roll(&expr, &mut rand::thread_rng());
+    if let [only] = rolls.as_slice() {
+        return client.send_privmsg(&msg.channel, format!("🎲 {} rolled {}: {}", who, label, only));
+    }
+    // Repeats share a line, only wrapping when they don't fit
+    let entries = rolls.iter().enumerate().map(|(i, roll)| format!("#{}: {}", i + 1, roll));
+    for line in prefixed_lines(&format!("🎲 {} rolled {}: ", who, label), entries, " | ") {
+        client.send_privmsg(&msg.channel, line)?;
+    }
+    Ok(())
+}
+
+fn save_macro(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        return client.send_privmsg(&msg.channel, "You must be logged in to use this command");
+    };
+    if msg.args.len() < 3 {
+        client.send_privmsg(&msg.channel, "Usage: !roll save <name> <dice>")?;
+        return audit(client, db, settings, msg, "usage");
+    }
+    let name = msg.args[1].to_lowercase();
+    if !valid_name(&name) {
+        client.send_privmsg(&msg.channel, format!(
+            "Macro names are up to {} letters, digits, '-' or '_'", MAX_MACRO_NAME
+        ))?;
+        return audit(client, db, settings, msg, "invalid name");
+    }
+    let expr = msg.args[2..].join(" ");
+    if let Err(e) = dice::parse(&expr) {
+        client.send_privmsg(&msg.channel, format!("Error saving macro: {}", e))?;
+        return audit(client, db, settings, msg, &format!("invalid dice: {}", e));
+    }
+
+    let key = format!("{}{}", MACRO_PREFIX, name);
+    if let Err(e) = check_quota(db, settings, username, &key, &expr) {
+        client.send_privmsg(&msg.channel, &e)?;
+        return audit(client, db, settings, msg, &e);
+    }
+    match db.set_user_data(username, &key, &expr) {
+        Ok(()) => {
+            client.send_privmsg(&msg.channel, format!("Saved macro {}: {}", name, expr))?;
+            audit(client, db, settings, msg, "ok")
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error saving macro: {}", e))?;
+            audit(client, db, settings, msg, &format!("error: {}", e))
+        }
+    }
+}
+
+fn delete_macro(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        return client.send_privmsg(&msg.channel, "You must be logged in to use this command");
+    };
+    let Some(name) = msg.args.get(1).map(|n| n.to_lowercase()) else {
+        client.send_privmsg(&msg.channel, "Usage: !roll del <name>")?;
+        return audit(client, db, settings, msg, "usage");
+    };
+    match db.delete_user_data(username, &format!("{}{}", MACRO_PREFIX, name)) {
+        Ok(true) => {
+            client.send_privmsg(&msg.channel, format!("Deleted macro {}", name))?;
+            audit(client, db, settings, msg, "ok")
+        }
+        Ok(false) => {
+            client.send_privmsg(&msg.channel, format!("No macro named {}", name))?;
+            audit(client, db, settings, msg, "not found")
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error deleting macro: {}", e))?;
+            audit(client, db, settings, msg, &format!("error: {}", e))
+        }
+    }
+}
+
+fn list_macros(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        return client.send_privmsg(&msg.channel, "You must be logged in to use this command");
+    };
+    match db.list_user_data_page(username, Some(MACRO_PREFIX), MAX_LISTED, 0) {
+        Ok((_, 0)) => client.send_privmsg(&msg.channel, "No saved macros. Use: !roll save <name> <dice>"),
+        Ok((macros, total)) => {
+            let mut entries: Vec<String> = macros.iter()
+                .map(|(key, expr)| format!("{} = {}", &key[MACRO_PREFIX.len()..], expr))
+                .collect();
+            if total > macros.len() {
+                entries.push(format!("… {} more", total - macros.len()));
+            }
+            client.send_privmsg(&msg.channel, format!("🎲 Macros: {}", entries.join(", ")))
+        }
+        Err(e) => client.send_privmsg(&msg.channel, format!("Error listing macros: {}", e)),
+    }
+}
@@ -0,0 +1,63 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::games::GameManager;
+use crate::games::hangman::Difficulty;
+use crate::games::scramble::{new_scramble_game, ScrambleOptions, DEFAULT_ROUNDS, MAX_ROUNDS};
+use super::{cmd_game, send_leaderboard, start_game};
+
+const TOP_SIZE: usize = 10;
+
+/// `!scramble start` and `!scramble top`; everything else is handled like any other game.
+pub async fn cmd_scramble(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    match msg.args.first().map(String::as_str) {
+        Some("start") => scramble_start(client, msg, db, games, settings).await,
+        Some("top") => scramble_top(client, msg, db),
+        _ => cmd_game(client, msg, db, games, settings).await,
+    }
+}
+
+/// `!scramble start [rounds] [category] [easy|hard]`, in any order. Words
+/// come from the hangman word lists.
+async fn scramble_start(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    let mut rounds = None;
+    let mut category = None;
+    let mut difficulty = None;
+    for arg in &msg.args[1..] {
+        let repeated = if let Ok(n) = arg.parse::<usize>() {
+            rounds.replace(n).is_some()
+        } else if let Some(d) = Difficulty::parse(arg) {
+            difficulty.replace(d).is_some()
+        } else {
+            category.replace(arg.to_lowercase()).is_some()
+        };
+        if repeated {
+            client.send_privmsg(&msg.channel, "Usage: !scramble start [rounds] [category] [easy|hard]")?;
+            return Ok(());
+        }
+    }
+    let rounds = rounds.unwrap_or(DEFAULT_ROUNDS);
+    if !(1..=MAX_ROUNDS).contains(&rounds) {
+        client.send_privmsg(&msg.channel, format!("Rounds must be between 1 and {}", MAX_ROUNDS))?;
+        return Ok(());
+    }
+
+    match new_scramble_game(db, settings, &ScrambleOptions { rounds, category, difficulty }) {
+        Ok(game) => start_game(client, msg, db, games, Box::new(game)).await,
+        Err(e) => client.send_privmsg(&msg.channel, e),
+    }
+}
+
+fn scramble_top(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    let channel = match msg.args.get(1).map(String::as_str) {
+        Some("global") => None,
+        Some("channel") | None => msg.channel.starts_with('#').then_some(msg.channel.as_str()),
+        Some(_) => {
+            client.send_privmsg(&msg.channel, "Usage: !scramble top [channel|global]")?;
+            return Ok(());
+        }
+    };
+    let scope = channel.unwrap_or("everywhere");
+    send_leaderboard(client, msg, "Scramble", scope, db.scramble_leaderboard(channel, TOP_SIZE))
+}
@@ -0,0 +1,172 @@
+use chrono::{DateTime, Utc};
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::{Database, TournamentStatus};
+use crate::games::GameManager;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can};
+use crate::roles::GAMES_MANAGE;
+use crate::tournament::{self, KINDS, MAX_ROUNDS};
+
+const USAGE: &str = "Usage: !tournament create <hangman|trivia|scramble> <rounds> <now|30m|20:00|fri 20:00> | join | start | cancel | standings";
+
+/// `!tournament create|join|start|cancel|standings`. Rounds are started and
+/// scored by the scheduler; this only sets tournaments up.
+pub async fn cmd_tournament(client: &Client, msg: &ParsedMessage, db: &Database, game_manager: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    match msg.args.first().map(String::as_str) {
+        Some("join") => join(client, msg, db),
+        Some("standings") | None => standings(client, msg, db),
+        Some(action @ ("create" | "start" | "cancel")) => {
+            let Some(username) = &msg.author else {
+                return client.send_privmsg(&msg.channel, "You must be logged in to use this command");
+            };
+            if !can(db, username, GAMES_MANAGE) {
+                client.send_privmsg(&msg.channel, "Permission denied. Only admins can manage tournaments")?;
+                return audit(client, db, settings, msg, "denied");
+            }
+            if !msg.channel.starts_with('#') {
+                return client.send_privmsg(&msg.channel, "Tournaments are run in a channel");
+            }
+            let result = match action {
+                "create" => create(client, msg, db),
+                "start" => start(client, msg, db),
+                _ => cancel(client, msg, db, game_manager).await,
+            };
+            match result {
+                Ok(outcome) => audit(client, db, settings, msg, outcome),
+                Err(e) => {
+                    client.send_privmsg(&msg.channel, format!("Error updating tournament: {}", e))?;
+                    audit(client, db, settings, msg, &format!("error: {}", e))
+                }
+            }
+        }
+        Some(_) => client.send_privmsg(&msg.channel, USAGE),
+    }
+}
+
+/// What an admin action did, for the audit log.
+type Outcome = Result<&'static str, String>;
+
+fn create(client: &Client, msg: &ParsedMessage, db: &Database) -> Outcome {
+    let send = |text: &str| client.send_privmsg(&msg.channel, text).map_err(|e| e.to_string());
+    let (Some(kind), Some(rounds)) = (msg.args.get(1), msg.args.get(2)) else {
+        send(USAGE)?;
+        return Ok("usage");
+    };
+    if !KINDS.contains(&kind.as_str()) {
+        send(&format!("Tournaments can be played in {}", KINDS.join(", ")))?;
+        return Ok("invalid kind");
+    }
+    let Some(rounds) = rounds.parse::<u32>().ok().filter(|n| (1..=MAX_ROUNDS).contains(n)) else {
+        send(&format!("A tournament has 1 to {} rounds", MAX_ROUNDS))?;
+        return Ok("invalid rounds");
+    };
+    let Some(starts_at) = tournament::parse_when(&msg.args[3..], Utc::now()) else {
+        send("Give the start as now, a delay such as 30m, a UTC time such as 20:00, or a day and time such as fri 20:00")?;
+        return Ok("invalid time");
+    };
+    if let Some(active) = db.active_tournament(&msg.channel).map_err(|e| e.to_string())? {
+        send(&format!("There is already a {} tournament in this channel. Cancel it first with !tournament cancel", active.kind))?;
+        return Ok("already active");
+    }
+
+    db.create_tournament(&msg.channel, kind, rounds, starts_at.timestamp()).map_err(|e| e.to_string())?;
+    send(&format!(
+        "🏁 A {}-round {} tournament starts {}. Sign up with !tournament join",
+        rounds, kind, when(starts_at.timestamp())
+    ))?;
+    Ok("ok")
+}
+
+fn start(client: &Client, msg: &ParsedMessage, db: &Database) -> Outcome {
+    let send = |text: &str| client.send_privmsg(&msg.channel, text).map_err(|e| e.to_string());
+    match db.active_tournament(&msg.channel).map_err(|e| e.to_string())? {
+        Some(active) if active.status == TournamentStatus::Scheduled => {
+            db.reschedule_tournament(active.id, Utc::now().timestamp()).map_err(|e| e.to_string())?;
+            send(&format!("🏁 The {} tournament is starting now", active.kind))?;
+            Ok("ok")
+        }
+        Some(active) => {
+            send(&format!("The {} tournament is already running (round {}/{})", active.kind, active.round, active.rounds))?;
+            Ok("already running")
+        }
+        None => {
+            send("There is no tournament scheduled in this channel")?;
+            Ok("none")
+        }
+    }
+}
+
+async fn cancel(client: &Client, msg: &ParsedMessage, db: &Database, game_manager: &GameManager) -> Outcome {
+    let send = |text: &str| client.send_privmsg(&msg.channel, text).map_err(|e| e.to_string());
+    let Some(active) = db.active_tournament(&msg.channel).map_err(|e| e.to_string())? else {
+        send("There is no tournament in this channel")?;
+        return Ok("none");
+    };
+    db.cancel_tournament(active.id).map_err(|e| e.to_string())?;
+    // A round in play carries on as an ordinary game
+    game_manager.release_tournament(db, active.id).await;
+    send(&format!("🏁 The {} tournament has been cancelled", active.kind))?;
+    Ok("ok")
+}
+
+fn join(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        return client.send_privmsg(&msg.channel, "You must be logged in to use this command");
+    };
+    let active = match db.active_tournament(&msg.channel) {
+        Ok(Some(active)) => active,
+        Ok(None) => return client.send_privmsg(&msg.channel, "There is no tournament to join in this channel"),
+        Err(e) => return client.send_privmsg(&msg.channel, format!("Error loading tournament: {}", e)),
+    };
+    match db.join_tournament(active.id, username) {
+        Ok(true) => {
+            let players = db.tournament_players(active.id).map(|p| p.len()).unwrap_or(0);
+            client.send_privmsg(&msg.channel, format!(
+                "{} joined the {} tournament ({} players)", username, active.kind, players
+            ))
+        }
+        Ok(false) => client.send_privmsg(&msg.channel, format!("{}, you're already signed up", username)),
+        Err(e) => client.send_privmsg(&msg.channel, format!("Error joining tournament: {}", e)),
+    }
+}
+
+fn standings(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    let reply = match db.active_tournament(&msg.channel) {
+        Ok(Some(active)) if active.status == TournamentStatus::Scheduled => {
+            db.tournament_players(active.id).map(|players| {
+                let signed_up = if players.is_empty() { "nobody yet".to_string() } else { players.join(", ") };
+                format!(
+                    "The {}-round {} tournament starts {}. Signed up: {}",
+                    active.rounds, active.kind, when(active.starts_at), signed_up
+                )
+            })
+        }
+        Ok(Some(active)) => {
+            let table = tournament::results_table(&active.kind).unwrap_or_default();
+            db.tournament_scores(&active, table).map(|scores| {
+                tournament::standings(&format!("📊 {} tournament, round {}/{}", active.kind, active.round, active.rounds), &scores)
+            })
+        }
+        Ok(None) => match db.last_finished_tournament(&msg.channel) {
+            Ok(Some(last)) => db.tournament_results(last.id).map(|results| {
+                tournament::standings(&format!("🏆 Last {} tournament", last.kind), &results)
+            }),
+            Ok(None) => Ok("No tournaments have been played in this channel".to_string()),
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    match reply {
+        Ok(reply) => client.send_privmsg(&msg.channel, &reply),
+        Err(e) => client.send_privmsg(&msg.channel, format!("Error loading standings: {}", e)),
+    }
+}
+
+/// "at 20:00 UTC on Fri 17 Nov", or "now" once the time has come.
+fn when(at: i64) -> String {
+    match DateTime::<Utc>::from_timestamp(at, 0) {
+        Some(time) if time > Utc::now() => time.format("at %H:%M UTC on %a %d %b").to_string(),
+        _ => "now".to_string(),
+    }
+}
@@ -0,0 +1,58 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::games::GameManager;
+use crate::games::trivia::{new_trivia_game, TriviaOptions, DEFAULT_ROUNDS, MAX_ROUNDS};
+use super::{cmd_game, send_leaderboard, start_game};
+
+const TOP_SIZE: usize = 10;
+
+/// `!trivia start` and `!trivia top`; everything else is handled like any other game.
+pub async fn cmd_trivia(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    match msg.args.first().map(String::as_str) {
+        Some("start") => trivia_start(client, msg, db, games, settings).await,
+        Some("top") => trivia_top(client, msg, db),
+        _ => cmd_game(client, msg, db, games, settings).await,
+    }
+}
+
+/// `!trivia start [rounds] [category]`, in either order.
+async fn trivia_start(client: &Client, msg: &ParsedMessage, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+    let mut rounds = None;
+    let mut category = None;
+    for arg in &msg.args[1..] {
+        match arg.parse::<usize>() {
+            Ok(n) if rounds.is_none() => rounds = Some(n),
+            Err(_) if category.is_none() => category = Some(arg.to_lowercase()),
+            _ => {
+                client.send_privmsg(&msg.channel, "Usage: !trivia start [rounds] [category]")?;
+                return Ok(());
+            }
+        }
+    }
+    let rounds = rounds.unwrap_or(DEFAULT_ROUNDS);
+    if !(1..=MAX_ROUNDS).contains(&rounds) {
+        client.send_privmsg(&msg.channel, format!("Rounds must be between 1 and {}", MAX_ROUNDS))?;
+        return Ok(());
+    }
+
+    match new_trivia_game(settings, &TriviaOptions { rounds, category }) {
+        Ok(game) => start_game(client, msg, db, games, Box::new(game)).await,
+        Err(e) => client.send_privmsg(&msg.channel, e),
+    }
+}
+
+fn trivia_top(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    let channel = match msg.args.get(1).map(String::as_str) {
+        Some("global") => None,
+        Some("channel") | None => msg.channel.starts_with('#').then_some(msg.channel.as_str()),
+        Some(_) => {
+            client.send_privmsg(&msg.channel, "Usage: !trivia top [channel|global]")?;
+            return Ok(());
+        }
+    };
+
+    let scope = channel.unwrap_or("everywhere");
+    send_leaderboard(client, msg, "Trivia", scope, db.trivia_leaderboard(channel, TOP_SIZE))
+}
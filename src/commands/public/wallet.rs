@@ -0,0 +1,148 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, format_remaining};
+use crate::achievements::{Event, EventBus};
+use super::send_leaderboard;
+
+const TOP_SIZE: usize = 10;
+/// Ledger entries shown by `!balance history`.
+const HISTORY_SIZE: usize = 5;
+const DAY: i64 = 24 * 60 * 60;
+
+/// `!balance [nick]`, `!balance top` and `!balance history`.
+pub fn cmd_balance(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    match msg.args.first().map(String::as_str) {
+        Some("top") => send_top(client, msg, db),
+        Some("history") => history(client, msg, db),
+        nick => send_balance(client, msg, db, nick),
+    }
+}
+
+/// Shows the wallet of `nick`, or of the sender when none is given.
+pub fn send_balance(client: &Client, msg: &ParsedMessage, db: &Database, nick: Option<&str>) -> irc::error::Result<()> {
+    let Some(nick) = nick.or(msg.author.as_deref()) else {
+        return Ok(());
+    };
+    match db.chip_balance(nick) {
+        Ok(balance) => client.send_privmsg(&msg.channel, format!("💰 {} has {} chips", nick, balance)),
+        Err(e) => client.send_privmsg(&msg.channel, format!("Error loading chips: {}", e)),
+    }
+}
+
+/// The biggest wallets.
+pub fn send_top(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    send_leaderboard(client, msg, "Chips", "everywhere", db.chips_leaderboard(TOP_SIZE))
+}
+
+fn history(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        return client.send_privmsg(&msg.channel, "You must be logged in to use this command");
+    };
+    match db.chip_ledger(username, HISTORY_SIZE) {
+        Ok(entries) if entries.is_empty() => {
+            client.send_privmsg(&msg.channel, format!("{} has no chip history yet", username))
+        }
+        Ok(entries) => {
+            let entries: Vec<String> = entries.iter()
+                .map(|(amount, balance, reason, _)| format!("{:+} {} ({})", amount, reason, balance))
+                .collect();
+            client.send_privmsg(&msg.channel, format!("📒 {}: {}", username, entries.join(", ")))
+        }
+        Err(e) => client.send_privmsg(&msg.channel, format!("Error loading chip history: {}", e)),
+    }
+}
+
+/// `!give <nick> <amount>`: moves chips from the sender's wallet to another's.
+pub fn cmd_give(client: &Client, msg: &ParsedMessage, db: &Database, events: &EventBus, settings: &Settings) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        return client.send_privmsg(&msg.channel, "You must be logged in to use this command");
+    };
+    let (Some(target), Some(amount)) = (msg.args.first(), msg.args.get(1)) else {
+        client.send_privmsg(&msg.channel, "Usage: !give <nick> <amount>")?;
+        return audit(client, db, settings, msg, "usage");
+    };
+    let Some(amount) = amount.parse::<i64>().ok().filter(|n| *n > 0) else {
+        client.send_privmsg(&msg.channel, "The amount must be a whole number of chips above 0")?;
+        return audit(client, db, settings, msg, "invalid amount");
+    };
+    if target.eq_ignore_ascii_case(username) {
+        client.send_privmsg(&msg.channel, "You can't give chips to yourself")?;
+        return audit(client, db, settings, msg, "self");
+    }
+    // Gifts only come from wallets that have been in use for a while
+    let now = chrono::Utc::now().timestamp();
+    let min_age = settings.give_min_wallet_age.as_secs() as i64;
+    match db.wallet_opened_at(username) {
+        Ok(Some(opened)) if now - opened >= min_age => {}
+        Ok(Some(opened)) => {
+            client.send_privmsg(&msg.channel, format!(
+                "{}, new wallets can't give chips yet. Yours can in {}", username, format_remaining(opened + min_age - now)
+            ))?;
+            return audit(client, db, settings, msg, "new wallet");
+        }
+        Ok(None) => {
+            client.send_privmsg(&msg.channel, format!("{}, you don't have {} chips", username, amount))?;
+            return audit(client, db, settings, msg, "insufficient chips");
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error giving chips: {}", e))?;
+            return audit(client, db, settings, msg, &format!("error: {}", e));
+        }
+    }
+    // Gifts only go to players already in the game, not to any nick typed
+    match db.has_wallet(target) {
+        Ok(true) => {}
+        Ok(false) => {
+            client.send_privmsg(&msg.channel, format!("{} has no wallet yet. They get one by playing or with !daily", target))?;
+            return audit(client, db, settings, msg, "no wallet");
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error giving chips: {}", e))?;
+            return audit(client, db, settings, msg, &format!("error: {}", e));
+        }
+    }
+
+    match db.transfer_chips(username, target, amount) {
+        Ok(Some((from_balance, to_balance))) => {
+            client.send_privmsg(&msg.channel, format!(
+                "💸 {} gave {} {} chips ({} now has {}, {} has {})",
+                username, target, amount, username, from_balance, target, to_balance
+            ))?;
+            events.publish(&msg.channel, target, Event::ChipBalance(to_balance));
+            audit(client, db, settings, msg, "ok")
+        }
+        Ok(None) => {
+            client.send_privmsg(&msg.channel, format!("{}, you don't have {} chips", username, amount))?;
+            audit(client, db, settings, msg, "insufficient chips")
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error giving chips: {}", e))?;
+            audit(client, db, settings, msg, &format!("error: {}", e))
+        }
+    }
+}
+
+/// `!daily`: pays `Settings::daily_chips` once per UTC day.
+pub fn cmd_daily(client: &Client, msg: &ParsedMessage, db: &Database, events: &EventBus, settings: &Settings) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        return client.send_privmsg(&msg.channel, "You must be logged in to use this command");
+    };
+    let now = chrono::Utc::now().timestamp();
+    match db.claim_daily_chips(username, settings.daily_chips, now) {
+        Ok(Some(balance)) => {
+            events.publish(&msg.channel, username, Event::ChipBalance(balance));
+            client.send_privmsg(&msg.channel, format!(
+                "🎁 {} claims {} chips (balance {})", username, settings.daily_chips, balance
+            ))
+        }
+        Ok(None) => {
+            let minutes = (DAY - now % DAY + 59) / 60;
+            client.send_privmsg(&msg.channel, format!(
+                "{}, you've had today's chips. Come back in {}h {}m", username, minutes / 60, minutes % 60
+            ))
+        }
+        Err(e) => client.send_privmsg(&msg.channel, format!("Error claiming chips: {}", e)),
+    }
+}
@@ -1,23 +1,29 @@
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
 use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::audit;
 
-pub fn cmd_del(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+pub fn cmd_del(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
     if let Some(username) = &msg.author {
         if msg.args.is_empty() {
             client.send_privmsg(&msg.channel, "Usage: !del <key>")?;
+            audit(client, db, settings, msg, "usage")?;
         } else {
             let key = &msg.args[0];
             
             match db.delete_user_data(username, key) {
                 Ok(true) => {
-                    client.send_privmsg(&msg.channel, &format!("Deleted: {}", key))?;
+                    client.send_privmsg(&msg.channel, format!("Deleted: {}", key))?;
+                    audit(client, db, settings, msg, "ok")?;
                 }
                 Ok(false) => {
-                    client.send_privmsg(&msg.channel, &format!("Key not found: {}", key))?;
+                    client.send_privmsg(&msg.channel, format!("Key not found: {}", key))?;
+                    audit(client, db, settings, msg, "not found")?;
                 }
                 Err(e) => {
-                    client.send_privmsg(&msg.channel, &format!("Error deleting data: {}", e))?;
+                    client.send_privmsg(&msg.channel, format!("Error deleting data: {}", e))?;
+                    audit(client, db, settings, msg, &format!("error: {}", e))?;
                 }
             }
         }
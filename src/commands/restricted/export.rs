@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use irc::client::prelude::Client;
+use serde_json::json;
+use crate::message::ParsedMessage;
+use crate::database::{Database, table_rows};
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can, pack_lines, safe_file_name, MAX_LINE};
+use crate::roles::DATA_EXPORT_ALL;
+
+pub fn cmd_export(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+        return Ok(());
+    };
+
+    let result = match msg.args.first().map(String::as_str) {
+        None => export_own(client, msg, db, username)?,
+        Some("all") => {
+            if !can(db, username, DATA_EXPORT_ALL) {
+                client.send_privmsg(&msg.channel, "Permission denied. Only admins can export the database")?;
+                return audit(client, db, settings, msg, "denied");
+            }
+            export_all(client, msg, db, settings)?
+        }
+        Some(_) => {
+            client.send_privmsg(&msg.channel, "Usage: !export (PMs your keys) or !export all [file]")?;
+            "usage".to_string()
+        }
+    };
+    audit(client, db, settings, msg, &result)
+}
+
+/// Sends the user's own keys by private message as JSON objects of keys and
+/// values, as many keys to a line as fit.
+fn export_own(client: &Client, msg: &ParsedMessage, db: &Database, username: &str) -> irc::error::Result<String> {
+    match db.list_user_data(username) {
+        Ok(data) if data.is_empty() => {
+            client.send_privmsg(&msg.channel, "No stored data")?;
+            Ok("0 keys".to_string())
+        }
+        Ok(data) => {
+            let entries = data.iter().map(|(key, value)| format!("{}:{}", json!(key), json!(value)));
+            for line in pack_lines(entries, ",", MAX_LINE - 2) {
+                client.send_privmsg(username, format!("{{{}}}", line))?;
+            }
+            client.send_privmsg(&msg.channel, format!("Sent {} keys to {} by private message", data.len(), username))?;
+            Ok(format!("{} keys", data.len()))
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error exporting data: {}", e))?;
+            Ok(format!("error: {}", e))
+        }
+    }
+}
+
+/// Writes a full export into the configured export directory.
+fn export_all(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<String> {
+    let default_name = format!(
+        "export-{}.json",
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+    );
+    let Some(name) = safe_file_name(msg.args.get(1).map_or(default_name.as_str(), String::as_str)) else {
+        client.send_privmsg(&msg.channel, "File names may only contain letters, digits, '.', '_' and '-'")?;
+        return Ok("invalid file name".to_string());
+    };
+
+    let path = Path::new(&settings.export_dir).join(name);
+    let written = db.export_json()
+        .map_err(|e| e.to_string())
+        .and_then(|doc| {
+            let text = serde_json::to_string_pretty(&doc).map_err(|e| e.to_string())?;
+            fs::create_dir_all(&settings.export_dir).map_err(|e| e.to_string())?;
+            fs::write(&path, text).map_err(|e| e.to_string())?;
+            Ok(doc)
+        });
+
+    match written {
+        Ok(doc) => {
+            let summary = table_rows(&doc)
+                .into_iter()
+                .map(|(table, rows)| format!("{} {}", rows, table))
+                .collect::<Vec<_>>()
+                .join(", ");
+            client.send_privmsg(&msg.channel, format!("Exported {} to {}", summary, path.display()))?;
+            Ok(format!("wrote {}", path.display()))
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error exporting database: {}", e))?;
+            Ok(format!("error: {}", e))
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can};
+use crate::roles::DATA_SEARCH_ALL;
+
+const MAX_RESULTS: usize = 10;
+
+pub fn cmd_find(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    if let Some(username) = &msg.author {
+        // Admins may search everyone's data with --all
+        let all_users = msg.args.first().is_some_and(|arg| arg == "--all");
+        let words = if all_users { &msg.args[1..] } else { &msg.args[..] };
+
+        if words.is_empty() {
+            client.send_privmsg(&msg.channel, "Usage: !find [--all] <text>")?;
+            return audit(client, db, settings, msg, "usage");
+        }
+
+        if all_users && !can(db, username, DATA_SEARCH_ALL) {
+            client.send_privmsg(&msg.channel, "Permission denied. Only admins can search all users")?;
+            return audit(client, db, settings, msg, "denied");
+        }
+
+        let owner = if all_users { None } else { Some(username.as_str()) };
+        match db.search_user_data(owner, &words.join(" "), MAX_RESULTS) {
+            Ok((_, 0)) => {
+                client.send_privmsg(&msg.channel, "No matches found")?;
+                audit(client, db, settings, msg, "0 matches")?;
+            }
+            Ok((results, total)) => {
+                for (user, key, value) in results {
+                    if all_users {
+                        client.send_privmsg(&msg.channel, format!("{}/{}: {}", user, key, value))?;
+                    } else {
+                        client.send_privmsg(&msg.channel, format!("{}: {}", key, value))?;
+                    }
+                }
+                if total > MAX_RESULTS {
+                    client.send_privmsg(&msg.channel, format!("... and {} more matches", total - MAX_RESULTS))?;
+                }
+                audit(client, db, settings, msg, &format!("{} matches", total))?;
+            }
+            Err(e) => {
+                client.send_privmsg(&msg.channel, format!("Error searching data: {}", e))?;
+                audit(client, db, settings, msg, &format!("error: {}", e))?;
+            }
+        }
+    } else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+    }
+    Ok(())
+}
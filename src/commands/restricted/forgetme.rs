@@ -0,0 +1,42 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, forget_summary, guard_own_removal, stored_row_count};
+
+pub fn cmd_forgetme(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+        return Ok(());
+    };
+
+    if msg.args.first().map(String::as_str) != Some("confirm") {
+        match stored_row_count(db, username) {
+            Ok(0) => client.send_privmsg(&msg.channel, "Nothing is stored about you")?,
+            Ok(rows) => client.send_privmsg(&msg.channel, format!(
+                "This permanently deletes all {} records stored about you, including permissions. Type !forgetme confirm to proceed",
+                rows
+            ))?,
+            Err(e) => client.send_privmsg(&msg.channel, format!("Error checking stored data: {}", e))?,
+        }
+        return audit(client, db, settings, msg, "confirmation requested");
+    }
+
+    // Forgetting yourself removes your grant, so the last owner can't
+    if !guard_own_removal(client, db, settings, msg)? {
+        return Ok(());
+    }
+
+    match db.forget_user(username) {
+        Ok(removed) => {
+            let summary = forget_summary(&removed);
+            client.send_privmsg(&msg.channel, format!("{}, your data has been deleted ({})", username, summary))?;
+            audit(client, db, settings, msg, &format!("deleted {}", summary))?;
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error deleting data: {}", e))?;
+            audit(client, db, settings, msg, &format!("error: {}", e))?;
+        }
+    }
+    Ok(())
+}
@@ -1,23 +1,29 @@
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
 use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::audit;
 
-pub fn cmd_get(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+pub fn cmd_get(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
     if let Some(username) = &msg.author {
         if msg.args.is_empty() {
             client.send_privmsg(&msg.channel, "Usage: !get <key>")?;
+            audit(client, db, settings, msg, "usage")?;
         } else {
             let key = &msg.args[0];
             
             match db.get_user_data(username, key) {
                 Ok(Some(value)) => {
-                    client.send_privmsg(&msg.channel, &format!("{} = {}", key, value))?;
+                    client.send_privmsg(&msg.channel, format!("{} = {}", key, value))?;
+                    audit(client, db, settings, msg, "ok")?;
                 }
                 Ok(None) => {
-                    client.send_privmsg(&msg.channel, &format!("Key not found: {}", key))?;
+                    client.send_privmsg(&msg.channel, format!("Key not found: {}", key))?;
+                    audit(client, db, settings, msg, "not found")?;
                 }
                 Err(e) => {
-                    client.send_privmsg(&msg.channel, &format!("Error retrieving data: {}", e))?;
+                    client.send_privmsg(&msg.channel, format!("Error retrieving data: {}", e))?;
+                    audit(client, db, settings, msg, &format!("error: {}", e))?;
                 }
             }
         }
@@ -1,13 +1,15 @@
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
 use crate::database::Database;
-use crate::commands::utils::has_permission;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can};
+use crate::roles::CHANNEL_JOIN;
 
-pub fn cmd_join(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+pub fn cmd_join(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
     if let Some(username) = &msg.author {
-        if !has_permission(db, username) {
+        if !can(db, username, CHANNEL_JOIN) {
             client.send_privmsg(&msg.channel, "Permission denied. You don't have permission to use this command")?;
-            return Ok(());
+            return audit(client, db, settings, msg, "denied");
         }
     } else {
         client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
@@ -16,13 +18,16 @@ pub fn cmd_join(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::err
 
     if msg.args.is_empty() {
         client.send_privmsg(&msg.channel, "Usage: !join <#channel>")?;
+        audit(client, db, settings, msg, "usage")?;
     } else {
         let channel = &msg.args[0];
         if !channel.starts_with('#') {
             client.send_privmsg(&msg.channel, "Channel name must start with #")?;
+            audit(client, db, settings, msg, "invalid channel")?;
         } else {
             client.send_join(channel)?;
-            client.send_privmsg(&msg.channel, &format!("Joining {}", channel))?;
+            client.send_privmsg(&msg.channel, format!("Joining {}", channel))?;
+            audit(client, db, settings, msg, "ok")?;
         }
     }
     Ok(())
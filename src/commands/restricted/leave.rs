@@ -1,13 +1,15 @@
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
 use crate::database::Database;
-use crate::commands::utils::has_permission;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can};
+use crate::roles::CHANNEL_JOIN;
 
-pub fn cmd_leave(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+pub fn cmd_leave(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
     if let Some(username) = &msg.author {
-        if !has_permission(db, username) {
+        if !can(db, username, CHANNEL_JOIN) {
             client.send_privmsg(&msg.channel, "Permission denied. You don't have permission to use this command")?;
-            return Ok(());
+            return audit(client, db, settings, msg, "denied");
         }
     } else {
         client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
@@ -21,5 +23,5 @@ pub fn cmd_leave(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::er
     };
     
     client.send_part(&channel)?;
-    Ok(())
+    audit(client, db, settings, msg, &format!("parted {}", channel))
 }
@@ -1,21 +1,53 @@
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
 use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::audit;
 
-pub fn cmd_list(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+const PAGE_SIZE: usize = 10;
+
+pub fn cmd_list(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
     if let Some(username) = &msg.author {
-        match db.list_user_data(username) {
-            Ok(data) => {
+        // A lone number is a page; otherwise the first argument is a key pattern
+        let (pattern, page_arg) = match msg.args.as_slice() {
+            [] => (None, None),
+            [only] if only.parse::<usize>().is_ok() => (None, Some(only)),
+            [pattern] => (Some(pattern.as_str()), None),
+            [pattern, page, ..] => (Some(pattern.as_str()), Some(page)),
+        };
+
+        let page = match page_arg.map(|p| p.parse::<usize>()) {
+            None => 1,
+            Some(Ok(page)) if page > 0 => page,
+            Some(_) => {
+                client.send_privmsg(&msg.channel, "Usage: !list [pattern] [page]")?;
+                return audit(client, db, settings, msg, "usage");
+            }
+        };
+
+        match db.list_user_data_page(username, pattern, PAGE_SIZE, (page - 1) * PAGE_SIZE) {
+            Ok((_, 0)) => {
+                match pattern {
+                    Some(pattern) => client.send_privmsg(&msg.channel, format!("No keys matching '{}'", pattern))?,
+                    None => client.send_privmsg(&msg.channel, "No stored data")?,
+                }
+                audit(client, db, settings, msg, "0 keys")?;
+            }
+            Ok((data, total)) => {
+                let pages = total.div_ceil(PAGE_SIZE);
                 if data.is_empty() {
-                    client.send_privmsg(&msg.channel, "No stored data")?;
+                    client.send_privmsg(&msg.channel, format!("Page {} is out of range (1-{})", page, pages))?;
                 } else {
+                    client.send_privmsg(&msg.channel, format!("Page {}/{} ({} keys)", page, pages, total))?;
                     for (key, value) in data {
-                        client.send_privmsg(&msg.channel, &format!("{}: {}", key, value))?;
+                        client.send_privmsg(&msg.channel, format!("{}: {}", key, value))?;
                     }
                 }
+                audit(client, db, settings, msg, &format!("{} keys", total))?;
             }
             Err(e) => {
-                client.send_privmsg(&msg.channel, &format!("Error listing data: {}", e))?;
+                client.send_privmsg(&msg.channel, format!("Error listing data: {}", e))?;
+                audit(client, db, settings, msg, &format!("error: {}", e))?;
             }
         }
     } else {
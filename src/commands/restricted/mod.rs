@@ -4,6 +4,11 @@ mod set;
 mod get;
 mod del;
 mod list;
+mod find;
+mod quota;
+mod export;
+mod mydata;
+mod forgetme;
 
 pub use join::cmd_join;
 pub use leave::cmd_leave;
@@ -11,3 +16,8 @@ pub use set::cmd_set;
 pub use get::cmd_get;
 pub use del::cmd_del;
 pub use list::cmd_list;
+pub use find::cmd_find;
+pub use quota::cmd_quota;
+pub use export::cmd_export;
+pub use mydata::cmd_mydata;
+pub use forgetme::cmd_forgetme;
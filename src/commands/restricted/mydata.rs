@@ -0,0 +1,52 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::commands::utils::{audit, can};
+use crate::roles::DATA_INSPECT;
+
+pub fn cmd_mydata(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    let Some(username) = &msg.author else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+        return Ok(());
+    };
+
+    // Admins can request the report for someone else; it still goes to the admin
+    let subject = match msg.args.first() {
+        Some(other) if other != username => {
+            if !can(db, username, DATA_INSPECT) {
+                client.send_privmsg(&msg.channel, "Permission denied. Only admins can view other users' data")?;
+                return audit(client, db, settings, msg, "denied");
+            }
+            other.as_str()
+        }
+        _ => username.as_str(),
+    };
+
+    let result = match db.user_report(subject) {
+        Ok(report) if report.is_empty() => {
+            client.send_privmsg(username, format!("Nothing is stored about {}", subject))?;
+            "0 rows".to_string()
+        }
+        Ok(report) => {
+            client.send_privmsg(username, format!("Everything stored about {}:", subject))?;
+            let mut count = 0;
+            for (table, rows) in report {
+                for row in rows {
+                    client.send_privmsg(username, format!("  {}: {}", table, row))?;
+                    count += 1;
+                }
+            }
+            format!("{} rows", count)
+        }
+        Err(e) => {
+            client.send_privmsg(username, format!("Error building data report: {}", e))?;
+            format!("error: {}", e)
+        }
+    };
+
+    if msg.channel != *username {
+        client.send_privmsg(&msg.channel, format!("Sent the data report to {} by private message", username))?;
+    }
+    audit(client, db, settings, msg, &result)
+}
@@ -0,0 +1,35 @@
+use irc::client::prelude::Client;
+use crate::message::ParsedMessage;
+use crate::database::Database;
+use crate::settings::Settings;
+use crate::quota;
+use crate::commands::utils::audit;
+
+pub fn cmd_quota(client: &Client, msg: &ParsedMessage, db: &Database, settings: &Settings) -> irc::error::Result<()> {
+    if let Some(username) = &msg.author {
+        let usage = db.get_permission_level(username)
+            .and_then(|level| Ok((level, db.user_data_usage(username)?)));
+
+        match usage {
+            Ok((level, (keys, bytes))) => match quota::tier_for(&settings.quotas, level) {
+                Some(tier) => {
+                    client.send_privmsg(&msg.channel, format!(
+                        "{}: {}/{} keys, {}/{} bytes used (keys up to {} chars, values up to {} chars)",
+                        username, keys, tier.max_keys, bytes, tier.max_total_bytes, tier.max_key_len, tier.max_value_len
+                    ))?;
+                }
+                None => {
+                    client.send_privmsg(&msg.channel, format!("{}: {} keys, {} bytes used (no quota)", username, keys, bytes))?;
+                }
+            },
+            Err(e) => {
+                client.send_privmsg(&msg.channel, format!("Error checking quota: {}", e))?;
+                return audit(client, db, settings, msg, &format!("error: {}", e));
+            }
+        }
+        audit(client, db, settings, msg, "ok")?;
+    } else {
+        client.send_privmsg(&msg.channel, "You must be logged in to use this command")?;
+    }
+    Ok(())
+}
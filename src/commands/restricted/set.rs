@@ -1,21 +1,35 @@
 use irc::client::prelude::Client;
 use crate::message::ParsedMessage;
 use crate::database::Database;
+use crate::achievements::{Event, EventBus};
+use crate::settings::Settings;
+use crate::commands::utils::{audit, check_quota};
 
-pub fn cmd_set(client: &Client, msg: &ParsedMessage, db: &Database) -> irc::error::Result<()> {
+pub fn cmd_set(client: &Client, msg: &ParsedMessage, db: &Database, events: &EventBus, settings: &Settings) -> irc::error::Result<()> {
     if let Some(username) = &msg.author {
         if msg.args.len() < 2 {
             client.send_privmsg(&msg.channel, "Usage: !set <key> <value>")?;
+            audit(client, db, settings, msg, "usage")?;
         } else {
             let key = &msg.args[0];
             let value = msg.args[1..].join(" ");
+
+            if let Err(e) = check_quota(db, settings, username, key, &value) {
+                client.send_privmsg(&msg.channel, &e)?;
+                return audit(client, db, settings, msg, &e);
+            }
             
             match db.set_user_data(username, key, &value) {
                 Ok(_) => {
-                    client.send_privmsg(&msg.channel, &format!("Saved: {} = {}", key, value))?;
+                    client.send_privmsg(&msg.channel, format!("Saved: {} = {}", key, value))?;
+                    audit(client, db, settings, msg, "ok")?;
+                    if let Ok((keys, _)) = db.user_data_usage(username) {
+                        events.publish(&msg.channel, username, Event::KeysStored(keys));
+                    }
                 }
                 Err(e) => {
-                    client.send_privmsg(&msg.channel, &format!("Error saving data: {}", e))?;
+                    client.send_privmsg(&msg.channel, format!("Error saving data: {}", e))?;
+                    audit(client, db, settings, msg, &format!("error: {}", e))?;
                 }
             }
         }
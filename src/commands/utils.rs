@@ -1,88 +1,412 @@
+use std::time::Duration;
+use irc::client::prelude::Client;
 use crate::database::Database;
+use crate::message::ParsedMessage;
+use crate::quota::{self, Usage};
+use crate::roles::{check_grant, check_owner_remains, GrantError};
+use crate::settings::Settings;
 
-pub fn has_permission(db: &Database, username: &str) -> bool {
-    match db.get_permission_level(username) {
-        Ok(level) => level > 0,
-        Err(_) => false,
+/// Whether a user's role grants `capability`. See `crate::roles` for the list.
+pub fn can(db: &Database, username: &str, capability: &str) -> bool {
+    db.has_capability(username, capability).unwrap_or(false)
+}
+
+/// Applies `roles::check_grant` to the sender changing `target`'s grant to
+/// `new_level` (`None` removes it). Refusals are reported and audited;
+/// returns whether the change may go ahead.
+pub fn guard_grant_change(
+    client: &Client,
+    db: &Database,
+    settings: &Settings,
+    msg: &ParsedMessage,
+    target: &str,
+    new_level: Option<i32>,
+) -> irc::error::Result<bool> {
+    let actor = msg.author.as_deref().unwrap_or_default();
+    let checked = db.roles().and_then(|roles| {
+        let owner_level = roles.iter().map(|role| role.level).max().unwrap_or(i32::MAX);
+        Ok(check_grant(
+            &roles,
+            &settings.grant_levels,
+            db.get_permission_level(actor)?,
+            db.get_permission_level(target)?,
+            new_level,
+            db.count_permissions_at_least(owner_level)?,
+        ))
+    });
+    allowed(client, db, settings, msg, checked)
+}
+
+/// Refuses, with the same reply and audit entry as [`guard_grant_change`],
+/// when the sender removing their own grant would leave no owner. Returns
+/// whether the removal may go ahead.
+pub fn guard_own_removal(client: &Client, db: &Database, settings: &Settings, msg: &ParsedMessage) -> irc::error::Result<bool> {
+    let actor = msg.author.as_deref().unwrap_or_default();
+    let checked = db.roles().and_then(|roles| {
+        let owner_level = roles.iter().map(|role| role.level).max().unwrap_or(i32::MAX);
+        Ok(check_owner_remains(
+            &roles,
+            db.get_permission_level(actor)?,
+            None,
+            db.count_permissions_at_least(owner_level)?,
+        ))
+    });
+    allowed(client, db, settings, msg, checked)
+}
+
+/// Reports and audits a refused or failed grant check.
+fn allowed(
+    client: &Client,
+    db: &Database,
+    settings: &Settings,
+    msg: &ParsedMessage,
+    checked: Result<Result<(), GrantError>, rusqlite::Error>,
+) -> irc::error::Result<bool> {
+    match checked {
+        Ok(Ok(())) => Ok(true),
+        Ok(Err(e)) => {
+            client.send_privmsg(&msg.channel, format!("Permission denied. {}", e))?;
+            audit(client, db, settings, msg, &format!("refused: {}", e))?;
+            Ok(false)
+        }
+        Err(e) => {
+            client.send_privmsg(&msg.channel, format!("Error checking permissions: {}", e))?;
+            audit(client, db, settings, msg, &format!("error: {}", e))?;
+            Ok(false)
+        }
+    }
+}
+
+/// Records a privileged command and its outcome in the audit log, and mirrors
+/// the entry to the configured audit channel.
+pub fn audit(client: &Client, db: &Database, settings: &Settings, msg: &ParsedMessage, result: &str) -> irc::error::Result<()> {
+    let actor = msg.author.as_deref().unwrap_or("unknown");
+    let arguments = audit_arguments(msg);
+
+    if let Err(e) = db.record_audit(actor, msg.hostmask.as_deref(), &msg.command, &arguments, &msg.channel, result) {
+        eprintln!("Failed to write audit log: {}", e);
+    }
+
+    if let Some(log_channel) = &settings.audit_channel {
+        client.send_privmsg(log_channel, format!(
+            "[audit] {} ran !{} {} in {}: {}",
+            msg.hostmask.as_deref().unwrap_or(actor), msg.command, arguments, msg.channel, result
+        ))?;
+    }
+    Ok(())
+}
+
+/// Checks a write against the user's quota tier, returning the message to show on failure.
+pub fn check_quota(db: &Database, settings: &Settings, username: &str, key: &str, value: &str) -> Result<(), String> {
+    let level = db.get_permission_level(username)
+        .map_err(|e| format!("Error checking quota: {}", e))?;
+    let Some(tier) = quota::tier_for(&settings.quotas, level) else {
+        return Ok(());
+    };
+
+    let (keys, bytes) = db.user_data_usage(username)
+        .map_err(|e| format!("Error checking quota: {}", e))?;
+    let existing = db.get_user_data(username, key)
+        .map_err(|e| format!("Error checking quota: {}", e))?;
+
+    quota::check_set(tier, Usage { keys, bytes }, key, value, existing.as_deref())
+        .map_err(|e| format!("Not saved: {}", e))
+}
+
+/// Command arguments as recorded in the audit log. Stored values are left out
+/// so the log never holds data that is encrypted at rest.
+fn audit_arguments(msg: &ParsedMessage) -> String {
+    match msg.command.as_str() {
+        "set" if msg.args.len() > 1 => format!("{} <value>", msg.args[0]),
+        _ => msg.args.join(" "),
     }
 }
 
-pub fn is_admin(db: &Database, username: &str) -> bool {
-    match db.get_permission_level(username) {
-        Ok(level) => level >= 10,
-        Err(_) => false,
+/// Parses a duration such as `30s`, `15m`, `8h`, `7d` or `2w`.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    number.checked_mul(seconds).map(Duration::from_secs)
+}
+
+/// What `!audit` filters by.
+#[derive(Debug, Default, PartialEq)]
+pub struct AuditFilter<'a> {
+    pub actor: Option<&'a str>,
+    pub command: Option<&'a str>,
+    /// How far back to look.
+    pub since: Option<Duration>,
+}
+
+/// Parses `!audit` arguments: `user:<nick>`, `cmd:<command>` or `!<command>`,
+/// and a period such as `2h`. Any other word is a nick, so users named after
+/// a command can be looked up too. `None` if a filter is given twice.
+pub fn parse_audit_filter(args: &[String]) -> Option<AuditFilter<'_>> {
+    let mut filter = AuditFilter::default();
+    for arg in args {
+        let (slot, value) = if let Some(command) = arg.strip_prefix("cmd:").or_else(|| arg.strip_prefix('!')) {
+            (&mut filter.command, command)
+        } else if let Some(actor) = arg.strip_prefix("user:") {
+            (&mut filter.actor, actor)
+        } else if let Some(duration) = parse_duration(arg) {
+            if filter.since.replace(duration).is_some() {
+                return None;
+            }
+            continue;
+        } else {
+            (&mut filter.actor, arg.as_str())
+        };
+        if value.is_empty() || slot.replace(value).is_some() {
+            return None;
+        }
+    }
+    Some(filter)
+}
+
+/// Bytes of text per reply line, leaving room under IRC's 512-byte limit
+/// for the `PRIVMSG <target> :` command and the bot's relayed prefix.
+pub const MAX_LINE: usize = 400;
+
+/// Joins `items` with `separator` into as few lines of at most `max` bytes
+/// as possible. An item longer than `max` gets a line of its own.
+pub fn pack_lines<I: IntoIterator<Item = String>>(items: I, separator: &str, max: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for item in items {
+        match lines.last_mut() {
+            Some(line) if line.len() + separator.len() + item.len() <= max => {
+                line.push_str(separator);
+                line.push_str(&item);
+            }
+            _ => lines.push(item),
+        }
+    }
+    lines
+}
+
+/// `prefix` followed by `items` joined with `separator`, wrapped so every
+/// line fits in `MAX_LINE`. Only the first line carries the prefix.
+pub fn prefixed_lines<I: IntoIterator<Item = String>>(prefix: &str, items: I, separator: &str) -> Vec<String> {
+    let mut lines = pack_lines(items, separator, MAX_LINE - prefix.len());
+    if let Some(first) = lines.first_mut() {
+        first.insert_str(0, prefix);
+    }
+    lines
+}
+
+/// Formats a number of seconds as the two largest units, e.g. `7h 59m` or `2d 3h`.
+pub fn format_remaining(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Counts the rows `!forgetme` would delete for a user.
+pub fn stored_row_count(db: &Database, username: &str) -> Result<usize, rusqlite::Error> {
+    Ok(db.user_report(username)?.iter().map(|(_, rows)| rows.len()).sum())
+}
+
+/// Describes what `Database::forget_user` removed, e.g. "3 users_data, 1 permissions".
+pub fn forget_summary(removed: &[(String, usize)]) -> String {
+    if removed.is_empty() {
+        return "nothing stored".to_string();
     }
+    removed
+        .iter()
+        .map(|(table, rows)| format!("{} {}", rows, table))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Accepts a bare file name made of letters, digits, `.`, `_` and `-` so chat
+/// commands can't reach outside their directory.
+pub fn safe_file_name(name: &str) -> Option<&str> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    valid.then_some(name)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::roles::{CHANNEL_JOIN, PERMS_GRANT};
 
     fn create_test_db() -> Database {
-        Database::new(":memory:").expect("Failed to create database")
+        let db = Database::new(":memory:").expect("Failed to create database");
+        db.seed_roles(&Settings::default().roles).expect("Failed to seed roles");
+        db
     }
 
     #[test]
-    fn test_has_permission_true() {
+    fn test_can_with_role() {
         let db = create_test_db();
         db.grant_permission("user", 5).expect("Failed to grant");
         
-        assert!(has_permission(&db, "user"));
+        assert!(can(&db, "user", CHANNEL_JOIN));
     }
 
     #[test]
-    fn test_has_permission_false() {
+    fn test_can_level_zero() {
         let db = create_test_db();
         db.grant_permission("user", 0).expect("Failed to grant");
         
-        assert!(!has_permission(&db, "user"));
+        assert!(!can(&db, "user", CHANNEL_JOIN));
     }
 
     #[test]
-    fn test_has_permission_no_permission() {
+    fn test_can_no_permission() {
         let db = create_test_db();
         
-        assert!(!has_permission(&db, "nobody"));
+        assert!(!can(&db, "nobody", CHANNEL_JOIN));
     }
 
     #[test]
-    fn test_is_admin_true() {
+    fn test_can_admin_capability() {
         let db = create_test_db();
         db.grant_permission("admin", 10).expect("Failed to grant");
+        db.grant_permission("user", 5).expect("Failed to grant");
         
-        assert!(is_admin(&db, "admin"));
+        assert!(can(&db, "admin", PERMS_GRANT));
+        assert!(!can(&db, "user", PERMS_GRANT));
+        assert!(!can(&db, "nobody", PERMS_GRANT));
     }
 
     #[test]
-    fn test_is_admin_false() {
+    fn test_can_inherits_lower_roles() {
         let db = create_test_db();
-        db.grant_permission("user", 5).expect("Failed to grant");
+        db.grant_permission("owner", 15).expect("Failed to grant");
         
-        assert!(!is_admin(&db, "user"));
+        assert!(can(&db, "owner", PERMS_GRANT));
+        assert!(can(&db, "owner", CHANNEL_JOIN));
     }
 
     #[test]
-    fn test_is_admin_no_permission() {
+    fn test_can_boundary() {
         let db = create_test_db();
+        db.grant_permission("user1", 9).expect("Failed to grant");
+        db.grant_permission("user2", 10).expect("Failed to grant");
         
-        assert!(!is_admin(&db, "nobody"));
+        assert!(!can(&db, "user1", PERMS_GRANT));
+        assert!(can(&db, "user2", PERMS_GRANT));
     }
 
     #[test]
-    fn test_is_admin_high_level() {
-        let db = create_test_db();
-        db.grant_permission("superadmin", 15).expect("Failed to grant");
-        
-        assert!(is_admin(&db, "superadmin"));
+    fn test_pack_lines() {
+        let items = ["alpha", "beta", "gamma", "delta"].map(String::from);
+        assert_eq!(pack_lines(items.clone(), ", ", 11), vec!["alpha, beta", "gamma", "delta"]);
+        assert_eq!(pack_lines(items.clone(), ", ", 100), vec!["alpha, beta, gamma, delta"]);
+        assert_eq!(pack_lines(items, ", ", 3), vec!["alpha", "beta", "gamma", "delta"]);
+        assert!(pack_lines(Vec::new(), ", ", 10).is_empty());
     }
 
     #[test]
-    fn test_permission_boundary() {
+    fn test_prefixed_lines() {
+        let items = (0..100).map(|i| format!("item {}", i));
+        let lines = prefixed_lines("Items: ", items, ", ");
+        assert!(lines.len() > 1);
+        assert!(lines[0].starts_with("Items: item 0, item 1"));
+        assert!(!lines[1].starts_with("Items"));
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE));
+        assert!(prefixed_lines("Items: ", Vec::new(), ", ").is_empty());
+    }
+
+    #[test]
+    fn test_safe_file_name() {
+        assert_eq!(safe_file_name("backup-1.json"), Some("backup-1.json"));
+        assert_eq!(safe_file_name("../bot_data.db"), None);
+        assert_eq!(safe_file_name("dir/file.json"), None);
+        assert_eq!(safe_file_name(".hidden"), None);
+        assert_eq!(safe_file_name(""), None);
+    }
+
+    #[test]
+    fn test_forget_summary() {
+        assert_eq!(forget_summary(&[]), "nothing stored");
+        let removed = vec![("users_data".to_string(), 3), ("permissions".to_string(), 1)];
+        assert_eq!(forget_summary(&removed), "3 users_data, 1 permissions");
+    }
+
+    #[test]
+    fn test_stored_row_count() {
         let db = create_test_db();
-        db.grant_permission("user1", 9).expect("Failed to grant");
-        db.grant_permission("user2", 10).expect("Failed to grant");
-        
-        assert!(!is_admin(&db, "user1"));
-        assert!(is_admin(&db, "user2"));
+        db.set_user_data("user", "a", "1").expect("Failed to set");
+        db.set_user_data("user", "b", "2").expect("Failed to set");
+        db.grant_permission("user", 1).expect("Failed to grant");
+
+        assert_eq!(stored_row_count(&db, "user").unwrap(), 3);
+        assert_eq!(stored_row_count(&db, "nobody").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("15m"), Some(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_duration("8h"), Some(Duration::from_secs(8 * 3600)));
+        assert_eq!(parse_duration("7d"), Some(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse_duration("2w"), Some(Duration::from_secs(14 * 86400)));
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert_eq!(parse_duration("8"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("8x"), None);
+        assert_eq!(parse_duration("-8h"), None);
+        assert_eq!(parse_duration("8hh"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn test_parse_audit_filter() {
+        let args = |text: &str| text.split_whitespace().map(String::from).collect::<Vec<_>>();
+        let parsed = |text: &str| parse_audit_filter(&args(text)).map(|f| (f.actor.map(str::to_string), f.command.map(str::to_string), f.since));
+
+        assert_eq!(parsed(""), Some((None, None, None)));
+        // A bare word is always a nick, even one named after a command
+        assert_eq!(parsed("roll"), Some((Some("roll".to_string()), None, None)));
+        assert_eq!(parsed("cmd:roll"), Some((None, Some("roll".to_string()), None)));
+        assert_eq!(parsed("!roll 2h"), Some((None, Some("roll".to_string()), Some(Duration::from_secs(7200)))));
+        assert_eq!(parsed("user:give cmd:give"), Some((Some("give".to_string()), Some("give".to_string()), None)));
+        assert_eq!(parsed("user:2h 1d"), Some((Some("2h".to_string()), None, Some(Duration::from_secs(86400)))));
+        assert_eq!(parsed("alice bob"), None);
+        assert_eq!(parsed("cmd:set !get"), None);
+        assert_eq!(parsed("1h 2h"), None);
+        assert_eq!(parsed("user:"), None);
+    }
+
+    #[test]
+    fn test_format_remaining() {
+        assert_eq!(format_remaining(0), "0s");
+        assert_eq!(format_remaining(45), "45s");
+        assert_eq!(format_remaining(90), "1m 30s");
+        assert_eq!(format_remaining(8 * 3600 - 60), "7h 59m");
+        assert_eq!(format_remaining(2 * 86400 + 3 * 3600 + 59), "2d 3h");
+        assert_eq!(format_remaining(-5), "0s");
+    }
+
+    #[test]
+    fn test_audit_arguments_redacts_values() {
+        let set = ParsedMessage::parse("!set token hunter2 extra", Some("u".to_string()), "#ch".to_string());
+        assert_eq!(audit_arguments(&set), "token <value>");
+
+        let grant = ParsedMessage::parse("!grant bob 5", Some("u".to_string()), "#ch".to_string());
+        assert_eq!(audit_arguments(&grant), "bob 5");
     }
 }
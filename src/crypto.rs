@@ -0,0 +1,119 @@
+use std::fmt;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+const NONCE_LEN: usize = 24;
+
+/// Environment variable read for the data encryption key when none is set in `Settings`.
+pub const KEY_ENV_VAR: &str = "RUSTY_DATA_KEY";
+
+#[derive(Debug, PartialEq)]
+pub enum CryptoError {
+    InvalidKey,
+    Malformed,
+    Decrypt,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::InvalidKey => write!(f, "encryption key must be 64 hex characters"),
+            CryptoError::Malformed => write!(f, "encrypted value is malformed"),
+            CryptoError::Decrypt => write!(f, "cannot decrypt value (wrong key?)"),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+/// Authenticated encryption for stored values (XChaCha20-Poly1305 with a
+/// random nonce per value). Ciphertexts are stored as hex of `nonce || sealed`.
+#[derive(Clone)]
+pub struct Cipher {
+    aead: XChaCha20Poly1305,
+}
+
+impl Cipher {
+    pub fn from_hex(key: &str) -> Result<Self, CryptoError> {
+        let bytes = hex::decode(key.trim()).map_err(|_| CryptoError::InvalidKey)?;
+        if bytes.len() != 32 {
+            return Err(CryptoError::InvalidKey);
+        }
+        Ok(Cipher { aead: XChaCha20Poly1305::new(Key::from_slice(&bytes)) })
+    }
+
+    /// A fresh random key, hex encoded.
+    pub fn generate_key() -> String {
+        hex::encode(XChaCha20Poly1305::generate_key(&mut OsRng))
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> String {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = self.aead.encrypt(&nonce, plaintext.as_bytes())
+            .expect("encrypting an in-memory buffer cannot fail");
+        let mut out = nonce.to_vec();
+        out.extend(sealed);
+        hex::encode(out)
+    }
+
+    pub fn decrypt(&self, stored: &str) -> Result<String, CryptoError> {
+        let bytes = hex::decode(stored).map_err(|_| CryptoError::Malformed)?;
+        if bytes.len() < NONCE_LEN {
+            return Err(CryptoError::Malformed);
+        }
+        let (nonce, sealed) = bytes.split_at(NONCE_LEN);
+        let plain = self.aead.decrypt(XNonce::from_slice(nonce), sealed)
+            .map_err(|_| CryptoError::Decrypt)?;
+        String::from_utf8(plain).map_err(|_| CryptoError::Malformed)
+    }
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Cipher(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let cipher = Cipher::from_hex(&Cipher::generate_key()).unwrap();
+        let sealed = cipher.encrypt("secret token ✓");
+        assert_ne!(sealed, "secret token ✓");
+        assert_eq!(cipher.decrypt(&sealed), Ok("secret token ✓".to_string()));
+    }
+
+    #[test]
+    fn test_nonce_is_random() {
+        let cipher = Cipher::from_hex(&Cipher::generate_key()).unwrap();
+        assert_ne!(cipher.encrypt("same"), cipher.encrypt("same"));
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let a = Cipher::from_hex(&Cipher::generate_key()).unwrap();
+        let b = Cipher::from_hex(&Cipher::generate_key()).unwrap();
+        assert_eq!(b.decrypt(&a.encrypt("secret")), Err(CryptoError::Decrypt));
+    }
+
+    #[test]
+    fn test_tampering_detected() {
+        let cipher = Cipher::from_hex(&Cipher::generate_key()).unwrap();
+        let mut sealed = cipher.encrypt("secret").into_bytes();
+        let last = sealed.len() - 1;
+        sealed[last] = if sealed[last] == b'0' { b'1' } else { b'0' };
+        assert_eq!(cipher.decrypt(&String::from_utf8(sealed).unwrap()), Err(CryptoError::Decrypt));
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        assert_eq!(Cipher::from_hex("abcd").unwrap_err(), CryptoError::InvalidKey);
+        assert_eq!(Cipher::from_hex("zz").unwrap_err(), CryptoError::InvalidKey);
+        let cipher = Cipher::from_hex(&Cipher::generate_key()).unwrap();
+        assert_eq!(cipher.decrypt("not hex"), Err(CryptoError::Malformed));
+        assert_eq!(cipher.decrypt("abcd"), Err(CryptoError::Malformed));
+    }
+}
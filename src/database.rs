@@ -1,7 +1,38 @@
+mod transfer;
+mod backup;
+mod audit;
+mod privacy;
+mod encryption;
+mod roles;
+mod games;
+mod hangman;
+mod trivia;
+mod scramble;
+mod wallet;
+mod achievements;
+mod tournaments;
+
 use rusqlite::{Connection, params, OptionalExtension};
+use rusqlite::types::Type;
+use crate::crypto::Cipher;
+
+pub use transfer::{privileged_tables, ImportMode, table_rows};
+pub use backup::validate_snapshot;
+pub use hangman::HangmanResult;
+pub use trivia::TriviaResult;
+pub use scramble::ScrambleResult;
+pub use tournaments::{Tournament, TournamentRound, TournamentStatus};
+
+/// A full-text search match: `(username, key, value)`.
+pub type SearchHit = (String, String, String);
+
+/// A permission grant: `(username, level, expires_at)`. Permanent grants have no expiry.
+pub type PermissionGrant = (String, i32, Option<i64>);
 
 pub struct Database {
     conn: Connection,
+    /// When set, new values are encrypted before they are stored.
+    cipher: Option<Cipher>,
 }
 
 impl Database {
@@ -20,6 +51,11 @@ impl Database {
             [],
         )?;
 
+        // Marks values stored encrypted, so plaintext and encrypted rows can coexist
+        if !has_column(&conn, "users_data", "encrypted")? {
+            conn.execute("ALTER TABLE users_data ADD COLUMN encrypted INTEGER NOT NULL DEFAULT 0", [])?;
+        }
+
         // Create permissions table if it doesn't exist
         conn.execute(
             "CREATE TABLE IF NOT EXISTS permissions (
@@ -30,28 +66,306 @@ impl Database {
             [],
         )?;
 
-        Ok(Database { conn })
+        // Unix time at which a temporary grant lapses; NULL for permanent grants
+        if !has_column(&conn, "permissions", "expires_at")? {
+            conn.execute("ALTER TABLE permissions ADD COLUMN expires_at INTEGER", [])?;
+        }
+
+        // The permanent level a temporary grant stands in for, restored when it lapses
+        if !has_column(&conn, "permissions", "permanent_level")? {
+            conn.execute("ALTER TABLE permissions ADD COLUMN permanent_level INTEGER", [])?;
+        }
+
+        // Named roles and the capabilities each one adds; see `seed_roles`
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS roles (
+                name TEXT PRIMARY KEY,
+                level INTEGER NOT NULL UNIQUE
+            );
+            CREATE TABLE IF NOT EXISTS role_capabilities (
+                role TEXT NOT NULL,
+                capability TEXT NOT NULL,
+                PRIMARY KEY (role, capability)
+            );
+            CREATE TABLE IF NOT EXISTS seeded_capabilities (
+                role TEXT NOT NULL,
+                capability TEXT NOT NULL,
+                PRIMARY KEY (role, capability)
+            );",
+        )?;
+
+        // Games saved before head-to-head games existed were keyed by channel and kind only;
+        // the table is rebuilt with `players` in its key and the saves copied across
+        let keyed_by_channel = has_column(&conn, "active_games", "channel")?
+            && !has_column(&conn, "active_games", "players")?;
+        if keyed_by_channel {
+            conn.execute("ALTER TABLE active_games RENAME TO active_games_old", [])?;
+        }
+
+        // Games in progress, saved after every move so they survive restarts. `players`
+        // is empty for channel games and "alice bob" for head-to-head games
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS active_games (
+                channel TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                players TEXT NOT NULL DEFAULT '',
+                state TEXT NOT NULL,
+                started_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                PRIMARY KEY (channel, kind, players)
+            )",
+            [],
+        )?;
+
+        if keyed_by_channel {
+            conn.execute_batch(
+                "INSERT INTO active_games (channel, kind, state, started_at, updated_at)
+                 SELECT channel, kind, state, started_at, updated_at FROM active_games_old;
+                 DROP TABLE active_games_old;",
+            )?;
+        }
+
+        // One row per player per finished hangman game
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS hangman_results (
+                id INTEGER PRIMARY KEY,
+                channel TEXT NOT NULL,
+                username TEXT NOT NULL,
+                correct INTEGER NOT NULL,
+                wrong INTEGER NOT NULL,
+                solved INTEGER NOT NULL,
+                points INTEGER NOT NULL,
+                finished_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS hangman_results_username ON hangman_results (username)", [])?;
+
+        // One row per player per finished trivia game
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS trivia_results (
+                id INTEGER PRIMARY KEY,
+                channel TEXT NOT NULL,
+                username TEXT NOT NULL,
+                correct INTEGER NOT NULL,
+                points INTEGER NOT NULL,
+                finished_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )",
+            [],
+        )?;
+
+        // One row per player per finished scramble game
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS scramble_results (
+                id INTEGER PRIMARY KEY,
+                channel TEXT NOT NULL,
+                username TEXT NOT NULL,
+                solved INTEGER NOT NULL,
+                points INTEGER NOT NULL,
+                finished_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )",
+            [],
+        )?;
+
+        // The tournament round a result was played for, if any; see `tournament_scores`
+        for table in ["hangman_results", "trivia_results", "scramble_results"] {
+            if !has_column(&conn, table, "tournament_id")? {
+                conn.execute_batch(&format!(
+                    "ALTER TABLE {0} ADD COLUMN tournament_id INTEGER;
+                     ALTER TABLE {0} ADD COLUMN tournament_round INTEGER;",
+                    table
+                ))?;
+            }
+        }
+
+        // Each player's wallet, shared by every game; players without a row have no chips
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS chips (
+                username TEXT PRIMARY KEY,
+                balance INTEGER NOT NULL CHECK (balance >= 0),
+                updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )",
+            [],
+        )?;
+
+        // Unix time of the player's last `!daily` claim
+        if !has_column(&conn, "chips", "daily_claimed_at")? {
+            conn.execute("ALTER TABLE chips ADD COLUMN daily_claimed_at INTEGER", [])?;
+        }
+
+        // Every change to a wallet, with the balance it left and why it was made
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS chip_ledger (
+                id INTEGER PRIMARY KEY,
+                username TEXT NOT NULL,
+                amount INTEGER NOT NULL,
+                balance INTEGER NOT NULL,
+                reason TEXT NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS chip_ledger_username ON chip_ledger (username)", [])?;
+
+        // Running totals achievements are measured against, e.g. `hangman.solves`; see `crate::achievements`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS achievement_stats (
+                username TEXT NOT NULL,
+                stat TEXT NOT NULL,
+                value INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                PRIMARY KEY (username, stat)
+            )",
+            [],
+        )?;
+
+        // Achievements each player has unlocked, by id
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS achievements (
+                username TEXT NOT NULL,
+                achievement TEXT NOT NULL,
+                unlocked_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                PRIMARY KEY (username, achievement)
+            )",
+            [],
+        )?;
+
+        // Tournaments: rounds of one game in a channel, scored from the rounds' rows in that game's results table
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tournaments (
+                id INTEGER PRIMARY KEY,
+                channel TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                rounds INTEGER NOT NULL,
+                starts_at INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                round INTEGER NOT NULL DEFAULT 0,
+                next_round_at INTEGER,
+                started_at INTEGER,
+                finished_at INTEGER,
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            );
+            CREATE TABLE IF NOT EXISTS tournament_players (
+                tournament_id INTEGER NOT NULL,
+                username TEXT NOT NULL,
+                joined_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                PRIMARY KEY (tournament_id, username)
+            );
+            CREATE TABLE IF NOT EXISTS tournament_results (
+                tournament_id INTEGER NOT NULL,
+                username TEXT NOT NULL,
+                points INTEGER NOT NULL,
+                PRIMARY KEY (tournament_id, username)
+            );",
+        )?;
+
+        // Words hangman and scramble pick from, grouped into categories; see `seed_hangman_words`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS hangman_words (
+                word TEXT PRIMARY KEY,
+                category TEXT NOT NULL,
+                difficulty TEXT NOT NULL,
+                added_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )",
+            [],
+        )?;
+
+        // Create audit_log table if it doesn't exist
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY,
+                actor TEXT NOT NULL,
+                hostmask TEXT,
+                command TEXT NOT NULL,
+                arguments TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                result TEXT NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )",
+            [],
+        )?;
+        conn.execute_batch(
+            "CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log BEGIN
+                SELECT RAISE(ABORT, 'audit_log is append-only');
+            END;
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log BEGIN
+                SELECT RAISE(ABORT, 'audit_log is append-only');
+            END;",
+        )?;
+
+        // Full-text index over plaintext values, kept in sync with users_data by
+        // triggers. Encrypted values are never indexed.
+        let fts_exists: bool = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'users_data_fts'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS users_data_fts USING fts5(
+                data_value,
+                content = 'users_data',
+                content_rowid = 'id'
+            );
+            DROP TRIGGER IF EXISTS users_data_fts_insert;
+            DROP TRIGGER IF EXISTS users_data_fts_delete;
+            DROP TRIGGER IF EXISTS users_data_fts_update;
+            CREATE TRIGGER users_data_fts_insert AFTER INSERT ON users_data WHEN new.encrypted = 0 BEGIN
+                INSERT INTO users_data_fts (rowid, data_value) VALUES (new.id, new.data_value);
+            END;
+            CREATE TRIGGER users_data_fts_delete AFTER DELETE ON users_data WHEN old.encrypted = 0 BEGIN
+                INSERT INTO users_data_fts (users_data_fts, rowid, data_value) VALUES ('delete', old.id, old.data_value);
+            END;
+            CREATE TRIGGER users_data_fts_update AFTER UPDATE ON users_data BEGIN
+                INSERT INTO users_data_fts (users_data_fts, rowid, data_value)
+                    SELECT 'delete', old.id, old.data_value WHERE old.encrypted = 0;
+                INSERT INTO users_data_fts (rowid, data_value)
+                    SELECT new.id, new.data_value WHERE new.encrypted = 0;
+            END;",
+        )?;
+
+        // Index rows written before the FTS table existed
+        if !fts_exists {
+            conn.execute("INSERT INTO users_data_fts (users_data_fts) VALUES ('rebuild')", [])?;
+        }
+
+        Ok(Database { conn, cipher: None })
+    }
+
+    /// Sets the cipher used for values written from now on. Rows already
+    /// stored keep their current form; see `reencrypt_user_data` to migrate them.
+    pub fn set_cipher(&mut self, cipher: Option<Cipher>) {
+        self.cipher = cipher;
     }
 
     pub fn set_user_data(&self, username: &str, key: &str, value: &str) -> Result<(), rusqlite::Error> {
+        let (stored, encrypted) = match &self.cipher {
+            Some(cipher) => (cipher.encrypt(value), true),
+            None => (value.to_string(), false),
+        };
+
+        // Upsert rather than INSERT OR REPLACE so the FTS update triggers fire
         self.conn.execute(
-            "INSERT OR REPLACE INTO users_data (username, data_key, data_value)
-             VALUES (?, ?, ?)",
-            params![username, key, value],
+            "INSERT INTO users_data (username, data_key, data_value, encrypted)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT(username, data_key) DO UPDATE SET
+                data_value = excluded.data_value,
+                encrypted = excluded.encrypted",
+            params![username, key, stored, encrypted],
         )?;
         Ok(())
     }
 
     pub fn get_user_data(&self, username: &str, key: &str) -> Result<Option<String>, rusqlite::Error> {
         let mut stmt = self.conn.prepare(
-            "SELECT data_value FROM users_data WHERE username = ? AND data_key = ?"
+            "SELECT data_value, encrypted FROM users_data WHERE username = ? AND data_key = ?"
         )?;
         
         let value = stmt.query_row(params![username, key], |row| {
-            row.get(0)
+            Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
         }).optional()?;
         
-        Ok(value)
+        value.map(|(value, encrypted)| self.open_value(value, encrypted)).transpose()
     }
 
     pub fn delete_user_data(&self, username: &str, key: &str) -> Result<bool, rusqlite::Error> {
@@ -64,24 +378,167 @@ impl Database {
 
     pub fn list_user_data(&self, username: &str) -> Result<Vec<(String, String)>, rusqlite::Error> {
         let mut stmt = self.conn.prepare(
-            "SELECT data_key, data_value FROM users_data WHERE username = ?"
+            "SELECT data_key, data_value, encrypted FROM users_data WHERE username = ? ORDER BY data_key"
         )?;
         
         let data = stmt.query_map(params![username], |row| {
-            Ok((row.get(0)?, row.get(1)?))
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
         })?.collect::<Result<Vec<_>, _>>()?;
         
-        Ok(data)
+        self.open_entries(data)
+    }
+
+    /// Lists one page of a user's keys, sorted by key, optionally filtered by
+    /// a key pattern. Patterns containing `*`, `?` or `[` are treated as globs,
+    /// anything else as a key prefix. Returns the page and the total match count.
+    pub fn list_user_data_page(
+        &self,
+        username: &str,
+        pattern: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<(Vec<(String, String)>, usize), rusqlite::Error> {
+        let glob = key_glob(pattern.unwrap_or(""));
+
+        let total: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM users_data WHERE username = ? AND data_key GLOB ?",
+            params![username, glob],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT data_key, data_value, encrypted FROM users_data
+             WHERE username = ? AND data_key GLOB ?
+             ORDER BY data_key
+             LIMIT ? OFFSET ?"
+        )?;
+
+        let data = stmt.query_map(params![username, glob, limit as i64, offset as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok((self.open_entries(data)?, total as usize))
+    }
+
+    /// Full-text search over stored values, best matches first. Encrypted
+    /// values are not indexed and never match. When `username`
+    /// is `None` every user's data is searched. Returns `(username, key, value)`
+    /// rows and the total match count.
+    pub fn search_user_data(
+        &self,
+        username: Option<&str>,
+        text: &str,
+        limit: usize,
+    ) -> Result<(Vec<SearchHit>, usize), rusqlite::Error> {
+        let query = fts_query(text);
+        if query.is_empty() {
+            return Ok((Vec::new(), 0));
+        }
+
+        let total: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM users_data_fts
+             JOIN users_data ON users_data.id = users_data_fts.rowid
+             WHERE users_data_fts MATCH ?1 AND users_data.encrypted = 0
+               AND (?2 IS NULL OR users_data.username = ?2)",
+            params![query, username],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT users_data.username, users_data.data_key, users_data.data_value
+             FROM users_data_fts
+             JOIN users_data ON users_data.id = users_data_fts.rowid
+             WHERE users_data_fts MATCH ?1 AND users_data.encrypted = 0
+               AND (?2 IS NULL OR users_data.username = ?2)
+             ORDER BY users_data_fts.rank, users_data.username, users_data.data_key
+             LIMIT ?3"
+        )?;
+
+        let data = stmt.query_map(params![query, username, limit as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok((data, total as usize))
+    }
+
+    /// Decrypts a stored value if it was written encrypted.
+    fn open_value(&self, value: String, encrypted: bool) -> Result<String, rusqlite::Error> {
+        if !encrypted {
+            return Ok(value);
+        }
+        let cipher = self.cipher.as_ref().ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(1, Type::Text, "value is encrypted but no key is configured".into())
+        })?;
+        cipher.decrypt(&value)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, Type::Text, Box::new(e)))
+    }
+
+    fn open_entries(&self, rows: Vec<(String, String, bool)>) -> Result<Vec<(String, String)>, rusqlite::Error> {
+        rows.into_iter()
+            .map(|(key, value, encrypted)| Ok((key, self.open_value(value, encrypted)?)))
+            .collect()
+    }
+
+    /// Returns how many keys a user has stored and their combined size in bytes.
+    /// Encrypted values are opened first, so sizes are the plaintext's, the
+    /// same as the quota checks measure new values.
+    pub fn user_data_usage(&self, username: &str) -> Result<(usize, usize), rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT data_key, data_value, encrypted FROM users_data WHERE username = ?"
+        )?;
+        let rows = stmt.query_map(params![username], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let entries = self.open_entries(rows)?;
+        let bytes = entries.iter().map(|(key, value)| key.len() + value.len()).sum();
+        Ok((entries.len(), bytes))
     }
 
     pub fn grant_permission(&self, username: &str, level: i32) -> Result<(), rusqlite::Error> {
         self.conn.execute(
-            "INSERT OR REPLACE INTO permissions (username, permission_level) VALUES (?, ?)",
+            "INSERT OR REPLACE INTO permissions (username, permission_level, expires_at, permanent_level)
+             VALUES (?, ?, NULL, NULL)",
             params![username, level],
         )?;
         Ok(())
     }
 
+    /// Grants a permission level that lapses at the unix time `expires_at`.
+    /// The user's permanent level, if any, comes back when it does.
+    pub fn grant_permission_until(&self, username: &str, level: i32, expires_at: i64) -> Result<(), rusqlite::Error> {
+        self.conn.execute(
+            "INSERT INTO permissions (username, permission_level, expires_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (username) DO UPDATE SET
+                 permanent_level = CASE WHEN expires_at IS NULL THEN permission_level ELSE permanent_level END,
+                 permission_level = ?2,
+                 expires_at = ?3",
+            params![username, level, expires_at],
+        )?;
+        Ok(())
+    }
+
+    /// Ends grants that expired at or before `now` and returns them as
+    /// `(username, level)`. Users go back to their permanent level, if any.
+    pub fn expire_permissions(&self, now: i64) -> Result<Vec<(String, i32)>, rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut expired = {
+            let mut stmt = tx.prepare(
+                "SELECT username, permission_level FROM permissions WHERE expires_at IS NOT NULL AND expires_at <= ?"
+            )?;
+            let rows = stmt.query_map(params![now], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<(String, i32)>, _>>()?
+        };
+        tx.execute(
+            "UPDATE permissions SET permission_level = permanent_level, expires_at = NULL, permanent_level = NULL
+             WHERE expires_at IS NOT NULL AND expires_at <= ? AND permanent_level IS NOT NULL",
+            params![now],
+        )?;
+        tx.execute("DELETE FROM permissions WHERE expires_at IS NOT NULL AND expires_at <= ?", params![now])?;
+        tx.commit()?;
+        expired.sort();
+
+        Ok(expired)
+    }
+
     pub fn revoke_permission(&self, username: &str) -> Result<bool, rusqlite::Error> {
         let rows = self.conn.execute(
             "DELETE FROM permissions WHERE username = ?",
@@ -91,34 +548,83 @@ impl Database {
     }
 
     pub fn get_permission_level(&self, username: &str) -> Result<i32, rusqlite::Error> {
-        let mut stmt = self.conn.prepare(
-            "SELECT permission_level FROM permissions WHERE username = ?"
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            // Expired grants are ignored even before the sweeper removes them
+            "SELECT {} FROM permissions WHERE username = ?",
+            CURRENT_LEVEL
+        ))?;
         
-        let level = stmt.query_row(params![username], |row| {
+        let level: Option<Option<i32>> = stmt.query_row(params![username], |row| {
             row.get(0)
         }).optional()?;
         
-        Ok(level.unwrap_or(0)) // Default to 0 (no permissions)
+        Ok(level.flatten().unwrap_or(0)) // Default to 0 (no permissions)
     }
 
-    pub fn list_users_with_permissions(&self) -> Result<Vec<(String, i32)>, rusqlite::Error> {
-        let mut stmt = self.conn.prepare(
-            "SELECT username, permission_level FROM permissions ORDER BY username"
-        )?;
+    /// Counts current grants at `level` or above.
+    pub fn count_permissions_at_least(&self, level: i32) -> Result<usize, rusqlite::Error> {
+        self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM permissions WHERE {} >= ?", CURRENT_LEVEL),
+            params![level],
+            |row| row.get::<_, i64>(0).map(|count| count as usize),
+        )
+    }
+
+    pub fn list_users_with_permissions(&self) -> Result<Vec<PermissionGrant>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT username, {0},
+                    CASE WHEN expires_at > strftime('%s','now') THEN expires_at END
+             FROM permissions
+             WHERE {0} IS NOT NULL
+             ORDER BY username",
+            CURRENT_LEVEL
+        ))?;
         
         let users = stmt.query_map([], |row| {
-            Ok((row.get(0)?, row.get(1)?))
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
         })?.collect::<Result<Vec<_>, _>>()?;
         
         Ok(users)
     }
 }
 
+/// A user's level as of now: their grant, or once a temporary grant has
+/// expired, the permanent level it stood in for (NULL if none).
+const CURRENT_LEVEL: &str = "CASE WHEN expires_at IS NULL OR expires_at > strftime('%s','now')
+         THEN permission_level ELSE permanent_level END";
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(names.iter().any(|name| name == column))
+}
+
+fn key_glob(pattern: &str) -> String {
+    if pattern.contains(['*', '?', '[']) {
+        pattern.to_string()
+    } else {
+        format!("{}*", pattern)
+    }
+}
+
+/// Turns free text into an FTS5 query that matches every word literally, so
+/// user input can't inject FTS operators or cause syntax errors.
+fn fts_query(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
     fn create_test_db() -> Database {
         Database::new(":memory:").expect("Failed to create in-memory database")
     }
@@ -252,9 +758,90 @@ mod tests {
         
         let users = db.list_users_with_permissions().expect("Failed to list");
         assert_eq!(users.len(), 3);
-        assert!(users.contains(&("alice".to_string(), 10)));
-        assert!(users.contains(&("bob".to_string(), 5)));
-        assert!(users.contains(&("charlie".to_string(), 15)));
+        assert!(users.contains(&("alice".to_string(), 10, None)));
+        assert!(users.contains(&("bob".to_string(), 5, None)));
+        assert!(users.contains(&("charlie".to_string(), 15, None)));
+    }
+
+    #[test]
+    fn test_count_permissions_at_least() {
+        let db = create_test_db();
+        db.grant_permission("a", 15).expect("Failed to grant");
+        db.grant_permission("b", 10).expect("Failed to grant");
+        db.grant_permission_until("c", 15, now() - 1).expect("Failed to grant");
+
+        assert_eq!(db.count_permissions_at_least(15).unwrap(), 1);
+        assert_eq!(db.count_permissions_at_least(10).unwrap(), 2);
+        assert_eq!(db.count_permissions_at_least(20).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_temporary_permission() {
+        let db = create_test_db();
+        let expires_at = now() + 3600;
+        db.grant_permission_until("oncall", 5, expires_at).expect("Failed to grant");
+
+        assert_eq!(db.get_permission_level("oncall").unwrap(), 5);
+        assert_eq!(db.list_users_with_permissions().unwrap(), vec![("oncall".to_string(), 5, Some(expires_at))]);
+    }
+
+    #[test]
+    fn test_expired_permission_is_ignored_before_sweep() {
+        let db = create_test_db();
+        db.grant_permission_until("oncall", 5, now() - 1).expect("Failed to grant");
+
+        assert_eq!(db.get_permission_level("oncall").unwrap(), 0);
+        assert!(db.list_users_with_permissions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_expire_permissions() {
+        let db = create_test_db();
+        let now = now();
+        db.grant_permission("admin", 10).expect("Failed to grant");
+        db.grant_permission_until("early", 5, now - 10).expect("Failed to grant");
+        db.grant_permission_until("late", 3, now + 10).expect("Failed to grant");
+
+        assert_eq!(db.expire_permissions(now).unwrap(), vec![("early".to_string(), 5)]);
+        assert!(db.expire_permissions(now).unwrap().is_empty());
+        assert_eq!(db.expire_permissions(now + 10).unwrap(), vec![("late".to_string(), 3)]);
+        assert_eq!(db.get_permission_level("admin").unwrap(), 10);
+    }
+
+    #[test]
+    fn test_temporary_grant_restores_permanent_level() {
+        let db = create_test_db();
+        db.grant_permission("user", 5).expect("Failed to grant");
+        db.grant_permission_until("user", 10, now() + 10).expect("Failed to grant");
+        assert_eq!(db.get_permission_level("user").unwrap(), 10);
+        // A second temporary grant keeps the permanent level underneath
+        db.grant_permission_until("user", 8, now() + 5).expect("Failed to grant");
+
+        assert_eq!(db.expire_permissions(now() + 20).unwrap(), vec![("user".to_string(), 8)]);
+        assert_eq!(db.get_permission_level("user").unwrap(), 5);
+        assert_eq!(db.list_users_with_permissions().unwrap(), vec![("user".to_string(), 5, None)]);
+        assert!(db.expire_permissions(now() + 20).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_expired_grant_falls_back_before_sweep() {
+        let db = create_test_db();
+        db.grant_permission("user", 5).expect("Failed to grant");
+        db.grant_permission_until("user", 10, now() - 1).expect("Failed to grant");
+
+        assert_eq!(db.get_permission_level("user").unwrap(), 5);
+        assert_eq!(db.count_permissions_at_least(10).unwrap(), 0);
+        assert_eq!(db.list_users_with_permissions().unwrap(), vec![("user".to_string(), 5, None)]);
+    }
+
+    #[test]
+    fn test_permanent_grant_replaces_temporary() {
+        let db = create_test_db();
+        db.grant_permission_until("user", 5, now() + 10).expect("Failed to grant");
+        db.grant_permission("user", 5).expect("Failed to grant");
+
+        assert!(db.expire_permissions(now() + 20).unwrap().is_empty());
+        assert_eq!(db.get_permission_level("user").unwrap(), 5);
     }
 
     #[test]
@@ -276,4 +863,115 @@ mod tests {
         assert_eq!(val1, Some("value1".to_string()));
         assert_eq!(val2, Some("value2".to_string()));
     }
+
+    #[test]
+    fn test_list_user_data_sorted() {
+        let db = create_test_db();
+        db.set_user_data("erin", "zeta", "1").expect("Failed to set");
+        db.set_user_data("erin", "alpha", "2").expect("Failed to set");
+        db.set_user_data("erin", "mid", "3").expect("Failed to set");
+
+        let keys: Vec<String> = db.list_user_data("erin").expect("Failed to list")
+            .into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec!["alpha", "mid", "zeta"]);
+    }
+
+    #[test]
+    fn test_list_user_data_page_prefix() {
+        let db = create_test_db();
+        db.set_user_data("frank", "color", "blue").expect("Failed to set");
+        db.set_user_data("frank", "colour", "red").expect("Failed to set");
+        db.set_user_data("frank", "size", "large").expect("Failed to set");
+
+        let (data, total) = db.list_user_data_page("frank", Some("col"), 10, 0).expect("Failed to list");
+        assert_eq!(total, 2);
+        assert_eq!(data[0].0, "color");
+        assert_eq!(data[1].0, "colour");
+    }
+
+    #[test]
+    fn test_list_user_data_page_glob() {
+        let db = create_test_db();
+        db.set_user_data("gina", "home.city", "Paris").expect("Failed to set");
+        db.set_user_data("gina", "work.city", "Lyon").expect("Failed to set");
+        db.set_user_data("gina", "home.zip", "75001").expect("Failed to set");
+
+        let (data, total) = db.list_user_data_page("gina", Some("*.city"), 10, 0).expect("Failed to list");
+        assert_eq!(total, 2);
+        assert_eq!(data, vec![
+            ("home.city".to_string(), "Paris".to_string()),
+            ("work.city".to_string(), "Lyon".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_list_user_data_page_offset() {
+        let db = create_test_db();
+        for i in 0..5 {
+            db.set_user_data("hank", &format!("key{}", i), "v").expect("Failed to set");
+        }
+
+        let (data, total) = db.list_user_data_page("hank", None, 2, 2).expect("Failed to list");
+        assert_eq!(total, 5);
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].0, "key2");
+        assert_eq!(data[1].0, "key3");
+    }
+
+    #[test]
+    fn test_search_user_data() {
+        let db = create_test_db();
+        db.set_user_data("ivy", "pet", "a small brown dog").expect("Failed to set");
+        db.set_user_data("ivy", "car", "a red hatchback").expect("Failed to set");
+        db.set_user_data("jack", "pet", "a large brown cat").expect("Failed to set");
+
+        let (own, total) = db.search_user_data(Some("ivy"), "brown", 10).expect("Failed to search");
+        assert_eq!(total, 1);
+        assert_eq!(own[0], ("ivy".to_string(), "pet".to_string(), "a small brown dog".to_string()));
+
+        let (all, total) = db.search_user_data(None, "brown", 10).expect("Failed to search");
+        assert_eq!(total, 2);
+        assert_eq!(all.len(), 2);
+    }
+
+    #[test]
+    fn test_search_user_data_follows_updates() {
+        let db = create_test_db();
+        db.set_user_data("kate", "note", "old text").expect("Failed to set");
+        db.set_user_data("kate", "note", "new text").expect("Failed to replace");
+
+        let (old, _) = db.search_user_data(Some("kate"), "old", 10).expect("Failed to search");
+        assert!(old.is_empty());
+        let (new, _) = db.search_user_data(Some("kate"), "new", 10).expect("Failed to search");
+        assert_eq!(new.len(), 1);
+
+        db.delete_user_data("kate", "note").expect("Failed to delete");
+        let (gone, _) = db.search_user_data(Some("kate"), "new", 10).expect("Failed to search");
+        assert!(gone.is_empty());
+    }
+
+    #[test]
+    fn test_search_user_data_special_characters() {
+        let db = create_test_db();
+        db.set_user_data("leo", "quote", "say \"hello\" AND bye").expect("Failed to set");
+
+        let (data, _) = db.search_user_data(Some("leo"), "\"hello\" AND", 10).expect("Failed to search");
+        assert_eq!(data.len(), 1);
+        let (empty, total) = db.search_user_data(Some("leo"), "   ", 10).expect("Failed to search");
+        assert!(empty.is_empty());
+        assert_eq!(total, 0);
+    }
+
+    #[test]
+    fn test_user_data_usage() {
+        let db = create_test_db();
+        assert_eq!(db.user_data_usage("mia").expect("Failed to query"), (0, 0));
+
+        db.set_user_data("mia", "ab", "cde").expect("Failed to set");
+        db.set_user_data("mia", "k", "é").expect("Failed to set");
+        db.set_user_data("other", "key", "value").expect("Failed to set");
+
+        // 2 + 3 bytes, plus 1 + 2 bytes for the two-byte 'é'
+        assert_eq!(db.user_data_usage("mia").expect("Failed to query"), (2, 8));
+    }
 }
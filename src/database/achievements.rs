@@ -0,0 +1,118 @@
+use rusqlite::{params, OptionalExtension};
+use super::Database;
+
+/// Seconds in a day; streaks count UTC days.
+const DAY: i64 = 24 * 60 * 60;
+
+impl Database {
+    /// A player's achievement stat; stats never touched are 0.
+    pub fn stat(&self, username: &str, stat: &str) -> Result<i64, rusqlite::Error> {
+        let value = self.conn.query_row(
+            "SELECT value FROM achievement_stats WHERE username = ? AND stat = ?",
+            params![username, stat],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(value.unwrap_or(0))
+    }
+
+    /// Adds `amount` to a stat and returns its new value.
+    pub fn add_stat(&self, username: &str, stat: &str, amount: i64) -> Result<i64, rusqlite::Error> {
+        self.conn.query_row(
+            "INSERT INTO achievement_stats (username, stat, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT(username, stat) DO UPDATE SET value = value + ?3, updated_at = strftime('%s','now')
+             RETURNING value",
+            params![username, stat, amount],
+            |row| row.get(0),
+        )
+    }
+
+    /// Raises a stat to `value` if it is lower, and returns what it is now.
+    pub fn raise_stat(&self, username: &str, stat: &str, value: i64) -> Result<i64, rusqlite::Error> {
+        self.conn.query_row(
+            "INSERT INTO achievement_stats (username, stat, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT(username, stat) DO UPDATE SET value = max(value, ?3), updated_at = strftime('%s','now')
+             RETURNING value",
+            params![username, stat, value],
+            |row| row.get(0),
+        )
+    }
+
+    /// Counts the UTC day of `now` towards a streak and returns its length:
+    /// the same day leaves it alone, the next day extends it and a later day
+    /// starts it again at 1. `updated_at` holds the last day counted.
+    pub fn record_streak(&self, username: &str, stat: &str, now: i64) -> Result<i64, rusqlite::Error> {
+        self.conn.query_row(
+            "INSERT INTO achievement_stats (username, stat, value, updated_at) VALUES (?1, ?2, 1, ?3)
+             ON CONFLICT(username, stat) DO UPDATE SET
+                 value = CASE
+                     WHEN updated_at / ?4 = ?3 / ?4 THEN value
+                     WHEN updated_at / ?4 = ?3 / ?4 - 1 THEN value + 1
+                     ELSE 1
+                 END,
+                 updated_at = ?3
+             RETURNING value",
+            params![username, stat, now, DAY],
+            |row| row.get(0),
+        )
+    }
+
+    /// Records an achievement. Returns false if the player already had it.
+    pub fn unlock_achievement(&self, username: &str, achievement: &str) -> Result<bool, rusqlite::Error> {
+        let rows = self.conn.execute(
+            "INSERT OR IGNORE INTO achievements (username, achievement) VALUES (?, ?)",
+            params![username, achievement],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// The ids of a player's achievements, in the order they were unlocked.
+    pub fn achievements(&self, username: &str) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT achievement FROM achievements WHERE username = ? ORDER BY unlocked_at, rowid"
+        )?;
+        let rows = stmt.query_map(params![username], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    #[test]
+    fn test_stats() {
+        let db = create_test_db();
+        assert_eq!(db.stat("alice", "hangman.solves").unwrap(), 0);
+        assert_eq!(db.add_stat("alice", "hangman.solves", 1).unwrap(), 1);
+        assert_eq!(db.add_stat("alice", "hangman.solves", 2).unwrap(), 3);
+        assert_eq!(db.raise_stat("alice", "data.keys", 10).unwrap(), 10);
+        assert_eq!(db.raise_stat("alice", "data.keys", 4).unwrap(), 10);
+        assert_eq!(db.stat("alice", "data.keys").unwrap(), 10);
+        assert_eq!(db.stat("bob", "data.keys").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_streak() {
+        let db = create_test_db();
+        assert_eq!(db.record_streak("alice", "streak", 10).unwrap(), 1);
+        assert_eq!(db.record_streak("alice", "streak", DAY - 1).unwrap(), 1);
+        assert_eq!(db.record_streak("alice", "streak", DAY).unwrap(), 2);
+        assert_eq!(db.record_streak("alice", "streak", 2 * DAY + 5).unwrap(), 3);
+        assert_eq!(db.record_streak("alice", "streak", 4 * DAY).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_unlock() {
+        let db = create_test_db();
+        assert!(db.unlock_achievement("alice", "flawless").unwrap());
+        assert!(db.unlock_achievement("alice", "first_solve").unwrap());
+        assert!(!db.unlock_achievement("alice", "flawless").unwrap());
+        assert_eq!(db.achievements("alice").unwrap(), vec!["flawless", "first_solve"]);
+        assert!(db.achievements("bob").unwrap().is_empty());
+    }
+}
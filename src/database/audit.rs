@@ -0,0 +1,131 @@
+use rusqlite::params;
+use super::Database;
+
+/// One row of the audit log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub actor: String,
+    pub hostmask: Option<String>,
+    pub command: String,
+    pub arguments: String,
+    pub channel: String,
+    pub result: String,
+    pub created_at: i64,
+}
+
+impl Database {
+    /// Appends an entry to the audit log.
+    pub fn record_audit(
+        &self,
+        actor: &str,
+        hostmask: Option<&str>,
+        command: &str,
+        arguments: &str,
+        channel: &str,
+        result: &str,
+    ) -> Result<(), rusqlite::Error> {
+        self.conn.execute(
+            "INSERT INTO audit_log (actor, hostmask, command, arguments, channel, result)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![actor, hostmask, command, arguments, channel, result],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the newest audit entries, optionally filtered by actor, command
+    /// and a minimum unix timestamp.
+    pub fn audit_entries(
+        &self,
+        actor: Option<&str>,
+        command: Option<&str>,
+        since: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT actor, hostmask, command, arguments, channel, result, created_at
+             FROM audit_log
+             WHERE (?1 IS NULL OR actor = ?1)
+               AND (?2 IS NULL OR command = ?2)
+               AND (?3 IS NULL OR created_at >= ?3)
+             ORDER BY created_at DESC, id DESC
+             LIMIT ?4"
+        )?;
+
+        let entries = stmt.query_map(params![actor, command, since, limit as i64], |row| {
+            Ok(AuditEntry {
+                actor: row.get(0)?,
+                hostmask: row.get(1)?,
+                command: row.get(2)?,
+                arguments: row.get(3)?,
+                channel: row.get(4)?,
+                result: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    #[test]
+    fn test_record_and_query_audit() {
+        let db = create_test_db();
+        db.record_audit("alice", Some("alice!a@host"), "grant", "bob 5", "#ops", "ok").unwrap();
+        db.record_audit("bob", None, "join", "#new", "#ops", "denied").unwrap();
+
+        let all = db.audit_entries(None, None, None, 10).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].actor, "bob");
+        assert_eq!(all[1].hostmask, Some("alice!a@host".to_string()));
+
+        let by_actor = db.audit_entries(Some("alice"), None, None, 10).unwrap();
+        assert_eq!(by_actor.len(), 1);
+        assert_eq!(by_actor[0].arguments, "bob 5");
+
+        let by_command = db.audit_entries(None, Some("join"), None, 10).unwrap();
+        assert_eq!(by_command.len(), 1);
+        assert_eq!(by_command[0].result, "denied");
+    }
+
+    #[test]
+    fn test_audit_since_and_limit() {
+        let db = create_test_db();
+        db.conn.execute(
+            "INSERT INTO audit_log (actor, command, arguments, channel, result, created_at)
+             VALUES ('old', 'grant', '', '#ch', 'ok', 1000)",
+            [],
+        ).unwrap();
+        db.record_audit("new", None, "grant", "", "#ch", "ok").unwrap();
+        db.record_audit("newer", None, "grant", "", "#ch", "ok").unwrap();
+
+        let recent = db.audit_entries(None, None, Some(2000), 10).unwrap();
+        assert!(recent.iter().all(|e| e.actor != "old"));
+        assert_eq!(db.audit_entries(None, None, None, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_audit_log_is_append_only() {
+        let db = create_test_db();
+        db.record_audit("alice", None, "grant", "bob 5", "#ops", "ok").unwrap();
+
+        assert!(db.conn.execute("UPDATE audit_log SET result = 'forged'", []).is_err());
+        assert!(db.conn.execute("DELETE FROM audit_log", []).is_err());
+        assert_eq!(db.audit_entries(None, None, None, 10).unwrap()[0].result, "ok");
+    }
+
+    #[test]
+    fn test_forget_user_keeps_audit_log() {
+        let db = create_test_db();
+        db.record_audit("alice", None, "forgetme", "alice", "#ch", "ok").unwrap();
+        db.forget_user("alice").unwrap();
+        assert_eq!(db.audit_entries(Some("alice"), None, None, 10).unwrap().len(), 1);
+    }
+}
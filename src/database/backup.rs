@@ -0,0 +1,114 @@
+use std::path::Path;
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use super::Database;
+
+/// Tables a snapshot must contain to be restorable.
+const REQUIRED_TABLES: &[&str] = &["users_data", "permissions"];
+
+impl Database {
+    /// Writes a consistent copy of the live database to `path` using SQLite's
+    /// online backup API, so the bot keeps running while it is taken.
+    pub fn backup_to(&self, path: &Path) -> Result<(), rusqlite::Error> {
+        self.conn.backup(DatabaseName::Main, path, None)
+    }
+
+    /// Replaces the live database with the contents of a snapshot. Callers
+    /// should run [`validate_snapshot`] first.
+    pub fn restore_from(&mut self, path: &Path) -> Result<(), rusqlite::Error> {
+        self.conn.restore(DatabaseName::Main, path, None::<fn(rusqlite::backup::Progress)>)
+    }
+}
+
+/// Checks that a snapshot opens, passes SQLite's integrity check and has the
+/// bot's tables. Returns a description of the problem if not.
+pub fn validate_snapshot(path: &Path) -> Result<(), String> {
+    if !path.is_file() {
+        return Err(format!("{} does not exist", path.display()));
+    }
+
+    // The snapshot is only opened read-only, so verifying it can never change
+    // it. The checks run on an in-memory copy, because FTS5's part of the
+    // integrity check needs a writable handle.
+    let snapshot = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("cannot open snapshot: {}", e))?;
+    let mut conn = Connection::open_in_memory().map_err(|e| format!("cannot open snapshot: {}", e))?;
+    Backup::new(&snapshot, &mut conn)
+        .and_then(|copy| copy.step(-1))
+        .map_err(|e| format!("not a valid database: {}", e))?;
+
+    let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| format!("not a valid database: {}", e))?;
+    if integrity != "ok" {
+        return Err(format!("integrity check failed: {}", integrity));
+    }
+
+    for table in REQUIRED_TABLES {
+        let found: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+            [table],
+            |row| row.get(0),
+        ).map_err(|e| format!("cannot read schema: {}", e))?;
+        if found == 0 {
+            return Err(format!("missing table {}", table));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("rustirc-backup-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("Failed to create temp dir");
+        dir.join(name)
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let path = temp_path("roundtrip.db");
+        let _ = fs::remove_file(&path);
+
+        let db = Database::new(":memory:").expect("Failed to create database");
+        db.set_user_data("alice", "color", "blue").expect("Failed to set");
+        db.backup_to(&path).expect("Failed to back up");
+        let taken = fs::read(&path).expect("Failed to read snapshot");
+        assert_eq!(validate_snapshot(&path), Ok(()));
+        assert_eq!(fs::read(&path).expect("Failed to read snapshot"), taken, "verifying changed the snapshot");
+
+        let mut restored = Database::new(":memory:").expect("Failed to create database");
+        restored.set_user_data("bob", "pet", "cat").expect("Failed to set");
+        restored.restore_from(&path).expect("Failed to restore");
+
+        assert_eq!(restored.get_user_data("alice", "color").unwrap(), Some("blue".to_string()));
+        assert_eq!(restored.get_user_data("bob", "pet").unwrap(), None);
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_validate_rejects_missing_file() {
+        assert!(validate_snapshot(&temp_path("does-not-exist.db")).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_garbage() {
+        let path = temp_path("garbage.db");
+        fs::write(&path, b"this is not a database at all, just some bytes").expect("Failed to write");
+        assert!(validate_snapshot(&path).is_err());
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_validate_rejects_foreign_database() {
+        let path = temp_path("foreign.db");
+        let _ = fs::remove_file(&path);
+        Connection::open(&path).unwrap()
+            .execute("CREATE TABLE other (id INTEGER)", []).unwrap();
+
+        assert_eq!(validate_snapshot(&path), Err("missing table users_data".to_string()));
+        fs::remove_file(&path).ok();
+    }
+}
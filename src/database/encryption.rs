@@ -0,0 +1,185 @@
+use rusqlite::params;
+use crate::crypto::Cipher;
+use super::Database;
+
+impl Database {
+    /// Rewrites every stored value under a new key in one transaction.
+    /// Encrypted rows are opened with `old`; every row is then sealed with `new`,
+    /// or stored as plaintext when `new` is `None`. Returns the rows rewritten.
+    pub fn reencrypt_user_data(&self, old: Option<&Cipher>, new: Option<&Cipher>) -> Result<usize, rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let rows: Vec<(i64, String, bool)> = {
+            let mut stmt = tx.prepare("SELECT id, data_value, encrypted FROM users_data")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            rows.collect::<Result<_, _>>()?
+        };
+
+        let mut rewritten = 0;
+        for (id, value, encrypted) in rows {
+            let plaintext = if encrypted {
+                let cipher = old.ok_or_else(|| conversion_error("row is encrypted but no current key was given"))?;
+                cipher.decrypt(&value).map_err(|e| conversion_error(&e.to_string()))?
+            } else {
+                value
+            };
+
+            let (stored, sealed) = match new {
+                Some(cipher) => (cipher.encrypt(&plaintext), true),
+                None => (plaintext, false),
+            };
+            tx.execute(
+                "UPDATE users_data SET data_value = ?, encrypted = ? WHERE id = ?",
+                params![stored, sealed, id],
+            )?;
+            rewritten += 1;
+        }
+
+        tx.commit()?;
+        Ok(rewritten)
+    }
+
+    /// Counts stored values as `(encrypted, plaintext)`.
+    pub fn encryption_status(&self) -> Result<(usize, usize), rusqlite::Error> {
+        self.conn.query_row(
+            "SELECT COALESCE(SUM(encrypted), 0), COUNT(*) - COALESCE(SUM(encrypted), 0) FROM users_data",
+            [],
+            |row| Ok((row.get::<_, i64>(0)? as usize, row.get::<_, i64>(1)? as usize)),
+        )
+    }
+}
+
+fn conversion_error(message: &str) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> Cipher {
+        Cipher::from_hex(&Cipher::generate_key()).unwrap()
+    }
+
+    fn raw_value(db: &Database, username: &str, key: &str) -> (String, bool) {
+        db.conn.query_row(
+            "SELECT data_value, encrypted FROM users_data WHERE username = ? AND data_key = ?",
+            params![username, key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_values_encrypted_at_rest() {
+        let mut db = Database::new(":memory:").unwrap();
+        db.set_cipher(Some(cipher()));
+        db.set_user_data("alice", "token", "hunter2").unwrap();
+
+        let (stored, encrypted) = raw_value(&db, "alice", "token");
+        assert!(encrypted);
+        assert!(!stored.contains("hunter2"));
+        assert_eq!(db.get_user_data("alice", "token").unwrap(), Some("hunter2".to_string()));
+        assert_eq!(db.list_user_data("alice").unwrap(), vec![("token".to_string(), "hunter2".to_string())]);
+    }
+
+    #[test]
+    fn test_mixed_rows_readable() {
+        let mut db = Database::new(":memory:").unwrap();
+        db.set_user_data("alice", "old", "plain").unwrap();
+        db.set_cipher(Some(cipher()));
+        db.set_user_data("alice", "new", "sealed").unwrap();
+
+        assert_eq!(db.encryption_status().unwrap(), (1, 1));
+        let (page, _) = db.list_user_data_page("alice", None, 10, 0).unwrap();
+        assert_eq!(page, vec![
+            ("new".to_string(), "sealed".to_string()),
+            ("old".to_string(), "plain".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_usage_counts_plaintext() {
+        let mut db = Database::new(":memory:").unwrap();
+        db.set_user_data("alice", "old", "plain").unwrap();
+        db.set_cipher(Some(cipher()));
+        db.set_user_data("alice", "token", "hunter2").unwrap();
+
+        // 3 + 5 and 5 + 7 bytes, however the values are stored
+        assert_eq!(db.user_data_usage("alice").unwrap(), (2, 20));
+    }
+
+    #[test]
+    fn test_encrypted_row_without_key_errors() {
+        let mut db = Database::new(":memory:").unwrap();
+        db.set_cipher(Some(cipher()));
+        db.set_user_data("alice", "token", "hunter2").unwrap();
+        db.set_cipher(None);
+
+        assert!(db.get_user_data("alice", "token").is_err());
+    }
+
+    #[test]
+    fn test_encrypted_values_not_searchable() {
+        let mut db = Database::new(":memory:").unwrap();
+        db.set_user_data("alice", "note", "plain words").unwrap();
+        db.set_cipher(Some(cipher()));
+        db.set_user_data("alice", "secret", "hidden words").unwrap();
+
+        let (hits, total) = db.search_user_data(Some("alice"), "words", 10).unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(hits[0].1, "note");
+    }
+
+    #[test]
+    fn test_reencrypt_rotates_key() {
+        let (old, new) = (cipher(), cipher());
+        let mut db = Database::new(":memory:").unwrap();
+        db.set_user_data("alice", "plain", "one").unwrap();
+        db.set_cipher(Some(old.clone()));
+        db.set_user_data("alice", "sealed", "two").unwrap();
+
+        assert_eq!(db.reencrypt_user_data(Some(&old), Some(&new)).unwrap(), 2);
+        assert_eq!(db.encryption_status().unwrap(), (2, 0));
+
+        db.set_cipher(Some(new));
+        assert_eq!(db.get_user_data("alice", "plain").unwrap(), Some("one".to_string()));
+        assert_eq!(db.get_user_data("alice", "sealed").unwrap(), Some("two".to_string()));
+        db.set_cipher(Some(old));
+        assert!(db.get_user_data("alice", "sealed").is_err());
+    }
+
+    #[test]
+    fn test_reencrypt_to_plaintext_restores_search() {
+        let key = cipher();
+        let mut db = Database::new(":memory:").unwrap();
+        db.set_cipher(Some(key.clone()));
+        db.set_user_data("alice", "note", "findable text").unwrap();
+
+        db.reencrypt_user_data(Some(&key), None).unwrap();
+        db.set_cipher(None);
+        assert_eq!(raw_value(&db, "alice", "note"), ("findable text".to_string(), false));
+        let (hits, _) = db.search_user_data(Some("alice"), "findable", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+    }
+
+    #[test]
+    fn test_reencrypt_wrong_key_rolls_back() {
+        let mut db = Database::new(":memory:").unwrap();
+        db.set_user_data("alice", "plain", "one").unwrap();
+        db.set_cipher(Some(cipher()));
+        db.set_user_data("alice", "sealed", "two").unwrap();
+
+        assert!(db.reencrypt_user_data(Some(&cipher()), None).is_err());
+        assert_eq!(db.encryption_status().unwrap(), (1, 1));
+    }
+
+    #[test]
+    fn test_report_shows_decrypted_values() {
+        let mut db = Database::new(":memory:").unwrap();
+        db.set_cipher(Some(cipher()));
+        db.set_user_data("alice", "token", "hunter2").unwrap();
+
+        let report = db.user_report("alice").unwrap();
+        assert_eq!(report, vec![("users_data".to_string(), vec!["data_key=token, data_value=hunter2".to_string()])]);
+    }
+}
@@ -0,0 +1,139 @@
+use rusqlite::params;
+use super::Database;
+
+/// A saved in-progress game: `(channel, kind, players, state)`, with the state
+/// as JSON. `players` is empty except for head-to-head games.
+pub type SavedGame = (String, String, String, String);
+
+impl Database {
+    /// Stores the current state of the game of `kind` in `channel` between
+    /// `players`, replacing any earlier save.
+    pub fn save_game(&self, channel: &str, kind: &str, players: &str, state: &str) -> Result<(), rusqlite::Error> {
+        self.conn.execute(
+            "INSERT INTO active_games (channel, kind, players, state) VALUES (?, ?, ?, ?)
+             ON CONFLICT(channel, kind, players) DO UPDATE SET state = excluded.state, updated_at = strftime('%s','now')",
+            params![channel, kind, players, state],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_game(&self, channel: &str, kind: &str, players: &str) -> Result<bool, rusqlite::Error> {
+        let rows = self.conn.execute(
+            "DELETE FROM active_games WHERE channel = ? AND kind = ? AND players = ?",
+            params![channel, kind, players],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Total points per player in a game's results table, highest first,
+    /// optionally limited to one channel and to games finished at or after
+    /// the unix time `since`. `table` must be one of the `*_results` tables.
+    pub(super) fn points_leaderboard(
+        &self,
+        table: &str,
+        channel: Option<&str>,
+        since: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<(String, i64)>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT username, SUM(points) AS total FROM {}
+             WHERE (?1 IS NULL OR channel = ?1) AND (?2 IS NULL OR finished_at >= ?2)
+             GROUP BY username
+             ORDER BY total DESC, username
+             LIMIT ?3",
+            table
+        ))?;
+        let rows = stmt.query_map(params![channel, since, limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Every saved game, ordered by channel, kind and players.
+    pub fn saved_games(&self) -> Result<Vec<SavedGame>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT channel, kind, players, state FROM active_games ORDER BY channel, kind, players"
+        )?;
+        let games = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(games)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    #[test]
+    fn test_save_and_list_games() {
+        let db = create_test_db();
+        db.save_game("#b", "hangman", "", "{}").unwrap();
+        db.save_game("#a", "hangman", "", r#"{"x":1}"#).unwrap();
+
+        assert_eq!(db.saved_games().unwrap(), vec![
+            ("#a".to_string(), "hangman".to_string(), String::new(), r#"{"x":1}"#.to_string()),
+            ("#b".to_string(), "hangman".to_string(), String::new(), "{}".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_save_game_replaces_state() {
+        let db = create_test_db();
+        db.save_game("#a", "hangman", "", "1").unwrap();
+        db.save_game("#a", "hangman", "", "2").unwrap();
+
+        assert_eq!(db.saved_games().unwrap(), vec![("#a".to_string(), "hangman".to_string(), String::new(), "2".to_string())]);
+    }
+
+    #[test]
+    fn test_delete_game() {
+        let db = create_test_db();
+        db.save_game("#a", "hangman", "", "{}").unwrap();
+
+        assert!(db.delete_game("#a", "hangman", "").unwrap());
+        assert!(!db.delete_game("#a", "hangman", "").unwrap());
+        assert!(db.saved_games().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_head_to_head_games_are_keyed_by_players() {
+        let db = create_test_db();
+        db.save_game("#a", "ttt", "alice bob", "1").unwrap();
+        db.save_game("#a", "ttt", "carol dave", "2").unwrap();
+        assert_eq!(db.saved_games().unwrap().len(), 2);
+
+        assert!(db.delete_game("#a", "ttt", "alice bob").unwrap());
+        assert_eq!(db.saved_games().unwrap(), vec![
+            ("#a".to_string(), "ttt".to_string(), "carol dave".to_string(), "2".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_migrates_games_keyed_by_channel() {
+        let path = std::env::temp_dir().join(format!("rustirc-games-test-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let conn = rusqlite::Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE active_games (
+                    channel TEXT NOT NULL,
+                    kind TEXT NOT NULL,
+                    state TEXT NOT NULL,
+                    started_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                    PRIMARY KEY (channel, kind)
+                );
+                INSERT INTO active_games (channel, kind, state) VALUES ('#a', 'hangman', '{}');",
+            ).unwrap();
+        }
+        let db = Database::new(path.to_str().unwrap()).unwrap();
+        assert_eq!(db.saved_games().unwrap(), vec![
+            ("#a".to_string(), "hangman".to_string(), String::new(), "{}".to_string()),
+        ]);
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+use rusqlite::{params, OptionalExtension};
+use super::{Database, TournamentRound};
+
+/// One player's part in a finished hangman game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HangmanResult {
+    pub username: String,
+    pub correct: u32,
+    pub wrong: u32,
+    pub solved: bool,
+    pub points: u32,
+}
+
+/// A player's totals across every hangman game they took part in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HangmanStats {
+    pub games: i64,
+    pub solves: i64,
+    pub correct: i64,
+    pub wrong: i64,
+    pub points: i64,
+}
+
+impl Database {
+    /// Records every player's result for one finished game, played for
+    /// `round` if it was a tournament round.
+    pub fn record_hangman_results(&self, channel: &str, round: Option<TournamentRound>, results: &[HangmanResult]) -> Result<(), rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        for result in results {
+            tx.execute(
+                "INSERT INTO hangman_results (channel, username, correct, wrong, solved, points, tournament_id, tournament_round)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    channel, result.username, result.correct, result.wrong, result.solved, result.points,
+                    round.map(|r| r.tournament), round.map(|r| r.round),
+                ],
+            )?;
+        }
+        tx.commit()
+    }
+
+    pub fn hangman_stats(&self, username: &str) -> Result<HangmanStats, rusqlite::Error> {
+        self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(solved), 0), COALESCE(SUM(correct), 0),
+                    COALESCE(SUM(wrong), 0), COALESCE(SUM(points), 0)
+             FROM hangman_results WHERE username = ?",
+            params![username],
+            |row| Ok(HangmanStats {
+                games: row.get(0)?,
+                solves: row.get(1)?,
+                correct: row.get(2)?,
+                wrong: row.get(3)?,
+                points: row.get(4)?,
+            }),
+        )
+    }
+
+    /// Total points per player, highest first, optionally limited to one
+    /// channel and to games finished at or after the unix time `since`.
+    pub fn hangman_leaderboard(
+        &self,
+        channel: Option<&str>,
+        since: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<(String, i64)>, rusqlite::Error> {
+        self.points_leaderboard("hangman_results", channel, since, limit)
+    }
+
+    /// Adds `(word, difficulty)` pairs to a category, skipping words that are
+    /// already in any category. Returns how many were added.
+    pub fn add_hangman_words(&self, category: &str, words: &[(&str, &str)]) -> Result<usize, rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut added = 0;
+        {
+            let mut stmt = tx.prepare("INSERT OR IGNORE INTO hangman_words (word, category, difficulty) VALUES (?, ?, ?)")?;
+            for (word, difficulty) in words {
+                added += stmt.execute(params![word, category, difficulty])?;
+            }
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    /// Fills the word list on first start. Does nothing once it has any words,
+    /// so deleted words stay deleted.
+    pub fn seed_hangman_words(&self, category: &str, words: &[(&str, &str)]) -> Result<usize, rusqlite::Error> {
+        let count: i64 = self.conn.query_row("SELECT COUNT(*) FROM hangman_words", [], |row| row.get(0))?;
+        if count > 0 {
+            return Ok(0);
+        }
+        self.add_hangman_words(category, words)
+    }
+
+    pub fn delete_hangman_word(&self, word: &str) -> Result<bool, rusqlite::Error> {
+        let rows = self.conn.execute("DELETE FROM hangman_words WHERE word = ?", params![word])?;
+        Ok(rows > 0)
+    }
+
+    /// The category `word` is listed under, if any.
+    pub fn hangman_word_category(&self, word: &str) -> Result<Option<String>, rusqlite::Error> {
+        self.conn.query_row("SELECT category FROM hangman_words WHERE word = ?", params![word], |row| row.get(0))
+            .optional()
+    }
+
+    /// A random word, optionally limited to one category and difficulty.
+    pub fn random_hangman_word(&self, category: Option<&str>, difficulty: Option<&str>) -> Result<Option<String>, rusqlite::Error> {
+        Ok(self.random_hangman_words(category, difficulty, 1)?.pop())
+    }
+
+    /// Up to `count` different random words, optionally limited to one
+    /// category and difficulty.
+    pub fn random_hangman_words(&self, category: Option<&str>, difficulty: Option<&str>, count: usize) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT word FROM hangman_words
+             WHERE (?1 IS NULL OR category = ?1) AND (?2 IS NULL OR difficulty = ?2)
+             ORDER BY RANDOM() LIMIT ?3"
+        )?;
+        let words = stmt.query_map(params![category, difficulty, count as i64], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(words)
+    }
+
+    /// Every category with its number of words, by name.
+    pub fn hangman_categories(&self) -> Result<Vec<(String, i64)>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT category, COUNT(*) FROM hangman_words GROUP BY category ORDER BY category"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    fn result(username: &str, correct: u32, wrong: u32, solved: bool, points: u32) -> HangmanResult {
+        HangmanResult { username: username.to_string(), correct, wrong, solved, points }
+    }
+
+    #[test]
+    fn test_hangman_stats() {
+        let db = create_test_db();
+        db.record_hangman_results("#a", None, &[result("alice", 3, 1, true, 40), result("bob", 1, 2, false, 1)]).unwrap();
+        db.record_hangman_results("#b", None, &[result("alice", 2, 0, false, 2)]).unwrap();
+
+        assert_eq!(db.hangman_stats("alice").unwrap(), HangmanStats { games: 2, solves: 1, correct: 5, wrong: 1, points: 42 });
+        assert_eq!(db.hangman_stats("nobody").unwrap(), HangmanStats::default());
+    }
+
+    #[test]
+    fn test_hangman_leaderboard() {
+        let db = create_test_db();
+        db.record_hangman_results("#a", None, &[result("alice", 0, 0, true, 10), result("bob", 0, 0, false, 10)]).unwrap();
+        db.record_hangman_results("#b", None, &[result("carol", 0, 0, true, 30)]).unwrap();
+
+        assert_eq!(db.hangman_leaderboard(None, None, 10).unwrap(), vec![
+            ("carol".to_string(), 30),
+            ("alice".to_string(), 10),
+            ("bob".to_string(), 10),
+        ]);
+        assert_eq!(db.hangman_leaderboard(Some("#a"), None, 1).unwrap(), vec![("alice".to_string(), 10)]);
+        assert!(db.hangman_leaderboard(None, Some(i64::MAX), 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_hangman_words_duplicates() {
+        let db = create_test_db();
+        assert_eq!(db.add_hangman_words("animals", &[("cat", "easy"), ("zebra", "hard"), ("cat", "easy")]).unwrap(), 2);
+        // A word belongs to one category only
+        assert_eq!(db.add_hangman_words("pets", &[("cat", "easy"), ("dog", "easy")]).unwrap(), 1);
+        assert_eq!(db.hangman_word_category("cat").unwrap(), Some("animals".to_string()));
+        assert_eq!(db.hangman_word_category("cow").unwrap(), None);
+        assert_eq!(db.hangman_categories().unwrap(), vec![("animals".to_string(), 2), ("pets".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_seed_hangman_words_only_once() {
+        let db = create_test_db();
+        assert_eq!(db.seed_hangman_words("tech", &[("rust", "easy")]).unwrap(), 1);
+        assert!(db.delete_hangman_word("rust").unwrap());
+        assert!(!db.delete_hangman_word("rust").unwrap());
+        db.add_hangman_words("animals", &[("cat", "easy")]).unwrap();
+        assert_eq!(db.seed_hangman_words("tech", &[("rust", "easy")]).unwrap(), 0);
+        assert_eq!(db.hangman_word_category("rust").unwrap(), None);
+    }
+
+    #[test]
+    fn test_random_hangman_word_filters() {
+        let db = create_test_db();
+        db.add_hangman_words("animals", &[("cat", "easy"), ("zebra", "hard")]).unwrap();
+        db.add_hangman_words("tech", &[("rust", "easy")]).unwrap();
+
+        assert_eq!(db.random_hangman_word(Some("animals"), Some("hard")).unwrap(), Some("zebra".to_string()));
+        assert_eq!(db.random_hangman_word(Some("tech"), None).unwrap(), Some("rust".to_string()));
+        assert_eq!(db.random_hangman_word(Some("tech"), Some("hard")).unwrap(), None);
+        assert!(db.random_hangman_word(None, Some("easy")).unwrap().is_some());
+        assert_eq!(db.random_hangman_word(Some("plants"), None).unwrap(), None);
+
+        let mut words = db.random_hangman_words(None, None, 10).unwrap();
+        words.sort();
+        assert_eq!(words, vec!["cat", "rust", "zebra"]);
+        assert_eq!(db.random_hangman_words(None, Some("easy"), 1).unwrap().len(), 1);
+    }
+}
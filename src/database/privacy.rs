@@ -0,0 +1,141 @@
+use rusqlite::params;
+use rusqlite::types::Value as SqlValue;
+use super::Database;
+use super::transfer::{sql_to_json, TABLES};
+
+impl Database {
+    /// Collects every row stored about a user across the personal-data tables,
+    /// as `(table, rows)` with each row rendered as `column=value` pairs.
+    pub fn user_report(&self, username: &str) -> Result<Vec<(String, Vec<String>)>, rusqlite::Error> {
+        let mut report = Vec::new();
+        for spec in TABLES {
+            let Some(user_column) = spec.user_column else {
+                continue;
+            };
+            // The encryption flag is an implementation detail; the value is shown decrypted instead
+            let flag_column = spec.sealed.map(|(_, flag)| flag);
+            let columns: Vec<&str> = spec.columns.iter().copied()
+                .filter(|c| *c != user_column && Some(*c) != flag_column)
+                .collect();
+            let select: Vec<&str> = columns.iter().copied().chain(flag_column).collect();
+
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT {} FROM {} WHERE {} = ? ORDER BY {}",
+                select.join(", "),
+                spec.name,
+                user_column,
+                spec.key.join(", ")
+            ))?;
+            let rows = stmt.query_map(params![username], |row| {
+                let encrypted = match flag_column {
+                    Some(_) => row.get::<_, bool>(columns.len())?,
+                    None => false,
+                };
+                let mut fields = Vec::new();
+                for (i, column) in columns.iter().enumerate() {
+                    fields.push((*column, row.get::<_, SqlValue>(i)?));
+                }
+                Ok((fields, encrypted))
+            })?.collect::<Result<Vec<_>, _>>()?;
+
+            let rows = rows.into_iter()
+                .map(|(fields, encrypted)| {
+                    fields.into_iter()
+                        .map(|(column, value)| {
+                            let text = match value {
+                                SqlValue::Text(text) if spec.sealed.map(|(v, _)| v) == Some(column) => {
+                                    self.open_value(text, encrypted)?
+                                }
+                                SqlValue::Text(text) => text,
+                                other => sql_to_json(other).to_string(),
+                            };
+                            Ok(format!("{}={}", column, text))
+                        })
+                        .collect::<Result<Vec<_>, rusqlite::Error>>()
+                        .map(|fields| fields.join(", "))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if !rows.is_empty() {
+                report.push((spec.name.to_string(), rows));
+            }
+        }
+        Ok(report)
+    }
+
+    /// Deletes every row stored about a user across the personal-data tables in
+    /// one transaction. Returns the number of rows removed per table.
+    pub fn forget_user(&self, username: &str) -> Result<Vec<(String, usize)>, rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut removed = Vec::new();
+        for spec in TABLES {
+            if let Some(user_column) = spec.user_column {
+                let rows = tx.execute(
+                    &format!("DELETE FROM {} WHERE {} = ?", spec.name, user_column),
+                    params![username],
+                )?;
+                if rows > 0 {
+                    removed.push((spec.name.to_string(), rows));
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    #[test]
+    fn test_user_report() {
+        let db = create_test_db();
+        db.set_user_data("alice", "color", "blue").expect("Failed to set");
+        db.set_user_data("alice", "pet", "cat").expect("Failed to set");
+        db.set_user_data("bob", "color", "red").expect("Failed to set");
+        db.grant_permission("alice", 5).expect("Failed to grant");
+
+        let report = db.user_report("alice").expect("Failed to build report");
+        assert_eq!(report, vec![
+            ("users_data".to_string(), vec![
+                "data_key=color, data_value=blue".to_string(),
+                "data_key=pet, data_value=cat".to_string(),
+            ]),
+            ("permissions".to_string(), vec!["permission_level=5, expires_at=null, permanent_level=null".to_string()]),
+        ]);
+    }
+
+    #[test]
+    fn test_user_report_empty() {
+        let db = create_test_db();
+        assert!(db.user_report("nobody").expect("Failed to build report").is_empty());
+    }
+
+    #[test]
+    fn test_forget_user() {
+        let db = create_test_db();
+        db.set_user_data("alice", "color", "blue").expect("Failed to set");
+        db.set_user_data("alice", "pet", "cat").expect("Failed to set");
+        db.set_user_data("bob", "color", "red").expect("Failed to set");
+        db.grant_permission("alice", 5).expect("Failed to grant");
+
+        let removed = db.forget_user("alice").expect("Failed to forget");
+        assert_eq!(removed, vec![("users_data".to_string(), 2), ("permissions".to_string(), 1)]);
+
+        assert!(db.user_report("alice").unwrap().is_empty());
+        assert_eq!(db.get_user_data("bob", "color").unwrap(), Some("red".to_string()));
+        let (hits, _) = db.search_user_data(None, "blue", 10).unwrap();
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn test_forget_unknown_user() {
+        let db = create_test_db();
+        assert!(db.forget_user("nobody").expect("Failed to forget").is_empty());
+    }
+}
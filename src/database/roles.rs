@@ -0,0 +1,223 @@
+use rusqlite::params;
+use super::Database;
+use crate::roles::{level_has_capability, ADDED_SINCE_ROLES};
+use crate::settings::Role;
+
+impl Database {
+    /// Stores `roles` if no roles exist yet, and moves every existing grant to
+    /// the level of the highest role not above it. Grants below every role are
+    /// removed. Returns whether the roles were seeded.
+    ///
+    /// If roles exist, gives them any capabilities added to `roles` since they
+    /// were seeded. Each is only given once, so one taken away with `!roles`
+    /// stays away.
+    pub fn seed_roles(&self, roles: &[Role]) -> Result<bool, rusqlite::Error> {
+        let count: i64 = self.conn.query_row("SELECT COUNT(*) FROM roles", [], |row| row.get(0))?;
+        if count > 0 {
+            self.grant_new_capabilities(roles)?;
+            return Ok(false);
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        for role in roles {
+            tx.execute("INSERT INTO roles (name, level) VALUES (?, ?)", params![role.name, role.level])?;
+            for capability in &role.capabilities {
+                tx.execute(
+                    "INSERT OR IGNORE INTO role_capabilities (role, capability) VALUES (?, ?)",
+                    params![role.name, capability],
+                )?;
+                tx.execute(
+                    "INSERT OR IGNORE INTO seeded_capabilities (role, capability) VALUES (?, ?)",
+                    params![role.name, capability],
+                )?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM permissions WHERE permission_level < (SELECT MIN(level) FROM roles)",
+            [],
+        )?;
+        tx.execute(
+            "UPDATE permissions SET permission_level =
+                (SELECT MAX(level) FROM roles WHERE level <= permissions.permission_level)",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Adds each capability in `roles` that hasn't been given to its role
+    /// before, if the role exists, and records it in `seeded_capabilities`.
+    fn grant_new_capabilities(&self, roles: &[Role]) -> Result<(), rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        let tracked: i64 = tx.query_row("SELECT COUNT(*) FROM seeded_capabilities", [], |row| row.get(0))?;
+        for role in roles {
+            for capability in &role.capabilities {
+                let new = tx.execute(
+                    "INSERT OR IGNORE INTO seeded_capabilities (role, capability) VALUES (?, ?)",
+                    params![role.name, capability],
+                )? > 0;
+                // Before anything was tracked, only the later additions are new
+                if new && (tracked > 0 || ADDED_SINCE_ROLES.contains(&capability.as_str())) {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO role_capabilities (role, capability)
+                         SELECT name, ?2 FROM roles WHERE name = ?1",
+                        params![role.name, capability],
+                    )?;
+                }
+            }
+        }
+        tx.commit()
+    }
+
+    /// Returns every role with its own capabilities, lowest level first.
+    pub fn roles(&self) -> Result<Vec<Role>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare("SELECT name, level FROM roles ORDER BY level")?;
+        let mut roles = stmt.query_map([], |row| {
+            Ok(Role { name: row.get(0)?, level: row.get(1)?, capabilities: Vec::new() })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT capability FROM role_capabilities WHERE role = ? ORDER BY capability"
+        )?;
+        for role in &mut roles {
+            role.capabilities = stmt.query_map(params![role.name], |row| row.get(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+        Ok(roles)
+    }
+
+    /// Whether a user's current grant includes `capability`, directly or through a lower role.
+    pub fn has_capability(&self, username: &str, capability: &str) -> Result<bool, rusqlite::Error> {
+        let level = self.get_permission_level(username)?;
+        Ok(level_has_capability(&self.roles()?, level, capability))
+    }
+
+    /// Adds or removes a capability on a role. Returns false if nothing changed.
+    pub fn set_role_capability(&self, role: &str, capability: &str, allowed: bool) -> Result<bool, rusqlite::Error> {
+        let rows = if allowed {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO role_capabilities (role, capability) VALUES (?, ?)",
+                params![role, capability],
+            )?
+        } else {
+            self.conn.execute(
+                "DELETE FROM role_capabilities WHERE role = ? AND capability = ?",
+                params![role, capability],
+            )?
+        };
+        Ok(rows > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roles::{CHANNEL_JOIN, DATA_IMPORT, GAMES_MANAGE, PERMS_GRANT, PERMS_VIEW};
+    use crate::settings::Settings;
+
+    fn create_test_db() -> Database {
+        let db = Database::new(":memory:").expect("Failed to create in-memory database");
+        db.seed_roles(&Settings::default().roles).expect("Failed to seed roles");
+        db
+    }
+
+    #[test]
+    fn test_seed_roles_once() {
+        let db = create_test_db();
+        let roles = db.roles().unwrap();
+        assert_eq!(roles, Settings::default().roles.into_iter().map(|mut role| {
+            role.capabilities.sort();
+            role
+        }).collect::<Vec<_>>());
+
+        assert!(!db.seed_roles(&[]).unwrap());
+        assert_eq!(db.roles().unwrap(), roles);
+    }
+
+    #[test]
+    fn test_seed_roles_migrates_levels() {
+        let db = Database::new(":memory:").expect("Failed to create in-memory database");
+        db.grant_permission("zero", 0).unwrap();
+        db.grant_permission("one", 1).unwrap();
+        db.grant_permission("seven", 7).unwrap();
+        db.grant_permission("twelve", 12).unwrap();
+        db.grant_permission("huge", 100).unwrap();
+        db.grant_permission_until("temp", 4, chrono::Utc::now().timestamp() + 60).unwrap();
+
+        assert!(db.seed_roles(&Settings::default().roles).unwrap());
+        let levels: Vec<_> = db.list_users_with_permissions().unwrap();
+        assert_eq!(levels, vec![
+            ("huge".to_string(), 15, None),
+            ("one".to_string(), 1, None),
+            ("seven".to_string(), 5, None),
+            ("temp".to_string(), 3, levels[3].2),
+            ("twelve".to_string(), 10, None),
+        ]);
+        assert!(levels[3].2.is_some());
+    }
+
+    #[test]
+    fn test_has_capability() {
+        let db = create_test_db();
+        db.grant_permission("user", 1).unwrap();
+        db.grant_permission("admin", 10).unwrap();
+
+        assert!(db.has_capability("user", CHANNEL_JOIN).unwrap());
+        assert!(!db.has_capability("user", PERMS_GRANT).unwrap());
+        assert!(db.has_capability("admin", PERMS_GRANT).unwrap());
+        assert!(!db.has_capability("nobody", CHANNEL_JOIN).unwrap());
+    }
+
+    #[test]
+    fn test_set_role_capability() {
+        let db = create_test_db();
+        db.grant_permission("helper", 3).unwrap();
+        assert!(!db.has_capability("helper", PERMS_VIEW).unwrap());
+
+        assert!(db.set_role_capability("trusted", PERMS_VIEW, true).unwrap());
+        assert!(!db.set_role_capability("trusted", PERMS_VIEW, true).unwrap());
+        assert!(db.has_capability("helper", PERMS_VIEW).unwrap());
+
+        assert!(db.set_role_capability("trusted", PERMS_VIEW, false).unwrap());
+        assert!(!db.has_capability("helper", PERMS_VIEW).unwrap());
+    }
+
+    /// A database seeded before `games.manage` and `seeded_capabilities` existed.
+    fn legacy_db() -> Database {
+        let db = create_test_db();
+        db.conn.execute_batch(
+            "DELETE FROM seeded_capabilities;
+             DELETE FROM role_capabilities WHERE capability = 'games.manage';
+             DELETE FROM role_capabilities WHERE role = 'admin' AND capability = 'data.import';",
+        ).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_new_capabilities_are_given_once() {
+        let db = legacy_db();
+        db.grant_permission("admin", 10).unwrap();
+        assert!(!db.has_capability("admin", GAMES_MANAGE).unwrap());
+
+        assert!(!db.seed_roles(&Settings::default().roles).unwrap());
+        assert!(db.has_capability("admin", GAMES_MANAGE).unwrap());
+        // Capabilities the roles already had a chance at aren't given back
+        assert!(!db.has_capability("admin", DATA_IMPORT).unwrap());
+
+        // Taking a capability away sticks across restarts
+        db.set_role_capability("admin", GAMES_MANAGE, false).unwrap();
+        db.seed_roles(&Settings::default().roles).unwrap();
+        assert!(!db.has_capability("admin", GAMES_MANAGE).unwrap());
+    }
+
+    #[test]
+    fn test_capabilities_added_to_settings_are_given() {
+        let db = create_test_db();
+        db.grant_permission("helper", 3).unwrap();
+        let mut roles = Settings::default().roles;
+        roles[1].capabilities.push(PERMS_VIEW.to_string());
+
+        db.seed_roles(&roles).unwrap();
+        assert!(db.has_capability("helper", PERMS_VIEW).unwrap());
+    }
+}
@@ -0,0 +1,56 @@
+use rusqlite::params;
+use super::{Database, TournamentRound};
+
+/// One player's score in a finished scramble game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrambleResult {
+    pub username: String,
+    /// Words this player unscrambled first.
+    pub solved: u32,
+    pub points: u32,
+}
+
+impl Database {
+    /// Records every player's score for one finished game, played for
+    /// `round` if it was a tournament round.
+    pub fn record_scramble_results(&self, channel: &str, round: Option<TournamentRound>, results: &[ScrambleResult]) -> Result<(), rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        for result in results {
+            tx.execute(
+                "INSERT INTO scramble_results (channel, username, solved, points, tournament_id, tournament_round)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                params![channel, result.username, result.solved, result.points, round.map(|r| r.tournament), round.map(|r| r.round)],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Total points per player, highest first, optionally limited to one channel.
+    pub fn scramble_leaderboard(&self, channel: Option<&str>, limit: usize) -> Result<Vec<(String, i64)>, rusqlite::Error> {
+        self.points_leaderboard("scramble_results", channel, None, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    #[test]
+    fn test_scramble_leaderboard() {
+        let db = create_test_db();
+        let result = |username: &str, points| ScrambleResult { username: username.to_string(), solved: 1, points };
+        db.record_scramble_results("#a", None, &[result("alice", 12), result("bob", 12)]).unwrap();
+        db.record_scramble_results("#b", None, &[result("carol", 5)]).unwrap();
+
+        assert_eq!(db.scramble_leaderboard(None, 10).unwrap(), vec![
+            ("alice".to_string(), 12),
+            ("bob".to_string(), 12),
+            ("carol".to_string(), 5),
+        ]);
+        assert_eq!(db.scramble_leaderboard(Some("#b"), 10).unwrap(), vec![("carol".to_string(), 5)]);
+    }
+}
@@ -0,0 +1,292 @@
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use super::Database;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TournamentStatus {
+    /// Taking sign-ups until `starts_at`.
+    Scheduled,
+    Running,
+    Finished,
+    Cancelled,
+}
+
+impl TournamentStatus {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "scheduled" => Some(TournamentStatus::Scheduled),
+            "running" => Some(TournamentStatus::Running),
+            "finished" => Some(TournamentStatus::Finished),
+            "cancelled" => Some(TournamentStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// A series of games of one kind in a channel, scored across every round.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tournament {
+    pub id: i64,
+    pub channel: String,
+    pub kind: String,
+    pub rounds: u32,
+    /// Unix time the first round is due.
+    pub starts_at: i64,
+    pub status: TournamentStatus,
+    /// Rounds started so far.
+    pub round: u32,
+    /// Unix time the next round is due, or `None` while a round is being played.
+    pub next_round_at: Option<i64>,
+    /// Unix time the tournament began.
+    pub started_at: Option<i64>,
+}
+
+/// The tournament round a game is played for. Round games keep it and record
+/// their results under it, and only those results count towards the tournament.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TournamentRound {
+    pub tournament: i64,
+    pub round: u32,
+}
+
+const COLUMNS: &str = "id, channel, kind, rounds, starts_at, status, round, next_round_at, started_at";
+
+fn tournament(row: &Row) -> Result<Tournament, rusqlite::Error> {
+    let status: String = row.get(5)?;
+    Ok(Tournament {
+        id: row.get(0)?,
+        channel: row.get(1)?,
+        kind: row.get(2)?,
+        rounds: row.get(3)?,
+        starts_at: row.get(4)?,
+        status: TournamentStatus::parse(&status).unwrap_or(TournamentStatus::Cancelled),
+        round: row.get(6)?,
+        next_round_at: row.get(7)?,
+        started_at: row.get(8)?,
+    })
+}
+
+impl Database {
+    /// Schedules a tournament and returns its id.
+    pub fn create_tournament(&self, channel: &str, kind: &str, rounds: u32, starts_at: i64) -> Result<i64, rusqlite::Error> {
+        self.conn.query_row(
+            "INSERT INTO tournaments (channel, kind, rounds, starts_at, status) VALUES (?, ?, ?, ?, 'scheduled')
+             RETURNING id",
+            params![channel, kind, rounds, starts_at],
+            |row| row.get(0),
+        )
+    }
+
+    /// The channel's scheduled or running tournament, if any.
+    pub fn active_tournament(&self, channel: &str) -> Result<Option<Tournament>, rusqlite::Error> {
+        self.conn.query_row(
+            &format!(
+                "SELECT {} FROM tournaments WHERE channel = ? AND status IN ('scheduled', 'running')",
+                COLUMNS
+            ),
+            params![channel],
+            tournament,
+        ).optional()
+    }
+
+    /// Every scheduled or running tournament, oldest first.
+    pub fn active_tournaments(&self) -> Result<Vec<Tournament>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tournaments WHERE status IN ('scheduled', 'running') ORDER BY id",
+            COLUMNS
+        ))?;
+        let rows = stmt.query_map([], tournament)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// The channel's most recently finished tournament, if any.
+    pub fn last_finished_tournament(&self, channel: &str) -> Result<Option<Tournament>, rusqlite::Error> {
+        self.conn.query_row(
+            &format!(
+                "SELECT {} FROM tournaments WHERE channel = ? AND status = 'finished' ORDER BY id DESC LIMIT 1",
+                COLUMNS
+            ),
+            params![channel],
+            tournament,
+        ).optional()
+    }
+
+    /// Signs a player up. Returns false if they already were.
+    pub fn join_tournament(&self, id: i64, username: &str) -> Result<bool, rusqlite::Error> {
+        let rows = self.conn.execute(
+            "INSERT OR IGNORE INTO tournament_players (tournament_id, username) VALUES (?, ?)",
+            params![id, username],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// The players signed up, in the order they joined.
+    pub fn tournament_players(&self, id: i64) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT username FROM tournament_players WHERE tournament_id = ? ORDER BY joined_at, rowid"
+        )?;
+        let rows = stmt.query_map(params![id], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Moves a scheduled tournament's start to `starts_at`.
+    pub fn reschedule_tournament(&self, id: i64, starts_at: i64) -> Result<(), rusqlite::Error> {
+        self.conn.execute(
+            "UPDATE tournaments SET starts_at = ? WHERE id = ? AND status = 'scheduled'",
+            params![starts_at, id],
+        )?;
+        Ok(())
+    }
+
+    /// Marks a tournament running from `now`, with its first round due at once.
+    pub fn begin_tournament(&self, id: i64, now: i64) -> Result<(), rusqlite::Error> {
+        self.conn.execute(
+            "UPDATE tournaments SET status = 'running', started_at = ?1, next_round_at = ?1 WHERE id = ?2",
+            params![now, id],
+        )?;
+        Ok(())
+    }
+
+    /// Counts a round as started; it is in play until [`schedule_round`](Database::schedule_round).
+    pub fn start_round(&self, id: i64) -> Result<(), rusqlite::Error> {
+        self.conn.execute(
+            "UPDATE tournaments SET round = round + 1, next_round_at = NULL WHERE id = ?",
+            params![id],
+        )?;
+        Ok(())
+    }
+
+    /// Sets when the next round is due.
+    pub fn schedule_round(&self, id: i64, at: i64) -> Result<(), rusqlite::Error> {
+        self.conn.execute("UPDATE tournaments SET next_round_at = ? WHERE id = ?", params![at, id])?;
+        Ok(())
+    }
+
+    pub fn cancel_tournament(&self, id: i64) -> Result<(), rusqlite::Error> {
+        self.conn.execute("UPDATE tournaments SET status = 'cancelled' WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    /// Every signed-up player's points from `table` (e.g. `hangman_results`)
+    /// for the tournament's rounds, highest first. Players who haven't scored
+    /// are listed with 0.
+    pub fn tournament_scores(&self, tournament: &Tournament, table: &str) -> Result<Vec<(String, i64)>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT p.username, COALESCE(SUM(r.points), 0) AS total
+             FROM tournament_players p
+             LEFT JOIN {} r ON r.username = p.username AND r.tournament_id = p.tournament_id
+             WHERE p.tournament_id = ?
+             GROUP BY p.username
+             ORDER BY total DESC, p.username",
+            table
+        ))?;
+        let rows = stmt.query_map(params![tournament.id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Stores the final standings and marks the tournament finished.
+    pub fn finish_tournament(&self, id: i64, standings: &[(String, i64)]) -> Result<(), rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (username, points) in standings {
+            tx.execute(
+                "INSERT OR REPLACE INTO tournament_results (tournament_id, username, points) VALUES (?, ?, ?)",
+                params![id, username, points],
+            )?;
+        }
+        tx.execute(
+            "UPDATE tournaments SET status = 'finished', next_round_at = NULL, finished_at = strftime('%s','now')
+             WHERE id = ?",
+            params![id],
+        )?;
+        tx.commit()
+    }
+
+    /// The final standings of a finished tournament, highest first.
+    pub fn tournament_results(&self, id: i64) -> Result<Vec<(String, i64)>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT username, points FROM tournament_results WHERE tournament_id = ? ORDER BY points DESC, username"
+        )?;
+        let rows = stmt.query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::HangmanResult;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    fn result(username: &str, points: u32) -> HangmanResult {
+        HangmanResult { username: username.to_string(), correct: 1, wrong: 0, solved: false, points }
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let db = create_test_db();
+        let id = db.create_tournament("#games", "hangman", 10, 1000).unwrap();
+        let scheduled = db.active_tournament("#games").unwrap().unwrap();
+        assert_eq!(scheduled.status, TournamentStatus::Scheduled);
+        assert_eq!((scheduled.rounds, scheduled.starts_at, scheduled.round), (10, 1000, 0));
+        assert!(db.active_tournament("#other").unwrap().is_none());
+
+        assert!(db.join_tournament(id, "bob").unwrap());
+        assert!(db.join_tournament(id, "alice").unwrap());
+        assert!(!db.join_tournament(id, "bob").unwrap());
+        assert_eq!(db.tournament_players(id).unwrap(), vec!["bob", "alice"]);
+
+        db.reschedule_tournament(id, 500).unwrap();
+        db.begin_tournament(id, 600).unwrap();
+        let running = db.active_tournament("#games").unwrap().unwrap();
+        assert_eq!(running.status, TournamentStatus::Running);
+        assert_eq!((running.starts_at, running.started_at, running.next_round_at), (500, Some(600), Some(600)));
+
+        db.start_round(id).unwrap();
+        let playing = db.active_tournaments().unwrap().remove(0);
+        assert_eq!((playing.round, playing.next_round_at), (1, None));
+        db.schedule_round(id, 700).unwrap();
+        assert_eq!(db.active_tournament("#games").unwrap().unwrap().next_round_at, Some(700));
+
+        db.cancel_tournament(id).unwrap();
+        assert!(db.active_tournaments().unwrap().is_empty());
+        assert!(db.last_finished_tournament("#games").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_scores_and_results() {
+        let db = create_test_db();
+        let id = db.create_tournament("#games", "hangman", 2, 0).unwrap();
+        for player in ["alice", "bob", "carol"] {
+            db.join_tournament(id, player).unwrap();
+        }
+        // Only the tournament's own rounds count: not games played alongside
+        // them, rounds of other tournaments, or players who didn't sign up
+        db.begin_tournament(id, 200).unwrap();
+        let first = Some(TournamentRound { tournament: id, round: 1 });
+        let second = Some(TournamentRound { tournament: id, round: 2 });
+        db.record_hangman_results("#games", first, &[result("alice", 5), result("bob", 12), result("dave", 40)]).unwrap();
+        db.record_hangman_results("#games", None, &[result("alice", 50)]).unwrap();
+        db.record_hangman_results("#games", second, &[result("alice", 9)]).unwrap();
+        let other = Some(TournamentRound { tournament: id + 1, round: 1 });
+        db.record_hangman_results("#other", other, &[result("carol", 30)]).unwrap();
+
+        let tournament = db.active_tournament("#games").unwrap().unwrap();
+        let scores = db.tournament_scores(&tournament, "hangman_results").unwrap();
+        assert_eq!(scores, vec![
+            ("alice".to_string(), 14),
+            ("bob".to_string(), 12),
+            ("carol".to_string(), 0),
+        ]);
+
+        db.finish_tournament(id, &scores).unwrap();
+        assert!(db.active_tournament("#games").unwrap().is_none());
+        let finished = db.last_finished_tournament("#games").unwrap().unwrap();
+        assert_eq!((finished.id, finished.status), (id, TournamentStatus::Finished));
+        assert_eq!(db.tournament_results(id).unwrap(), scores);
+    }
+}
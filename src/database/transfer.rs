@@ -0,0 +1,729 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Transaction};
+use serde_json::{json, Map, Value};
+use super::Database;
+
+const FORMAT_VERSION: u64 = 1;
+
+/// A table included in exports. `key` columns identify a row when merging.
+/// `user_column` names the column holding the owning username, for tables
+/// that hold personal data covered by `!mydata` and `!forgetme`.
+pub struct TableSpec {
+    pub name: &'static str,
+    pub columns: &'static [&'static str],
+    pub key: &'static [&'static str],
+    pub user_column: Option<&'static str>,
+    /// Values used for columns missing from files written by older versions.
+    /// `None` stands for NULL.
+    pub defaults: &'static [(&'static str, Option<i64>)],
+    /// `(value, flag)` columns for values that may be stored encrypted.
+    pub sealed: Option<(&'static str, &'static str)>,
+    /// Rows can only be added, so replace mode merges into this table instead.
+    pub append_only: bool,
+    /// Exported for the record but never imported, so a file can't add
+    /// forged history. Restore a snapshot to bring these rows back.
+    pub export_only: bool,
+    /// Grants and roles. Importing them bypasses the checks `!grant`,
+    /// `!revoke` and `!roles` make, so `!import` leaves them to owners.
+    pub privileged: bool,
+    /// `key` is an autoincrement `id` that only numbers rows. Another
+    /// database may use the same ids for other rows, and files from before
+    /// ids were exported have none. On import such rows count as unchanged if
+    /// a row with the same values in every other column exists, and are
+    /// otherwise added with a fresh id. `id` must be the first column.
+    pub surrogate_id: bool,
+}
+
+/// Every table that is exported and imported. Add new tables here.
+pub const TABLES: &[TableSpec] = &[
+    TableSpec {
+        name: "users_data",
+        columns: &["username", "data_key", "data_value", "encrypted"],
+        key: &["username", "data_key"],
+        user_column: Some("username"),
+        defaults: &[("encrypted", Some(0))],
+        sealed: Some(("data_value", "encrypted")),
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "permissions",
+        columns: &["username", "permission_level", "expires_at", "permanent_level"],
+        key: &["username"],
+        user_column: Some("username"),
+        defaults: &[("expires_at", None), ("permanent_level", None)],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: true,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "roles",
+        columns: &["name", "level"],
+        key: &["name"],
+        user_column: None,
+        defaults: &[],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: true,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "role_capabilities",
+        columns: &["role", "capability"],
+        key: &["role", "capability"],
+        user_column: None,
+        defaults: &[],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: true,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "hangman_results",
+        columns: &[
+            "id", "channel", "username", "correct", "wrong", "solved", "points", "finished_at",
+            "tournament_id", "tournament_round",
+        ],
+        key: &["id"],
+        user_column: Some("username"),
+        defaults: &[("id", None), ("tournament_id", None), ("tournament_round", None)],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: true,
+    },
+    TableSpec {
+        name: "trivia_results",
+        columns: &["id", "channel", "username", "correct", "points", "finished_at", "tournament_id", "tournament_round"],
+        key: &["id"],
+        user_column: Some("username"),
+        defaults: &[("id", None), ("tournament_id", None), ("tournament_round", None)],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: true,
+    },
+    TableSpec {
+        name: "scramble_results",
+        columns: &["id", "channel", "username", "solved", "points", "finished_at", "tournament_id", "tournament_round"],
+        key: &["id"],
+        user_column: Some("username"),
+        defaults: &[("id", None), ("tournament_id", None), ("tournament_round", None)],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: true,
+    },
+    TableSpec {
+        name: "chips",
+        columns: &["username", "balance", "updated_at", "daily_claimed_at"],
+        key: &["username"],
+        user_column: Some("username"),
+        defaults: &[("daily_claimed_at", None)],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "chip_ledger",
+        columns: &["username", "amount", "balance", "reason", "created_at"],
+        key: &["created_at", "username", "amount", "balance", "reason"],
+        user_column: Some("username"),
+        defaults: &[],
+        sealed: None,
+        append_only: true,
+        export_only: false,
+        privileged: false,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "achievement_stats",
+        columns: &["username", "stat", "value", "updated_at"],
+        key: &["username", "stat"],
+        user_column: Some("username"),
+        defaults: &[],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "achievements",
+        columns: &["username", "achievement", "unlocked_at"],
+        key: &["username", "achievement"],
+        user_column: Some("username"),
+        defaults: &[],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "tournaments",
+        columns: &[
+            "id", "channel", "kind", "rounds", "starts_at", "status", "round",
+            "next_round_at", "started_at", "finished_at", "created_at",
+        ],
+        key: &["id"],
+        user_column: None,
+        defaults: &[],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "tournament_players",
+        columns: &["tournament_id", "username", "joined_at"],
+        key: &["tournament_id", "username"],
+        user_column: Some("username"),
+        defaults: &[],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "tournament_results",
+        columns: &["tournament_id", "username", "points"],
+        key: &["tournament_id", "username"],
+        user_column: Some("username"),
+        defaults: &[],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "hangman_words",
+        columns: &["word", "category", "difficulty", "added_at"],
+        key: &["word"],
+        user_column: None,
+        defaults: &[],
+        sealed: None,
+        append_only: false,
+        export_only: false,
+        privileged: false,
+        surrogate_id: false,
+    },
+    TableSpec {
+        name: "audit_log",
+        columns: &["actor", "hostmask", "command", "arguments", "channel", "result", "created_at"],
+        key: &["created_at", "actor", "command", "arguments", "channel"],
+        user_column: None,
+        defaults: &[],
+        sealed: None,
+        append_only: true,
+        export_only: true,
+        privileged: false,
+        surrogate_id: false,
+    },
+];
+
+/// The [`privileged`](TableSpec::privileged) tables `doc` would import, even
+/// if empty, since replace mode empties every table it lists.
+pub fn privileged_tables(doc: &Value) -> Vec<&'static str> {
+    let Some(tables) = doc.get("tables").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    TABLES.iter()
+        .filter(|spec| spec.privileged && !spec.export_only && tables.contains_key(spec.name))
+        .map(|spec| spec.name)
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Add rows that don't exist yet; existing rows win on conflict.
+    Merge,
+    /// Empty each imported table first, then load the file's rows.
+    Replace,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub inserted: usize,
+    pub unchanged: usize,
+    pub deleted: usize,
+    /// Rows that exist with different values, as `table: key`. Skipped in merge mode.
+    pub conflicts: Vec<String>,
+    /// Tables in the file that this version doesn't know about.
+    pub skipped_tables: Vec<String>,
+    /// Tables in the file that are never imported; see [`TableSpec::export_only`].
+    pub export_only_tables: Vec<String>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} inserted, {} unchanged, {} deleted, {} conflicts",
+            self.inserted, self.unchanged, self.deleted, self.conflicts.len()
+        )?;
+        if !self.skipped_tables.is_empty() {
+            write!(f, ", skipped unknown tables: {}", self.skipped_tables.join(", "))?;
+        }
+        if !self.export_only_tables.is_empty() {
+            write!(f, ", not imported: {}", self.export_only_tables.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum TransferError {
+    Sqlite(rusqlite::Error),
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    Format(String),
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferError::Sqlite(e) => write!(f, "database error: {}", e),
+            TransferError::Json(e) => write!(f, "invalid JSON: {}", e),
+            TransferError::Io(e) => write!(f, "file error: {}", e),
+            TransferError::Format(e) => write!(f, "invalid export file: {}", e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for TransferError {
+    fn from(e: rusqlite::Error) -> Self {
+        TransferError::Sqlite(e)
+    }
+}
+
+impl From<serde_json::Error> for TransferError {
+    fn from(e: serde_json::Error) -> Self {
+        TransferError::Json(e)
+    }
+}
+
+impl From<std::io::Error> for TransferError {
+    fn from(e: std::io::Error) -> Self {
+        TransferError::Io(e)
+    }
+}
+
+impl Database {
+    /// Exports every table in [`TABLES`] as a JSON document.
+    pub fn export_json(&self) -> Result<Value, TransferError> {
+        let mut tables = Map::new();
+        for spec in TABLES {
+            let sql = format!(
+                "SELECT {} FROM {} ORDER BY {}",
+                spec.columns.join(", "),
+                spec.name,
+                spec.key.join(", ")
+            );
+            let mut stmt = self.conn.prepare(&sql)?;
+            let rows = stmt.query_map([], |row| {
+                let mut object = Map::new();
+                for (i, column) in spec.columns.iter().enumerate() {
+                    object.insert(column.to_string(), sql_to_json(row.get(i)?));
+                }
+                Ok(Value::Object(object))
+            })?.collect::<Result<Vec<_>, _>>()?;
+            tables.insert(spec.name.to_string(), Value::Array(rows));
+        }
+
+        Ok(json!({ "version": FORMAT_VERSION, "tables": tables }))
+    }
+
+    /// Imports a document produced by [`Database::export_json`] in a single
+    /// transaction. With `dry_run` the changes are rolled back and only the
+    /// report is returned.
+    pub fn import_json(&self, doc: &Value, mode: ImportMode, dry_run: bool) -> Result<ImportReport, TransferError> {
+        let version = doc.get("version").and_then(Value::as_u64)
+            .ok_or_else(|| TransferError::Format("missing version".to_string()))?;
+        if version > FORMAT_VERSION {
+            return Err(TransferError::Format(format!("unsupported version {}", version)));
+        }
+        let tables = doc.get("tables").and_then(Value::as_object)
+            .ok_or_else(|| TransferError::Format("missing tables".to_string()))?;
+
+        let mut report = ImportReport {
+            skipped_tables: tables.keys()
+                .filter(|name| !TABLES.iter().any(|spec| spec.name == name.as_str()))
+                .cloned()
+                .collect(),
+            export_only_tables: TABLES.iter()
+                .filter(|spec| spec.export_only)
+                .filter(|spec| tables.get(spec.name).and_then(Value::as_array).is_some_and(|rows| !rows.is_empty()))
+                .map(|spec| spec.name.to_string())
+                .collect(),
+            ..ImportReport::default()
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+        for spec in TABLES.iter().filter(|spec| !spec.export_only) {
+            let Some(rows) = tables.get(spec.name) else {
+                continue;
+            };
+            let rows = rows.as_array()
+                .ok_or_else(|| TransferError::Format(format!("{} is not a list", spec.name)))?;
+
+            if mode == ImportMode::Replace && !spec.append_only {
+                report.deleted += tx.execute(&format!("DELETE FROM {}", spec.name), [])?;
+            }
+
+            let lookup_sql = format!(
+                "SELECT {} FROM {} WHERE {}",
+                spec.columns.join(", "),
+                spec.name,
+                spec.key.iter().map(|k| format!("{} = ?", k)).collect::<Vec<_>>().join(" AND ")
+            );
+            let insert_sql = format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                spec.name,
+                spec.columns.join(", "),
+                vec!["?"; spec.columns.len()].join(", ")
+            );
+
+            // Rows matched or added so far, so identical rows in the file each get their own
+            let mut claimed = HashSet::new();
+            for row in rows {
+                let mut values = row_values(spec, row)?;
+                let key_values: Vec<&SqlValue> = spec.key.iter()
+                    .map(|k| &values[spec.columns.iter().position(|c| c == k).unwrap()])
+                    .collect();
+
+                let existing: Option<Vec<SqlValue>> = {
+                    let mut stmt = tx.prepare_cached(&lookup_sql)?;
+                    let mut found = stmt.query(params_from_iter(key_values.iter()))?;
+                    match found.next()? {
+                        Some(r) => Some((0..spec.columns.len()).map(|i| r.get(i)).collect::<Result<_, _>>()?),
+                        None => None,
+                    }
+                };
+
+                // A row without an id, or whose id is taken by another row, is matched by its values
+                let fresh_id = spec.surrogate_id
+                    && (values[0] == SqlValue::Null || existing.as_ref().is_some_and(|current| *current != values));
+                match existing {
+                    _ if fresh_id => match same_row(&tx, spec, &values, &claimed)? {
+                        Some(id) => {
+                            claimed.insert(id);
+                            report.unchanged += 1;
+                        }
+                        None => {
+                            values[0] = SqlValue::Null;
+                            tx.prepare_cached(&insert_sql)?.execute(params_from_iter(values.iter()))?;
+                            claimed.insert(tx.last_insert_rowid());
+                            report.inserted += 1;
+                        }
+                    },
+                    None => {
+                        tx.prepare_cached(&insert_sql)?.execute(params_from_iter(values.iter()))?;
+                        if spec.surrogate_id {
+                            claimed.insert(tx.last_insert_rowid());
+                        }
+                        report.inserted += 1;
+                    }
+                    Some(current) if current == values => {
+                        if let SqlValue::Integer(id) = current[0] {
+                            claimed.insert(id);
+                        }
+                        report.unchanged += 1;
+                    }
+                    Some(_) => {
+                        let key = key_values.iter().map(|v| sql_to_json((*v).clone()).to_string()).collect::<Vec<_>>();
+                        report.conflicts.push(format!("{}: {}", spec.name, key.join("/")));
+                    }
+                }
+            }
+        }
+
+        if dry_run {
+            tx.rollback()?;
+        } else {
+            tx.commit()?;
+        }
+        Ok(report)
+    }
+}
+
+/// The id of a row of a [`surrogate_id`](TableSpec::surrogate_id) table with
+/// the same values as `values` in every column but `id`, other than `claimed` ones.
+fn same_row(tx: &Transaction, spec: &TableSpec, values: &[SqlValue], claimed: &HashSet<i64>) -> Result<Option<i64>, rusqlite::Error> {
+    let sql = format!(
+        "SELECT id FROM {} WHERE {} ORDER BY id",
+        spec.name,
+        spec.columns[1..].iter().map(|c| format!("{} IS ?", c)).collect::<Vec<_>>().join(" AND ")
+    );
+    let mut stmt = tx.prepare_cached(&sql)?;
+    let ids = stmt.query_map(params_from_iter(values[1..].iter()), |row| row.get::<_, i64>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ids.into_iter().find(|id| !claimed.contains(id)))
+}
+
+fn row_values(spec: &TableSpec, row: &Value) -> Result<Vec<SqlValue>, TransferError> {
+    let object = row.as_object()
+        .ok_or_else(|| TransferError::Format(format!("{} row is not an object", spec.name)))?;
+    spec.columns.iter()
+        .map(|column| match object.get(*column) {
+            Some(value) => json_to_sql(value)
+                .ok_or_else(|| TransferError::Format(format!("{}.{} has an unsupported value", spec.name, column))),
+            None => spec.defaults.iter()
+                .find(|(name, _)| name == column)
+                .map(|(_, value)| value.map_or(SqlValue::Null, SqlValue::Integer))
+                .ok_or_else(|| TransferError::Format(format!("{} row is missing {}", spec.name, column))),
+        })
+        .collect()
+}
+
+pub(super) fn sql_to_json(value: SqlValue) -> Value {
+    match value {
+        SqlValue::Null => Value::Null,
+        SqlValue::Integer(i) => json!(i),
+        SqlValue::Real(f) => json!(f),
+        SqlValue::Text(s) => Value::String(s),
+        SqlValue::Blob(bytes) => json!(bytes),
+    }
+}
+
+fn json_to_sql(value: &Value) -> Option<SqlValue> {
+    match value {
+        Value::Null => Some(SqlValue::Null),
+        Value::Bool(b) => Some(SqlValue::Integer(*b as i64)),
+        Value::Number(n) => n.as_i64().map(SqlValue::Integer).or_else(|| n.as_f64().map(SqlValue::Real)),
+        Value::String(s) => Some(SqlValue::Text(s.clone())),
+        _ => None,
+    }
+}
+
+/// Groups exported rows by table name, for callers that want to inspect a document.
+pub fn table_rows(doc: &Value) -> BTreeMap<String, usize> {
+    doc.get("tables")
+        .and_then(Value::as_object)
+        .map(|tables| {
+            tables.iter()
+                .map(|(name, rows)| (name.clone(), rows.as_array().map_or(0, Vec::len)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{ScrambleResult, TournamentRound, TriviaResult};
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    fn populated_db() -> Database {
+        let db = create_test_db();
+        db.set_user_data("alice", "color", "blue").expect("Failed to set");
+        db.set_user_data("bob", "pet", "cat").expect("Failed to set");
+        db.grant_permission("alice", 10).expect("Failed to grant");
+        db
+    }
+
+    #[test]
+    fn test_export_contains_all_tables() {
+        let doc = populated_db().export_json().expect("Failed to export");
+        assert_eq!(doc["version"], 1);
+        let counts = table_rows(&doc);
+        assert_eq!(counts.get("users_data"), Some(&2));
+        assert_eq!(counts.get("permissions"), Some(&1));
+        assert_eq!(doc["tables"]["users_data"][0]["data_key"], "color");
+    }
+
+    #[test]
+    fn test_round_trip_into_empty_database() {
+        let doc = populated_db().export_json().expect("Failed to export");
+        let target = create_test_db();
+
+        let report = target.import_json(&doc, ImportMode::Merge, false).expect("Failed to import");
+        assert_eq!(report.inserted, 3);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(target.get_user_data("bob", "pet").unwrap(), Some("cat".to_string()));
+        assert_eq!(target.get_permission_level("alice").unwrap(), 10);
+    }
+
+    #[test]
+    fn test_merge_reports_and_keeps_conflicts() {
+        let doc = populated_db().export_json().expect("Failed to export");
+        let target = create_test_db();
+        target.set_user_data("alice", "color", "green").expect("Failed to set");
+        target.set_user_data("bob", "pet", "cat").expect("Failed to set");
+
+        let report = target.import_json(&doc, ImportMode::Merge, false).expect("Failed to import");
+        assert_eq!(report.inserted, 1);
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.conflicts, vec!["users_data: \"alice\"/\"color\"".to_string()]);
+        assert_eq!(target.get_user_data("alice", "color").unwrap(), Some("green".to_string()));
+    }
+
+    #[test]
+    fn test_replace_overwrites_tables() {
+        let doc = populated_db().export_json().expect("Failed to export");
+        let target = create_test_db();
+        target.set_user_data("alice", "color", "green").expect("Failed to set");
+        target.set_user_data("carol", "old", "gone").expect("Failed to set");
+
+        target.record_audit("admin", None, "grant", "x 1", "#ch", "ok").expect("Failed to audit");
+
+        let report = target.import_json(&doc, ImportMode::Replace, false).expect("Failed to import");
+        assert_eq!(report.deleted, 2);
+        assert_eq!(report.inserted, 3);
+        assert_eq!(target.get_user_data("alice", "color").unwrap(), Some("blue".to_string()));
+        assert_eq!(target.get_user_data("carol", "old").unwrap(), None);
+        assert_eq!(target.audit_entries(None, None, None, 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_dry_run_changes_nothing() {
+        let doc = populated_db().export_json().expect("Failed to export");
+        let target = create_test_db();
+        target.set_user_data("alice", "color", "green").expect("Failed to set");
+
+        let report = target.import_json(&doc, ImportMode::Replace, true).expect("Failed to import");
+        assert_eq!(report.inserted, 3);
+        assert_eq!(target.get_user_data("alice", "color").unwrap(), Some("green".to_string()));
+        assert_eq!(target.get_permission_level("alice").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_import_rejects_malformed_documents() {
+        let db = create_test_db();
+        assert!(matches!(db.import_json(&json!({}), ImportMode::Merge, false), Err(TransferError::Format(_))));
+        assert!(matches!(db.import_json(&json!({"version": 99, "tables": {}}), ImportMode::Merge, false), Err(TransferError::Format(_))));
+
+        let missing_column = json!({"version": 1, "tables": {"permissions": [{"username": "x"}]}});
+        assert!(matches!(db.import_json(&missing_column, ImportMode::Merge, false), Err(TransferError::Format(_))));
+    }
+
+    #[test]
+    fn test_import_fills_defaults_for_older_files() {
+        let db = create_test_db();
+        let doc = json!({"version": 1, "tables": {"users_data": [
+            {"username": "x", "data_key": "k", "data_value": "v"}
+        ]}});
+
+        db.import_json(&doc, ImportMode::Merge, false).expect("Failed to import");
+        assert_eq!(db.get_user_data("x", "k").unwrap(), Some("v".to_string()));
+    }
+
+    #[test]
+    fn test_import_older_permissions_are_permanent() {
+        let db = create_test_db();
+        let doc = json!({"version": 1, "tables": {"permissions": [
+            {"username": "x", "permission_level": 5}
+        ]}});
+
+        db.import_json(&doc, ImportMode::Merge, false).expect("Failed to import");
+        assert_eq!(db.get_permission_level("x").unwrap(), 5);
+        assert_eq!(db.list_users_with_permissions().unwrap(), vec![("x".to_string(), 5, None)]);
+    }
+
+    #[test]
+    fn test_import_skips_unknown_tables() {
+        let db = create_test_db();
+        let doc = json!({"version": 1, "tables": {"karma": [], "permissions": [{"username": "x", "permission_level": 1}]}});
+
+        let report = db.import_json(&doc, ImportMode::Merge, false).expect("Failed to import");
+        assert_eq!(report.skipped_tables, vec!["karma".to_string()]);
+        assert_eq!(report.inserted, 1);
+    }
+
+    #[test]
+    fn test_privileged_tables() {
+        let doc = json!({"version": 1, "tables": {"users_data": [], "roles": [], "permissions": [{"username": "x"}]}});
+        assert_eq!(privileged_tables(&doc), vec!["permissions", "roles"]);
+        assert!(privileged_tables(&json!({"version": 1, "tables": {"chips": []}})).is_empty());
+        assert!(privileged_tables(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_import_never_adds_audit_entries() {
+        let db = create_test_db();
+        let doc = json!({"version": 1, "tables": {"audit_log": [{
+            "actor": "owner", "hostmask": null, "command": "grant", "arguments": "mallory 15",
+            "channel": "#ops", "result": "ok", "created_at": 1000
+        }]}});
+
+        for mode in [ImportMode::Merge, ImportMode::Replace] {
+            let report = db.import_json(&doc, mode, false).expect("Failed to import");
+            assert_eq!(report.inserted, 0);
+            assert_eq!(report.export_only_tables, vec!["audit_log".to_string()]);
+            assert!(report.to_string().ends_with(", not imported: audit_log"));
+        }
+        assert!(db.audit_entries(None, None, None, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tournament_rounds_survive_a_round_trip() {
+        let source = create_test_db();
+        let id = source.create_tournament("#games", "scramble", 1, 0).unwrap();
+        source.join_tournament(id, "alice").unwrap();
+        let round = Some(TournamentRound { tournament: id, round: 1 });
+        source.record_scramble_results("#games", round, &[ScrambleResult {
+            username: "alice".to_string(), solved: 2, points: 9,
+        }]).unwrap();
+        let doc = source.export_json().expect("Failed to export");
+
+        let target = create_test_db();
+        target.import_json(&doc, ImportMode::Merge, false).expect("Failed to import");
+        let tournament = target.active_tournament("#games").unwrap().unwrap();
+        assert_eq!(target.tournament_scores(&tournament, "scramble_results").unwrap(), vec![("alice".to_string(), 9)]);
+    }
+
+    #[test]
+    fn test_results_from_another_database_get_fresh_ids() {
+        let result = |username: &str, points: u32| TriviaResult { username: username.to_string(), correct: 1, points };
+        let source = create_test_db();
+        source.record_trivia_results("#q", None, &[result("alice", 3), result("alice", 3)]).unwrap();
+        source.conn.execute("UPDATE trivia_results SET finished_at = 100", []).unwrap();
+        let doc = source.export_json().expect("Failed to export");
+
+        // Ids 1 and 2 are already taken here by other games
+        let target = create_test_db();
+        target.record_trivia_results("#q", None, &[result("bob", 5), result("carol", 1)]).unwrap();
+        let report = target.import_json(&doc, ImportMode::Merge, false).expect("Failed to import");
+        assert_eq!((report.inserted, report.conflicts.len()), (2, 0));
+        let report = target.import_json(&doc, ImportMode::Merge, false).expect("Failed to import");
+        assert_eq!((report.inserted, report.unchanged), (0, 2));
+        assert_eq!(target.trivia_leaderboard(None, 10).unwrap(), vec![("alice".to_string(), 6), ("bob".to_string(), 5), ("carol".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_results_in_the_same_second_stay_apart() {
+        let source = create_test_db();
+        for points in [3, 5] {
+            source.conn.execute(
+                "INSERT INTO trivia_results (channel, username, correct, points, finished_at) VALUES ('#q', 'alice', 1, ?, 100)",
+                [points],
+            ).unwrap();
+        }
+        let doc = source.export_json().expect("Failed to export");
+
+        let target = create_test_db();
+        let report = target.import_json(&doc, ImportMode::Merge, false).expect("Failed to import");
+        assert_eq!(report.inserted, 2);
+        let report = target.import_json(&doc, ImportMode::Merge, false).expect("Failed to import");
+        assert_eq!((report.inserted, report.unchanged), (0, 2));
+    }
+}
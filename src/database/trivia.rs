@@ -0,0 +1,58 @@
+use rusqlite::params;
+use super::{Database, TournamentRound};
+
+/// One player's score in a finished trivia game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriviaResult {
+    pub username: String,
+    pub correct: u32,
+    pub points: u32,
+}
+
+impl Database {
+    /// Records every player's score for one finished game, played for
+    /// `round` if it was a tournament round.
+    pub fn record_trivia_results(&self, channel: &str, round: Option<TournamentRound>, results: &[TriviaResult]) -> Result<(), rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        for result in results {
+            tx.execute(
+                "INSERT INTO trivia_results (channel, username, correct, points, tournament_id, tournament_round)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                params![channel, result.username, result.correct, result.points, round.map(|r| r.tournament), round.map(|r| r.round)],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Total points per player, highest first, optionally limited to one channel.
+    pub fn trivia_leaderboard(&self, channel: Option<&str>, limit: usize) -> Result<Vec<(String, i64)>, rusqlite::Error> {
+        self.points_leaderboard("trivia_results", channel, None, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    fn result(username: &str, correct: u32, points: u32) -> TriviaResult {
+        TriviaResult { username: username.to_string(), correct, points }
+    }
+
+    #[test]
+    fn test_trivia_leaderboard() {
+        let db = create_test_db();
+        db.record_trivia_results("#a", None, &[result("alice", 2, 8), result("bob", 1, 3)]).unwrap();
+        db.record_trivia_results("#b", None, &[result("bob", 3, 12)]).unwrap();
+
+        assert_eq!(db.trivia_leaderboard(None, 10).unwrap(), vec![
+            ("bob".to_string(), 15),
+            ("alice".to_string(), 8),
+        ]);
+        assert_eq!(db.trivia_leaderboard(Some("#a"), 1).unwrap(), vec![("alice".to_string(), 8)]);
+        assert!(db.trivia_leaderboard(Some("#c"), 10).unwrap().is_empty());
+    }
+}
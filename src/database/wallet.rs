@@ -0,0 +1,242 @@
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use super::Database;
+
+/// Seconds in a day; `!daily` can be claimed once per UTC day.
+const DAY: i64 = 24 * 60 * 60;
+
+/// A wallet ledger entry: `(amount, balance, reason, created_at)`, where
+/// `balance` is what the change left.
+pub type LedgerEntry = (i64, i64, String, i64);
+
+impl Database {
+    /// A player's chip balance. Wallets start empty; `!daily` and game
+    /// winnings fill them.
+    pub fn chip_balance(&self, username: &str) -> Result<i64, rusqlite::Error> {
+        let balance = self.conn.query_row(
+            "SELECT balance FROM chips WHERE username = ?",
+            params![username],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(balance.unwrap_or(0))
+    }
+
+    /// Whether a player has a wallet, which they get with their first chips.
+    pub fn has_wallet(&self, username: &str) -> Result<bool, rusqlite::Error> {
+        wallet_exists(&self.conn, username)
+    }
+
+    /// Unix time of a player's first wallet change, or `None` if they have none.
+    pub fn wallet_opened_at(&self, username: &str) -> Result<Option<i64>, rusqlite::Error> {
+        self.conn.query_row(
+            "SELECT MIN(created_at) FROM chip_ledger WHERE username = ?",
+            params![username],
+            |row| row.get(0),
+        )
+    }
+
+    /// Adds `amount` chips to a player's wallet, or takes them when it is
+    /// negative, and records the change in the ledger with `reason`. Returns
+    /// the new balance, or `None`, changing nothing, if the player can't cover
+    /// it. Every game award, bet and refund goes through here.
+    pub fn adjust_chips(&self, username: &str, amount: i64, reason: &str) -> Result<Option<i64>, rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        let balance = apply(&tx, username, amount, reason)?;
+        if balance.is_some() {
+            tx.commit()?;
+        }
+        Ok(balance)
+    }
+
+    /// Moves `amount` chips from one player to another in a single
+    /// transaction. Returns both new balances, or `None`, moving nothing, if
+    /// `from` can't cover it or `to` has no wallet to receive them.
+    pub fn transfer_chips(&self, from: &str, to: &str, amount: i64) -> Result<Option<(i64, i64)>, rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        if !wallet_exists(&tx, to)? {
+            return Ok(None);
+        }
+        let Some(from_balance) = apply(&tx, from, -amount, &format!("give to {}", to))? else {
+            return Ok(None);
+        };
+        let Some(to_balance) = apply(&tx, to, amount, &format!("gift from {}", from))? else {
+            return Ok(None);
+        };
+        tx.commit()?;
+        Ok(Some((from_balance, to_balance)))
+    }
+
+    /// Pays a player's daily `amount` unless they already claimed it on the
+    /// UTC day of `now`. Returns the new balance, or `None` if already claimed.
+    pub fn claim_daily_chips(&self, username: &str, amount: i64, now: i64) -> Result<Option<i64>, rusqlite::Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        open_wallet(&tx, username)?;
+        let rows = tx.execute(
+            "UPDATE chips SET daily_claimed_at = ?1
+             WHERE username = ?2 AND (daily_claimed_at IS NULL OR daily_claimed_at / ?3 < ?1 / ?3)",
+            params![now, username, DAY],
+        )?;
+        if rows == 0 {
+            return Ok(None);
+        }
+        let balance = apply(&tx, username, amount, "daily")?;
+        tx.commit()?;
+        Ok(balance)
+    }
+
+    /// A player's most recent wallet changes, newest first.
+    pub fn chip_ledger(&self, username: &str, limit: usize) -> Result<Vec<LedgerEntry>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT amount, balance, reason, created_at FROM chip_ledger
+             WHERE username = ? ORDER BY id DESC LIMIT ?"
+        )?;
+        let rows = stmt.query_map(params![username, limit as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// The biggest chip balances, highest first.
+    pub fn chips_leaderboard(&self, limit: usize) -> Result<Vec<(String, i64)>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT username, balance FROM chips ORDER BY balance DESC, username LIMIT ?"
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+/// Creates an empty wallet for a player if they don't have one.
+fn open_wallet(tx: &Transaction, username: &str) -> Result<(), rusqlite::Error> {
+    tx.execute("INSERT OR IGNORE INTO chips (username, balance) VALUES (?, 0)", params![username])?;
+    Ok(())
+}
+
+fn wallet_exists(conn: &Connection, username: &str) -> Result<bool, rusqlite::Error> {
+    conn.query_row("SELECT EXISTS(SELECT 1 FROM chips WHERE username = ?)", params![username], |row| row.get(0))
+}
+
+/// Changes a balance within `tx`, refusing to take it below zero, and adds
+/// the ledger entry. The balance check is part of the UPDATE, so two commands
+/// can't both spend the same chips.
+fn apply(tx: &Transaction, username: &str, amount: i64, reason: &str) -> Result<Option<i64>, rusqlite::Error> {
+    open_wallet(tx, username)?;
+    let balance: Option<i64> = tx.query_row(
+        "UPDATE chips SET balance = balance + ?1, updated_at = strftime('%s','now')
+         WHERE username = ?2 AND balance + ?1 >= 0
+         RETURNING balance",
+        params![amount, username],
+        |row| row.get(0),
+    ).optional()?;
+    if let Some(balance) = balance {
+        tx.execute(
+            "INSERT INTO chip_ledger (username, amount, balance, reason) VALUES (?, ?, ?, ?)",
+            params![username, amount, balance, reason],
+        )?;
+    }
+    Ok(balance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    #[test]
+    fn test_wallets_start_empty() {
+        let db = create_test_db();
+        assert_eq!(db.chip_balance("alice").unwrap(), 0);
+        assert!(!db.has_wallet("alice").unwrap());
+        assert!(db.chips_leaderboard(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_wallet_opened_at() {
+        let db = create_test_db();
+        assert_eq!(db.wallet_opened_at("alice").unwrap(), None);
+        db.adjust_chips("alice", 10, "daily").unwrap();
+        db.conn.execute("UPDATE chip_ledger SET created_at = 100", []).unwrap();
+        db.adjust_chips("alice", 5, "hangman").unwrap();
+        assert_eq!(db.wallet_opened_at("alice").unwrap(), Some(100));
+    }
+
+    #[test]
+    fn test_adjust() {
+        let db = create_test_db();
+        assert_eq!(db.adjust_chips("alice", 1000, "daily").unwrap(), Some(1000));
+        assert_eq!(db.adjust_chips("alice", -400, "blackjack").unwrap(), Some(600));
+        assert_eq!(db.adjust_chips("alice", -1000, "blackjack").unwrap(), None);
+        assert_eq!(db.chip_balance("alice").unwrap(), 600);
+        assert_eq!(db.adjust_chips("alice", -600, "poker").unwrap(), Some(0));
+
+        assert_eq!(db.adjust_chips("alice", 1500, "blackjack").unwrap(), Some(1500));
+        assert_eq!(db.adjust_chips("bob", 50, "trivia").unwrap(), Some(50));
+        assert_eq!(db.chips_leaderboard(10).unwrap(), vec![
+            ("alice".to_string(), 1500),
+            ("bob".to_string(), 50),
+        ]);
+    }
+
+    #[test]
+    fn test_refused_change_leaves_no_trace() {
+        let db = create_test_db();
+        assert_eq!(db.adjust_chips("alice", -1, "poker").unwrap(), None);
+        assert!(db.chips_leaderboard(10).unwrap().is_empty());
+        assert!(db.chip_ledger("alice", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_ledger() {
+        let db = create_test_db();
+        db.adjust_chips("alice", 100, "daily").unwrap();
+        db.adjust_chips("alice", -100, "blackjack").unwrap();
+        db.adjust_chips("alice", 250, "blackjack").unwrap();
+        let ledger: Vec<(i64, i64, String)> = db.chip_ledger("alice", 10).unwrap()
+            .into_iter()
+            .map(|(amount, balance, reason, _)| (amount, balance, reason))
+            .collect();
+        // Every chip in a wallet is accounted for, back to the first
+        assert_eq!(ledger, vec![
+            (250, 250, "blackjack".to_string()),
+            (-100, 0, "blackjack".to_string()),
+            (100, 100, "daily".to_string()),
+        ]);
+        assert_eq!(db.chip_ledger("alice", 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_transfer() {
+        let db = create_test_db();
+        db.adjust_chips("alice", 1000, "daily").unwrap();
+        assert_eq!(db.transfer_chips("alice", "bob", 300).unwrap(), None);
+        assert!(!db.has_wallet("bob").unwrap());
+
+        db.adjust_chips("bob", 0, "open").unwrap();
+        assert_eq!(db.transfer_chips("alice", "bob", 300).unwrap(), Some((700, 300)));
+        assert_eq!(db.transfer_chips("alice", "bob", 1000).unwrap(), None);
+        assert_eq!(db.chip_balance("alice").unwrap(), 700);
+        assert_eq!(db.chip_balance("bob").unwrap(), 300);
+        assert_eq!(db.chip_ledger("bob", 1).unwrap()[0].2, "gift from alice");
+    }
+
+    #[test]
+    fn test_daily_once_per_utc_day() {
+        let db = create_test_db();
+        let midnight = 1_700_006_400; // 2023-11-15 00:00 UTC
+        assert_eq!(db.claim_daily_chips("alice", 100, midnight + 3600).unwrap(), Some(100));
+        assert_eq!(db.claim_daily_chips("alice", 100, midnight + DAY - 1).unwrap(), None);
+        assert_eq!(db.claim_daily_chips("alice", 100, midnight + DAY).unwrap(), Some(200));
+        assert_eq!(db.chip_ledger("alice", 1).unwrap()[0].2, "daily");
+    }
+
+    #[test]
+    fn test_balance_never_goes_negative() {
+        let db = create_test_db();
+        // The schema refuses a negative balance even if a caller skips `adjust_chips`
+        db.adjust_chips("alice", 0, "open").unwrap();
+        assert!(db.conn.execute("UPDATE chips SET balance = -1 WHERE username = 'alice'", []).is_err());
+    }
+}
@@ -0,0 +1,475 @@
+use std::fmt;
+use rand::Rng;
+
+/// Most dice one expression may roll, before explosions.
+pub const MAX_DICE: u32 = 100;
+/// Most sides a die may have.
+pub const MAX_SIDES: u32 = 1000;
+/// Most times `xN` may repeat an expression.
+pub const MAX_REPEATS: u32 = 10;
+/// Largest constant allowed in an expression.
+const MAX_CONSTANT: i64 = 10_000;
+/// Extra dice one exploding die may add before the chain stops.
+const MAX_EXPLOSIONS: u32 = 20;
+/// Dice listed per term before the rest are summarised.
+const MAX_SHOWN: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Keep {
+    Highest(u32),
+    Lowest(u32),
+    DropHighest(u32),
+    DropLowest(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dice {
+    pub count: u32,
+    pub sides: u32,
+    pub keep: Option<Keep>,
+    /// Dice showing their highest face are rolled again and added.
+    pub explode: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Dice(Dice),
+    Constant(i64),
+}
+
+/// A parsed expression such as `4d6kh3+2`, rolled `repeat` times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    /// Terms with their sign: `1` to add, `-1` to subtract.
+    pub terms: Vec<(i64, Term)>,
+    pub repeat: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiceError {
+    Empty,
+    Syntax(String),
+    TooManyDice,
+    BadSides,
+    TooManyRepeats,
+    ConstantTooLarge,
+    /// A keep or drop asks for more dice than are rolled.
+    KeepTooMany { keep: u32, count: u32 },
+}
+
+impl fmt::Display for DiceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiceError::Empty => write!(f, "nothing to roll"),
+            DiceError::Syntax(near) => write!(f, "can't read the dice near '{}'", near),
+            DiceError::TooManyDice => write!(f, "at most {} dice per roll", MAX_DICE),
+            DiceError::BadSides => write!(f, "dice need 2 to {} sides", MAX_SIDES),
+            DiceError::TooManyRepeats => write!(f, "at most {} repeats", MAX_REPEATS),
+            DiceError::ConstantTooLarge => write!(f, "numbers can be at most {}", MAX_CONSTANT),
+            DiceError::KeepTooMany { keep, count } => write!(f, "can't keep or drop {} of {} dice", keep, count),
+        }
+    }
+}
+
+/// Parses dice notation: terms like `3d6`, `d20`, `d%` or `5` joined by `+`
+/// and `-`. Dice take modifiers `khN`/`k N`, `klN`, `dhN`, `dlN` (N defaults
+/// to 1), `!` to explode, and `adv`/`dis` to roll twice and keep the
+/// higher/lower. A trailing ` xN` repeats the whole roll.
+pub fn parse(text: &str) -> Result<Expr, DiceError> {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    let mut repeat = 1;
+    if let Some(n) = words.last().and_then(|w| w.strip_prefix(['x', 'X'])).and_then(|n| n.parse::<u32>().ok()) {
+        if !(1..=MAX_REPEATS).contains(&n) {
+            return Err(DiceError::TooManyRepeats);
+        }
+        repeat = n;
+        words.pop();
+    }
+    let text = words.concat().to_lowercase();
+    if text.is_empty() {
+        return Err(DiceError::Empty);
+    }
+
+    let mut parser = Parser { text: &text, pos: 0 };
+    let mut terms = Vec::new();
+    let mut sign = if parser.eat("-") { -1 } else { parser.eat("+"); 1 };
+    loop {
+        terms.push((sign, parser.term()?));
+        if parser.eat("+") {
+            sign = 1;
+        } else if parser.eat("-") {
+            sign = -1;
+        } else if parser.done() {
+            break;
+        } else {
+            return Err(parser.error());
+        }
+    }
+
+    let dice: u32 = terms.iter()
+        .map(|(_, term)| match term {
+            Term::Dice(d) => d.count,
+            Term::Constant(_) => 0,
+        })
+        .sum();
+    if dice > MAX_DICE {
+        return Err(DiceError::TooManyDice);
+    }
+    Ok(Expr { terms, repeat })
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.text.len()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn number(&mut self) -> Option<u64> {
+        let digits = self.rest().chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        let value = self.rest()[..digits].parse().unwrap_or(u64::MAX);
+        self.pos += digits;
+        Some(value)
+    }
+
+    fn error(&self) -> DiceError {
+        let near: String = self.rest().chars().take(10).collect();
+        DiceError::Syntax(if near.is_empty() { "the end".to_string() } else { near })
+    }
+
+    fn term(&mut self) -> Result<Term, DiceError> {
+        let count = self.number();
+        if !self.eat("d") {
+            return match count {
+                Some(n) if n <= MAX_CONSTANT as u64 => Ok(Term::Constant(n as i64)),
+                Some(_) => Err(DiceError::ConstantTooLarge),
+                None => Err(self.error()),
+            };
+        }
+        let count = count.unwrap_or(1);
+        let sides = if self.eat("%") { 100 } else { self.number().ok_or_else(|| self.error())? };
+        if count > MAX_DICE as u64 {
+            return Err(DiceError::TooManyDice);
+        }
+        if !(2..=MAX_SIDES as u64).contains(&sides) {
+            return Err(DiceError::BadSides);
+        }
+        let mut dice = Dice { count: count as u32, sides: sides as u32, keep: None, explode: false };
+
+        loop {
+            // Errors point at the modifier, not past it
+            let start = self.pos;
+            if self.eat("!") {
+                dice.explode = true;
+            } else if self.eat("adv") || self.eat("dis") {
+                let advantage = self.text[..self.pos].ends_with("adv");
+                if dice.count > 2 || dice.keep.is_some() {
+                    self.pos = start;
+                    return Err(self.error());
+                }
+                dice.count = 2;
+                dice.keep = Some(if advantage { Keep::Highest(1) } else { Keep::Lowest(1) });
+            } else if let Some(make) = self.keep_modifier() {
+                if dice.keep.is_some() {
+                    self.pos = start;
+                    return Err(self.error());
+                }
+                let n = self.number().unwrap_or(1).min(u32::MAX as u64) as u32;
+                if n > dice.count {
+                    return Err(DiceError::KeepTooMany { keep: n, count: dice.count });
+                }
+                dice.keep = Some(make(n));
+            } else {
+                break;
+            }
+        }
+        Ok(Term::Dice(dice))
+    }
+
+    /// Reads `kh`, `kl`, `k`, `dh` or `dl`. A bare `d` isn't a modifier,
+    /// since it would start another die.
+    fn keep_modifier(&mut self) -> Option<fn(u32) -> Keep> {
+        for (token, make) in [("kh", Keep::Highest as fn(u32) -> Keep), ("kl", Keep::Lowest), ("k", Keep::Highest), ("dh", Keep::DropHighest), ("dl", Keep::DropLowest)] {
+            if self.eat(token) {
+                return Some(make);
+            }
+        }
+        None
+    }
+}
+
+/// One die in a roll.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Die {
+    pub value: u32,
+    /// Counted in the total, i.e. not dropped by a keep or drop.
+    pub kept: bool,
+    /// Showed its highest face and caused another die to be rolled.
+    pub exploded: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TermRoll {
+    Dice(Vec<Die>),
+    Constant(i64),
+}
+
+impl TermRoll {
+    fn value(&self) -> i64 {
+        match self {
+            TermRoll::Dice(dice) => dice.iter().filter(|d| d.kept).map(|d| d.value as i64).sum(),
+            TermRoll::Constant(n) => *n,
+        }
+    }
+}
+
+/// The result of rolling an expression once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Roll {
+    pub terms: Vec<(i64, TermRoll)>,
+    pub total: i64,
+}
+
+/// Rolls `expr` once per repeat, drawing from `rng`.
+pub fn roll<R: Rng>(expr: &Expr, rng: &mut R) -> Vec<Roll> {
+    (0..expr.repeat).map(|_| roll_once(expr, rng)).collect()
+}
+
+fn roll_once<R: Rng>(expr: &Expr, rng: &mut R) -> Roll {
+    let terms: Vec<(i64, TermRoll)> = expr.terms.iter()
+        .map(|(sign, term)| {
+            let rolled = match term {
+                Term::Dice(dice) => TermRoll::Dice(roll_dice(dice, rng)),
+                Term::Constant(n) => TermRoll::Constant(*n),
+            };
+            (*sign, rolled)
+        })
+        .collect();
+    let total = terms.iter().map(|(sign, term)| sign * term.value()).sum();
+    Roll { terms, total }
+}
+
+fn roll_dice<R: Rng>(dice: &Dice, rng: &mut R) -> Vec<Die> {
+    let mut rolled = Vec::new();
+    for _ in 0..dice.count {
+        let mut explosions = 0;
+        loop {
+            let value = rng.gen_range(1..=dice.sides);
+            let exploded = dice.explode && value == dice.sides && explosions < MAX_EXPLOSIONS;
+            rolled.push(Die { value, kept: true, exploded });
+            if !exploded {
+                break;
+            }
+            explosions += 1;
+        }
+    }
+
+    if let Some(keep) = dice.keep {
+        // Rank dice from highest to lowest; ties keep their roll order
+        let mut order: Vec<usize> = (0..rolled.len()).collect();
+        order.sort_by(|&a, &b| rolled[b].value.cmp(&rolled[a].value));
+        let n = rolled.len();
+        let dropped: Vec<usize> = match keep {
+            Keep::Highest(k) => order[(k as usize).min(n)..].to_vec(),
+            Keep::Lowest(k) => order[..n - (k as usize).min(n)].to_vec(),
+            Keep::DropHighest(k) => order[..(k as usize).min(n)].to_vec(),
+            Keep::DropLowest(k) => order[n - (k as usize).min(n)..].to_vec(),
+        };
+        for i in dropped {
+            rolled[i].kept = false;
+        }
+    }
+    rolled
+}
+
+impl fmt::Display for Roll {
+    /// e.g. `[6, 4, (1), 3] + 2 = 15`: dropped dice in parentheses, `!` after exploded ones.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (sign, term)) in self.terms.iter().enumerate() {
+            match (i, *sign) {
+                (0, -1) => write!(f, "-")?,
+                (0, _) => {}
+                (_, -1) => write!(f, " - ")?,
+                _ => write!(f, " + ")?,
+            }
+            match term {
+                TermRoll::Constant(n) => write!(f, "{}", n)?,
+                TermRoll::Dice(dice) => {
+                    let mut shown: Vec<String> = dice.iter().take(MAX_SHOWN)
+                        .map(|die| {
+                            let face = if die.exploded { format!("{}!", die.value) } else { die.value.to_string() };
+                            if die.kept { face } else { format!("({})", face) }
+                        })
+                        .collect();
+                    if dice.len() > MAX_SHOWN {
+                        shown.push(format!("… {} more", dice.len() - MAX_SHOWN));
+                    }
+                    write!(f, "[{}]", shown.join(", "))?;
+                }
+            }
+        }
+        write!(f, " = {}", self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use rand::RngCore;
+
+    /// Rolls the given faces in order on dice with `sides` sides.
+    struct Faces {
+        sides: u64,
+        faces: Vec<u32>,
+        next: usize,
+    }
+
+    fn faces(sides: u64, faces: &[u32]) -> Faces {
+        Faces { sides, faces: faces.to_vec(), next: 0 }
+    }
+
+    impl RngCore for Faces {
+        fn next_u32(&mut self) -> u32 {
+            let face = self.faces[self.next % self.faces.len()] as u64;
+            self.next += 1;
+            // gen_range scales a u32 by the number of sides and keeps the
+            // high half, so pick the smallest value that lands on `face`
+            (((face - 1) << 32).div_ceil(self.sides)) as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.next_u32() as u64
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for byte in dest {
+                *byte = self.next_u32() as u8;
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    fn dice(count: u32, sides: u32, keep: Option<Keep>, explode: bool) -> Term {
+        Term::Dice(Dice { count, sides, keep, explode })
+    }
+
+    /// Rolls `text` with a fixed seed.
+    fn seeded(text: &str) -> Vec<Roll> {
+        roll(&parse(text).unwrap(), &mut StdRng::seed_from_u64(42))
+    }
+
+    #[test]
+    fn test_parse_terms() {
+        assert_eq!(parse("4d6kh3+2").unwrap(), Expr {
+            terms: vec![(1, dice(4, 6, Some(Keep::Highest(3)), false)), (1, Term::Constant(2))],
+            repeat: 1,
+        });
+        assert_eq!(parse("d20 - 1").unwrap().terms, vec![(1, dice(1, 20, None, false)), (-1, Term::Constant(1))]);
+        assert_eq!(parse("-d%").unwrap().terms, vec![(-1, dice(1, 100, None, false))]);
+        assert_eq!(parse("2D10!DL").unwrap().terms, vec![(1, dice(2, 10, Some(Keep::DropLowest(1)), true))]);
+        assert_eq!(parse("3d6 x6").unwrap().repeat, 6);
+    }
+
+    #[test]
+    fn test_parse_advantage() {
+        assert_eq!(parse("2d20adv").unwrap().terms, vec![(1, dice(2, 20, Some(Keep::Highest(1)), false))]);
+        assert_eq!(parse("d20dis+5").unwrap().terms[0], (1, dice(2, 20, Some(Keep::Lowest(1)), false)));
+        assert_eq!(parse("3d20adv"), Err(DiceError::Syntax("adv".to_string())));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(""), Err(DiceError::Empty));
+        assert_eq!(parse("x3"), Err(DiceError::Empty));
+        assert_eq!(parse("2d"), Err(DiceError::Syntax("the end".to_string())));
+        assert_eq!(parse("3d6*2"), Err(DiceError::Syntax("*2".to_string())));
+        assert_eq!(parse("hello"), Err(DiceError::Syntax("hello".to_string())));
+        assert_eq!(parse("4d6kh5"), Err(DiceError::KeepTooMany { keep: 5, count: 4 }));
+        assert_eq!(parse("4d6kh1kl1"), Err(DiceError::Syntax("kl1".to_string())));
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(parse("101d6"), Err(DiceError::TooManyDice));
+        assert_eq!(parse("60d6+60d6"), Err(DiceError::TooManyDice));
+        assert!(parse("100d1000").is_ok());
+        assert_eq!(parse("1d1001"), Err(DiceError::BadSides));
+        assert_eq!(parse("1d1"), Err(DiceError::BadSides));
+        assert_eq!(parse("99999999999999999999d6"), Err(DiceError::TooManyDice));
+        assert_eq!(parse("d6 x11"), Err(DiceError::TooManyRepeats));
+        assert_eq!(parse("d6+10001"), Err(DiceError::ConstantTooLarge));
+    }
+
+    #[test]
+    fn test_keep_highest() {
+        let expr = parse("4d6kh3+2").unwrap();
+        let rolls = roll(&expr, &mut faces(6, &[6, 4, 1, 3]));
+        assert_eq!(rolls[0].to_string(), "[6, 4, (1), 3] + 2 = 15");
+    }
+
+    #[test]
+    fn test_drop_and_disadvantage() {
+        let rolls = roll(&parse("4d6dh1").unwrap(), &mut faces(6, &[6, 6, 1, 3]));
+        // Ties: the first of two sixes is the one dropped
+        assert_eq!(rolls[0].to_string(), "[(6), 6, 1, 3] = 10");
+        let rolls = roll(&parse("d20dis").unwrap(), &mut faces(20, &[18, 4]));
+        assert_eq!(rolls[0].to_string(), "[(18), 4] = 4");
+    }
+
+    #[test]
+    fn test_exploding() {
+        let rolls = roll(&parse("2d6!").unwrap(), &mut faces(6, &[6, 6, 2, 4]));
+        assert_eq!(rolls[0].to_string(), "[6!, 6!, 2, 4] = 18");
+        // A die that always explodes stops after the cap
+        let rolls = roll(&parse("d6!").unwrap(), &mut faces(6, &[6]));
+        assert_eq!(rolls[0].total, 6 * (MAX_EXPLOSIONS as i64 + 1));
+    }
+
+    #[test]
+    fn test_repeats_are_independent() {
+        let rolls = seeded("3d6 x6");
+        assert_eq!(rolls.len(), 6);
+        for roll in &rolls {
+            assert!((3..=18).contains(&roll.total));
+        }
+        // The same seed gives the same rolls
+        assert_eq!(rolls, seeded("3d6 x6"));
+    }
+
+    #[test]
+    fn test_long_rolls_are_summarised() {
+        let rolls = seeded("30d6");
+        assert!(rolls[0].to_string().contains("… 10 more"));
+    }
+
+    #[test]
+    fn test_negative_terms() {
+        let rolls = roll(&parse("-2+d4-1").unwrap(), &mut faces(4, &[4]));
+        assert_eq!(rolls[0].to_string(), "-2 + [4] - 1 = 1");
+    }
+}
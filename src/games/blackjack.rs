@@ -0,0 +1,578 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use super::{Game, Reply};
+use super::cards::{blackjack_value, show, Card, Deck};
+
+pub const MIN_BET: i64 = 1;
+pub const MAX_BET: i64 = 1000;
+const MAX_SEATS: usize = 6;
+/// Time each player has to act before standing automatically.
+const TURN_LIMIT: Duration = Duration::from_secs(60);
+/// The dealer draws below this total, and stands on soft totals too.
+const DEALER_STANDS: u32 = 17;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Seat {
+    player: String,
+    bet: i64,
+    hand: Vec<Card>,
+    /// Stood, doubled, bust or dealt blackjack: no more moves this round.
+    done: bool,
+}
+
+impl Seat {
+    fn value(&self) -> u32 {
+        blackjack_value(&self.hand).0
+    }
+
+    fn natural(&self) -> bool {
+        self.hand.len() == 2 && self.value() == 21
+    }
+
+    fn describe(&self) -> String {
+        format!("{}: {} ({})", self.player, show(&self.hand), self.value())
+    }
+}
+
+/// One round of blackjack against the bot dealer: players bet, then
+/// `deal` starts play and each seat acts in turn. The round ends once the
+/// dealer has played and every bet is settled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlackjackGame {
+    deck: Deck,
+    seats: Vec<Seat>,
+    dealer: Vec<Card>,
+    /// Cards have been dealt.
+    dealt: bool,
+    /// The seat whose move it is.
+    turn: usize,
+    /// Chips owed to players that haven't been handed out yet.
+    owed: Vec<(String, i64)>,
+}
+
+impl BlackjackGame {
+    pub fn new() -> Self {
+        BlackjackGame::with_deck(Deck::shuffled(&mut rand::thread_rng()))
+    }
+
+    pub fn with_deck(deck: Deck) -> Self {
+        BlackjackGame {
+            deck,
+            seats: Vec::new(),
+            dealer: Vec::new(),
+            dealt: false,
+            turn: 0,
+            owed: Vec::new(),
+        }
+    }
+
+    fn seat(&self, player: &str) -> Option<&Seat> {
+        self.seats.iter().find(|s| s.player == player)
+    }
+
+    /// Why `player` can't bet `args`, or the amount they bet.
+    fn check_bet(&self, player: &str, args: &[String]) -> Result<i64, String> {
+        if self.dealt {
+            return Err("Bets are closed for this round".to_string());
+        }
+        if self.seat(player).is_some() {
+            return Err(format!("{}, you've already bet this round", player));
+        }
+        if self.seats.len() >= MAX_SEATS {
+            return Err(format!("The table is full ({} seats)", MAX_SEATS));
+        }
+        // Two cards for every seat and the dealer
+        if (self.seats.len() + 2) * 2 > self.deck.remaining() {
+            return Err("The deck can't deal another hand".to_string());
+        }
+        match args.get(1).and_then(|a| a.parse::<i64>().ok()) {
+            Some(bet) if (MIN_BET..=MAX_BET).contains(&bet) => Ok(bet),
+            _ => Err(format!("Usage: !blackjack bet <{}-{}>", MIN_BET, MAX_BET)),
+        }
+    }
+
+    /// Why `player` can't double down now.
+    fn check_double(&self, player: &str) -> Result<i64, String> {
+        let seat = self.current(player)?;
+        if seat.hand.len() != 2 {
+            return Err("You can only double on your first two cards".to_string());
+        }
+        if self.deck.remaining() == 0 {
+            return Err("The deck is out of cards. Use !blackjack stand".to_string());
+        }
+        Ok(seat.bet)
+    }
+
+    /// The seat of `player` if it's their move.
+    fn current(&self, player: &str) -> Result<&Seat, String> {
+        if !self.dealt {
+            return Err("Cards haven't been dealt yet. Use !blackjack deal".to_string());
+        }
+        match self.seats.get(self.turn) {
+            Some(seat) if seat.player == player => Ok(seat),
+            Some(seat) => Err(format!("It's {}'s move", seat.player)),
+            None => Err("Nobody is left to play".to_string()),
+        }
+    }
+
+    fn bet(&mut self, player: &str, args: &[String]) -> Reply {
+        match self.check_bet(player, args) {
+            Ok(bet) => {
+                self.seats.push(Seat { player: player.to_string(), bet, hand: Vec::new(), done: false });
+                Reply::new(vec![format!("💰 {} bets {}. Deal with !blackjack deal", player, bet)])
+            }
+            Err(e) => Reply::refused(vec![e]),
+        }
+    }
+
+    fn deal(&mut self, player: &str) -> Reply {
+        if self.dealt {
+            return Reply::refused(vec!["Cards are already dealt".to_string()]);
+        }
+        if self.seat(player).is_none() {
+            return Reply::refused(vec![format!("Place a bet first: !blackjack bet <{}-{}>", MIN_BET, MAX_BET)]);
+        }
+        self.dealt = true;
+        for seat in &mut self.seats {
+            seat.hand = self.deck.deal_many(2).expect("bets are refused once the deck can't deal every hand");
+        }
+        self.dealer = self.deck.deal_many(2).expect("bets are refused once the deck can't deal every hand");
+
+        let mut lines = vec![format!("🃏 Dealer shows {} 🂠", self.dealer[0])];
+        for seat in &mut self.seats {
+            if seat.natural() {
+                seat.done = true;
+                lines.push(format!("{} — blackjack!", seat.describe()));
+            } else {
+                lines.push(seat.describe());
+            }
+        }
+        // The dealer checks for blackjack before anyone plays
+        if blackjack_value(&self.dealer).0 == 21 {
+            return self.settle(lines);
+        }
+        self.turn = 0;
+        self.advance(lines)
+    }
+
+    fn hit(&mut self, player: &str) -> Reply {
+        if let Err(e) = self.current(player) {
+            return Reply::refused(vec![e]);
+        }
+        let Some(card) = self.deck.deal() else {
+            return Reply::refused(vec!["The deck is out of cards. Use !blackjack stand".to_string()]);
+        };
+        let seat = &mut self.seats[self.turn];
+        seat.hand.push(card);
+        let line = match seat.value() {
+            total if total > 21 => {
+                seat.done = true;
+                format!("{} — bust!", seat.describe())
+            }
+            21 => {
+                seat.done = true;
+                seat.describe()
+            }
+            _ => return Reply::new(vec![format!("{}. Hit or stand?", seat.describe())]),
+        };
+        self.advance(vec![line])
+    }
+
+    fn stand(&mut self, player: &str) -> Reply {
+        if let Err(e) = self.current(player) {
+            return Reply::refused(vec![e]);
+        }
+        let seat = &mut self.seats[self.turn];
+        seat.done = true;
+        let line = format!("{} stands on {}", seat.player, seat.value());
+        self.advance(vec![line])
+    }
+
+    fn double(&mut self, player: &str) -> Reply {
+        if let Err(e) = self.check_double(player) {
+            return Reply::refused(vec![e]);
+        }
+        let card = self.deck.deal().expect("doubling is refused once the deck is out of cards");
+        let seat = &mut self.seats[self.turn];
+        seat.bet *= 2;
+        seat.hand.push(card);
+        seat.done = true;
+        let bust = if seat.value() > 21 { " — bust!" } else { "" };
+        let line = format!("{} doubles to {}: {}{}", seat.player, seat.bet, seat.describe(), bust);
+        self.advance(vec![line])
+    }
+
+    /// Moves to the next seat that still has to play, or to the dealer.
+    fn advance(&mut self, mut lines: Vec<String>) -> Reply {
+        match self.seats.iter().position(|s| !s.done) {
+            Some(next) => {
+                self.turn = next;
+                let seat = &self.seats[next];
+                let double = if seat.hand.len() == 2 { ", double" } else { "" };
+                lines.push(format!("Your move, {}: hit, stand{}", seat.player, double));
+                Reply::new(lines)
+            }
+            None => {
+                self.turn = self.seats.len();
+                self.dealer_plays(&mut lines);
+                self.settle(lines)
+            }
+        }
+    }
+
+    fn dealer_plays(&mut self, lines: &mut Vec<String>) {
+        // Nothing to play for if every hand is bust or already paid as blackjack
+        let contested = self.seats.iter().any(|s| s.value() <= 21 && !s.natural());
+        while contested && blackjack_value(&self.dealer).0 < DEALER_STANDS {
+            // The dealer stands on what they have if the deck runs out
+            let Some(card) = self.deck.deal() else {
+                break;
+            };
+            self.dealer.push(card);
+        }
+        let total = blackjack_value(&self.dealer).0;
+        let bust = if total > 21 { " — bust!" } else { "" };
+        lines.push(format!("🃏 Dealer: {} ({}){}", show(&self.dealer), total, bust));
+    }
+
+    /// Pays every seat against the dealer's hand and ends the round.
+    fn settle(&mut self, mut lines: Vec<String>) -> Reply {
+        let dealer = blackjack_value(&self.dealer).0;
+        let dealer_natural = self.dealer.len() == 2 && dealer == 21;
+        if dealer_natural {
+            lines.push(format!("🃏 Dealer: {} — blackjack!", show(&self.dealer)));
+        }
+        let mut results = Vec::new();
+        for seat in &self.seats {
+            let value = seat.value();
+            let payout = if value > 21 {
+                0
+            } else if seat.natural() && !dealer_natural {
+                seat.bet + seat.bet * 3 / 2
+            } else if dealer_natural && !seat.natural() {
+                0
+            } else if dealer > 21 || value > dealer {
+                seat.bet * 2
+            } else if value == dealer {
+                seat.bet
+            } else {
+                0
+            };
+            results.push(match payout - seat.bet {
+                0 => format!("{} pushes", seat.player),
+                net if net > 0 => format!("{} wins {} (+{})", seat.player, payout, net),
+                net => format!("{} loses ({})", seat.player, net),
+            });
+            if payout > 0 {
+                self.owed.push((seat.player.clone(), payout));
+            }
+        }
+        lines.push(format!("🏁 {}", results.join(", ")));
+        Reply::finished(lines)
+    }
+
+    /// Hands every bet back, for a round that ends unplayed.
+    fn refund(&mut self) {
+        for seat in &self.seats {
+            self.owed.push((seat.player.clone(), seat.bet));
+        }
+        self.seats.clear();
+    }
+}
+
+impl Default for BlackjackGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game for BlackjackGame {
+    fn kind(&self) -> &'static str {
+        "blackjack"
+    }
+
+    fn start(&mut self) -> Vec<String> {
+        vec![format!(
+            "🎮 Blackjack! Place bets with !blackjack bet <{}-{}>, then !blackjack deal. Blackjack pays 3:2, the dealer stands on 17.",
+            MIN_BET, MAX_BET
+        )]
+    }
+
+    fn input(&mut self, player: &str, args: &[String]) -> Reply {
+        match args.first().map(String::as_str) {
+            Some("bet") => self.bet(player, args),
+            Some("deal") => self.deal(player),
+            Some("hit") => self.hit(player),
+            Some("stand") => self.stand(player),
+            Some("double") => self.double(player),
+            _ => Reply::refused(vec!["Unknown blackjack subcommand. Use: start, bet, deal, hit, stand, double, status, quit, chips, top".to_string()]),
+        }
+    }
+
+    fn render(&self) -> String {
+        if !self.dealt {
+            let bets: Vec<String> = self.seats.iter().map(|s| format!("{} {}", s.player, s.bet)).collect();
+            if bets.is_empty() {
+                return "🃏 Blackjack: waiting for bets".to_string();
+            }
+            return format!("🃏 Blackjack: bets from {}. Deal with !blackjack deal", bets.join(", "));
+        }
+        let hands: Vec<String> = self.seats.iter().map(Seat::describe).collect();
+        let mut line = format!("🃏 Dealer shows {} 🂠 | {}", self.dealer[0], hands.join(" | "));
+        if let Some(seat) = self.seats.get(self.turn) {
+            line.push_str(&format!(" | {} to play", seat.player));
+        }
+        line
+    }
+
+    fn leave(&mut self, player: &str, manage: bool) -> Option<Reply> {
+        let seat = self.seats.iter().position(|s| s.player == player);
+        if seat.is_none() && !manage {
+            return Some(Reply::refused(vec!["Only players at the table or game managers can end this game".to_string()]));
+        }
+        if !self.dealt {
+            // Before the deal a player just takes their bet back and the table stays open
+            let i = seat?;
+            let seat = self.seats.remove(i);
+            self.owed.push((seat.player.clone(), seat.bet));
+            return Some(Reply { moved: false, ..Reply::new(vec![format!("{} leaves the table and takes back their bet of {}", player, seat.bet)]) });
+        }
+        // Once cards are out, quitting can't take a losing bet back
+        match seat {
+            Some(i) if self.seats[i].done => Some(Reply::refused(vec![format!(
+                "{}, your hand is already played. It's settled when the dealer plays", player
+            )])),
+            Some(i) => {
+                self.seats[i].done = true;
+                let line = format!("{} leaves and stands on {}", player, self.seats[i].value());
+                if i == self.turn {
+                    Some(self.advance(vec![line]))
+                } else {
+                    Some(Reply { moved: false, ..Reply::new(vec![line]) })
+                }
+            }
+            None => {
+                for seat in &mut self.seats {
+                    seat.done = true;
+                }
+                Some(self.advance(vec![format!("{} ends the round. Every hand stands", player)]))
+            }
+        }
+    }
+
+    fn quit(&mut self) -> Vec<String> {
+        self.refund();
+        vec!["Blackjack game ended. Bets were returned.".to_string()]
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).expect("blackjack state is always serializable")
+    }
+
+    fn timeout(&mut self) -> Vec<String> {
+        self.refund();
+        vec!["⏰ Blackjack timed out. Bets were returned.".to_string()]
+    }
+
+    fn turn_limit(&self) -> Option<Duration> {
+        self.dealt.then_some(TURN_LIMIT)
+    }
+
+    fn turn_timeout(&mut self) -> Reply {
+        let Some(seat) = self.seats.get_mut(self.turn) else {
+            return Reply::default();
+        };
+        seat.done = true;
+        let line = format!("⌛ {} took too long and stands on {}", seat.player, seat.value());
+        self.advance(vec![line])
+    }
+
+    fn stake(&self, player: &str, args: &[String]) -> i64 {
+        match args.first().map(String::as_str) {
+            Some("bet") => self.check_bet(player, args).unwrap_or(0),
+            Some("double") => self.check_double(player).unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn payouts(&mut self) -> Vec<(String, i64)> {
+        std::mem::take(&mut self.owed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::cards::cards;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    /// A table where the given cards come off the deck in order: two per
+    /// seat, then the dealer's two, then hits.
+    fn table(deck: &str, bets: &[(&str, i64)]) -> BlackjackGame {
+        seated(BlackjackGame::with_deck(Deck::stacked(cards(deck))), bets)
+    }
+
+    fn seated(mut game: BlackjackGame, bets: &[(&str, i64)]) -> BlackjackGame {
+        for (player, bet) in bets {
+            assert_eq!(game.stake(player, &args(&format!("bet {}", bet))), *bet);
+            game.input(player, &args(&format!("bet {}", bet)));
+        }
+        game
+    }
+
+    #[test]
+    fn test_bets() {
+        let mut game = seated(BlackjackGame::new(), &[("alice", 50)]);
+        assert_eq!(game.stake("alice", &args("bet 50")), 0);
+        assert_eq!(game.input("alice", &args("bet 50")).lines, vec!["alice, you've already bet this round"]);
+        assert_eq!(game.stake("bob", &args("bet 0")), 0);
+        assert_eq!(game.stake("bob", &args("bet 5000")), 0);
+        assert_eq!(game.stake("bob", &args("bet lots")), 0);
+        assert_eq!(game.input("bob", &args("deal")).lines, vec!["Place a bet first: !blackjack bet <1-1000>"]);
+        assert!(game.payouts().is_empty());
+    }
+
+    #[test]
+    fn test_stand_and_win() {
+        // alice: 10 9, dealer: 10 7
+        let mut game = table("10s 9h 10d 7c", &[("alice", 50)]);
+        let reply = game.input("alice", &args("deal"));
+        assert_eq!(reply.lines, vec!["🃏 Dealer shows 10♦ 🂠", "alice: 10♠ 9♥ (19)", "Your move, alice: hit, stand, double"]);
+        assert_eq!(game.input("bob", &args("hit")).lines, vec!["It's alice's move"]);
+
+        let reply = game.input("alice", &args("stand"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec!["alice stands on 19", "🃏 Dealer: 10♦ 7♣ (17)", "🏁 alice wins 100 (+50)"]);
+        assert_eq!(game.payouts(), vec![("alice".to_string(), 100)]);
+        assert!(game.payouts().is_empty());
+    }
+
+    #[test]
+    fn test_turns_bust_and_dealer_draws() {
+        // alice: 10 6, bob: 9 9, dealer: 10 4; alice hits 10, dealer draws 5
+        let mut game = table("10s 6h 9d 9c 10d 4c 10h 5s", &[("alice", 20), ("bob", 30)]);
+        game.input("alice", &args("deal"));
+        let reply = game.input("alice", &args("hit"));
+        assert_eq!(reply.lines, vec!["alice: 10♠ 6♥ 10♥ (26) — bust!", "Your move, bob: hit, stand, double"]);
+        let reply = game.input("bob", &args("stand"));
+        assert_eq!(reply.lines[1..], ["🃏 Dealer: 10♦ 4♣ 5♠ (19)", "🏁 alice loses (-20), bob loses (-30)"]);
+        assert!(game.payouts().is_empty());
+    }
+
+    #[test]
+    fn test_blackjack_pays_three_to_two() {
+        let mut game = table("As Kh 10d 7c", &[("alice", 10)]);
+        let reply = game.input("alice", &args("deal"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines[1], "alice: A♠ K♥ (21) — blackjack!");
+        assert_eq!(reply.lines.last().unwrap(), "🏁 alice wins 25 (+15)");
+        assert_eq!(game.payouts(), vec![("alice".to_string(), 25)]);
+    }
+
+    #[test]
+    fn test_dealer_blackjack() {
+        let mut game = table("10s 9h Ad Kc", &[("alice", 10)]);
+        let reply = game.input("alice", &args("deal"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines[2..], ["🃏 Dealer: A♦ K♣ — blackjack!", "🏁 alice loses (-10)"]);
+    }
+
+    #[test]
+    fn test_double() {
+        let mut game = table("5s 6h 10d 8c 10h", &[("alice", 40)]);
+        game.input("alice", &args("deal"));
+        assert_eq!(game.stake("alice", &args("double")), 40);
+        let reply = game.input("alice", &args("double"));
+        assert_eq!(reply.lines, vec!["alice doubles to 80: alice: 5♠ 6♥ 10♥ (21)", "🃏 Dealer: 10♦ 8♣ (18)", "🏁 alice wins 160 (+80)"]);
+        // No doubling after a hit
+        let mut game = table("2s 3h 10d 8c 4h", &[("alice", 40)]);
+        game.input("alice", &args("deal"));
+        game.input("alice", &args("hit"));
+        assert_eq!(game.stake("alice", &args("double")), 0);
+    }
+
+    #[test]
+    fn test_push() {
+        let mut game = table("10s 8h 10d 8c", &[("alice", 40)]);
+        game.input("alice", &args("deal"));
+        assert_eq!(game.input("alice", &args("stand")).lines.last().unwrap(), "🏁 alice pushes");
+        assert_eq!(game.payouts(), vec![("alice".to_string(), 40)]);
+    }
+
+    #[test]
+    fn test_quit_refunds_bets() {
+        let mut game = seated(BlackjackGame::new(), &[("alice", 40), ("bob", 5)]);
+        // Before the deal a player leaves alone and the table stays open
+        let reply = game.leave("alice", false).unwrap();
+        assert!(!reply.moved && !reply.finished);
+        assert_eq!(reply.lines, vec!["alice leaves the table and takes back their bet of 40"]);
+        assert_eq!(game.payouts(), vec![("alice".to_string(), 40)]);
+        assert_eq!(game.render(), "🃏 Blackjack: bets from bob 5. Deal with !blackjack deal");
+
+        // A manager away from the table ends it for everyone
+        assert!(game.leave("mod", true).is_none());
+        game.quit();
+        assert_eq!(game.payouts(), vec![("bob".to_string(), 5)]);
+    }
+
+    #[test]
+    fn test_deck_never_runs_dry() {
+        // alice: 10 2, dealer: 10 7, and nothing left to hit
+        let mut game = table("10s 2h 10d 7c", &[("alice", 10)]);
+        assert_eq!(game.stake("bob", &args("bet 10")), 0);
+        assert_eq!(game.input("bob", &args("bet 10")).lines, vec!["The deck can't deal another hand"]);
+        game.input("alice", &args("deal"));
+        let reply = game.input("alice", &args("hit"));
+        assert!(!reply.moved);
+        assert_eq!(reply.lines, vec!["The deck is out of cards. Use !blackjack stand"]);
+        assert_eq!(game.stake("alice", &args("double")), 0);
+    }
+
+    #[test]
+    fn test_quit_after_the_deal_stands() {
+        // alice: 10 8, bob: 9 7, dealer: 10 8
+        let mut game = table("10s 8h 9d 7c 10d 8c", &[("alice", 40), ("bob", 5)]);
+        assert_eq!(game.leave("carol", false).unwrap().lines, vec!["Only players at the table or game managers can end this game"]);
+        game.input("alice", &args("deal"));
+
+        let reply = game.leave("bob", false).unwrap();
+        assert!(!reply.moved && !reply.finished);
+        assert_eq!(reply.lines, vec!["bob leaves and stands on 16"]);
+        let reply = game.leave("alice", false).unwrap();
+        assert!(reply.finished);
+        assert_eq!(reply.lines[1..], ["🃏 Dealer: 10♦ 8♣ (18)", "🏁 alice pushes, bob loses (-5)"]);
+        assert_eq!(game.payouts(), vec![("alice".to_string(), 40)]);
+    }
+
+    #[test]
+    fn test_manager_quit_settles_the_round() {
+        let mut game = table("10s 9h 10d 7c", &[("alice", 50)]);
+        game.input("alice", &args("deal"));
+        let reply = game.leave("mod", true).unwrap();
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec!["mod ends the round. Every hand stands", "🃏 Dealer: 10♦ 7♣ (17)", "🏁 alice wins 100 (+50)"]);
+    }
+
+    #[test]
+    fn test_turn_timeout_stands() {
+        let mut game = table("10s 8h 10d 9c", &[("alice", 40)]);
+        assert!(game.turn_limit().is_none());
+        game.input("alice", &args("deal"));
+        assert!(game.turn_limit().is_some());
+        let reply = game.turn_timeout();
+        assert!(reply.finished);
+        assert_eq!(reply.lines[0], "⌛ alice took too long and stands on 18");
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut game = table("10s 8h 10d 9c", &[("alice", 40)]);
+        game.input("alice", &args("deal"));
+        let restored: BlackjackGame = serde_json::from_value(game.save()).unwrap();
+        assert_eq!(restored.render(), game.render());
+    }
+}
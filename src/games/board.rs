@@ -0,0 +1,378 @@
+use std::time::Duration;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::achievements::Event;
+use super::{capitalize, Game, Reply};
+
+/// Time the challenged player has to accept.
+const CHALLENGE_LIMIT: Duration = Duration::from_secs(5 * 60);
+
+/// mIRC colour codes used on boards.
+pub const RED: u8 = 4;
+pub const BLUE: u8 = 12;
+pub const YELLOW: u8 = 8;
+
+/// `text` in mIRC colour `code`.
+pub fn colour(code: u8, text: &str) -> String {
+    format!("\x03{:02}{}\x03", code, text)
+}
+
+/// How a game stands after a move.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Continue,
+    /// The player who moved won.
+    Won,
+    /// Nobody can win, for the reason given.
+    Drawn(&'static str),
+}
+
+/// The rules of a two-player board game. [`BoardGame`] takes care of
+/// challenges, turns, resignation, draw offers and move timeouts.
+pub trait Rules: Default + Serialize + DeserializeOwned + Send + 'static {
+    /// The command name, e.g. "ttt".
+    const KIND: &'static str;
+    /// The name used in messages, e.g. "tic-tac-toe".
+    const NAME: &'static str;
+    /// How to write a move, for usage messages.
+    const MOVE_HELP: &'static str;
+    /// Time each player has for a move before losing on time.
+    const MOVE_LIMIT: Duration;
+    /// The two sides, the one that moves first first.
+    const SIDES: [&'static str; 2];
+
+    /// Plays `mv` for `side` (0 or 1). Returns the move as it should be
+    /// announced and how the game stands, or why the move isn't allowed.
+    fn play(&mut self, side: usize, mv: &str) -> Result<(String, Outcome), String>;
+
+    /// The board, one line per row.
+    fn board(&self) -> Vec<String>;
+}
+
+/// A board game between two players: one challenges the other, who has to
+/// accept before the challenger makes the first move.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BoardGame<R> {
+    rules: R,
+    /// The challenger first; they play the first side.
+    players: [String; 2],
+    accepted: bool,
+    /// The index in `players` of whose move it is.
+    turn: usize,
+    /// The player who offered a draw, until the other answers or moves.
+    draw_offer: Option<usize>,
+    /// Achievement events not yet collected; see `Game::events`.
+    #[serde(skip)]
+    events: Vec<(String, Event)>,
+}
+
+impl<R: Rules> BoardGame<R> {
+    pub fn challenge(challenger: &str, opponent: &str) -> Self {
+        BoardGame {
+            rules: R::default(),
+            players: [challenger.to_string(), opponent.to_string()],
+            accepted: false,
+            turn: 0,
+            draw_offer: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// Ends the game with a win for `side`.
+    fn win(&mut self, side: usize, line: String) -> Reply {
+        if self.accepted {
+            self.events.push((self.players[side].clone(), Event::BoardGameWon(R::KIND)));
+        }
+        Reply::finished(vec![line])
+    }
+
+    fn side(&self, player: &str) -> Option<usize> {
+        self.players.iter().position(|p| p.eq_ignore_ascii_case(player))
+    }
+
+    fn describe_players(&self) -> String {
+        format!("{} ({}) vs {} ({})", self.players[0], R::SIDES[0], self.players[1], R::SIDES[1])
+    }
+
+    /// `Err` with a message unless `player` is in a game that has been accepted.
+    fn playing(&self, player: &str) -> Result<usize, String> {
+        let side = self.side(player).ok_or_else(|| format!("{}, this isn't your game", player))?;
+        if !self.accepted {
+            return Err(format!("Waiting for {} to accept the challenge", self.players[1]));
+        }
+        Ok(side)
+    }
+
+    fn accept(&mut self, player: &str) -> Reply {
+        if self.accepted {
+            return Reply::refused(vec!["The game has already started".to_string()]);
+        }
+        if self.side(player) != Some(1) {
+            return Reply::refused(vec![format!("Only {} can accept this challenge", self.players[1])]);
+        }
+        self.accepted = true;
+        let mut lines = vec![format!("♟️ {}: {}", capitalize(R::NAME), self.describe_players())];
+        lines.extend(self.rules.board());
+        lines.push(self.prompt());
+        Reply::new(lines)
+    }
+
+    fn decline(&mut self, player: &str) -> Reply {
+        if !self.accepted {
+            if self.side(player) != Some(1) {
+                return Reply::refused(vec![format!("Only {} can decline this challenge", self.players[1])]);
+            }
+            return Reply::finished(vec![format!("{} declined {}'s challenge", self.players[1], self.players[0])]);
+        }
+        match (self.side(player), self.draw_offer) {
+            (Some(side), Some(offer)) if side != offer => {
+                self.draw_offer = None;
+                // Answering an offer isn't a move, so the clock keeps running
+                Reply { moved: false, ..Reply::new(vec![format!("{} declines the draw", self.players[side])]) }
+            }
+            _ => Reply::refused(vec!["There's no draw offer to decline".to_string()]),
+        }
+    }
+
+    fn resign(&mut self, player: &str) -> Reply {
+        let side = match self.playing(player) {
+            Ok(side) => side,
+            Err(e) => return Reply::refused(vec![e]),
+        };
+        let line = format!("🏳️ {} resigns. {} wins!", self.players[side], self.players[1 - side]);
+        self.win(1 - side, line)
+    }
+
+    fn draw(&mut self, player: &str) -> Reply {
+        let side = match self.playing(player) {
+            Ok(side) => side,
+            Err(e) => return Reply::refused(vec![e]),
+        };
+        match self.draw_offer {
+            Some(offer) if offer == side => Reply::refused(vec![format!(
+                "You've already offered a draw. Waiting for {}", self.players[1 - side]
+            )]),
+            Some(_) => Reply::finished(vec![format!("🤝 Draw agreed between {} and {}", self.players[0], self.players[1])]),
+            None => {
+                self.draw_offer = Some(side);
+                // Offering isn't a move either, or it could stall a lost game
+                Reply { moved: false, ..Reply::new(vec![format!(
+                    "{} offers a draw. {}: !{} draw to accept or !{} decline",
+                    self.players[side], self.players[1 - side], R::KIND, R::KIND
+                )]) }
+            }
+        }
+    }
+
+    fn play(&mut self, player: &str, mv: Option<&String>) -> Reply {
+        let side = match self.playing(player) {
+            Ok(side) => side,
+            Err(e) => return Reply::refused(vec![e]),
+        };
+        if side != self.turn {
+            return Reply::refused(vec![format!("It's {}'s move", self.players[self.turn])]);
+        }
+        let Some(mv) = mv else {
+            return Reply::refused(vec![format!("Usage: !{} move {}", R::KIND, R::MOVE_HELP)]);
+        };
+        let (notation, outcome) = match self.rules.play(side, mv) {
+            Ok(played) => played,
+            Err(e) => return Reply::refused(vec![e]),
+        };
+        // Moving instead of answering turns a draw offer down
+        self.draw_offer = None;
+        let mut lines = vec![format!("{} plays {}", self.players[side], notation)];
+        lines.extend(self.rules.board());
+        match outcome {
+            Outcome::Continue => {
+                self.turn = 1 - side;
+                lines.push(self.prompt());
+                Reply::new(lines)
+            }
+            Outcome::Won => {
+                lines.push(format!("🏆 {} wins!", self.players[side]));
+                self.events.push((self.players[side].clone(), Event::BoardGameWon(R::KIND)));
+                Reply::finished(lines)
+            }
+            Outcome::Drawn(reason) => {
+                lines.push(format!("🤝 Draw: {}", reason));
+                Reply::finished(lines)
+            }
+        }
+    }
+
+    fn prompt(&self) -> String {
+        format!("{} ({}) to move: !{} move {}", self.players[self.turn], R::SIDES[self.turn], R::KIND, R::MOVE_HELP)
+    }
+}
+
+impl<R: Rules> Game for BoardGame<R> {
+    fn kind(&self) -> &'static str {
+        R::KIND
+    }
+
+    fn start(&mut self) -> Vec<String> {
+        vec![format!(
+            "⚔️ {} challenges {} to {}! {}: !{} accept or !{} decline",
+            self.players[0], self.players[1], R::NAME, self.players[1], R::KIND, R::KIND
+        )]
+    }
+
+    fn input(&mut self, player: &str, args: &[String]) -> Reply {
+        match args.first().map(String::as_str) {
+            Some("accept") => self.accept(player),
+            Some("decline") => self.decline(player),
+            Some("resign") => self.resign(player),
+            Some("draw") => self.draw(player),
+            Some("move") => self.play(player, args.get(1)),
+            // Anything else is taken as a move, e.g. `!chess e4`
+            _ => self.play(player, args.first()),
+        }
+    }
+
+    fn render(&self) -> String {
+        if !self.accepted {
+            return format!("{}: {} challenged {}, waiting for an answer", capitalize(R::NAME), self.players[0], self.players[1]);
+        }
+        format!("{}: {}, {} to move", capitalize(R::NAME), self.describe_players(), self.players[self.turn])
+    }
+
+    fn status(&self) -> Vec<String> {
+        let mut lines = vec![self.render()];
+        if self.accepted {
+            lines.extend(self.rules.board());
+        }
+        lines
+    }
+
+    fn quit(&mut self) -> Vec<String> {
+        vec![format!("{} between {} and {} ended.", capitalize(R::NAME), self.players[0], self.players[1])]
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).expect("board game state is always serializable")
+    }
+
+    fn timeout(&mut self) -> Vec<String> {
+        vec![format!("⏰ {} between {} and {} timed out.", capitalize(R::NAME), self.players[0], self.players[1])]
+    }
+
+    fn turn_limit(&self) -> Option<Duration> {
+        Some(if self.accepted { R::MOVE_LIMIT } else { CHALLENGE_LIMIT })
+    }
+
+    fn turn_timeout(&mut self) -> Reply {
+        if !self.accepted {
+            return Reply::finished(vec![format!(
+                "⌛ {} didn't answer {}'s challenge", self.players[1], self.players[0]
+            )]);
+        }
+        let line = format!("⌛ {} ran out of time. {} wins!", self.players[self.turn], self.players[1 - self.turn]);
+        self.win(1 - self.turn, line)
+    }
+
+    fn events(&mut self) -> Vec<(String, Event)> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn pair(&self) -> Option<[&str; 2]> {
+        Some([&self.players[0], &self.players[1]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tictactoe::TicTacToe;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    fn accepted() -> BoardGame<TicTacToe> {
+        let mut game = BoardGame::challenge("alice", "bob");
+        game.input("bob", &args("accept"));
+        game
+    }
+
+    #[test]
+    fn test_challenge() {
+        let mut game = BoardGame::<TicTacToe>::challenge("alice", "Bob");
+        assert_eq!(game.start(), vec!["⚔️ alice challenges Bob to tic-tac-toe! Bob: !ttt accept or !ttt decline"]);
+        assert_eq!(game.pair(), Some(["alice", "Bob"]));
+        assert_eq!(game.input("alice", &args("move b2")).lines, vec!["Waiting for Bob to accept the challenge"]);
+        assert_eq!(game.input("alice", &args("accept")).lines, vec!["Only Bob can accept this challenge"]);
+        assert_eq!(game.turn_limit(), Some(CHALLENGE_LIMIT));
+
+        let reply = game.input("bob", &args("accept"));
+        assert_eq!(reply.lines[0], "♟️ Tic-tac-toe: alice (X) vs Bob (O)");
+        assert_eq!(reply.lines.last().unwrap(), "alice (X) to move: !ttt move <a1-c3>");
+        assert_eq!(game.turn_limit(), Some(TicTacToe::MOVE_LIMIT));
+    }
+
+    #[test]
+    fn test_decline_and_challenge_timeout() {
+        let mut game = BoardGame::<TicTacToe>::challenge("alice", "bob");
+        let reply = game.input("bob", &args("decline"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec!["bob declined alice's challenge"]);
+
+        let mut game = BoardGame::<TicTacToe>::challenge("alice", "bob");
+        assert_eq!(game.turn_timeout().lines, vec!["⌛ bob didn't answer alice's challenge"]);
+    }
+
+    #[test]
+    fn test_turns() {
+        let mut game = accepted();
+        assert_eq!(game.input("bob", &args("move b2")).lines, vec!["It's alice's move"]);
+        assert_eq!(game.input("carol", &args("move b2")).lines, vec!["carol, this isn't your game"]);
+        assert_eq!(game.input("alice", &args("move")).lines, vec!["Usage: !ttt move <a1-c3>"]);
+        let reply = game.input("alice", &args("b2"));
+        assert_eq!(reply.lines[0], "alice plays b2");
+        assert_eq!(reply.lines.last().unwrap(), "bob (O) to move: !ttt move <a1-c3>");
+    }
+
+    #[test]
+    fn test_resign() {
+        let mut game = accepted();
+        let reply = game.input("bob", &args("resign"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec!["🏳️ bob resigns. alice wins!"]);
+    }
+
+    #[test]
+    fn test_draw_offers() {
+        let mut game = accepted();
+        assert_eq!(game.input("alice", &args("draw")).lines, vec!["alice offers a draw. bob: !ttt draw to accept or !ttt decline"]);
+        assert_eq!(game.input("alice", &args("draw")).lines, vec!["You've already offered a draw. Waiting for bob"]);
+        assert_eq!(game.input("bob", &args("decline")).lines, vec!["bob declines the draw"]);
+        assert_eq!(game.input("bob", &args("decline")).lines, vec!["There's no draw offer to decline"]);
+
+        // A move turns the offer down
+        game.input("bob", &args("draw"));
+        game.input("alice", &args("b2"));
+        assert!(!game.input("alice", &args("draw")).finished);
+
+        let reply = game.input("bob", &args("draw"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec!["🤝 Draw agreed between alice and bob"]);
+    }
+
+    #[test]
+    fn test_move_timeout_loses() {
+        let mut game = accepted();
+        game.input("alice", &args("b2"));
+        let reply = game.turn_timeout();
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec!["⌛ bob ran out of time. alice wins!"]);
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut game = accepted();
+        game.input("alice", &args("b2"));
+        let restored: BoardGame<TicTacToe> = serde_json::from_value(game.save()).unwrap();
+        assert_eq!(restored.status(), game.status());
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+const SUITS: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+/// A playing card. Ranks run from 2 to 14, with the ace high.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Card {
+    pub rank: u8,
+    pub suit: Suit,
+}
+
+pub const ACE: u8 = 14;
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rank = match self.rank {
+            11 => "J".to_string(),
+            12 => "Q".to_string(),
+            13 => "K".to_string(),
+            ACE => "A".to_string(),
+            n => n.to_string(),
+        };
+        let suit = match self.suit {
+            Suit::Clubs => '♣',
+            Suit::Diamonds => '♦',
+            Suit::Hearts => '♥',
+            Suit::Spades => '♠',
+        };
+        write!(f, "{}{}", rank, suit)
+    }
+}
+
+/// Cards separated by spaces, e.g. "A♠ 10♦".
+pub fn show(cards: &[Card]) -> String {
+    cards.iter().map(Card::to_string).collect::<Vec<_>>().join(" ")
+}
+
+/// Parses cards written like "As 10d Kh", for tests.
+#[cfg(test)]
+pub fn cards(text: &str) -> Vec<Card> {
+    text.split_whitespace()
+        .map(|card| {
+            let (rank, suit) = card.split_at(card.len() - 1);
+            let rank = match rank {
+                "J" => 11,
+                "Q" => 12,
+                "K" => 13,
+                "A" => ACE,
+                n => n.parse().expect("card rank"),
+            };
+            let suit = match suit {
+                "c" => Suit::Clubs,
+                "d" => Suit::Diamonds,
+                "h" => Suit::Hearts,
+                "s" => Suit::Spades,
+                _ => panic!("card suit"),
+            };
+            Card { rank, suit }
+        })
+        .collect()
+}
+
+/// Cards left to deal.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Deck {
+    /// Dealt from the end.
+    cards: Vec<Card>,
+}
+
+impl Deck {
+    /// A full 52-card deck in a random order.
+    pub fn shuffled<R: Rng>(rng: &mut R) -> Self {
+        let mut cards: Vec<Card> = SUITS.iter()
+            .flat_map(|&suit| (2..=ACE).map(move |rank| Card { rank, suit }))
+            .collect();
+        cards.shuffle(rng);
+        Deck { cards }
+    }
+
+    /// A deck that deals `cards` in the order given, for tests.
+    #[cfg(test)]
+    pub fn stacked(mut cards: Vec<Card>) -> Self {
+        cards.reverse();
+        Deck { cards }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Deals the next card, or `None` once the deck is used up. Games never
+    /// reshuffle mid-hand, so no card can turn up twice.
+    pub fn deal(&mut self) -> Option<Card> {
+        self.cards.pop()
+    }
+
+    /// Deals `count` cards, or none at all if fewer are left.
+    pub fn deal_many(&mut self, count: usize) -> Option<Vec<Card>> {
+        if count > self.cards.len() {
+            return None;
+        }
+        Some((0..count).filter_map(|_| self.cards.pop()).collect())
+    }
+}
+
+/// The blackjack total of `cards`, counting aces as 11 where that doesn't
+/// bust, and whether an ace is still counted as 11 ("soft").
+pub fn blackjack_value(cards: &[Card]) -> (u32, bool) {
+    let mut total = 0;
+    let mut aces = 0;
+    for card in cards {
+        total += match card.rank {
+            ACE => {
+                aces += 1;
+                11
+            }
+            11..=13 => 10,
+            n => n as u32,
+        };
+    }
+    while total > 21 && aces > 0 {
+        total -= 10;
+        aces -= 1;
+    }
+    (total, aces > 0)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    HighCard,
+    Pair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+}
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::HighCard => "high card",
+            Category::Pair => "a pair",
+            Category::TwoPair => "two pair",
+            Category::ThreeOfAKind => "three of a kind",
+            Category::Straight => "a straight",
+            Category::Flush => "a flush",
+            Category::FullHouse => "a full house",
+            Category::FourOfAKind => "four of a kind",
+            Category::StraightFlush => "a straight flush",
+        }
+    }
+}
+
+/// The strength of a poker hand. Hands compare by category, then by the
+/// tiebreak ranks in order.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HandRank {
+    pub category: Category,
+    pub ranks: Vec<u8>,
+}
+
+/// The best five-card poker hand among `cards`, which holds five to seven cards.
+pub fn evaluate(cards: &[Card]) -> HandRank {
+    let n = cards.len();
+    (0u32..1 << n)
+        .filter(|mask| mask.count_ones() == 5)
+        .map(|mask| {
+            let five: Vec<Card> = (0..n).filter(|i| mask & (1 << i) != 0).map(|i| cards[i]).collect();
+            evaluate_five(&five)
+        })
+        .max()
+        .expect("at least five cards")
+}
+
+fn evaluate_five(cards: &[Card]) -> HandRank {
+    // Ranks grouped by how often they appear, biggest groups and then highest ranks first
+    let mut groups: Vec<(usize, u8)> = Vec::new();
+    for rank in (2..=ACE).rev() {
+        let count = cards.iter().filter(|c| c.rank == rank).count();
+        if count > 0 {
+            groups.push((count, rank));
+        }
+    }
+    groups.sort_by(|a, b| b.cmp(a));
+    let ranks: Vec<u8> = groups.iter().map(|&(_, rank)| rank).collect();
+
+    let flush = cards.iter().all(|c| c.suit == cards[0].suit);
+    let straight_high = match ranks.as_slice() {
+        [ACE, 5, 4, 3, 2] => Some(5),
+        [high, .., low] if ranks.len() == 5 && high - low == 4 => Some(*high),
+        _ => None,
+    };
+
+    let category = match (straight_high, flush, groups[0].0, groups.get(1).map(|g| g.0)) {
+        (Some(_), true, _, _) => Category::StraightFlush,
+        (_, _, 4, _) => Category::FourOfAKind,
+        (_, _, 3, Some(2)) => Category::FullHouse,
+        (_, true, _, _) => Category::Flush,
+        (Some(_), _, _, _) => Category::Straight,
+        (_, _, 3, _) => Category::ThreeOfAKind,
+        (_, _, 2, Some(2)) => Category::TwoPair,
+        (_, _, 2, _) => Category::Pair,
+        _ => Category::HighCard,
+    };
+    match straight_high {
+        Some(high) if matches!(category, Category::Straight | Category::StraightFlush) => HandRank { category, ranks: vec![high] },
+        _ => HandRank { category, ranks },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn rank(text: &str) -> HandRank {
+        evaluate(&cards(text))
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(show(&cards("As 10d Jc 2h")), "A♠ 10♦ J♣ 2♥");
+    }
+
+    #[test]
+    fn test_deck() {
+        let mut deck = Deck::shuffled(&mut StdRng::seed_from_u64(1));
+        let mut dealt = deck.deal_many(52).unwrap();
+        dealt.sort_by_key(|c| (c.rank, c.suit as u8));
+        dealt.dedup();
+        assert_eq!(dealt.len(), 52);
+        // An empty deck stays empty
+        assert_eq!(deck.deal(), None);
+
+        let mut deck = Deck::stacked(cards("As Kd 2c"));
+        assert_eq!(deck.deal_many(4), None);
+        assert_eq!(deck.deal_many(2), Some(cards("As Kd")));
+        assert_eq!(deck.remaining(), 1);
+    }
+
+    #[test]
+    fn test_blackjack_value() {
+        assert_eq!(blackjack_value(&cards("As Kd")), (21, true));
+        assert_eq!(blackjack_value(&cards("As Ad 9c")), (21, true));
+        assert_eq!(blackjack_value(&cards("As 6d 9c")), (16, false));
+        assert_eq!(blackjack_value(&cards("Qs 6d 9c")), (25, false));
+    }
+
+    #[test]
+    fn test_categories() {
+        assert_eq!(rank("As Ks Qs Js 10s").category, Category::StraightFlush);
+        assert_eq!(rank("9s 9d 9c 9h 2s").category, Category::FourOfAKind);
+        assert_eq!(rank("9s 9d 9c 2h 2s").category, Category::FullHouse);
+        assert_eq!(rank("As 9s 7s 4s 2s").category, Category::Flush);
+        assert_eq!(rank("5s 4d 3c 2h Ah"), HandRank { category: Category::Straight, ranks: vec![5] });
+        assert_eq!(rank("9s 9d 9c Kh 2s").category, Category::ThreeOfAKind);
+        assert_eq!(rank("9s 9d 2c Kh 2s"), HandRank { category: Category::TwoPair, ranks: vec![9, 2, 13] });
+        assert_eq!(rank("9s 9d 3c Kh 2s").category, Category::Pair);
+        assert_eq!(rank("As 9d 3c Kh 2s").category, Category::HighCard);
+    }
+
+    #[test]
+    fn test_best_of_seven() {
+        assert_eq!(rank("As Ad Kc Kh Qs Qd 2c"), HandRank { category: Category::TwoPair, ranks: vec![ACE, 13, 12] });
+        assert_eq!(rank("2h 3h 9h Jh Qd Kh Ac").category, Category::Flush);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert!(rank("As Ad 5c 4h 2s") > rank("Ks Kd Qc Jh 10s"));
+        assert!(rank("As Ad Kc 4h 2s") > rank("Ah Ac Qc Jh 10s"));
+        // The wheel is the lowest straight
+        assert!(rank("6s 5d 4c 3h 2s") > rank("5s 4d 3c 2h Ah"));
+        assert_eq!(rank("As Ad Kc 4h 2s"), rank("Ah Ac Kd 4s 2d"));
+    }
+}
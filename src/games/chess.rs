@@ -0,0 +1,566 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use super::board::{Outcome, Rules};
+
+const WHITE: usize = 0;
+const KNIGHT_STEPS: [(i32, i32); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i32, i32); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const ROOK_LINES: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_LINES: [(i32, i32); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+/// Half-moves without a capture or pawn move before the game is drawn.
+const FIFTY_MOVES: u32 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Kind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl Kind {
+    fn letter(self) -> char {
+        match self {
+            Kind::Pawn => 'P',
+            Kind::Knight => 'N',
+            Kind::Bishop => 'B',
+            Kind::Rook => 'R',
+            Kind::Queen => 'Q',
+            Kind::King => 'K',
+        }
+    }
+
+    fn from_letter(letter: char) -> Option<Kind> {
+        match letter.to_ascii_uppercase() {
+            'P' => Some(Kind::Pawn),
+            'N' => Some(Kind::Knight),
+            'B' => Some(Kind::Bishop),
+            'R' => Some(Kind::Rook),
+            'Q' => Some(Kind::Queen),
+            'K' => Some(Kind::King),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Piece {
+    kind: Kind,
+    /// 0 for white, 1 for black.
+    side: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Move {
+    from: usize,
+    to: usize,
+    promotion: Option<Kind>,
+}
+
+/// A chess position. White is side 0. Moves are read in standard algebraic
+/// notation (`e4`, `Nbd2`, `exd5`, `O-O`, `e8=Q`) or as coordinates (`e2e4`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chess {
+    /// Indexed by `rank * 8 + file`, so a1 is 0 and h8 is 63.
+    board: Vec<Option<Piece>>,
+    /// Castling still allowed: white king side, white queen side, then black's.
+    castling: [bool; 4],
+    /// The square a pawn skipped with a double step on the last move.
+    en_passant: Option<usize>,
+    /// Half-moves since the last capture or pawn move.
+    halfmove_clock: u32,
+}
+
+impl Default for Chess {
+    fn default() -> Self {
+        let back = [Kind::Rook, Kind::Knight, Kind::Bishop, Kind::Queen, Kind::King, Kind::Bishop, Kind::Knight, Kind::Rook];
+        let mut board = vec![None; 64];
+        for file in 0..8 {
+            board[file] = Some(Piece { kind: back[file], side: WHITE });
+            board[8 + file] = Some(Piece { kind: Kind::Pawn, side: WHITE });
+            board[48 + file] = Some(Piece { kind: Kind::Pawn, side: 1 });
+            board[56 + file] = Some(Piece { kind: back[file], side: 1 });
+        }
+        Chess { board, castling: [true; 4], en_passant: None, halfmove_clock: 0 }
+    }
+}
+
+/// The square `df` files and `dr` ranks away from `square`, if it's on the board.
+fn offset(square: usize, df: i32, dr: i32) -> Option<usize> {
+    let file = (square % 8) as i32 + df;
+    let rank = (square / 8) as i32 + dr;
+    ((0..8).contains(&file) && (0..8).contains(&rank)).then_some((rank * 8 + file) as usize)
+}
+
+/// The direction `side`'s pawns move in, as a rank offset.
+fn forward(side: usize) -> i32 {
+    if side == WHITE { 1 } else { -1 }
+}
+
+fn square_name(square: usize) -> String {
+    format!("{}{}", (b'a' + (square % 8) as u8) as char, square / 8 + 1)
+}
+
+fn parse_square(file: u8, rank: u8) -> Option<usize> {
+    ((b'a'..=b'h').contains(&file) && (b'1'..=b'8').contains(&rank))
+        .then(|| (rank - b'1') as usize * 8 + (file - b'a') as usize)
+}
+
+/// A move with the decorations players may or may not type removed:
+/// captures, checks, annotations, `=` and dashes. Castling zeros become
+/// `O` and a trailing promotion piece is upper-cased.
+fn normalize(text: &str) -> String {
+    let mut chars: Vec<char> = text.chars()
+        .filter(|c| !"x+#!?=-".contains(*c))
+        .map(|c| if c == '0' || c == 'o' { 'O' } else { c })
+        .collect();
+    if let [.., digit, piece] = chars[..] {
+        if digit.is_ascii_digit() && "qrbn".contains(piece) {
+            let last = chars.len() - 1;
+            chars[last] = piece.to_ascii_uppercase();
+        }
+    }
+    chars.into_iter().collect()
+}
+
+impl Chess {
+    fn at(&self, square: usize) -> Option<Piece> {
+        self.board[square]
+    }
+
+    fn king(&self, side: usize) -> usize {
+        self.board.iter()
+            .position(|p| *p == Some(Piece { kind: Kind::King, side }))
+            .expect("both kings are always on the board")
+    }
+
+    fn in_check(&self, side: usize) -> bool {
+        self.attacked(self.king(side), 1 - side)
+    }
+
+    /// Whether any piece of side `by` attacks `square`.
+    fn attacked(&self, square: usize, by: usize) -> bool {
+        let is = |sq: Option<usize>, kinds: &[Kind]| {
+            sq.and_then(|sq| self.at(sq)).is_some_and(|p| p.side == by && kinds.contains(&p.kind))
+        };
+        if [-1, 1].iter().any(|&df| is(offset(square, df, -forward(by)), &[Kind::Pawn])) {
+            return true;
+        }
+        if KNIGHT_STEPS.iter().any(|&(df, dr)| is(offset(square, df, dr), &[Kind::Knight])) {
+            return true;
+        }
+        if KING_STEPS.iter().any(|&(df, dr)| is(offset(square, df, dr), &[Kind::King])) {
+            return true;
+        }
+        let slides = |lines: &[(i32, i32)], kinds: &[Kind]| {
+            lines.iter().any(|&(df, dr)| {
+                let mut sq = square;
+                while let Some(next) = offset(sq, df, dr) {
+                    match self.at(next) {
+                        Some(p) => return p.side == by && kinds.contains(&p.kind),
+                        None => sq = next,
+                    }
+                }
+                false
+            })
+        };
+        slides(&ROOK_LINES, &[Kind::Rook, Kind::Queen]) || slides(&BISHOP_LINES, &[Kind::Bishop, Kind::Queen])
+    }
+
+    /// Moves that follow the pieces' rules but may leave the king in check.
+    fn pseudo_moves(&self, side: usize) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for from in 0..64 {
+            let Some(piece) = self.at(from).filter(|p| p.side == side) else {
+                continue;
+            };
+            let to = |to: usize, moves: &mut Vec<Move>| moves.push(Move { from, to, promotion: None });
+            match piece.kind {
+                Kind::Pawn => self.pawn_moves(from, side, &mut moves),
+                Kind::Knight | Kind::King => {
+                    let steps = if piece.kind == Kind::Knight { &KNIGHT_STEPS } else { &KING_STEPS };
+                    for &(df, dr) in steps {
+                        if let Some(sq) = offset(from, df, dr) {
+                            if self.at(sq).is_none_or(|p| p.side != side) {
+                                to(sq, &mut moves);
+                            }
+                        }
+                    }
+                }
+                Kind::Bishop | Kind::Rook | Kind::Queen => {
+                    let lines: Vec<(i32, i32)> = match piece.kind {
+                        Kind::Bishop => BISHOP_LINES.to_vec(),
+                        Kind::Rook => ROOK_LINES.to_vec(),
+                        _ => [ROOK_LINES, BISHOP_LINES].concat(),
+                    };
+                    for (df, dr) in lines {
+                        let mut sq = from;
+                        while let Some(next) = offset(sq, df, dr) {
+                            match self.at(next) {
+                                Some(p) => {
+                                    if p.side != side {
+                                        to(next, &mut moves);
+                                    }
+                                    break;
+                                }
+                                None => {
+                                    to(next, &mut moves);
+                                    sq = next;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        self.castling_moves(side, &mut moves);
+        moves
+    }
+
+    fn pawn_moves(&self, from: usize, side: usize, moves: &mut Vec<Move>) {
+        let dir = forward(side);
+        let start_rank = if side == WHITE { 1 } else { 6 };
+        let mut push = |to: usize| {
+            if to / 8 == 0 || to / 8 == 7 {
+                for kind in [Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight] {
+                    moves.push(Move { from, to, promotion: Some(kind) });
+                }
+            } else {
+                moves.push(Move { from, to, promotion: None });
+            }
+        };
+        if let Some(one) = offset(from, 0, dir).filter(|&sq| self.at(sq).is_none()) {
+            push(one);
+            if from / 8 == start_rank {
+                if let Some(two) = offset(from, 0, 2 * dir).filter(|&sq| self.at(sq).is_none()) {
+                    push(two);
+                }
+            }
+        }
+        for df in [-1, 1] {
+            if let Some(sq) = offset(from, df, dir) {
+                let capture = self.at(sq).is_some_and(|p| p.side != side);
+                if capture || self.en_passant == Some(sq) {
+                    push(sq);
+                }
+            }
+        }
+    }
+
+    fn castling_moves(&self, side: usize, moves: &mut Vec<Move>) {
+        let home = if side == WHITE { 0 } else { 56 };
+        let king = home + 4;
+        if self.at(king) != Some(Piece { kind: Kind::King, side }) || self.in_check(side) {
+            return;
+        }
+        // King side, then queen side: the rook's corner, squares that must be
+        // empty and squares the king crosses, which mustn't be attacked
+        let wings = [(home + 7, vec![home + 5, home + 6], [home + 5, home + 6]), (home, vec![home + 1, home + 2, home + 3], [home + 3, home + 2])];
+        for (i, (corner, empty, crossed)) in wings.into_iter().enumerate() {
+            if self.castling[side * 2 + i]
+                && self.at(corner) == Some(Piece { kind: Kind::Rook, side })
+                && empty.iter().all(|&sq| self.at(sq).is_none())
+                && crossed.iter().all(|&sq| !self.attacked(sq, 1 - side))
+            {
+                moves.push(Move { from: king, to: crossed[1], promotion: None });
+            }
+        }
+    }
+
+    fn legal_moves(&self, side: usize) -> Vec<Move> {
+        self.pseudo_moves(side)
+            .into_iter()
+            .filter(|&m| {
+                let mut after = self.clone();
+                after.apply(m);
+                !after.in_check(side)
+            })
+            .collect()
+    }
+
+    fn apply(&mut self, m: Move) {
+        let piece = self.board[m.from].take().expect("moves start on a piece");
+        let mut capture = self.board[m.to].is_some();
+        if piece.kind == Kind::Pawn && Some(m.to) == self.en_passant && m.from % 8 != m.to % 8 {
+            let taken = offset(m.to, 0, -forward(piece.side)).expect("the passed pawn is on the board");
+            self.board[taken] = None;
+            capture = true;
+        }
+        if piece.kind == Kind::King && m.from.abs_diff(m.to) == 2 {
+            let (rook_from, rook_to) = if m.to > m.from { (m.from + 3, m.from + 1) } else { (m.from - 4, m.from - 1) };
+            self.board[rook_to] = self.board[rook_from].take();
+        }
+        self.en_passant = (piece.kind == Kind::Pawn && m.from.abs_diff(m.to) == 16).then_some((m.from + m.to) / 2);
+        // Moving the king or a rook, or losing the rook, gives up castling on that side
+        for (i, (king, corner)) in [(4, 7), (4, 0), (60, 63), (60, 56)].into_iter().enumerate() {
+            if m.from == king || m.from == corner || m.to == corner {
+                self.castling[i] = false;
+            }
+        }
+        self.halfmove_clock = if piece.kind == Kind::Pawn || capture { 0 } else { self.halfmove_clock + 1 };
+        self.board[m.to] = Some(Piece { kind: m.promotion.unwrap_or(piece.kind), ..piece });
+    }
+
+    /// `m` in standard algebraic notation, without a check sign. `legal`
+    /// holds every legal move, to tell pieces that could make it apart.
+    fn san(&self, m: Move, legal: &[Move]) -> String {
+        let piece = self.at(m.from).expect("moves start on a piece");
+        if piece.kind == Kind::King && m.from.abs_diff(m.to) == 2 {
+            return if m.to > m.from { "O-O" } else { "O-O-O" }.to_string();
+        }
+        let capture = self.at(m.to).is_some() || (piece.kind == Kind::Pawn && m.from % 8 != m.to % 8);
+        let from = square_name(m.from);
+        let mut san = String::new();
+        if piece.kind == Kind::Pawn {
+            if capture {
+                san.push_str(&from[..1]);
+            }
+        } else {
+            san.push(piece.kind.letter());
+            let rivals: Vec<usize> = legal.iter()
+                .filter(|o| o.to == m.to && o.from != m.from && self.at(o.from) == Some(piece))
+                .map(|o| o.from)
+                .collect();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|r| r % 8 != m.from % 8) {
+                    san.push_str(&from[..1]);
+                } else if rivals.iter().all(|r| r / 8 != m.from / 8) {
+                    san.push_str(&from[1..]);
+                } else {
+                    san.push_str(&from);
+                }
+            }
+        }
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&square_name(m.to));
+        if let Some(kind) = m.promotion {
+            san.push('=');
+            san.push(kind.letter());
+        }
+        san
+    }
+
+    /// Finds the legal move `text` stands for. Promotions default to a queen.
+    fn parse(&self, text: &str, legal: &[Move]) -> Option<Move> {
+        let input = normalize(text);
+        let bytes = input.as_bytes();
+        if bytes.len() == 4 || bytes.len() == 5 {
+            let from = parse_square(bytes[0], bytes[1]);
+            let to = parse_square(bytes[2], bytes[3]);
+            if let (Some(from), Some(to)) = (from, to) {
+                let promotion = bytes.get(4).and_then(|&b| Kind::from_letter(b as char));
+                return legal.iter().copied().find(|m| {
+                    m.from == from && m.to == to && (m.promotion == promotion || (promotion.is_none() && m.promotion == Some(Kind::Queen)))
+                });
+            }
+        }
+
+        let mut spellings = vec![input.clone()];
+        // Piece letters typed in lower case; a leading "b" is tried as a b-pawn first
+        if let Some(first) = input.chars().next().filter(|c| "nbrqk".contains(*c)) {
+            spellings.push(format!("{}{}", first.to_ascii_uppercase(), &input[1..]));
+        }
+        spellings.iter().find_map(|spelling| {
+            legal.iter().copied().find(|&m| {
+                let san = normalize(&self.san(m, legal));
+                san == *spelling || (m.promotion == Some(Kind::Queen) && san == format!("{}Q", spelling))
+            })
+        })
+    }
+
+    /// Neither side has enough pieces left to mate: bare kings, or a king
+    /// and one bishop or knight against a bare king.
+    fn insufficient_material(&self) -> bool {
+        let pieces: Vec<Kind> = self.board.iter().flatten().map(|p| p.kind).filter(|&k| k != Kind::King).collect();
+        match pieces[..] {
+            [] => true,
+            [kind] => matches!(kind, Kind::Bishop | Kind::Knight),
+            _ => false,
+        }
+    }
+
+    /// A position from the piece placement part of a FEN string, with no
+    /// castling rights, for tests.
+    #[cfg(test)]
+    fn from_placement(placement: &str) -> Self {
+        let mut board = vec![None; 64];
+        for (row, rank) in placement.split('/').enumerate() {
+            let mut file = 0;
+            for c in rank.chars() {
+                match c.to_digit(10) {
+                    Some(n) => file += n as usize,
+                    None => {
+                        let side = if c.is_ascii_uppercase() { WHITE } else { 1 };
+                        let kind = Kind::from_letter(c).expect("piece letter");
+                        board[(7 - row) * 8 + file] = Some(Piece { kind, side });
+                        file += 1;
+                    }
+                }
+            }
+        }
+        Chess { board, castling: [false; 4], en_passant: None, halfmove_clock: 0 }
+    }
+}
+
+impl Rules for Chess {
+    const KIND: &'static str = "chess";
+    const NAME: &'static str = "chess";
+    const MOVE_HELP: &'static str = "<move, e.g. e4 or Nf3>";
+    const MOVE_LIMIT: Duration = Duration::from_secs(10 * 60);
+    const SIDES: [&'static str; 2] = ["white", "black"];
+
+    fn play(&mut self, side: usize, mv: &str) -> Result<(String, Outcome), String> {
+        let legal = self.legal_moves(side);
+        let m = self.parse(mv, &legal).ok_or_else(|| format!("{} isn't a legal move", mv))?;
+        let mut notation = self.san(m, &legal);
+        self.apply(m);
+
+        let opponent = 1 - side;
+        let check = self.in_check(opponent);
+        let outcome = if self.legal_moves(opponent).is_empty() {
+            if check {
+                notation.push('#');
+                Outcome::Won
+            } else {
+                Outcome::Drawn("stalemate")
+            }
+        } else {
+            if check {
+                notation.push('+');
+            }
+            if self.halfmove_clock >= FIFTY_MOVES {
+                Outcome::Drawn("fifty moves without a capture or pawn move")
+            } else if self.insufficient_material() {
+                Outcome::Drawn("insufficient material")
+            } else {
+                Outcome::Continue
+            }
+        };
+        Ok((notation, outcome))
+    }
+
+    fn board(&self) -> Vec<String> {
+        let mut lines: Vec<String> = (0..8).rev()
+            .map(|rank| {
+                let squares: Vec<String> = (0..8)
+                    .map(|file| match self.at(rank * 8 + file) {
+                        Some(p) if p.side == WHITE => p.kind.letter().to_string(),
+                        Some(p) => p.kind.letter().to_ascii_lowercase().to_string(),
+                        None => ".".to_string(),
+                    })
+                    .collect();
+                format!("{} {}", rank + 1, squares.join(" "))
+            })
+            .collect();
+        lines.push("  a b c d e f g h".to_string());
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays `moves` from the starting position, white first, and returns
+    /// the last move's notation and outcome.
+    fn play(game: &mut Chess, moves: &str) -> (String, Outcome) {
+        let mut last = None;
+        for (i, mv) in moves.split_whitespace().enumerate() {
+            last = Some(game.play(i % 2, mv).unwrap_or_else(|e| panic!("{}", e)));
+        }
+        last.expect("at least one move")
+    }
+
+    #[test]
+    fn test_opening_moves() {
+        let mut game = Chess::default();
+        assert_eq!(game.legal_moves(WHITE).len(), 20);
+        assert_eq!(game.play(0, "e4").unwrap(), ("e4".to_string(), Outcome::Continue));
+        assert_eq!(game.play(1, "e2e4").unwrap_err(), "e2e4 isn't a legal move");
+        assert_eq!(game.play(1, "e7e5").unwrap().0, "e5");
+        assert_eq!(game.play(0, "nf3").unwrap().0, "Nf3");
+        assert_eq!(game.play(1, "Ke6").unwrap_err(), "Ke6 isn't a legal move");
+    }
+
+    #[test]
+    fn test_scholars_mate() {
+        let mut game = Chess::default();
+        let last = play(&mut game, "e4 e5 Bc4 Nc6 Qh5 Nf6 Qxf7#");
+        assert_eq!(last, ("Qxf7#".to_string(), Outcome::Won));
+    }
+
+    #[test]
+    fn test_castling() {
+        let mut game = Chess::default();
+        assert_eq!(play(&mut game, "e4 e5 Nf3 Nc6 Bc4 Bc5 0-0").0, "O-O");
+        assert_eq!(game.at(6), Some(Piece { kind: Kind::King, side: WHITE }));
+        assert_eq!(game.at(5), Some(Piece { kind: Kind::Rook, side: WHITE }));
+        assert!(!game.castling[0] && !game.castling[1]);
+
+        // Not through an attacked square
+        let mut game = Chess::from_placement("4k3/8/8/8/8/8/5r2/4K2R");
+        game.castling[0] = true;
+        assert!(game.play(0, "O-O").is_err());
+    }
+
+    #[test]
+    fn test_en_passant() {
+        let mut game = Chess::default();
+        assert_eq!(play(&mut game, "e4 a6 e5 d5 exd6").0, "exd6");
+        assert_eq!(game.at(35), None);
+        // Only straight after the double step
+        let mut game = Chess::default();
+        play(&mut game, "e4 a6 e5 d5 a3 a5");
+        assert!(game.play(0, "exd6").is_err());
+    }
+
+    #[test]
+    fn test_promotion() {
+        let mut game = Chess::from_placement("8/4P3/8/8/8/k7/8/4K3");
+        assert_eq!(game.clone().play(0, "e8=N").unwrap().0, "e8=N");
+        assert_eq!(game.clone().play(0, "e7e8n").unwrap().0, "e8=N");
+        assert_eq!(game.play(0, "e8").unwrap().0, "e8=Q");
+        assert_eq!(game.at(60), Some(Piece { kind: Kind::Queen, side: WHITE }));
+    }
+
+    #[test]
+    fn test_disambiguation() {
+        let mut game = Chess::default();
+        play(&mut game, "Nf3 Nf6 d3 d6");
+        assert_eq!(game.clone().play(0, "Nd2").unwrap_err(), "Nd2 isn't a legal move");
+        assert_eq!(game.play(0, "Nbd2").unwrap().0, "Nbd2");
+    }
+
+    #[test]
+    fn test_pinned_piece_cannot_move() {
+        let mut game = Chess::from_placement("4r1k1/8/8/8/8/8/4B3/4K3");
+        assert_eq!(game.play(0, "Bd3").unwrap_err(), "Bd3 isn't a legal move");
+    }
+
+    #[test]
+    fn test_stalemate_and_insufficient_material() {
+        let mut game = Chess::from_placement("k7/8/1K6/2Q5/8/8/8/8");
+        assert_eq!(game.play(0, "Qc7").unwrap(), ("Qc7".to_string(), Outcome::Drawn("stalemate")));
+
+        let mut game = Chess::from_placement("4k3/8/8/8/8/8/3r4/2B1K3");
+        assert_eq!(game.play(0, "Bxd2").unwrap(), ("Bxd2".to_string(), Outcome::Drawn("insufficient material")));
+    }
+
+    #[test]
+    fn test_check_sign() {
+        let mut game = Chess::default();
+        assert_eq!(play(&mut game, "e4 f5 Qh5").0, "Qh5+");
+    }
+
+    #[test]
+    fn test_board() {
+        let board = Chess::default().board();
+        assert_eq!(board[0], "8 r n b q k b n r");
+        assert_eq!(board[7], "1 R N B Q K B N R");
+        assert_eq!(board[8], "  a b c d e f g h");
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use super::board::{colour, Outcome, Rules, RED, YELLOW};
+
+const COLUMNS: usize = 7;
+const ROWS: usize = 6;
+
+/// Connect four on a 7×6 board. A move names the column, 1 to 7, and the
+/// disc drops to the lowest free row.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConnectFour {
+    /// The side in each cell, row by row from the bottom.
+    cells: Vec<Option<usize>>,
+}
+
+impl Default for ConnectFour {
+    fn default() -> Self {
+        ConnectFour { cells: vec![None; COLUMNS * ROWS] }
+    }
+}
+
+impl ConnectFour {
+    fn cell(&self, column: usize, row: usize) -> Option<usize> {
+        self.cells[row * COLUMNS + column]
+    }
+
+    /// Whether the disc at `column`, `row` is part of four in a row.
+    fn connects(&self, column: usize, row: usize) -> bool {
+        let side = self.cell(column, row);
+        [(1, 0), (0, 1), (1, 1), (1, -1)].iter().any(|&(dc, dr)| {
+            // Discs of the same side running away from this one in both directions
+            let run = |sign: isize| {
+                (1..4)
+                    .map(|i| (column as isize + sign * dc * i, row as isize + sign * dr * i))
+                    .take_while(|&(c, r)| {
+                        (0..COLUMNS as isize).contains(&c)
+                            && (0..ROWS as isize).contains(&r)
+                            && self.cell(c as usize, r as usize) == side
+                    })
+                    .count()
+            };
+            1 + run(1) + run(-1) >= 4
+        })
+    }
+}
+
+impl Rules for ConnectFour {
+    const KIND: &'static str = "c4";
+    const NAME: &'static str = "connect four";
+    const MOVE_HELP: &'static str = "<1-7>";
+    const MOVE_LIMIT: Duration = Duration::from_secs(3 * 60);
+    const SIDES: [&'static str; 2] = ["red", "yellow"];
+
+    fn play(&mut self, side: usize, mv: &str) -> Result<(String, Outcome), String> {
+        let column = match mv.parse::<usize>() {
+            Ok(n) if (1..=COLUMNS).contains(&n) => n - 1,
+            _ => return Err(format!("Columns are numbered 1 to {}", COLUMNS)),
+        };
+        let row = (0..ROWS)
+            .find(|&row| self.cell(column, row).is_none())
+            .ok_or_else(|| format!("Column {} is full", column + 1))?;
+        self.cells[row * COLUMNS + column] = Some(side);
+
+        let outcome = if self.connects(column, row) {
+            Outcome::Won
+        } else if self.cells.iter().all(Option::is_some) {
+            Outcome::Drawn("the board is full")
+        } else {
+            Outcome::Continue
+        };
+        Ok((format!("column {}", column + 1), outcome))
+    }
+
+    fn board(&self) -> Vec<String> {
+        let mut lines: Vec<String> = (0..ROWS).rev()
+            .map(|row| {
+                let discs: Vec<String> = (0..COLUMNS)
+                    .map(|column| match self.cell(column, row) {
+                        Some(0) => colour(RED, "X"),
+                        Some(_) => colour(YELLOW, "O"),
+                        None => ".".to_string(),
+                    })
+                    .collect();
+                format!("|{}|", discs.join(" "))
+            })
+            .collect();
+        lines.push(format!(" {}", (1..=COLUMNS).map(|n| n.to_string()).collect::<Vec<_>>().join(" ")));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays the columns in turn, starting with side 0, and returns the last outcome.
+    fn play(game: &mut ConnectFour, columns: &[&str]) -> Outcome {
+        let mut outcome = Outcome::Continue;
+        for (i, column) in columns.iter().enumerate() {
+            outcome = game.play(i % 2, column).unwrap().1;
+        }
+        outcome
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let mut game = ConnectFour::default();
+        assert_eq!(game.play(0, "0").unwrap_err(), "Columns are numbered 1 to 7");
+        assert_eq!(game.play(0, "x").unwrap_err(), "Columns are numbered 1 to 7");
+        play(&mut game, &["1", "1", "1", "1", "1", "1"]);
+        assert_eq!(game.play(0, "1").unwrap_err(), "Column 1 is full");
+    }
+
+    #[test]
+    fn test_vertical_and_horizontal() {
+        let mut game = ConnectFour::default();
+        assert_eq!(play(&mut game, &["1", "2", "1", "2", "1", "2", "1"]), Outcome::Won);
+
+        let mut game = ConnectFour::default();
+        assert_eq!(play(&mut game, &["1", "1", "2", "2", "4", "4"]), Outcome::Continue);
+        // The gap in column 3 completes the row from the middle
+        assert_eq!(game.play(0, "3").unwrap(), ("column 3".to_string(), Outcome::Won));
+    }
+
+    #[test]
+    fn test_diagonal() {
+        let mut game = ConnectFour::default();
+        let outcome = play(&mut game, &["1", "2", "2", "3", "3", "4", "3", "4", "4", "7", "4"]);
+        assert_eq!(outcome, Outcome::Won);
+    }
+
+    #[test]
+    fn test_full_board_is_a_draw() {
+        let mut game = ConnectFour::default();
+        // Fills the board without ever lining up four
+        let moves = "1 3 1 1 1 1 2 1 2 2 2 2 5 2 5 3 3 3 3 4 3 4 4 4 4 5 4 7 5 5 6 5 6 6 6 7 7 7 7 6 7 6";
+        let moves: Vec<&str> = moves.split_whitespace().collect();
+        assert_eq!(play(&mut game, &moves[..41]), Outcome::Continue);
+        // The last move is side 1's, the 42nd of the game
+        assert_eq!(game.play(1, moves[41]).unwrap().1, Outcome::Drawn("the board is full"));
+    }
+
+    #[test]
+    fn test_board() {
+        let mut game = ConnectFour::default();
+        play(&mut game, &["4", "4"]);
+        let board = game.board();
+        assert_eq!(board.len(), ROWS + 1);
+        assert_eq!(board[ROWS - 2], format!("|. . . {} . . .|", colour(YELLOW, "O")));
+        assert_eq!(board[ROWS - 1], format!("|. . . {} . . .|", colour(RED, "X")));
+        assert_eq!(board[ROWS], " 1 2 3 4 5 6 7");
+    }
+}
@@ -0,0 +1,85 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Words dropped from the start of answers, so "The Beatles" matches "beatles".
+const ARTICLES: &[&str] = &["the", "a", "an"];
+
+/// Composes accents (NFC), lowercases `text`, drops punctuation and leading
+/// articles, and collapses whitespace, so answers compare on their words alone.
+pub fn normalize(text: &str) -> String {
+    let cleaned: String = text.nfc()
+        .flat_map(char::to_lowercase)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let mut words: Vec<&str> = cleaned.split_whitespace().collect();
+    if words.len() > 1 && ARTICLES.contains(&words[0]) {
+        words.remove(0);
+    }
+    words.join(" ")
+}
+
+/// Edit distance between two strings, counted in chars.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Typos tolerated in an answer of `len` chars: none for short answers,
+/// where one letter can make a different word, then one per five chars.
+fn tolerance(len: usize) -> usize {
+    if len <= 3 { 0 } else { (len / 5).clamp(1, 3) }
+}
+
+/// Whether `guess` is close enough to `answer` to count.
+pub fn is_match(guess: &str, answer: &str) -> bool {
+    let guess = normalize(guess);
+    let answer = normalize(answer);
+    if guess.is_empty() {
+        return false;
+    }
+    levenshtein(&guess, &answer) <= tolerance(answer.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  The  Beatles! "), "beatles");
+        assert_eq!(normalize("Rock'n'Roll"), "rock n roll");
+        assert_eq!(normalize("The"), "the");
+        assert_eq!(normalize("Ça va?"), "ça va");
+        assert_eq!(normalize("C\u{327}a va?"), "ça va");
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+        assert_eq!(levenshtein("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn test_is_match() {
+        assert!(is_match("the beatles", "Beatles"));
+        assert!(is_match("Missisippi", "Mississippi"));
+        assert!(is_match("Jupitr", "Jupiter"));
+        // Short answers must be exact
+        assert!(is_match("cat", "Cat"));
+        assert!(!is_match("car", "cat"));
+        // Too many typos
+        assert!(!is_match("Jptr", "Jupiter"));
+        assert!(!is_match("", "anything"));
+        assert!(!is_match("?!", "anything"));
+    }
+}
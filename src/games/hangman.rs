@@ -1,31 +1,250 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use unicode_normalization::UnicodeNormalization;
+use super::{Game, Reply};
+use crate::achievements::Event;
+use crate::database::{Database, HangmanResult, TournamentRound};
+use crate::settings::Settings;
 
-const WORDS: &[&str] = &[
+/// Built-in words, seeded into the `hangman_words` table under
+/// [`DEFAULT_CATEGORY`] the first time the bot starts.
+pub const WORDS: &[&str] = &[
     "programming", "rust", "bot", "hangman", "database",
     "function", "variable", "algorithm", "network", "server",
     "client", "protocol", "compiler", "library", "framework",
     "testing", "debugging", "performance", "security", "encryption",
 ];
 
+pub const DEFAULT_CATEGORY: &str = "tech";
+
 const MAX_WRONG_GUESSES: u32 = 6;
 
-#[derive(Clone, Debug)]
+/// Wrong guesses a failed solve costs unless the game says otherwise.
+const DEFAULT_SOLVE_PENALTY: u32 = 2;
+
+/// Fewest letters and most characters in a word or phrase from a word list.
+const MIN_LETTERS: usize = 3;
+const MAX_LENGTH: usize = 30;
+
+/// Characters allowed between the words of a phrase. They are shown from the start.
+const PHRASE_PUNCTUATION: &str = " -'.,!?&";
+
+/// Letters uncommon in English text; each one makes a word harder to guess.
+const RARE_LETTERS: &str = "jqxzkv";
+const UNCOMMON_LETTERS: &str = "bfgpwy";
+
+/// Words scoring at least this much are `Hard`; see [`Difficulty::of`].
+const HARD_SCORE: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Hard,
+}
+
+impl Difficulty {
+    /// Rates a word by its length plus a penalty for rare letters, which
+    /// players are slow to try.
+    pub fn of(word: &str) -> Self {
+        let score: usize = word.chars()
+            .filter(|c| c.is_alphabetic())
+            .map(|c| if RARE_LETTERS.contains(c) { 4 } else if UNCOMMON_LETTERS.contains(c) { 2 } else { 1 })
+            .sum();
+        if score >= HARD_SCORE { Difficulty::Hard } else { Difficulty::Easy }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "easy" => Some(Difficulty::Easy),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Hard => "hard",
+        }
+    }
+}
+
+/// Lowercases a candidate word or phrase and collapses runs of spaces, or
+/// returns `None` if it can't be played: letters and simple punctuation, at
+/// least 3 letters and at most 30 characters.
+pub fn normalize_word(word: &str) -> Option<String> {
+    let word = canonical(word);
+    let letters = word.chars().filter(|c| c.is_alphabetic()).count();
+    let valid = letters >= MIN_LETTERS
+        && word.chars().count() <= MAX_LENGTH
+        && word.chars().all(|c| c.is_alphabetic() || PHRASE_PUNCTUATION.contains(c));
+    valid.then_some(word)
+}
+
+/// Composes accents (NFC), lowercases and collapses runs of spaces, so a
+/// letter typed as `e` plus a combining accent matches a precomposed `é`.
+fn canonical(text: &str) -> String {
+    text.nfc().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Letters one player guessed in a game.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Tally {
+    correct: u32,
+    wrong: u32,
+}
+
+/// Who may guess.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Mode {
+    /// Everyone guesses whenever they like.
+    #[default]
+    Coop,
+    /// Players join a lobby, then take turns in join order. `order[0]` hosts.
+    Turns { order: Vec<String>, turn: usize, open: bool },
+    /// `setter` chose the word privately and can't guess it.
+    Versus { setter: String },
+}
+
+/// Players a turn-based game needs before the host can begin.
+const MIN_TURN_PLAYERS: usize = 2;
+
+/// What `!hangman start` asks for. The default is any word, guessed by everyone.
+#[derive(Clone, Debug, Default)]
+pub struct HangmanOptions {
+    pub category: Option<String>,
+    pub difficulty: Option<Difficulty>,
+    /// Time per guess in speed mode.
+    pub speed_limit: Option<Duration>,
+    /// The host of a turn-based game.
+    pub host: Option<String>,
+}
+
+/// A game with a word from the word lists, set up as `options` and
+/// `settings` ask. Returns the message to show if no word fits.
+pub fn new_hangman_game(db: &Database, settings: &Settings, options: &HangmanOptions) -> Result<HangmanGame, String> {
+    let category = options.category.as_deref();
+    let game = match db.random_hangman_word(category, options.difficulty.map(Difficulty::as_str)) {
+        Ok(Some(word)) => HangmanGame::with_word(&word, category),
+        // Only fall back to the built-in words when nothing in particular was asked for
+        Ok(None) if category.is_none() && options.difficulty.is_none() => HangmanGame::new(),
+        Ok(None) => {
+            let mut wanted = match options.difficulty {
+                Some(d) => format!("{} words", d.as_str()),
+                None => "words".to_string(),
+            };
+            if let Some(category) = category {
+                wanted.push_str(&format!(" in {}", category));
+            }
+            return Err(format!("There are no {}. See !hangman categories", wanted));
+        }
+        Err(e) => return Err(format!("Error picking a word: {}", e)),
+    };
+    let mut game = game.with_solve_penalty(settings.hangman_solve_penalty);
+    if let Some(limit) = options.speed_limit {
+        game = game.with_speed_limit(limit);
+    }
+    if let Some(host) = &options.host {
+        game = game.with_turns(host);
+    }
+    Ok(game)
+}
+
+/// Bonus for solving: longer words and fewer wrong guesses are worth more.
+pub fn solve_points(word: &str, remaining: u32) -> u32 {
+    word.chars().filter(|c| c.is_alphabetic()).count() as u32 * (remaining + 1)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HangmanGame {
     word: String,
     guessed_letters: HashSet<char>,
     wrong_count: u32,
+    /// Everyone who has made a guess, with their letter tallies.
+    #[serde(default)]
+    players: BTreeMap<String, Tally>,
+    /// Whoever guessed the last missing letter.
+    #[serde(default)]
+    solver: Option<String>,
+    /// Unix time the game began.
+    #[serde(default)]
+    started_at: i64,
+    /// The word list category the word came from, if one was chosen.
+    #[serde(default)]
+    category: Option<String>,
+    /// Wrong guesses charged for a failed solve.
+    #[serde(default = "default_solve_penalty")]
+    solve_penalty: u32,
+    /// Seconds allowed per guess in speed mode; each miss costs a guess.
+    #[serde(default)]
+    speed_limit: Option<u64>,
+    #[serde(default)]
+    mode: Mode,
+    /// The tournament round this game is played for, if any.
+    #[serde(default)]
+    tournament: Option<TournamentRound>,
+    /// The solve bonus, until it is paid into the solver's wallet.
+    #[serde(default)]
+    owed: Vec<(String, i64)>,
+    /// Achievement events not yet collected; see `Game::events`.
+    #[serde(skip)]
+    events: Vec<(String, Event)>,
+}
+
+fn default_solve_penalty() -> u32 {
+    DEFAULT_SOLVE_PENALTY
 }
 
 impl HangmanGame {
+    /// A game with a random built-in word, for when the word list is empty.
     pub fn new() -> Self {
-        let word = WORDS[rand::random::<usize>() % WORDS.len()].to_lowercase();
+        Self::with_word(WORDS[rand::random::<usize>() % WORDS.len()], None)
+    }
+
+    pub fn with_word(word: &str, category: Option<&str>) -> Self {
         HangmanGame {
-            word,
+            word: canonical(word),
             guessed_letters: HashSet::new(),
             wrong_count: 0,
+            players: BTreeMap::new(),
+            solver: None,
+            started_at: chrono::Utc::now().timestamp(),
+            category: category.map(str::to_string),
+            solve_penalty: DEFAULT_SOLVE_PENALTY,
+            speed_limit: None,
+            mode: Mode::Coop,
+            tournament: None,
+            owed: Vec::new(),
+            events: Vec::new(),
         }
     }
 
+    /// Makes the game turn-based, with a lobby hosted by `host`.
+    pub fn with_turns(mut self, host: &str) -> Self {
+        self.mode = Mode::Turns { order: vec![host.to_string()], turn: 0, open: true };
+        self
+    }
+
+    /// Marks `setter` as the player who chose the word.
+    pub fn with_setter(mut self, setter: &str) -> Self {
+        self.mode = Mode::Versus { setter: setter.to_string() };
+        self
+    }
+
+    /// Plays against the clock: every `limit` without a guess counts as a wrong guess.
+    pub fn with_speed_limit(mut self, limit: Duration) -> Self {
+        self.speed_limit = Some(limit.as_secs().max(1));
+        self
+    }
+
+    pub fn with_solve_penalty(mut self, penalty: u32) -> Self {
+        self.solve_penalty = penalty;
+        self
+    }
+
     pub fn guess(&mut self, letter: char) -> GuessResult {
         let letter = letter.to_lowercase().next().unwrap_or(letter);
 
@@ -51,11 +270,28 @@ impl HangmanGame {
         }
     }
 
+    /// Attempts the whole word or phrase. Case and repeated spaces don't
+    /// matter; a wrong attempt costs `solve_penalty` wrong guesses.
+    pub fn solve(&mut self, attempt: &str) -> GuessResult {
+        if canonical(attempt) == self.word {
+            self.guessed_letters.extend(self.word.chars().filter(|c| c.is_alphabetic()));
+            return GuessResult::Won;
+        }
+        self.wrong_count = (self.wrong_count + self.solve_penalty).min(MAX_WRONG_GUESSES);
+        if self.is_lost() {
+            GuessResult::Lost(self.word.clone())
+        } else {
+            GuessResult::Wrong
+        }
+    }
+
+    /// The word with unguessed letters blanked out. Spaces and punctuation
+    /// are always shown.
     pub fn display(&self) -> String {
         self.word
             .chars()
             .map(|c| {
-                if self.guessed_letters.contains(&c) {
+                if !c.is_alphabetic() || self.guessed_letters.contains(&c) {
                     c.to_string()
                 } else {
                     "_".to_string()
@@ -68,6 +304,7 @@ impl HangmanGame {
     pub fn is_won(&self) -> bool {
         self.word
             .chars()
+            .filter(|c| c.is_alphabetic())
             .all(|c| self.guessed_letters.contains(&c))
     }
 
@@ -92,6 +329,327 @@ impl HangmanGame {
     pub fn word(&self) -> &str {
         &self.word
     }
+
+    fn guess_input(&mut self, player: &str, letter: Option<&String>) -> Reply {
+        let Some(letter_str) = letter else {
+            return Reply::refused(vec!["Usage: !hangman guess <letter>".to_string()]);
+        };
+        // Count composed chars, not bytes, so accented letters are single letters too
+        let letter_str: String = letter_str.nfc().collect();
+        let mut chars = letter_str.chars();
+        let (Some(letter), None) = (chars.next(), chars.next()) else {
+            return Reply::refused(vec!["Please guess a single letter, or use !hangman solve <word>".to_string()]);
+        };
+        if !letter.is_alphabetic() {
+            return Reply::refused(vec!["Please guess a single letter".to_string()]);
+        }
+
+        let result = self.guess(letter);
+        let tally = self.players.entry(player.to_string()).or_default();
+        match result {
+            GuessResult::Correct | GuessResult::Won => tally.correct += 1,
+            GuessResult::Wrong | GuessResult::Lost(_) => tally.wrong += 1,
+            GuessResult::AlreadyGuessed => {}
+        }
+
+        match result {
+            GuessResult::AlreadyGuessed => {
+                Reply::refused(vec![format!("{} already guessed '{}' 🤔", player, letter)])
+            }
+            GuessResult::Correct => Reply::new(vec![
+                format!("{} guessed '{}' - ✓ Correct! Word: {}", player, letter, self.display()),
+                format!("Remaining guesses: {}", self.remaining()),
+            ]),
+            GuessResult::Wrong => Reply::new(vec![
+                format!("{} guessed '{}' - ✗ Wrong! Word: {}", player, letter, self.display()),
+                format!("Remaining guesses: {}", self.remaining()),
+            ]),
+            GuessResult::Won => self.won(player),
+            GuessResult::Lost(word) => self.lost(&word),
+        }
+    }
+
+    fn solve_input(&mut self, player: &str, words: &[String]) -> Reply {
+        if words.is_empty() {
+            return Reply::refused(vec!["Usage: !hangman solve <word or phrase>".to_string()]);
+        }
+        let attempt = words.join(" ");
+
+        let result = self.solve(&attempt);
+        if !matches!(result, GuessResult::Won) {
+            self.players.entry(player.to_string()).or_default().wrong += 1;
+        }
+
+        match result {
+            GuessResult::Won => self.won(player),
+            GuessResult::Lost(word) => self.lost(&word),
+            _ => Reply::new(vec![
+                format!("{} tried '{}' - ✗ Wrong! Word: {}", player, attempt, self.display()),
+                format!("Remaining guesses: {} (a wrong solve costs {})", self.remaining(), self.solve_penalty),
+            ]),
+        }
+    }
+
+    fn won(&mut self, player: &str) -> Reply {
+        self.players.entry(player.to_string()).or_default();
+        self.solver = Some(player.to_string());
+        // A setter could feed their word to a friend, so versus games are played for fun only
+        let mut lines = if self.is_versus() {
+            vec![format!("🎉 {} solved it! The word was: {}", player, self.word())]
+        } else {
+            let points = solve_points(self.word(), self.remaining());
+            self.owed.push((player.to_string(), points as i64));
+            self.events.push((player.to_string(), Event::HangmanSolved { wrong: self.wrong_count }));
+            vec![format!("🎉 {} solved it! The word was: {} (+{} points)", player, self.word(), points)]
+        };
+        lines.extend(self.contributions());
+        Reply::finished(lines)
+    }
+
+    fn is_versus(&self) -> bool {
+        matches!(self.mode, Mode::Versus { .. })
+    }
+
+    fn lost(&self, word: &str) -> Reply {
+        let mut lines = vec![match &self.mode {
+            Mode::Versus { setter } => format!("☠️ Game Over! {} stumped the channel with: {} 😢", setter, word),
+            _ => format!("☠️ Game Over! The word was: {} 😢", word),
+        }];
+        lines.extend(self.contributions());
+        Reply::finished(lines)
+    }
+
+    /// One line summing up what each player added, best first. Versus games
+    /// score no points, so only their guesses are shown.
+    fn contributions(&self) -> Option<String> {
+        let mut results = self.results();
+        if results.is_empty() {
+            return None;
+        }
+        results.sort_by(|a, b| b.points.cmp(&a.points).then_with(|| a.username.cmp(&b.username)));
+        let versus = self.is_versus();
+        let entries: Vec<String> = results.iter()
+            .map(|r| if versus {
+                format!("{} {}✓ {}✗", r.username, r.correct, r.wrong)
+            } else {
+                format!("{} {}✓ {}✗ {} pts", r.username, r.correct, r.wrong, r.points)
+            })
+            .collect();
+        Some(format!("Contributions: {}", entries.join(", ")))
+    }
+
+    /// Why `player` can't guess right now, if they can't.
+    fn refusal(&self, player: &str) -> Option<String> {
+        match &self.mode {
+            Mode::Coop => None,
+            Mode::Turns { order, open: true, .. } => Some(format!(
+                "Waiting for players: !hangman join, then {} begins with !hangman go", order[0]
+            )),
+            Mode::Turns { order, turn, .. } if order[*turn] != player => {
+                Some(format!("It's {}'s turn", order[*turn]))
+            }
+            Mode::Turns { .. } => None,
+            Mode::Versus { setter } if setter.eq_ignore_ascii_case(player) => {
+                Some("You set this word, so you can't guess it 🙂".to_string())
+            }
+            Mode::Versus { .. } => None,
+        }
+    }
+
+    /// Hands the turn to the next player, returning who that is.
+    fn pass_turn(&mut self) -> Option<&str> {
+        match &mut self.mode {
+            Mode::Turns { order, turn, open: false } => {
+                *turn = (*turn + 1) % order.len();
+                Some(&order[*turn])
+            }
+            _ => None,
+        }
+    }
+
+    fn join(&mut self, player: &str) -> Reply {
+        let Mode::Turns { order, open, .. } = &mut self.mode else {
+            return Reply::refused(vec!["This game has no lobby; just guess!".to_string()]);
+        };
+        if !*open {
+            return Reply::refused(vec!["This game has already begun".to_string()]);
+        }
+        if order.iter().any(|p| p == player) {
+            return Reply::refused(vec![format!("{} is already in the game", player)]);
+        }
+        order.push(player.to_string());
+        Reply::new(vec![format!("{} joined ({} players: {})", player, order.len(), order.join(", "))])
+    }
+
+    fn begin(&mut self, player: &str) -> Reply {
+        let Mode::Turns { order, open, .. } = &mut self.mode else {
+            return Reply::refused(vec!["This game has no lobby; just guess!".to_string()]);
+        };
+        if !*open {
+            return Reply::refused(vec!["This game has already begun".to_string()]);
+        }
+        if order[0] != player {
+            return Reply::refused(vec![format!("Only {} can begin the game", order[0])]);
+        }
+        if order.len() < MIN_TURN_PLAYERS {
+            return Reply::refused(vec![format!("Waiting for at least {} players: !hangman join", MIN_TURN_PLAYERS)]);
+        }
+        *open = false;
+        let (order, first) = (order.join(" → "), order[0].clone());
+        Reply::new(vec![
+            format!("Turn order: {}", order),
+            format!("{}, your turn! Word: {}", first, self.display()),
+        ])
+    }
+
+    /// Every player's result: a point per correct letter, plus the solve bonus for the solver.
+    pub fn results(&self) -> Vec<HangmanResult> {
+        self.players.iter()
+            .map(|(username, tally)| {
+                let solved = self.solver.as_deref() == Some(username);
+                let bonus = if solved { solve_points(&self.word, self.remaining()) } else { 0 };
+                HangmanResult {
+                    username: username.clone(),
+                    correct: tally.correct,
+                    wrong: tally.wrong,
+                    solved,
+                    points: tally.correct + bonus,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Game for HangmanGame {
+    fn kind(&self) -> &'static str {
+        "hangman"
+    }
+
+    fn start(&mut self) -> Vec<String> {
+        let intro = match &self.mode {
+            Mode::Coop => "🎮 Hangman game started! Everyone can play along!".to_string(),
+            Mode::Turns { order, .. } => format!(
+                "🎮 Turn-based hangman hosted by {}! Join with !hangman join; {} begins with !hangman go",
+                order[0], order[0]
+            ),
+            Mode::Versus { setter } => format!("🎮 {} picked a word. Can the channel beat them?", setter),
+        };
+        let mut lines = vec![
+            intro,
+            match &self.category {
+                Some(category) => format!("Word: {} ({})", self.display(), category),
+                None => format!("Word: {}", self.display()),
+            },
+            format!("Remaining guesses: {}", self.remaining()),
+            "Use !hangman guess <letter> to guess a letter".to_string(),
+        ];
+        if let Some(limit) = self.speed_limit {
+            lines.push(format!("⏱️ Speed mode: guess within {} seconds or lose a guess", limit));
+        }
+        lines
+    }
+
+    fn input(&mut self, player: &str, args: &[String]) -> Reply {
+        let subcommand = args.first().map(String::as_str);
+        match subcommand {
+            Some("join") => return self.join(player),
+            Some("go") => return self.begin(player),
+            Some("guess" | "solve") => {}
+            _ => return Reply::refused(vec!["Unknown hangman subcommand. Use: start, guess, solve, join, go, status, quit".to_string()]),
+        }
+        if let Some(refusal) = self.refusal(player) {
+            return Reply::refused(vec![refusal]);
+        }
+
+        let before = (self.guessed_letters.len(), self.wrong_count);
+        let mut reply = match subcommand {
+            Some("guess") => self.guess_input(player, args.get(1)),
+            _ => self.solve_input(player, &args[1..]),
+        };
+        // Only real guesses use up a turn, not typos or repeated letters
+        let moved = before != (self.guessed_letters.len(), self.wrong_count);
+        if moved && !reply.finished {
+            if let Some(next) = self.pass_turn() {
+                reply.lines.push(format!("Next up: {}", next));
+            }
+        }
+        reply
+    }
+
+    fn render(&self) -> String {
+        format!("Word: {}", self.display())
+    }
+
+    fn status(&self) -> Vec<String> {
+        let mut lines = vec![
+            self.render(),
+            format!("Guessed: {}", self.guessed()),
+            format!("Wrong: {}/{}", self.wrong_count(), MAX_WRONG_GUESSES),
+        ];
+        match &self.mode {
+            Mode::Turns { order, open: true, .. } => lines.push(format!("Lobby: {}", order.join(", "))),
+            Mode::Turns { order, turn, .. } => lines.push(format!("Turn: {} (order: {})", order[*turn], order.join(" → "))),
+            Mode::Versus { setter } => lines.push(format!("Word set by {}", setter)),
+            Mode::Coop => {}
+        }
+        lines
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).expect("hangman state is always serializable")
+    }
+
+    fn record(&self, db: &Database, channel: &str) -> Result<(), rusqlite::Error> {
+        if self.is_versus() {
+            return Ok(());
+        }
+        db.record_hangman_results(channel, self.tournament, &self.results())
+    }
+
+    fn timeout(&mut self) -> Vec<String> {
+        let mut lines = vec![format!("⏰ Hangman timed out. The word was: {}", self.word())];
+        lines.extend(self.contributions());
+        lines
+    }
+
+    fn turn_limit(&self) -> Option<Duration> {
+        // The clock doesn't run while a lobby is waiting for players
+        if let Mode::Turns { open: true, .. } = self.mode {
+            return None;
+        }
+        self.speed_limit.map(Duration::from_secs)
+    }
+
+    fn turn_timeout(&mut self) -> Reply {
+        self.wrong_count += 1;
+        if self.is_lost() {
+            return self.lost(&self.word);
+        }
+        let mut lines = vec![
+            format!("⏱️ Too slow! That costs a guess. Word: {}", self.display()),
+            format!("Remaining guesses: {}", self.remaining()),
+        ];
+        if let Some(next) = self.pass_turn() {
+            lines.push(format!("Next up: {}", next));
+        }
+        Reply::new(lines)
+    }
+
+    fn payouts(&mut self) -> Vec<(String, i64)> {
+        std::mem::take(&mut self.owed)
+    }
+
+    fn events(&mut self) -> Vec<(String, Event)> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn tournament_round(&self) -> Option<TournamentRound> {
+        self.tournament
+    }
+
+    fn set_tournament_round(&mut self, round: Option<TournamentRound>) {
+        self.tournament = round;
+    }
 }
 
 #[derive(Debug)]
@@ -107,6 +665,54 @@ pub enum GuessResult {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_difficulty() {
+        assert_eq!(Difficulty::of("rust"), Difficulty::Easy);
+        assert_eq!(Difficulty::of("server"), Difficulty::Easy);
+        // Long words are hard
+        assert_eq!(Difficulty::of("performance"), Difficulty::Hard);
+        // So are short words full of rare letters
+        assert_eq!(Difficulty::of("jazz"), Difficulty::Hard);
+        assert!(WORDS.iter().any(|w| Difficulty::of(w) == Difficulty::Easy));
+        assert!(WORDS.iter().any(|w| Difficulty::of(w) == Difficulty::Hard));
+    }
+
+    #[test]
+    fn test_normalize_word() {
+        assert_eq!(normalize_word(" Rust "), Some("rust".to_string()));
+        assert_eq!(normalize_word("café"), Some("café".to_string()));
+        assert_eq!(normalize_word("cafe\u{301}"), Some("café".to_string()));
+        assert_eq!(normalize_word("ab"), None);
+        assert_eq!(normalize_word("  Hello,   World! "), Some("hello, world!".to_string()));
+        assert_eq!(normalize_word("a-b"), None);
+        assert_eq!(normalize_word("r2d2"), None);
+        assert_eq!(normalize_word(&"a".repeat(31)), None);
+    }
+
+    #[test]
+    fn test_with_word() {
+        let mut game = HangmanGame::with_word("Crate", Some("tools"));
+        assert_eq!(game.word(), "crate");
+        assert_eq!(game.start()[1], "Word: _ _ _ _ _ (tools)");
+    }
+
+    #[test]
+    fn test_new_hangman_game_options() {
+        let db = Database::new(":memory:").unwrap();
+        let settings = Settings::default();
+        // Nothing asked for and no word lists: the built-in words
+        assert!(new_hangman_game(&db, &settings, &HangmanOptions::default()).is_ok());
+
+        db.add_hangman_words("tools", &[("crate", "easy")]).unwrap();
+        let options = HangmanOptions { category: Some("tools".to_string()), host: Some("alice".to_string()), ..Default::default() };
+        let game = new_hangman_game(&db, &settings, &options).unwrap();
+        assert_eq!(game.word(), "crate");
+
+        let options = HangmanOptions { difficulty: Some(Difficulty::Hard), ..options };
+        let error = new_hangman_game(&db, &settings, &options).unwrap_err();
+        assert_eq!(error, "There are no hard words in tools. See !hangman categories");
+    }
+
     #[test]
     fn test_new_game_initialization() {
         let game = HangmanGame::new();
@@ -303,4 +909,260 @@ mod tests {
         assert!(game1.guessed_letters.contains(&first_letter));
         assert!(!game2.guessed_letters.contains(&first_letter));
     }
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_input_guess() {
+        let mut game = HangmanGame::new();
+        let letter = game.word.chars().next().unwrap();
+
+        let reply = game.input("alice", &args(&format!("guess {}", letter)));
+        assert!(reply.lines[0].starts_with(&format!("alice guessed '{}'", letter)) || reply.finished);
+        assert!(game.guessed_letters.contains(&letter));
+    }
+
+    #[test]
+    fn test_input_rejects_bad_guesses() {
+        let mut game = HangmanGame::new();
+
+        assert_eq!(game.input("alice", &args("guess")).lines, vec!["Usage: !hangman guess <letter>"]);
+        assert_eq!(game.input("alice", &args("guess ab")).lines, vec!["Please guess a single letter, or use !hangman solve <word>"]);
+        assert!(game.input("alice", &args("dance")).lines[0].starts_with("Unknown hangman subcommand"));
+        assert!(game.guessed_letters.is_empty());
+    }
+
+    #[test]
+    fn test_input_win_finishes_game() {
+        let mut game = HangmanGame::new();
+        let letters: HashSet<char> = game.word.chars().collect();
+
+        let mut last = Reply::default();
+        for letter in letters {
+            last = game.input("alice", &args(&format!("guess {}", letter)));
+        }
+        assert!(last.finished);
+        assert!(last.lines[0].contains("alice solved it"));
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut game = HangmanGame::new();
+        game.input("alice", &args("guess e"));
+        game.input("bob", &args("guess z"));
+
+        let restored: HangmanGame = serde_json::from_value(game.save()).unwrap();
+        assert_eq!(restored.word, game.word);
+        assert_eq!(restored.guessed_letters, game.guessed_letters);
+        assert_eq!(restored.wrong_count, game.wrong_count);
+        assert_eq!(restored.players, game.players);
+        assert_eq!(restored.started_at, game.started_at);
+        assert_eq!(restored.players.keys().collect::<Vec<_>>(), vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn test_solve_whole_word() {
+        let mut game = HangmanGame::with_word("rust", None);
+        game.input("alice", &args("guess r"));
+        let reply = game.input("bob", &args("solve RUST"));
+        assert!(reply.finished);
+        assert!(game.is_won());
+        assert_eq!(game.solver.as_deref(), Some("bob"));
+        assert_eq!(reply.lines[0], "🎉 bob solved it! The word was: rust (+28 points)");
+        assert_eq!(game.payouts(), vec![("bob".to_string(), 28)]);
+    }
+
+    #[test]
+    fn test_wrong_solve_costs_penalty() {
+        let mut game = HangmanGame::with_word("rust", None).with_solve_penalty(3);
+        let reply = game.input("alice", &args("solve rest"));
+        assert!(!reply.finished);
+        assert_eq!(game.wrong_count(), 3);
+        assert_eq!(game.players["alice"].wrong, 1);
+
+        // The penalty never takes the count past the limit
+        let reply = game.input("alice", &args("solve rush"));
+        assert!(reply.finished);
+        assert_eq!(game.wrong_count(), MAX_WRONG_GUESSES);
+        assert!(game.solver.is_none());
+    }
+
+    #[test]
+    fn test_phrase_reveals_punctuation() {
+        let mut game = HangmanGame::with_word("Hello, world!", None);
+        assert_eq!(game.display(), "_ _ _ _ _ ,   _ _ _ _ _ !");
+        for letter in ["h", "e", "l", "o", "w", "r"] {
+            assert!(!game.input("alice", &args(&format!("guess {}", letter))).finished);
+        }
+        assert!(game.input("alice", &args("guess d")).finished);
+        assert!(game.is_won());
+        assert_eq!(solve_points("hello, world!", 6), 70);
+    }
+
+    #[test]
+    fn test_solve_phrase_ignores_spacing() {
+        let mut game = HangmanGame::with_word("ice cream", None);
+        assert!(game.input("alice", &args("solve Ice   Cream")).finished);
+        assert!(game.is_won());
+    }
+
+    #[test]
+    fn test_unicode_letters() {
+        let mut game = HangmanGame::with_word("Café", None);
+        let reply = game.input("alice", &args("guess É"));
+        assert_eq!(reply.lines[0], "alice guessed 'É' - ✓ Correct! Word: _ _ _ é");
+        assert_eq!(game.input("alice", &args("guess ab")).lines[0], "Please guess a single letter, or use !hangman solve <word>");
+        assert_eq!(game.input("alice", &args("guess 1")).lines[0], "Please guess a single letter");
+        assert_eq!(game.wrong_count(), 0);
+    }
+
+    #[test]
+    fn test_decomposed_accents_match() {
+        // A word stored decomposed, and guesses typed either way
+        let mut game = HangmanGame::with_word("nin\u{303}o", None);
+        assert_eq!(game.word(), "niño");
+        assert_eq!(game.input("alice", &args("guess n\u{303}")).lines[0], "alice guessed 'ñ' - ✓ Correct! Word: _ _ ñ _");
+
+        let mut game = HangmanGame::with_word("café", None);
+        assert!(game.input("alice", &args("solve CAFE\u{301}")).finished);
+        assert!(game.is_won());
+    }
+
+    #[test]
+    fn test_speed_mode() {
+        let mut game = HangmanGame::with_word("rust", None).with_speed_limit(Duration::from_secs(20));
+        assert_eq!(game.turn_limit(), Some(Duration::from_secs(20)));
+        assert_eq!(game.start().last().unwrap(), "⏱️ Speed mode: guess within 20 seconds or lose a guess");
+
+        let reply = game.turn_timeout();
+        assert!(!reply.finished);
+        assert_eq!(reply.lines[0], "⏱️ Too slow! That costs a guess. Word: _ _ _ _");
+        assert_eq!(game.wrong_count(), 1);
+
+        let restored: HangmanGame = serde_json::from_value(game.save()).unwrap();
+        assert_eq!(restored.turn_limit(), Some(Duration::from_secs(20)));
+
+        for _ in 1..MAX_WRONG_GUESSES - 1 {
+            assert!(!game.turn_timeout().finished);
+        }
+        let reply = game.turn_timeout();
+        assert!(reply.finished);
+        assert_eq!(reply.lines[0], "☠️ Game Over! The word was: rust 😢");
+        assert!(HangmanGame::new().turn_limit().is_none());
+    }
+
+    #[test]
+    fn test_turns_lobby() {
+        let mut game = HangmanGame::with_word("rust", None).with_turns("alice");
+        assert!(game.start()[0].starts_with("🎮 Turn-based hangman hosted by alice"));
+        assert_eq!(game.input("alice", &args("guess r")).lines[0], "Waiting for players: !hangman join, then alice begins with !hangman go");
+        assert_eq!(game.input("alice", &args("go")).lines[0], "Waiting for at least 2 players: !hangman join");
+
+        assert_eq!(game.input("bob", &args("join")).lines[0], "bob joined (2 players: alice, bob)");
+        assert_eq!(game.input("bob", &args("join")).lines[0], "bob is already in the game");
+        assert_eq!(game.input("bob", &args("go")).lines[0], "Only alice can begin the game");
+        assert_eq!(game.input("alice", &args("go")).lines, vec!["Turn order: alice → bob", "alice, your turn! Word: _ _ _ _"]);
+        assert_eq!(game.input("carol", &args("join")).lines[0], "This game has already begun");
+        assert!(game.guessed_letters.is_empty());
+    }
+
+    #[test]
+    fn test_turns_rotate() {
+        let mut game = HangmanGame::with_word("rust", None).with_turns("alice");
+        game.input("bob", &args("join"));
+        game.input("alice", &args("go"));
+
+        assert_eq!(game.input("bob", &args("guess r")).lines, vec!["It's alice's turn"]);
+        assert_eq!(game.input("alice", &args("guess r")).lines.last().unwrap(), "Next up: bob");
+        // Invalid and repeated guesses don't use up the turn
+        game.input("bob", &args("guess 1"));
+        game.input("bob", &args("guess r"));
+        assert_eq!(game.input("bob", &args("guess z")).lines.last().unwrap(), "Next up: alice");
+        assert_eq!(game.turn_timeout().lines.last().unwrap(), "Next up: bob");
+
+        let reply = game.input("bob", &args("solve rust"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines[1], "Contributions: bob 0✓ 1✗ 20 pts, alice 1✓ 0✗ 1 pts");
+    }
+
+    #[test]
+    fn test_lobby_stops_the_clock() {
+        let mut game = HangmanGame::with_word("rust", None)
+            .with_speed_limit(Duration::from_secs(10))
+            .with_turns("alice");
+        assert_eq!(game.turn_limit(), None);
+        game.input("bob", &args("join"));
+        game.input("alice", &args("go"));
+        assert_eq!(game.turn_limit(), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_versus_setter_cannot_guess() {
+        let mut game = HangmanGame::with_word("rust", None).with_setter("alice");
+        assert_eq!(game.start()[0], "🎮 alice picked a word. Can the channel beat them?");
+        assert_eq!(game.input("alice", &args("solve rust")).lines, vec!["You set this word, so you can't guess it 🙂"]);
+        assert_eq!(game.input("Alice", &args("guess r")).lines, vec!["You set this word, so you can't guess it 🙂"]);
+        assert!(!game.is_won());
+
+        game.input("bob", &args("guess r"));
+        let reply = game.input("bob", &args("solve rest"));
+        assert!(!reply.finished);
+        game.input("carol", &args("solve rush"));
+        let reply = game.input("carol", &args("solve ruse"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec![
+            "☠️ Game Over! alice stumped the channel with: rust 😢",
+            "Contributions: bob 1✓ 1✗, carol 0✓ 2✗",
+        ]);
+        assert_eq!(serde_json::from_value::<HangmanGame>(game.save()).unwrap().mode, game.mode);
+    }
+
+    #[test]
+    fn test_versus_is_played_for_fun() {
+        let db = Database::new(":memory:").unwrap();
+        let mut game = HangmanGame::with_word("rust", None).with_setter("alice");
+        let reply = game.input("bob", &args("solve rust"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines[0], "🎉 bob solved it! The word was: rust");
+        assert!(game.payouts().is_empty());
+        assert!(game.events().is_empty());
+        game.record(&db, "#channel1").unwrap();
+        assert_eq!(db.hangman_stats("bob").unwrap().games, 0);
+    }
+
+    #[test]
+    fn test_saves_without_mode_are_coop() {
+        let mut state = HangmanGame::with_word("rust", None).save();
+        state.as_object_mut().unwrap().remove("mode");
+        let restored: HangmanGame = serde_json::from_value(state).unwrap();
+        assert_eq!(restored.mode, Mode::Coop);
+    }
+
+    #[test]
+    fn test_solve_points() {
+        assert_eq!(solve_points("rust", 6), 28);
+        assert_eq!(solve_points("rust", 0), 4);
+        assert_eq!(solve_points("programming", 3), 44);
+    }
+
+    #[test]
+    fn test_results() {
+        let mut game = HangmanGame::new();
+        game.word = "rust".to_string();
+        game.input("alice", &args("guess r"));
+        game.input("bob", &args("guess x"));
+        game.input("bob", &args("guess u"));
+        game.input("alice", &args("guess s"));
+        game.input("bob", &args("guess r"));
+        let reply = game.input("carol", &args("guess t"));
+        assert!(reply.finished);
+
+        assert_eq!(game.results(), vec![
+            HangmanResult { username: "alice".to_string(), correct: 2, wrong: 0, solved: false, points: 2 },
+            HangmanResult { username: "bob".to_string(), correct: 1, wrong: 1, solved: false, points: 1 },
+            HangmanResult { username: "carol".to_string(), correct: 1, wrong: 0, solved: true, points: 1 + 4 * 6 },
+        ]);
+    }
 }
@@ -0,0 +1,41 @@
+/// Assigns standard competition ranks to scores sorted best first: equal
+/// scores share a rank and the following rank is skipped (1, 2, 2, 4).
+pub fn ranked<S: PartialEq + Copy>(scores: &[(String, S)]) -> Vec<(usize, &str, S)> {
+    let mut ranked = Vec::with_capacity(scores.len());
+    for (i, (name, score)) in scores.iter().enumerate() {
+        let rank = match ranked.last() {
+            Some(&(previous_rank, _, previous_score)) if previous_score == *score => previous_rank,
+            _ => i + 1,
+        };
+        ranked.push((rank, name.as_str(), *score));
+    }
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores(entries: &[(&str, i64)]) -> Vec<(String, i64)> {
+        entries.iter().map(|(name, score)| (name.to_string(), *score)).collect()
+    }
+
+    #[test]
+    fn test_ranked_without_ties() {
+        let scores = scores(&[("a", 30), ("b", 20), ("c", 10)]);
+        assert_eq!(ranked(&scores), vec![(1, "a", 30), (2, "b", 20), (3, "c", 10)]);
+    }
+
+    #[test]
+    fn test_ranked_with_ties() {
+        let scores = scores(&[("a", 30), ("b", 20), ("c", 20), ("d", 10), ("e", 10)]);
+        assert_eq!(ranked(&scores), vec![(1, "a", 30), (2, "b", 20), (2, "c", 20), (4, "d", 10), (4, "e", 10)]);
+    }
+
+    #[test]
+    fn test_ranked_all_tied_and_empty() {
+        let scores = scores(&[("a", 5), ("b", 5)]);
+        assert_eq!(ranked(&scores), vec![(1, "a", 5), (1, "b", 5)]);
+        assert!(ranked::<i64>(&[]).is_empty());
+    }
+}
@@ -1,144 +1,987 @@
 pub mod hangman;
+pub mod trivia;
+pub mod scramble;
+pub mod fuzzy;
+pub mod leaderboard;
+pub mod rounds;
+pub mod cards;
+pub mod blackjack;
+pub mod poker;
+pub mod board;
+pub mod tictactoe;
+pub mod connect4;
+pub mod chess;
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use serde_json::Value;
 use tokio::sync::Mutex;
-use hangman::HangmanGame;
+use hangman::{HangmanGame, HangmanOptions};
+use trivia::{TriviaGame, TriviaOptions};
+use scramble::{ScrambleGame, ScrambleOptions};
+use blackjack::BlackjackGame;
+use poker::PokerGame;
+use board::BoardGame;
+use tictactoe::TicTacToe;
+use connect4::ConnectFour;
+use chess::Chess;
+use crate::achievements::{Event, EventBus};
+use crate::database::{Database, TournamentRound};
+use crate::settings::Settings;
 
+/// Every game `!<kind>` can start.
+pub const KINDS: &[&str] = &["hangman", "trivia", "scramble", "blackjack", "poker", "ttt", "c4", "chess"];
+
+/// Creates a new game of `kind` with its start command's default options,
+/// or `None` if there is no such game. `Err` is the message to show when the
+/// game can't be set up.
+pub fn new_game(db: &Database, settings: &Settings, kind: &str) -> Option<Result<Box<dyn Game>, String>> {
+    let game: Result<Box<dyn Game>, String> = match kind {
+        "hangman" => hangman::new_hangman_game(db, settings, &HangmanOptions::default()).map(|g| Box::new(g) as Box<dyn Game>),
+        "trivia" => trivia::new_trivia_game(settings, &TriviaOptions::default()).map(|g| Box::new(g) as Box<dyn Game>),
+        "scramble" => scramble::new_scramble_game(db, settings, &ScrambleOptions::default()).map(|g| Box::new(g) as Box<dyn Game>),
+        "blackjack" => Ok(Box::new(BlackjackGame::new())),
+        "poker" => Ok(Box::new(PokerGame::new())),
+        _ => return None,
+    };
+    Some(game)
+}
+
+/// Creates a head-to-head game of `kind` in which `challenger` takes on
+/// `opponent`, or `None` if `kind` isn't a two-player board game.
+pub fn new_board_game(kind: &str, challenger: &str, opponent: &str) -> Option<Box<dyn Game>> {
+    match kind {
+        "ttt" => Some(Box::new(BoardGame::<TicTacToe>::challenge(challenger, opponent))),
+        "c4" => Some(Box::new(BoardGame::<ConnectFour>::challenge(challenger, opponent))),
+        "chess" => Some(Box::new(BoardGame::<Chess>::challenge(challenger, opponent))),
+        _ => None,
+    }
+}
+
+/// Rebuilds a game saved with [`Game::save`], or `None` if the state can't be read.
+pub fn restore_game(kind: &str, state: Value) -> Option<Box<dyn Game>> {
+    match kind {
+        "hangman" => serde_json::from_value::<HangmanGame>(state).ok().map(|g| Box::new(g) as Box<dyn Game>),
+        "trivia" => serde_json::from_value::<TriviaGame>(state).ok().map(|g| Box::new(g) as Box<dyn Game>),
+        "scramble" => serde_json::from_value::<ScrambleGame>(state).ok().map(|g| Box::new(g) as Box<dyn Game>),
+        "blackjack" => serde_json::from_value::<BlackjackGame>(state).ok().map(|g| Box::new(g) as Box<dyn Game>),
+        "poker" => serde_json::from_value::<PokerGame>(state).ok().map(|g| Box::new(g) as Box<dyn Game>),
+        "ttt" => serde_json::from_value::<BoardGame<TicTacToe>>(state).ok().map(|g| Box::new(g) as Box<dyn Game>),
+        "c4" => serde_json::from_value::<BoardGame<ConnectFour>>(state).ok().map(|g| Box::new(g) as Box<dyn Game>),
+        "chess" => serde_json::from_value::<BoardGame<Chess>>(state).ok().map(|g| Box::new(g) as Box<dyn Game>),
+        _ => None,
+    }
+}
+
+/// The subcommands of `kind`, for usage messages.
+pub fn subcommands(kind: &str) -> &'static str {
+    match kind {
+        "hangman" => "start|guess|solve|join|go|set|status|quit|stats|top|categories|words",
+        "trivia" | "scramble" => "start|status|quit|top",
+        "blackjack" => "start|bet|deal|hit|stand|double|status|quit|chips|top",
+        "poker" => "start|join|deal|cards|check|call|bet|raise|fold|status|quit|chips|top",
+        "ttt" | "c4" | "chess" => "challenge|accept|decline|move|draw|resign|status|quit",
+        _ => "start|status|quit",
+    }
+}
+
+/// What a game says in response to a move.
+#[derive(Debug, Default, PartialEq)]
+pub struct Reply {
+    pub lines: Vec<String>,
+    /// Lines for one player only, e.g. their hole cards: `(player, line)`.
+    pub notices: Vec<(String, String)>,
+    /// The game is over and should be removed.
+    pub finished: bool,
+    /// Something was played. Only moves restart the idle and turn clocks, so
+    /// usage errors, out-of-turn attempts and illegal moves can't hold a game open.
+    pub moved: bool,
+}
+
+impl Reply {
+    pub fn new(lines: Vec<String>) -> Self {
+        Reply { lines, notices: Vec::new(), finished: false, moved: true }
+    }
+
+    pub fn finished(lines: Vec<String>) -> Self {
+        Reply { lines, notices: Vec::new(), finished: true, moved: true }
+    }
+
+    /// A move that wasn't made, e.g. out of turn or illegal.
+    pub fn refused(lines: Vec<String>) -> Self {
+        Reply { lines, notices: Vec::new(), finished: false, moved: false }
+    }
+
+    pub fn with_notices(mut self, notices: Vec<(String, String)>) -> Self {
+        self.notices = notices;
+        self
+    }
+}
+
+/// A game played in a channel. `GameManager` owns running games and routes
+/// `!<kind> <subcommand>` to them: `start`, `status` and `quit` map to the
+/// methods of the same name, anything else goes to `input`.
+pub trait Game: Send {
+    /// The command name, e.g. "hangman".
+    fn kind(&self) -> &'static str;
+
+    /// Announces the game once it has been registered.
+    fn start(&mut self) -> Vec<String>;
+
+    /// Handles a move by `player`. `args` includes the subcommand.
+    fn input(&mut self, player: &str, args: &[String]) -> Reply;
+
+    /// Offers a plain channel message, one that isn't a command, to the game.
+    /// Returns `None` if it isn't a move, which then doesn't count as activity.
+    fn chat(&mut self, _player: &str, _text: &str) -> Option<Reply> {
+        None
+    }
+
+    /// A short description of the current state, one line.
+    fn render(&self) -> String;
+
+    /// The reply to `!<kind> status`.
+    fn status(&self) -> Vec<String> {
+        vec![self.render()]
+    }
+
+    /// Handles `!<kind> quit` from `player`; `manage` is whether they may
+    /// manage games. `None` ends the game for everyone with [`quit`](Game::quit).
+    /// Games with chips at stake use this to refuse onlookers and to turn a
+    /// quit mid-hand into the player forfeiting their own seat.
+    fn leave(&mut self, _player: &str, _manage: bool) -> Option<Reply> {
+        None
+    }
+
+    /// Ends the game at a player's request.
+    fn quit(&mut self) -> Vec<String> {
+        vec![format!("{} game ended.", capitalize(self.kind()))]
+    }
+
+    /// The state to save after every move; see [`restore_game`].
+    fn save(&self) -> Value;
+
+    /// Stores results once the game has been played to the end or timed out.
+    /// Games that were quit are not recorded.
+    fn record(&self, _db: &Database, _channel: &str) -> Result<(), rusqlite::Error> {
+        Ok(())
+    }
+
+    /// Ends the game because nobody played for too long.
+    fn timeout(&mut self) -> Vec<String> {
+        vec![format!("{} game timed out.", capitalize(self.kind()))]
+    }
+
+    /// How long players have for each move, for games played against the clock.
+    fn turn_limit(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Called when `turn_limit` passes without a move; the clock then starts over.
+    fn turn_timeout(&mut self) -> Reply {
+        Reply::default()
+    }
+
+    /// Chips `player` puts up for this move, e.g. a bet. They are taken from
+    /// the player's balance before `input` is called, and the move is refused
+    /// if the player can't cover them. A move that turns out to be invalid
+    /// should hand the stake back through `payouts`.
+    fn stake(&self, _player: &str, _args: &[String]) -> i64 {
+        0
+    }
+
+    /// Chips owed to players since the last call, e.g. winnings, or bets
+    /// returned when the game is quit. Called after every move, timeout and quit.
+    fn payouts(&mut self) -> Vec<(String, i64)> {
+        Vec::new()
+    }
+
+    /// Achievement events since the last call, with the player each counts
+    /// for. Called whenever [`payouts`](Game::payouts) is.
+    fn events(&mut self) -> Vec<(String, Event)> {
+        Vec::new()
+    }
+
+    /// The two players of a head-to-head game. Such games are keyed by their
+    /// players rather than taking over the channel, so any number of pairs
+    /// can play at once, and each player's commands reach their own game.
+    fn pair(&self) -> Option<[&str; 2]> {
+        None
+    }
+
+    /// The tournament round this game is played for, if any. Only players
+    /// who may manage games can quit a round.
+    fn tournament_round(&self) -> Option<TournamentRound> {
+        None
+    }
+
+    /// Makes this game a round of a tournament, or an ordinary game again
+    /// with `None`. Only the games in [`crate::tournament::KINDS`] keep it,
+    /// and record their results under it.
+    fn set_tournament_round(&mut self, _round: Option<TournamentRound>) {}
+}
+
+/// A running game: its channel, kind and, for head-to-head games, its
+/// players as "alice bob" in lowercase (empty for channel games).
+pub type GameKey = (String, &'static str, String);
+
+struct Running {
+    game: Box<dyn Game>,
+    last_move: Instant,
+    /// An idle warning was sent since the last move.
+    warned: bool,
+}
+
+impl Running {
+    fn new(game: Box<dyn Game>) -> Self {
+        Running { game, last_move: Instant::now(), warned: false }
+    }
+}
+
+#[derive(Clone)]
 pub struct GameManager {
-    hangman_games: Arc<Mutex<HashMap<String, HangmanGame>>>,
+    games: Arc<Mutex<HashMap<GameKey, Running>>>,
+    /// Only one game per channel; otherwise one of each kind.
+    exclusive: bool,
+    events: EventBus,
 }
 
 impl GameManager {
-    pub fn new() -> Self {
+    pub fn new(exclusive: bool) -> Self {
         GameManager {
-            hangman_games: Arc::new(Mutex::new(HashMap::new())),
+            games: Arc::new(Mutex::new(HashMap::new())),
+            exclusive,
+            events: EventBus::default(),
+        }
+    }
+
+    /// Publishes the games' achievement events, and wallet changes, on `events`.
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Registers a game in `channel` and returns its opening lines. If the
+    /// channel's limit is reached, returns the kind of the game in the way.
+    /// A head-to-head game is only refused if one of its players already has
+    /// a game of that kind in the channel.
+    pub async fn start(&self, db: &Database, channel: &str, mut game: Box<dyn Game>) -> Result<Vec<String>, &'static str> {
+        let mut games = self.games.lock().await;
+        let players = players_key(game.as_ref());
+        let blocking = games.keys()
+            .filter(|(c, _, _)| c == channel)
+            .find(|(_, kind, other)| {
+                if players.is_empty() {
+                    other.is_empty() && (self.exclusive || *kind == game.kind())
+                } else {
+                    *kind == game.kind() && other.split(' ').any(|p| players.split(' ').any(|q| p == q))
+                }
+            })
+            .map(|(_, kind, _)| *kind);
+        if let Some(kind) = blocking {
+            return Err(kind);
+        }
+
+        let lines = game.start();
+        save(db, channel, &players, game.as_ref());
+        games.insert((channel.to_string(), game.kind(), players), Running::new(game));
+        Ok(lines)
+    }
+
+    /// Passes a move to the game of `kind` in `channel`, removing it if the
+    /// move ended it. Returns `None` if no such game is running.
+    pub async fn input(&self, db: &Database, channel: &str, kind: &str, player: &str, args: &[String]) -> Option<Reply> {
+        let mut games = self.games.lock().await;
+        let key = find(&games, channel, kind, player)?;
+        let running = games.get_mut(&key)?;
+        let stake = running.game.stake(player, args);
+        if stake > 0 {
+            match db.adjust_chips(player, -stake, kind) {
+                Ok(Some(_)) => {}
+                Ok(None) => return Some(Reply::refused(vec![format!(
+                    "{}, you don't have {} chips. Check your balance with !balance", player, stake
+                )])),
+                Err(e) => return Some(Reply::refused(vec![format!("Error taking chips: {}", e)])),
+            }
+        }
+        let reply = running.game.input(player, args);
+        self.played(db, &key, running, &reply);
+        if reply.finished {
+            games.remove(&key);
+            delete(db, &key);
+        }
+        Some(reply)
+    }
+
+    /// Settles what a move owes, restarts the clocks if something was played,
+    /// and records the game if the move finished it or saves it if not.
+    fn played(&self, db: &Database, key: &GameKey, running: &mut Running, reply: &Reply) {
+        self.settle(db, &key.0, running.game.as_mut());
+        if reply.moved {
+            running.last_move = Instant::now();
+            running.warned = false;
+        }
+        if reply.finished {
+            record(db, &key.0, running.game.as_ref());
+        } else {
+            save(db, &key.0, &key.2, running.game.as_ref());
+        }
+    }
+
+    /// Offers a plain message to every game in `channel`, returning the
+    /// replies of the games that took it as a move.
+    pub async fn chat(&self, db: &Database, channel: &str, player: &str, text: &str) -> Vec<String> {
+        let mut games = self.games.lock().await;
+        let mut lines = Vec::new();
+        let mut finished = Vec::new();
+        for (key, running) in games.iter_mut().filter(|((c, _, _), _)| c == channel) {
+            let Some(reply) = running.game.chat(player, text) else {
+                continue;
+            };
+            self.played(db, key, running, &reply);
+            if reply.finished {
+                finished.push(key.clone());
+            }
+            lines.extend(reply.lines);
+        }
+        for key in finished {
+            games.remove(&key);
+            delete(db, &key);
+        }
+        lines
+    }
+
+    /// The status of the game of `kind` in `channel`, or of `player`'s own
+    /// game for head-to-head kinds.
+    pub async fn status(&self, channel: &str, kind: &str, player: &str) -> Option<Vec<String>> {
+        let games = self.games.lock().await;
+        let key = find(&games, channel, kind, player)?;
+        games.get(&key).map(|running| running.game.status())
+    }
+
+    /// The status of the game running under `key`, such as one just restored.
+    pub async fn status_of(&self, key: &GameKey) -> Option<Vec<String>> {
+        let games = self.games.lock().await;
+        games.get(key).map(|running| running.game.status())
+    }
+
+    /// The head-to-head games of `kind` in `channel`, as "alice vs bob".
+    pub async fn pairs(&self, channel: &str, kind: &str) -> Vec<String> {
+        let games = self.games.lock().await;
+        let mut pairs: Vec<String> = games.iter()
+            .filter(|((c, k, _), _)| c == channel && *k == kind)
+            .filter_map(|(_, running)| running.game.pair().map(|[a, b]| format!("{} vs {}", a, b)))
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    /// Whether a channel game of `kind` is running in `channel`.
+    pub async fn running(&self, channel: &str, kind: &str) -> bool {
+        let games = self.games.lock().await;
+        games.keys().any(|(c, k, players)| c == channel && *k == kind && players.is_empty())
+    }
+
+    /// Ends the game of `kind` in `channel` at `player`'s request, unless the
+    /// game refuses or only lets them forfeit their part; see [`Game::leave`].
+    /// `manage` is whether the player may manage games.
+    pub async fn quit(&self, db: &Database, channel: &str, kind: &str, player: &str, manage: bool) -> Option<Reply> {
+        let mut games = self.games.lock().await;
+        let key = find(&games, channel, kind, player)?;
+        let running = games.get_mut(&key)?;
+        if running.game.tournament_round().is_some() && !manage {
+            return Some(Reply::refused(vec![
+                format!("This {} game is a tournament round. Only game managers can end it", kind),
+            ]));
         }
+        if let Some(reply) = running.game.leave(player, manage) {
+            self.played(db, &key, running, &reply);
+            if reply.finished {
+                games.remove(&key);
+                delete(db, &key);
+            }
+            return Some(reply);
+        }
+        delete(db, &key);
+        let mut running = games.remove(&key)?;
+        let lines = running.game.quit();
+        self.settle(db, channel, running.game.as_mut());
+        Some(Reply::finished(lines))
     }
 
-    pub async fn new_hangman(&self, channel: &str) {
-        let mut games = self.hangman_games.lock().await;
-        games.insert(channel.to_string(), HangmanGame::new());
+    /// Lets the rounds of a cancelled tournament carry on as ordinary games,
+    /// which any player can quit and whose results aren't scored for it.
+    pub async fn release_tournament(&self, db: &Database, tournament: i64) {
+        let mut games = self.games.lock().await;
+        for ((channel, _, players), running) in games.iter_mut() {
+            if running.game.tournament_round().is_some_and(|round| round.tournament == tournament) {
+                running.game.set_tournament_round(None);
+                save(db, channel, players, running.game.as_ref());
+            }
+        }
     }
 
-    pub async fn hangman_guess(&self, channel: &str, letter: char) -> Option<hangman::GuessResult> {
-        let mut games = self.hangman_games.lock().await;
-        games.get_mut(channel).map(|game| game.guess(letter))
+    /// Pays what `game` owes and publishes its events.
+    fn settle(&self, db: &Database, channel: &str, game: &mut dyn Game) {
+        pay(db, &self.events, channel, game);
+        for (player, event) in game.events() {
+            self.events.publish(channel, &player, event);
+        }
     }
 
-    pub async fn hangman_game(&self, channel: &str) -> Option<HangmanGame> {
-        let games = self.hangman_games.lock().await;
-        games.get(channel).cloned()
+    /// Warns once about every game that will be ended by [`expire_idle`] in
+    /// `lead` or less, returning each channel and the warning.
+    ///
+    /// [`expire_idle`]: GameManager::expire_idle
+    pub async fn warn_idle(&self, idle: Duration, lead: Duration) -> Vec<(String, Vec<String>)> {
+        let Some(warn_after) = idle.checked_sub(lead).filter(|d| !d.is_zero()) else {
+            return Vec::new();
+        };
+        let mut games = self.games.lock().await;
+        let mut warnings = Vec::new();
+        for ((channel, kind, _), running) in games.iter_mut() {
+            if !running.warned && running.last_move.elapsed() >= warn_after {
+                running.warned = true;
+                warnings.push((channel.clone(), vec![format!(
+                    "⏳ The {} game ends in {} unless someone makes a move",
+                    kind, describe_duration(lead)
+                )]));
+            }
+        }
+        warnings.sort();
+        warnings
     }
 
-    pub async fn hangman_quit(&self, channel: &str) -> bool {
-        let mut games = self.hangman_games.lock().await;
-        games.remove(channel).is_some()
+    /// Calls [`Game::turn_timeout`] on every game whose turn clock has run out
+    /// and restarts the clock, removing games that ended. Returns each
+    /// channel and the game's reply.
+    pub async fn expire_turns(&self, db: &Database) -> Vec<(String, Vec<String>)> {
+        let mut games = self.games.lock().await;
+        let mut replies = Vec::new();
+        let mut finished = Vec::new();
+        for (key, running) in games.iter_mut() {
+            let Some(limit) = running.game.turn_limit() else {
+                continue;
+            };
+            if running.last_move.elapsed() < limit {
+                continue;
+            }
+            let reply = running.game.turn_timeout();
+            self.settle(db, &key.0, running.game.as_mut());
+            running.last_move = Instant::now();
+            if reply.finished {
+                record(db, &key.0, running.game.as_ref());
+                finished.push(key.clone());
+            } else {
+                save(db, &key.0, &key.2, running.game.as_ref());
+            }
+            replies.push((key.0.clone(), reply.lines));
+        }
+        for key in finished {
+            games.remove(&key);
+            delete(db, &key);
+        }
+        replies.sort();
+        replies
     }
 
-    pub fn clone_arc(&self) -> Arc<Mutex<HashMap<String, HangmanGame>>> {
-        Arc::clone(&self.hangman_games)
+    /// Ends every game with no moves for `idle`, returning each channel and
+    /// the game's closing lines.
+    pub async fn expire_idle(&self, db: &Database, idle: Duration) -> Vec<(String, Vec<String>)> {
+        let mut games = self.games.lock().await;
+        let expired: Vec<GameKey> = games.iter()
+            .filter(|(_, running)| running.last_move.elapsed() >= idle)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let mut ended = Vec::new();
+        for key in expired {
+            if let Some(mut running) = games.remove(&key) {
+                delete(db, &key);
+                let lines = running.game.timeout();
+                self.settle(db, &key.0, running.game.as_mut());
+                record(db, &key.0, running.game.as_ref());
+                ended.push((key.0, lines));
+            }
+        }
+        ended.sort();
+        ended
+    }
+
+    /// Loads the games saved in the database, e.g. after a restart. Returns
+    /// the channel, kind and players of each game restored; see [`GameKey`].
+    /// Saves that can't be read are dropped.
+    pub async fn restore(&self, db: &Database) -> Vec<GameKey> {
+        let saved = match db.saved_games() {
+            Ok(saved) => saved,
+            Err(e) => {
+                eprintln!("Failed to load saved games: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut games = self.games.lock().await;
+        let mut restored = Vec::new();
+        for (channel, kind, players, state) in saved {
+            let game = serde_json::from_str(&state).ok().and_then(|state| restore_game(&kind, state));
+            match game {
+                Some(game) => {
+                    let key = (channel, game.kind(), players);
+                    restored.push(key.clone());
+                    games.insert(key, Running::new(game));
+                }
+                None => {
+                    eprintln!("Dropping unreadable saved {} game in {}", kind, channel);
+                    if let Err(e) = db.delete_game(&channel, &kind, &players) {
+                        eprintln!("Failed to delete saved {} game in {}: {}", kind, channel, e);
+                    }
+                }
+            }
+        }
+        restored
     }
 }
 
-impl Clone for GameManager {
-    fn clone(&self) -> Self {
-        GameManager {
-            hangman_games: Arc::clone(&self.hangman_games),
+/// The key of the game of `kind` in `channel` that `player` plays in: the
+/// channel's own game, or their head-to-head game.
+fn find(games: &HashMap<GameKey, Running>, channel: &str, kind: &str, player: &str) -> Option<GameKey> {
+    let player = player.to_lowercase();
+    games.keys()
+        .find(|(c, k, players)| {
+            c == channel && *k == kind && (players.is_empty() || players.split(' ').any(|p| p == player))
+        })
+        .cloned()
+}
+
+/// The players part of a game's key; see [`GameKey`].
+fn players_key(game: &dyn Game) -> String {
+    let Some(pair) = game.pair() else {
+        return String::new();
+    };
+    let mut players = pair.map(str::to_lowercase);
+    players.sort();
+    players.join(" ")
+}
+
+fn save(db: &Database, channel: &str, players: &str, game: &dyn Game) {
+    if let Err(e) = db.save_game(channel, game.kind(), players, &game.save().to_string()) {
+        eprintln!("Failed to save {} game in {}: {}", game.kind(), channel, e);
+    }
+}
+
+fn record(db: &Database, channel: &str, game: &dyn Game) {
+    if let Err(e) = game.record(db, channel) {
+        eprintln!("Failed to record {} results in {}: {}", game.kind(), channel, e);
+    }
+}
+
+fn pay(db: &Database, events: &EventBus, channel: &str, game: &mut dyn Game) {
+    for (player, chips) in game.payouts() {
+        match db.adjust_chips(&player, chips, game.kind()) {
+            Ok(Some(balance)) => events.publish(channel, &player, Event::ChipBalance(balance)),
+            Ok(None) => eprintln!("{} couldn't cover {} chips owed to {}", player, -chips, game.kind()),
+            Err(e) => eprintln!("Failed to pay {} chips to {} from {}: {}", chips, player, game.kind(), e),
         }
     }
 }
 
+fn delete(db: &Database, (channel, kind, players): &GameKey) {
+    if let Err(e) = db.delete_game(channel, kind, players) {
+        eprintln!("Failed to delete saved {} game in {}: {}", kind, channel, e);
+    }
+}
+
+/// "1 minute", "5 minutes" or "30 seconds".
+fn describe_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        60 => "1 minute".to_string(),
+        s if s >= 60 && s % 60 == 0 => format!("{} minutes", s / 60),
+        1 => "1 second".to_string(),
+        s => format!("{} seconds", s),
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde::{Deserialize, Serialize};
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    /// A game that counts moves and ends after the third, refusing "pass". With
+    /// a turn limit, running out of time counts as a move.
+    #[derive(Serialize, Deserialize)]
+    struct Counter {
+        moves: u32,
+        #[serde(skip)]
+        limit: Option<Duration>,
+    }
+
+    impl Game for Counter {
+        fn kind(&self) -> &'static str {
+            "counter"
+        }
+
+        fn start(&mut self) -> Vec<String> {
+            vec!["counting".to_string()]
+        }
+
+        fn input(&mut self, player: &str, args: &[String]) -> Reply {
+            if args.first().is_some_and(|arg| arg == "pass") {
+                return Reply::refused(vec![format!("{} can't pass", player)]);
+            }
+            self.moves += 1;
+            let line = format!("{} moved, {} so far", player, self.moves);
+            if self.moves >= 3 {
+                Reply::finished(vec![line])
+            } else {
+                Reply::new(vec![line])
+            }
+        }
+
+        fn turn_limit(&self) -> Option<Duration> {
+            self.limit
+        }
+
+        fn turn_timeout(&mut self) -> Reply {
+            self.moves += 1;
+            let line = format!("too slow, {} so far", self.moves);
+            if self.moves >= 3 {
+                Reply::finished(vec![line])
+            } else {
+                Reply::new(vec![line])
+            }
+        }
+
+        fn render(&self) -> String {
+            format!("{} moves", self.moves)
+        }
+
+        fn save(&self) -> Value {
+            serde_json::to_value(self).unwrap()
+        }
+    }
+
+    fn counter() -> Box<dyn Game> {
+        Box::new(Counter { moves: 0, limit: None })
+    }
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
 
     #[tokio::test]
     async fn test_new_hangman_game() {
-        let manager = GameManager::new();
-        manager.new_hangman("#channel1").await;
-        
-        let game = manager.hangman_game("#channel1").await;
-        assert!(game.is_some());
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        let lines = manager.start(&db, "#channel1", new_game(&db, &Settings::default(), "hangman").unwrap().unwrap()).await.unwrap();
+
+        assert!(!lines.is_empty());
+        assert!(manager.status("#channel1", "hangman", "alice").await.is_some());
     }
 
     #[tokio::test]
-    async fn test_hangman_guess() {
-        let manager = GameManager::new();
-        manager.new_hangman("#channel1").await;
-        
-        let game = manager.hangman_game("#channel1").await.unwrap();
-        let first_letter = game.word().chars().next().unwrap();
-        drop(game);
-        
-        let result = manager.hangman_guess("#channel1", first_letter).await;
-        assert!(result.is_some());
+    async fn test_unknown_game_kind() {
+        let db = create_test_db();
+        assert!(new_game(&db, &Settings::default(), "solitaire").is_none());
     }
 
     #[tokio::test]
-    async fn test_hangman_quit() {
-        let manager = GameManager::new();
-        manager.new_hangman("#channel1").await;
-        
-        let quit_result = manager.hangman_quit("#channel1").await;
-        assert!(quit_result);
-        
-        let game = manager.hangman_game("#channel1").await;
-        assert!(game.is_none());
+    async fn test_input_routes_to_game() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+
+        let reply = manager.input(&db, "#channel1", "counter", "alice", &args("go")).await;
+        assert_eq!(reply.map(|r| r.lines), Some(vec!["alice moved, 1 so far".to_string()]));
+        assert_eq!(manager.status("#channel1", "counter", "alice").await, Some(vec!["1 moves".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_finished_game_is_removed() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+
+        for _ in 0..3 {
+            manager.input(&db, "#channel1", "counter", "alice", &args("go")).await;
+        }
+        assert!(manager.status("#channel1", "counter", "alice").await.is_none());
+        assert!(manager.start(&db, "#channel1", counter()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_moves_publish_events_and_pay() {
+        let db = create_test_db();
+        let (events, mut published) = EventBus::new();
+        let manager = GameManager::new(true).with_events(events);
+        let game = HangmanGame::with_word("rust", None);
+        manager.start(&db, "#channel1", Box::new(game)).await.unwrap();
+
+        let before = db.chip_balance("alice").unwrap();
+        manager.input(&db, "#channel1", "hangman", "alice", &args("solve rust")).await;
+        let balance = db.chip_balance("alice").unwrap();
+        assert!(balance > before);
+        assert_eq!(published.try_recv().unwrap().2, Event::ChipBalance(balance));
+        assert_eq!(published.try_recv().unwrap(), (
+            "#channel1".to_string(), "alice".to_string(), Event::HangmanSolved { wrong: 0 },
+        ));
+        assert!(published.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_quit() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+
+        let quit_result = manager.quit(&db, "#channel1", "counter", "alice", false).await;
+        assert_eq!(quit_result.map(|r| r.lines), Some(vec!["Counter game ended.".to_string()]));
+        assert!(manager.status("#channel1", "counter", "alice").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refused_quit_keeps_the_game() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", Box::new(BlackjackGame::new())).await.unwrap();
+
+        let reply = manager.quit(&db, "#channel1", "blackjack", "carol", false).await.unwrap();
+        assert!(!reply.finished);
+        assert!(manager.status("#channel1", "blackjack", "carol").await.is_some());
+        assert!(manager.quit(&db, "#channel1", "blackjack", "carol", true).await.unwrap().finished);
+        assert!(manager.status("#channel1", "blackjack", "carol").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pairs_are_listed_and_found_by_key() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", new_board_game("ttt", "Carol", "dave").unwrap()).await.unwrap();
+        manager.start(&db, "#channel1", new_board_game("ttt", "alice", "bob").unwrap()).await.unwrap();
+
+        assert_eq!(manager.pairs("#channel1", "ttt").await, vec!["Carol vs dave", "alice vs bob"]);
+        assert!(manager.pairs("#channel1", "c4").await.is_empty());
+        assert!(manager.status("#channel1", "ttt", "erin").await.is_none());
+        let key = ("#channel1".to_string(), "ttt", "carol dave".to_string());
+        assert_eq!(manager.status_of(&key).await, manager.status("#channel1", "ttt", "dave").await);
+    }
+
+    #[tokio::test]
+    async fn test_expire_idle() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+
+        assert!(manager.expire_idle(&db, Duration::from_secs(60)).await.is_empty());
+        let ended = manager.expire_idle(&db, Duration::ZERO).await;
+        assert_eq!(ended, vec![("#channel1".to_string(), vec!["Counter game timed out.".to_string()])]);
+        assert!(manager.status("#channel1", "counter", "alice").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_warn_idle_once_per_lull() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+
+        let idle = Duration::from_secs(60);
+        assert!(manager.warn_idle(idle, Duration::from_secs(30)).await.is_empty());
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        let warnings = manager.warn_idle(idle, idle - Duration::from_millis(1)).await;
+        assert_eq!(warnings, vec![(
+            "#channel1".to_string(),
+            vec!["⏳ The counter game ends in 59 seconds unless someone makes a move".to_string()],
+        )]);
+        assert!(manager.warn_idle(idle, idle - Duration::from_millis(1)).await.is_empty());
+
+        // A move starts a new lull, which gets its own warning
+        manager.input(&db, "#channel1", "counter", "alice", &args("go")).await;
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        assert_eq!(manager.warn_idle(idle, idle - Duration::from_millis(1)).await.len(), 1);
+        // A lead as long as the timeout itself is never warned about
+        manager.input(&db, "#channel1", "counter", "alice", &args("go")).await;
+        assert!(manager.warn_idle(idle, idle).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_refused_moves_keep_the_clock_running() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let reply = manager.input(&db, "#channel1", "counter", "alice", &args("pass")).await.unwrap();
+        assert!(!reply.moved);
+        let ended = manager.expire_idle(&db, Duration::from_millis(5)).await;
+        assert_eq!(ended.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expire_turns() {
+        let db = create_test_db();
+        let manager = GameManager::new(false);
+        manager.start(&db, "#channel1", Box::new(Counter { moves: 0, limit: Some(Duration::ZERO) })).await.unwrap();
+        manager.start(&db, "#channel2", counter()).await.unwrap();
+
+        let replies = manager.expire_turns(&db).await;
+        assert_eq!(replies, vec![("#channel1".to_string(), vec!["too slow, 1 so far".to_string()])]);
+        assert_eq!(db.saved_games().unwrap()[0].3, r#"{"moves":1}"#);
+
+        manager.expire_turns(&db).await;
+        let replies = manager.expire_turns(&db).await;
+        assert_eq!(replies, vec![("#channel1".to_string(), vec!["too slow, 3 so far".to_string()])]);
+        assert!(manager.status("#channel1", "counter", "alice").await.is_none());
+        assert_eq!(db.saved_games().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_chat_routes_answers() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        let question = trivia::Question {
+            question: "Largest planet?".to_string(),
+            answers: vec!["Jupiter".to_string()],
+            category: "science".to_string(),
+            difficulty: None,
+        };
+        manager.start(&db, "#channel1", Box::new(TriviaGame::new(vec![question], Duration::from_secs(15)))).await.unwrap();
+
+        assert!(manager.chat(&db, "#channel1", "alice", "saturn?").await.is_empty());
+        assert!(manager.chat(&db, "#channel2", "alice", "jupiter").await.is_empty());
+        let lines = manager.chat(&db, "#channel1", "alice", "jupiter").await;
+        assert_eq!(lines[0], "✅ alice got it: Jupiter (+4 points)");
+        assert!(manager.status("#channel1", "trivia", "alice").await.is_none());
+        assert!(db.saved_games().unwrap().is_empty());
+        assert_eq!(db.trivia_leaderboard(None, 10).unwrap(), vec![("alice".to_string(), 4)]);
+    }
+
+    #[tokio::test]
+    async fn test_games_ignore_chat_by_default() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+        assert!(manager.chat(&db, "#channel1", "alice", "hello").await.is_empty());
+        assert_eq!(manager.status("#channel1", "counter", "alice").await, Some(vec!["0 moves".to_string()]));
+    }
+
+    #[test]
+    fn test_describe_duration() {
+        assert_eq!(describe_duration(Duration::from_secs(60)), "1 minute");
+        assert_eq!(describe_duration(Duration::from_secs(300)), "5 minutes");
+        assert_eq!(describe_duration(Duration::from_secs(90)), "90 seconds");
+        assert_eq!(describe_duration(Duration::from_secs(1)), "1 second");
+    }
+
+    #[tokio::test]
+    async fn test_moves_are_saved() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+        manager.input(&db, "#channel1", "counter", "alice", &args("go")).await;
+
+        assert_eq!(db.saved_games().unwrap(), vec![
+            ("#channel1".to_string(), "counter".to_string(), String::new(), r#"{"moves":1}"#.to_string()),
+        ]);
+
+        manager.quit(&db, "#channel1", "counter", "alice", false).await;
+        assert!(db.saved_games().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restore_hangman() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", new_game(&db, &Settings::default(), "hangman").unwrap().unwrap()).await.unwrap();
+        manager.input(&db, "#channel1", "hangman", "alice", &args("guess e")).await;
+        let before = manager.status("#channel1", "hangman", "alice").await;
+
+        let restarted = GameManager::new(true);
+        assert_eq!(restarted.restore(&db).await, vec![("#channel1".to_string(), "hangman", String::new())]);
+        assert_eq!(restarted.status("#channel1", "hangman", "alice").await, before);
+    }
+
+    #[tokio::test]
+    async fn test_restore_drops_unreadable_games() {
+        let db = create_test_db();
+        db.save_game("#channel1", "hangman", "", "not json").unwrap();
+        db.save_game("#channel2", "solitaire", "", "{}").unwrap();
+
+        let manager = GameManager::new(true);
+        assert!(manager.restore(&db).await.is_empty());
+        assert!(db.saved_games().unwrap().is_empty());
     }
 
     #[tokio::test]
     async fn test_channel_isolation() {
-        let manager = GameManager::new();
-        manager.new_hangman("#channel1").await;
-        manager.new_hangman("#channel2").await;
-        
-        let game1 = manager.hangman_game("#channel1").await;
-        let game2 = manager.hangman_game("#channel2").await;
-        
-        // Both should exist and be different games
-        assert!(game1.is_some());
-        assert!(game2.is_some());
-        assert_ne!(game1.unwrap().word(), game2.unwrap().word());
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+        manager.start(&db, "#channel2", counter()).await.unwrap();
+
+        manager.input(&db, "#channel1", "counter", "alice", &args("go")).await;
+
+        assert_eq!(manager.status("#channel1", "counter", "alice").await, Some(vec!["1 moves".to_string()]));
+        assert_eq!(manager.status("#channel2", "counter", "alice").await, Some(vec!["0 moves".to_string()]));
     }
 
     #[tokio::test]
-    async fn test_nonexistent_game() {
-        let manager = GameManager::new();
-        let game = manager.hangman_game("#channel1").await;
-        assert!(game.is_none());
+    async fn test_one_per_channel() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+
+        assert_eq!(manager.start(&db, "#channel1", counter()).await, Err("counter"));
+        assert_eq!(manager.start(&db, "#channel1", new_game(&db, &Settings::default(), "hangman").unwrap().unwrap()).await, Err("counter"));
     }
 
     #[tokio::test]
-    async fn test_guess_nonexistent_game() {
-        let manager = GameManager::new();
-        let result = manager.hangman_guess("#channel1", 'a').await;
-        assert!(result.is_none());
+    async fn test_one_per_kind() {
+        let db = create_test_db();
+        let manager = GameManager::new(false);
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+
+        assert_eq!(manager.start(&db, "#channel1", counter()).await, Err("counter"));
+        assert!(manager.start(&db, "#channel1", new_game(&db, &Settings::default(), "hangman").unwrap().unwrap()).await.is_ok());
+        assert!(manager.status("#channel1", "counter", "alice").await.is_some());
+        assert!(manager.status("#channel1", "hangman", "alice").await.is_some());
     }
 
     #[tokio::test]
-    async fn test_quit_nonexistent_game() {
-        let manager = GameManager::new();
-        let result = manager.hangman_quit("#channel1").await;
-        assert!(!result);
+    async fn test_nonexistent_game() {
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        assert!(manager.status("#channel1", "hangman", "alice").await.is_none());
+        assert!(manager.input(&db, "#channel1", "hangman", "alice", &args("guess a")).await.is_none());
+        assert!(manager.quit(&db, "#channel1", "hangman", "alice", false).await.is_none());
     }
 
     #[tokio::test]
     async fn test_shared_game_state() {
-        let manager = GameManager::new();
-        manager.new_hangman("#channel1").await;
-        
-        // Get initial game state
-        let game1 = manager.hangman_game("#channel1").await.unwrap();
-        let word = game1.word().to_string();
-        let first_letter = word.chars().next().unwrap();
-        drop(game1);
-        
-        // Make a guess
-        manager.hangman_guess("#channel1", first_letter).await;
-        
-        // Verify the same game is modified
-        let game2 = manager.hangman_game("#channel1").await.unwrap();
-        assert!(game2.guessed().contains(first_letter));
+        let db = create_test_db();
+        let manager = GameManager::new(true);
+        let clone = manager.clone();
+        manager.start(&db, "#channel1", counter()).await.unwrap();
+
+        clone.input(&db, "#channel1", "counter", "bob", &args("go")).await;
+        assert_eq!(manager.status("#channel1", "counter", "alice").await, Some(vec!["1 moves".to_string()]));
+    }
+
+    #[test]
+    fn test_capitalize() {
+        assert_eq!(capitalize("hangman"), "Hangman");
+        assert_eq!(capitalize(""), "");
     }
 }
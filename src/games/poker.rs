@@ -0,0 +1,670 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use super::{Game, Reply};
+use super::cards::{evaluate, show, Card, Deck};
+
+/// Chips each player puts in the pot when they join.
+pub const ANTE: i64 = 10;
+pub const MAX_RAISE: i64 = 1000;
+const MIN_PLAYERS: usize = 2;
+const MAX_SEATS: usize = 8;
+/// Community cards dealt by the river.
+const BOARD_SIZE: usize = 5;
+/// Time each player has to act before checking or folding automatically.
+const TURN_LIMIT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Street {
+    /// Players are still joining.
+    Waiting,
+    Preflop,
+    Flop,
+    Turn,
+    River,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Seat {
+    player: String,
+    hole: Vec<Card>,
+    /// Chips put in the pot this hand, ante included.
+    in_pot: i64,
+    /// Chips put in during the current betting round.
+    round_bet: i64,
+    folded: bool,
+    /// Acted since the last bet or raise.
+    acted: bool,
+}
+
+/// One hand of Texas hold'em between the players in a channel. Everyone
+/// antes to join, hole cards go out privately on `deal`, and the hand is
+/// played through four betting rounds to a showdown. Bets come straight
+/// from each player's chip balance, so a player who can't cover a call
+/// has to fold.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PokerGame {
+    deck: Deck,
+    seats: Vec<Seat>,
+    board: Vec<Card>,
+    street: Street,
+    /// The seat whose move it is.
+    turn: usize,
+    /// Chips owed to players that haven't been handed out yet.
+    owed: Vec<(String, i64)>,
+}
+
+impl PokerGame {
+    pub fn new() -> Self {
+        PokerGame::with_deck(Deck::shuffled(&mut rand::thread_rng()))
+    }
+
+    pub fn with_deck(deck: Deck) -> Self {
+        PokerGame {
+            deck,
+            seats: Vec::new(),
+            board: Vec::new(),
+            street: Street::Waiting,
+            turn: 0,
+            owed: Vec::new(),
+        }
+    }
+
+    fn seat(&self, player: &str) -> Option<&Seat> {
+        self.seats.iter().find(|s| s.player == player)
+    }
+
+    fn pot(&self) -> i64 {
+        self.seats.iter().map(|s| s.in_pot).sum()
+    }
+
+    /// The most anyone has put in this betting round.
+    fn current_bet(&self) -> i64 {
+        self.seats.iter().map(|s| s.round_bet).max().unwrap_or(0)
+    }
+
+    fn to_call(&self, seat: &Seat) -> i64 {
+        self.current_bet() - seat.round_bet
+    }
+
+    fn in_hand(&self) -> impl Iterator<Item = &Seat> {
+        self.seats.iter().filter(|s| !s.folded)
+    }
+
+    /// Why `player` can't join, or the ante they put up.
+    fn check_join(&self, player: &str) -> Result<i64, String> {
+        if self.street != Street::Waiting {
+            return Err("The hand is already dealt".to_string());
+        }
+        if self.seat(player).is_some() {
+            return Err(format!("{}, you're already at the table", player));
+        }
+        if self.seats.len() >= MAX_SEATS {
+            return Err(format!("The table is full ({} seats)", MAX_SEATS));
+        }
+        // Two hole cards for every seat, and the board
+        if (self.seats.len() + 1) * 2 + BOARD_SIZE > self.deck.remaining() {
+            return Err("The deck can't deal another hand".to_string());
+        }
+        Ok(ANTE)
+    }
+
+    /// The seat of `player` if it's their move.
+    fn current(&self, player: &str) -> Result<&Seat, String> {
+        if self.street == Street::Waiting {
+            return Err("Cards haven't been dealt yet. Use !poker deal".to_string());
+        }
+        match self.seats.get(self.turn) {
+            Some(seat) if seat.player == player => Ok(seat),
+            Some(seat) => Err(format!("It's {}'s move", seat.player)),
+            None => Err("Nobody is left to play".to_string()),
+        }
+    }
+
+    /// Why `player` can't call now, or the chips it takes.
+    fn check_call(&self, player: &str) -> Result<i64, String> {
+        let seat = self.current(player)?;
+        match self.to_call(seat) {
+            0 => Err("There's nothing to call. Check or bet".to_string()),
+            amount => Ok(amount),
+        }
+    }
+
+    /// Why `player` can't bet or raise `args`, or the chips it takes,
+    /// including whatever they have to call first.
+    fn check_raise(&self, player: &str, args: &[String]) -> Result<i64, String> {
+        let seat = self.current(player)?;
+        match args.get(1).and_then(|a| a.parse::<i64>().ok()) {
+            Some(raise) if (1..=MAX_RAISE).contains(&raise) => Ok(self.to_call(seat) + raise),
+            _ => Err(format!("Usage: !poker {} <1-{}>", args[0], MAX_RAISE)),
+        }
+    }
+
+    fn join(&mut self, player: &str) -> Reply {
+        match self.check_join(player) {
+            Ok(ante) => {
+                self.seats.push(Seat {
+                    player: player.to_string(),
+                    hole: Vec::new(),
+                    in_pot: ante,
+                    round_bet: 0,
+                    folded: false,
+                    acted: false,
+                });
+                Reply::new(vec![format!(
+                    "💰 {} joins with a {} chip ante ({} at the table, pot {})",
+                    player, ante, self.seats.len(), self.pot()
+                )])
+            }
+            Err(e) => Reply::refused(vec![e]),
+        }
+    }
+
+    fn deal(&mut self, player: &str) -> Reply {
+        if self.street != Street::Waiting {
+            return Reply::refused(vec!["Cards are already dealt".to_string()]);
+        }
+        if self.seat(player).is_none() {
+            return Reply::refused(vec!["Join the table first: !poker join".to_string()]);
+        }
+        if self.seats.len() < MIN_PLAYERS {
+            return Reply::refused(vec![format!("Poker needs at least {} players", MIN_PLAYERS)]);
+        }
+        self.street = Street::Preflop;
+        for seat in &mut self.seats {
+            seat.hole = self.deck.deal_many(2).expect("seats are refused once the deck can't deal every hand");
+        }
+        let notices = self.seats.iter()
+            .map(|s| (s.player.clone(), format!("🃏 Your cards: {}", show(&s.hole))))
+            .collect();
+        let players: Vec<&str> = self.seats.iter().map(|s| s.player.as_str()).collect();
+        let lines = vec![format!(
+            "🃏 Dealt to {}. Pot {}. Your cards are sent by notice, or use !poker cards",
+            players.join(", "), self.pot()
+        )];
+        self.turn = 0;
+        self.prompt(lines).with_notices(notices)
+    }
+
+    fn cards(&self, player: &str) -> Reply {
+        match self.seat(player) {
+            Some(seat) if !seat.hole.is_empty() => {
+                Reply::refused(Vec::new()).with_notices(vec![(player.to_string(), format!("🃏 Your cards: {}", show(&seat.hole)))])
+            }
+            Some(_) => Reply::refused(vec!["Cards haven't been dealt yet".to_string()]),
+            None => Reply::refused(vec![format!("{}, you're not at the table", player)]),
+        }
+    }
+
+    fn check(&mut self, player: &str) -> Reply {
+        let seat = match self.current(player) {
+            Ok(seat) => seat,
+            Err(e) => return Reply::refused(vec![e]),
+        };
+        let to_call = self.to_call(seat);
+        if to_call > 0 {
+            return Reply::refused(vec![format!("You can't check, it's {} to call", to_call)]);
+        }
+        self.seats[self.turn].acted = true;
+        self.advance(vec![format!("{} checks", player)])
+    }
+
+    fn call(&mut self, player: &str) -> Reply {
+        let amount = match self.check_call(player) {
+            Ok(amount) => amount,
+            Err(e) => return Reply::refused(vec![e]),
+        };
+        self.put_in(amount);
+        self.advance(vec![format!("{} calls {}", player, amount)])
+    }
+
+    fn raise(&mut self, player: &str, args: &[String]) -> Reply {
+        let amount = match self.check_raise(player, args) {
+            Ok(amount) => amount,
+            Err(e) => return Reply::refused(vec![e]),
+        };
+        let raised = self.current_bet() > 0;
+        self.put_in(amount);
+        for seat in &mut self.seats {
+            seat.acted = false;
+        }
+        self.seats[self.turn].acted = true;
+        let line = if raised {
+            format!("{} raises to {}", player, self.current_bet())
+        } else {
+            format!("{} bets {}", player, amount)
+        };
+        self.advance(vec![line])
+    }
+
+    fn fold(&mut self, player: &str) -> Reply {
+        if let Err(e) = self.current(player) {
+            return Reply::refused(vec![e]);
+        }
+        self.seats[self.turn].folded = true;
+        self.advance(vec![format!("{} folds", player)])
+    }
+
+    /// Moves chips from the current seat into the pot.
+    fn put_in(&mut self, amount: i64) {
+        let seat = &mut self.seats[self.turn];
+        seat.in_pot += amount;
+        seat.round_bet += amount;
+        seat.acted = true;
+    }
+
+    /// Moves to the next seat that still has to act, to the next street
+    /// once the betting round is closed, or ends the hand.
+    fn advance(&mut self, mut lines: Vec<String>) -> Reply {
+        if self.in_hand().count() == 1 {
+            return self.settle(lines);
+        }
+        let current_bet = self.current_bet();
+        let n = self.seats.len();
+        let next = (1..=n)
+            .map(|i| (self.turn + i) % n)
+            .find(|&i| {
+                let seat = &self.seats[i];
+                !seat.folded && (!seat.acted || seat.round_bet < current_bet)
+            });
+        if let Some(next) = next {
+            self.turn = next;
+            return self.prompt(lines);
+        }
+
+        let (street, cards, name) = match self.street {
+            Street::Preflop => (Street::Flop, 3, "Flop"),
+            Street::Flop => (Street::Turn, 1, "Turn"),
+            Street::Turn => (Street::River, 1, "River"),
+            Street::Waiting | Street::River => return self.showdown(lines),
+        };
+        self.street = street;
+        self.board.extend(self.deck.deal_many(cards).expect("seats leave the board's cards in the deck"));
+        for seat in &mut self.seats {
+            seat.round_bet = 0;
+            seat.acted = false;
+        }
+        lines.push(format!("🂠 {}: {} (pot {})", name, show(&self.board), self.pot()));
+        self.turn = self.seats.iter().position(|s| !s.folded).expect("two players are still in");
+        self.prompt(lines)
+    }
+
+    /// Asks the current seat for a move.
+    fn prompt(&self, mut lines: Vec<String>) -> Reply {
+        let seat = &self.seats[self.turn];
+        let options = match self.to_call(seat) {
+            0 => "check, bet <n>, fold".to_string(),
+            to_call => format!("call {}, raise <n>, fold", to_call),
+        };
+        lines.push(format!("Your move, {}: {}", seat.player, options));
+        Reply::new(lines)
+    }
+
+    /// Compares the hands still in and splits the pot between the best.
+    fn showdown(&mut self, mut lines: Vec<String>) -> Reply {
+        let mut best = None;
+        let mut winners = Vec::new();
+        for seat in self.in_hand() {
+            let mut cards = seat.hole.clone();
+            cards.extend(&self.board);
+            let rank = evaluate(&cards);
+            lines.push(format!("{} shows {} — {}", seat.player, show(&seat.hole), rank.category.name()));
+            match &best {
+                Some(b) if rank < *b => {}
+                Some(b) if rank == *b => winners.push(seat.player.clone()),
+                _ => {
+                    best = Some(rank);
+                    winners = vec![seat.player.clone()];
+                }
+            }
+        }
+        let category = best.expect("someone is still in").category.name();
+        let pot = self.pot();
+        let share = pot / winners.len() as i64;
+        // Chips that don't split evenly go to the first winners in seat order
+        let mut odd = pot % winners.len() as i64;
+        for winner in &winners {
+            let extra = if odd > 0 { 1 } else { 0 };
+            odd -= extra;
+            self.owed.push((winner.clone(), share + extra));
+        }
+        if winners.len() == 1 {
+            lines.push(format!("🏁 {} wins {} with {}", winners[0], pot, category));
+        } else {
+            lines.push(format!("🏁 {} split {} with {}", winners.join(" and "), pot, category));
+        }
+        Reply::finished(lines)
+    }
+
+    /// Deals the rest of the board and shows down the hands still in.
+    fn run_out(&mut self, mut lines: Vec<String>) -> Reply {
+        let missing = BOARD_SIZE - self.board.len();
+        self.board.extend(self.deck.deal_many(missing).expect("seats leave the board's cards in the deck"));
+        lines.push(format!("🂠 Board: {} (pot {})", show(&self.board), self.pot()));
+        self.showdown(lines)
+    }
+
+    /// Gives the pot to the last player in after everyone else folded.
+    fn settle(&mut self, mut lines: Vec<String>) -> Reply {
+        let winner = self.in_hand().next().expect("one player is still in").player.clone();
+        let pot = self.pot();
+        lines.push(format!("🏁 {} wins {}", winner, pot));
+        self.owed.push((winner, pot));
+        Reply::finished(lines)
+    }
+
+    /// Hands every player's chips back, for a hand that ends unplayed.
+    fn refund(&mut self) {
+        for seat in &self.seats {
+            self.owed.push((seat.player.clone(), seat.in_pot));
+        }
+        self.seats.clear();
+    }
+}
+
+impl Default for PokerGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game for PokerGame {
+    fn kind(&self) -> &'static str {
+        "poker"
+    }
+
+    fn start(&mut self) -> Vec<String> {
+        vec![format!(
+            "🎮 Texas hold'em! Join with !poker join (ante {}), then !poker deal once {} or more are in.",
+            ANTE, MIN_PLAYERS
+        )]
+    }
+
+    fn input(&mut self, player: &str, args: &[String]) -> Reply {
+        match args.first().map(String::as_str) {
+            Some("join") => self.join(player),
+            Some("deal") => self.deal(player),
+            Some("cards") => self.cards(player),
+            Some("check") => self.check(player),
+            Some("call") => self.call(player),
+            Some("bet") | Some("raise") => self.raise(player, args),
+            Some("fold") => self.fold(player),
+            _ => Reply::refused(vec!["Unknown poker subcommand. Use: start, join, deal, cards, check, call, bet, raise, fold, status, quit, chips, top".to_string()]),
+        }
+    }
+
+    fn render(&self) -> String {
+        if self.street == Street::Waiting {
+            if self.seats.is_empty() {
+                return "🃏 Poker: waiting for players".to_string();
+            }
+            let players: Vec<&str> = self.seats.iter().map(|s| s.player.as_str()).collect();
+            return format!("🃏 Poker: {} at the table (pot {}). Deal with !poker deal", players.join(", "), self.pot());
+        }
+        let board = if self.board.is_empty() { "no cards yet".to_string() } else { show(&self.board) };
+        let players: Vec<String> = self.seats.iter()
+            .map(|s| if s.folded { format!("{} (folded)", s.player) } else { format!("{} {}", s.player, s.in_pot) })
+            .collect();
+        let mut line = format!("🃏 Board: {} | Pot {} | {}", board, self.pot(), players.join(", "));
+        if let Some(seat) = self.seats.get(self.turn) {
+            line.push_str(&format!(" | {} to play", seat.player));
+        }
+        line
+    }
+
+    fn leave(&mut self, player: &str, manage: bool) -> Option<Reply> {
+        let seat = self.seats.iter().position(|s| s.player == player);
+        if seat.is_none() && !manage {
+            return Some(Reply::refused(vec!["Only players at the table or game managers can end this game".to_string()]));
+        }
+        if self.street == Street::Waiting {
+            // Before the deal a player just takes their ante back and the table stays open
+            let i = seat?;
+            let seat = self.seats.remove(i);
+            self.owed.push((seat.player.clone(), seat.in_pot));
+            return Some(Reply { moved: false, ..Reply::new(vec![format!(
+                "{} leaves the table and takes back their {} chip ante ({} at the table)", player, seat.in_pot, self.seats.len()
+            )]) });
+        }
+        // Once cards are out, quitting can't take chips back out of the pot
+        match seat {
+            Some(i) if self.seats[i].folded => Some(Reply::refused(vec![format!("{}, you've already folded", player)])),
+            Some(i) => {
+                self.seats[i].folded = true;
+                let line = format!("{} leaves the table and folds", player);
+                if i == self.turn || self.in_hand().count() == 1 {
+                    Some(self.advance(vec![line]))
+                } else {
+                    Some(Reply { moved: false, ..Reply::new(vec![line]) })
+                }
+            }
+            None => Some(self.run_out(vec![format!("{} ends the hand. The board is dealt out", player)])),
+        }
+    }
+
+    fn quit(&mut self) -> Vec<String> {
+        self.refund();
+        vec!["Poker game ended. Chips were returned.".to_string()]
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).expect("poker state is always serializable")
+    }
+
+    fn timeout(&mut self) -> Vec<String> {
+        self.refund();
+        vec!["⏰ Poker timed out. Chips were returned.".to_string()]
+    }
+
+    fn turn_limit(&self) -> Option<Duration> {
+        (self.street != Street::Waiting).then_some(TURN_LIMIT)
+    }
+
+    fn turn_timeout(&mut self) -> Reply {
+        let Some(seat) = self.seats.get(self.turn) else {
+            return Reply::default();
+        };
+        let player = seat.player.clone();
+        let line = if self.to_call(seat) == 0 {
+            self.seats[self.turn].acted = true;
+            format!("⌛ {} took too long and checks", player)
+        } else {
+            self.seats[self.turn].folded = true;
+            format!("⌛ {} took too long and folds", player)
+        };
+        self.advance(vec![line])
+    }
+
+    fn stake(&self, player: &str, args: &[String]) -> i64 {
+        match args.first().map(String::as_str) {
+            Some("join") => self.check_join(player).unwrap_or(0),
+            Some("call") => self.check_call(player).unwrap_or(0),
+            Some("bet") | Some("raise") => self.check_raise(player, args).unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn payouts(&mut self) -> Vec<(String, i64)> {
+        std::mem::take(&mut self.owed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::cards::cards;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    /// A table where the given cards come off the deck in order: two per
+    /// player, then the flop, turn and river.
+    fn table(deck: &str, players: &[&str]) -> PokerGame {
+        let mut game = PokerGame::with_deck(Deck::stacked(cards(deck)));
+        for player in players {
+            assert_eq!(game.stake(player, &args("join")), ANTE);
+            game.input(player, &args("join"));
+        }
+        game
+    }
+
+    /// alice holds a pair of aces, bob a pair of kings, on a board that helps neither.
+    const ACES_VS_KINGS: &str = "As Ad Ks Kd 2c 7h 9s Jd 4c";
+    /// The same with carol holding a pair of sixes.
+    const THREE_HANDED: &str = "As Ad Ks Kd 6c 6h 2c 7h 9s Jd 4c";
+
+    #[test]
+    fn test_join_and_deal() {
+        let mut game = table(ACES_VS_KINGS, &["alice"]);
+        assert_eq!(game.stake("alice", &args("join")), 0);
+        assert_eq!(game.input("alice", &args("deal")).lines, vec!["Poker needs at least 2 players"]);
+        game.input("bob", &args("join"));
+        assert_eq!(game.input("carol", &args("deal")).lines, vec!["Join the table first: !poker join"]);
+
+        let reply = game.input("bob", &args("deal"));
+        assert_eq!(reply.lines, vec![
+            "🃏 Dealt to alice, bob. Pot 20. Your cards are sent by notice, or use !poker cards",
+            "Your move, alice: check, bet <n>, fold",
+        ]);
+        assert_eq!(reply.notices, vec![
+            ("alice".to_string(), "🃏 Your cards: A♠ A♦".to_string()),
+            ("bob".to_string(), "🃏 Your cards: K♠ K♦".to_string()),
+        ]);
+        assert!(!game.render().contains("A♠"));
+        assert_eq!(game.stake("carol", &args("join")), 0);
+        assert_eq!(game.input("bob", &args("cards")).notices, vec![("bob".to_string(), "🃏 Your cards: K♠ K♦".to_string())]);
+    }
+
+    #[test]
+    fn test_showdown() {
+        let mut game = table(ACES_VS_KINGS, &["alice", "bob"]);
+        game.input("alice", &args("deal"));
+        assert_eq!(game.input("bob", &args("check")).lines, vec!["It's alice's move"]);
+        game.input("alice", &args("check"));
+        let reply = game.input("bob", &args("check"));
+        assert_eq!(reply.lines, vec!["bob checks", "🂠 Flop: 2♣ 7♥ 9♠ (pot 20)", "Your move, alice: check, bet <n>, fold"]);
+
+        assert_eq!(game.stake("alice", &args("bet 30")), 30);
+        assert_eq!(game.input("alice", &args("bet 30")).lines, vec!["alice bets 30", "Your move, bob: call 30, raise <n>, fold"]);
+        assert_eq!(game.stake("bob", &args("call")), 30);
+        game.input("bob", &args("call"));
+        game.input("alice", &args("check"));
+        game.input("bob", &args("check"));
+        assert!(game.payouts().is_empty());
+        game.input("alice", &args("check"));
+        let reply = game.input("bob", &args("check"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines[1..], [
+            "alice shows A♠ A♦ — a pair",
+            "bob shows K♠ K♦ — a pair",
+            "🏁 alice wins 80 with a pair",
+        ]);
+        assert_eq!(game.payouts(), vec![("alice".to_string(), 80)]);
+    }
+
+    #[test]
+    fn test_raise_reopens_betting() {
+        let mut game = table(THREE_HANDED, &["alice", "bob", "carol"]);
+        game.input("alice", &args("deal"));
+        game.input("alice", &args("bet 10"));
+        assert_eq!(game.stake("bob", &args("raise 20")), 30);
+        assert_eq!(game.input("bob", &args("raise 20")).lines, vec!["bob raises to 30", "Your move, carol: call 30, raise <n>, fold"]);
+        game.input("carol", &args("fold"));
+        let reply = game.input("alice", &args("call"));
+        assert_eq!(reply.lines, vec!["alice calls 20", "🂠 Flop: 2♣ 7♥ 9♠ (pot 90)", "Your move, alice: check, bet <n>, fold"]);
+    }
+
+    #[test]
+    fn test_everyone_folds() {
+        let mut game = table(THREE_HANDED, &["alice", "bob", "carol"]);
+        game.input("alice", &args("deal"));
+        assert_eq!(game.input("alice", &args("call")).lines, vec!["There's nothing to call. Check or bet"]);
+        assert_eq!(game.stake("alice", &args("call")), 0);
+        assert_eq!(game.stake("alice", &args("bet 0")), 0);
+        game.input("alice", &args("bet 50"));
+        assert_eq!(game.input("bob", &args("check")).lines, vec!["You can't check, it's 50 to call"]);
+        game.input("bob", &args("fold"));
+        let reply = game.input("carol", &args("fold"));
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec!["carol folds", "🏁 alice wins 80"]);
+        assert_eq!(game.payouts(), vec![("alice".to_string(), 80)]);
+    }
+
+    #[test]
+    fn test_split_pot() {
+        // Both play the board's straight
+        let mut game = table("2s 3d 2h 3c 4s 4d 9s 10d Jc Qh Kd", &["alice", "bob", "carol"]);
+        game.input("carol", &args("deal"));
+        game.input("alice", &args("bet 1"));
+        game.input("bob", &args("call"));
+        game.input("carol", &args("fold"));
+        for _ in 0..3 {
+            game.input("alice", &args("check"));
+            game.input("bob", &args("check"));
+        }
+        assert_eq!(game.payouts(), vec![("alice".to_string(), 16), ("bob".to_string(), 16)]);
+    }
+
+    #[test]
+    fn test_turn_timeout() {
+        let mut game = table(ACES_VS_KINGS, &["alice", "bob"]);
+        assert!(game.turn_limit().is_none());
+        game.input("alice", &args("deal"));
+        assert!(game.turn_limit().is_some());
+        assert_eq!(game.turn_timeout().lines[0], "⌛ alice took too long and checks");
+        game.input("bob", &args("bet 5"));
+        let reply = game.turn_timeout();
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec!["⌛ alice took too long and folds", "🏁 bob wins 25"]);
+    }
+
+    #[test]
+    fn test_quit_refunds_chips() {
+        let mut game = table(ACES_VS_KINGS, &["alice", "bob"]);
+        assert_eq!(game.leave("carol", false).unwrap().lines, vec!["Only players at the table or game managers can end this game"]);
+        // Before the deal a player leaves alone and the table stays open
+        let reply = game.leave("alice", false).unwrap();
+        assert!(!reply.moved && !reply.finished);
+        assert_eq!(reply.lines, vec!["alice leaves the table and takes back their 10 chip ante (1 at the table)"]);
+        assert_eq!(game.payouts(), vec![("alice".to_string(), 10)]);
+        assert_eq!(game.stake("carol", &args("join")), ANTE);
+
+        // A manager away from the table ends it for everyone
+        assert!(game.leave("mod", true).is_none());
+        game.quit();
+        assert_eq!(game.payouts(), vec![("bob".to_string(), 10)]);
+    }
+
+    #[test]
+    fn test_quit_after_the_deal_folds() {
+        let mut game = table(THREE_HANDED, &["alice", "bob", "carol"]);
+        game.input("alice", &args("deal"));
+        game.input("alice", &args("bet 40"));
+        let reply = game.leave("carol", false).unwrap();
+        assert!(!reply.moved && !reply.finished);
+        assert_eq!(reply.lines, vec!["carol leaves the table and folds"]);
+        let reply = game.leave("alice", false).unwrap();
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec!["alice leaves the table and folds", "🏁 bob wins 70"]);
+        assert_eq!(game.payouts(), vec![("bob".to_string(), 70)]);
+    }
+
+    #[test]
+    fn test_manager_quit_shows_down() {
+        let mut game = table(ACES_VS_KINGS, &["alice", "bob"]);
+        game.input("alice", &args("deal"));
+        let reply = game.leave("mod", true).unwrap();
+        assert!(reply.finished);
+        assert_eq!(reply.lines[0], "mod ends the hand. The board is dealt out");
+        assert_eq!(reply.lines.last().unwrap(), "🏁 alice wins 20 with a pair");
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut game = table(ACES_VS_KINGS, &["alice", "bob"]);
+        game.input("alice", &args("deal"));
+        let restored: PokerGame = serde_json::from_value(game.save()).unwrap();
+        assert_eq!(restored.render(), game.render());
+    }
+}
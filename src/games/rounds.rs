@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use super::leaderboard::ranked;
+use crate::achievements::Event;
+
+/// A player's running total.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    /// Rounds this player won. Older saves call it `correct` or `solved`.
+    #[serde(alias = "correct", alias = "solved")]
+    pub won: u32,
+    pub points: u32,
+}
+
+/// The round, hint and score keeping shared by the games that ask the channel
+/// a series of questions and give hints on a timer: trivia and scramble.
+/// Games flatten it into their saves.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rounds {
+    /// Index of the round being played.
+    pub current: usize,
+    /// Hints shown in the current round.
+    pub hints: u32,
+    /// Seconds between hints.
+    hint_interval: u64,
+    scores: BTreeMap<String, Score>,
+    /// Points won since the last payout, which are paid out as chips.
+    #[serde(default)]
+    owed: Vec<(String, i64)>,
+    /// Achievement events not yet collected; see `Game::events`.
+    #[serde(skip)]
+    events: Vec<(String, Event)>,
+}
+
+impl Rounds {
+    pub fn new(hint_interval: Duration) -> Self {
+        Rounds {
+            current: 0,
+            hints: 0,
+            hint_interval: hint_interval.as_secs().max(1),
+            scores: BTreeMap::new(),
+            owed: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Credits `player` with winning the round, owing them `points` as chips.
+    pub fn win(&mut self, player: &str, points: u32, event: Event) {
+        let score = self.scores.entry(player.to_string()).or_default();
+        score.won += 1;
+        score.points += points;
+        self.owed.push((player.to_string(), points as i64));
+        self.events.push((player.to_string(), event));
+    }
+
+    /// Moves to the next of `total` rounds. Returns false once they're all played.
+    pub fn advance(&mut self, total: usize) -> bool {
+        self.current += 1;
+        self.hints = 0;
+        self.current < total
+    }
+
+    /// Uses up a hint if fewer than `max` have been given this round.
+    pub fn take_hint(&mut self, max: u32) -> bool {
+        if self.hints >= max {
+            return false;
+        }
+        self.hints += 1;
+        true
+    }
+
+    /// How long a round goes before the next hint.
+    pub fn hint_interval(&self) -> Duration {
+        Duration::from_secs(self.hint_interval)
+    }
+
+    /// "`prefix` Scores: 1. alice (7), 2. bob (3)", best first.
+    pub fn standings(&self, prefix: &str) -> String {
+        let mut scores: Vec<(String, u32)> = self.scores.iter()
+            .map(|(name, score)| (name.clone(), score.points))
+            .collect();
+        if scores.is_empty() {
+            return format!("{} Nobody scored.", prefix);
+        }
+        scores.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let entries: Vec<String> = ranked(&scores).into_iter()
+            .map(|(rank, name, points)| format!("{}. {} ({})", rank, name, points))
+            .collect();
+        format!("{} Scores: {}", prefix, entries.join(", "))
+    }
+
+    /// Every player who scored, by name.
+    pub fn scores(&self) -> impl Iterator<Item = (&String, &Score)> {
+        self.scores.iter()
+    }
+
+    pub fn payouts(&mut self) -> Vec<(String, i64)> {
+        std::mem::take(&mut self.owed)
+    }
+
+    pub fn events(&mut self) -> Vec<(String, Event)> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints_and_rounds() {
+        let mut rounds = Rounds::new(Duration::ZERO);
+        assert_eq!(rounds.hint_interval(), Duration::from_secs(1));
+        assert!(rounds.take_hint(1));
+        assert!(!rounds.take_hint(1));
+        assert!(rounds.advance(2));
+        assert_eq!((rounds.current, rounds.hints), (1, 0));
+        assert!(!rounds.advance(2));
+    }
+
+    #[test]
+    fn test_wins_are_scored_and_paid_once() {
+        let mut rounds = Rounds::new(Duration::from_secs(10));
+        assert_eq!(rounds.standings("🏁"), "🏁 Nobody scored.");
+        rounds.win("bob", 3, Event::TriviaAnswered);
+        rounds.win("alice", 3, Event::TriviaAnswered);
+        rounds.win("carol", 5, Event::TriviaAnswered);
+        assert_eq!(rounds.standings("📊"), "📊 Scores: 1. carol (5), 2. alice (3), 2. bob (3)");
+        assert_eq!(rounds.payouts().len(), 3);
+        assert!(rounds.payouts().is_empty());
+        assert_eq!(rounds.events().len(), 3);
+    }
+
+    #[test]
+    fn test_older_score_names_load() {
+        let trivia: Score = serde_json::from_str(r#"{"correct":2,"points":5}"#).unwrap();
+        let scramble: Score = serde_json::from_str(r#"{"solved":1,"points":4}"#).unwrap();
+        assert_eq!(trivia, Score { won: 2, points: 5 });
+        assert_eq!(scramble, Score { won: 1, points: 4 });
+    }
+}
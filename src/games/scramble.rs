@@ -0,0 +1,314 @@
+use std::time::Duration;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use super::{fuzzy, Game, Reply};
+use super::hangman::{Difficulty, WORDS};
+use super::rounds::Rounds;
+use crate::achievements::Event;
+use crate::database::{Database, ScrambleResult, TournamentRound};
+use crate::settings::Settings;
+
+/// Hints given per word before it is revealed.
+const MAX_HINTS: u32 = 2;
+
+/// Shuffles tried before settling for a scramble that equals the word.
+const SHUFFLE_ATTEMPTS: usize = 10;
+
+pub const DEFAULT_ROUNDS: usize = 5;
+pub const DEFAULT_HINT_INTERVAL: Duration = Duration::from_secs(20);
+pub const MAX_ROUNDS: usize = 20;
+
+/// Shuffles the letters of each word in `text`, keeping spaces and
+/// punctuation in place, and tries not to return `text` unchanged.
+pub fn scramble<R: Rng>(text: &str, rng: &mut R) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let positions: Vec<usize> = (0..chars.len()).filter(|&i| chars[i].is_alphabetic()).collect();
+    let mut scrambled = chars.clone();
+    for _ in 0..SHUFFLE_ATTEMPTS {
+        for word in positions.chunk_by(|a, b| b - a == 1) {
+            let mut letters: Vec<char> = word.iter().map(|&i| chars[i]).collect();
+            letters.shuffle(rng);
+            for (&i, letter) in word.iter().zip(letters) {
+                scrambled[i] = letter;
+            }
+        }
+        if scrambled != chars {
+            break;
+        }
+    }
+    scrambled.into_iter().collect()
+}
+
+/// What `!scramble start` asks for.
+#[derive(Clone, Debug)]
+pub struct ScrambleOptions {
+    pub rounds: usize,
+    pub category: Option<String>,
+    pub difficulty: Option<Difficulty>,
+}
+
+impl Default for ScrambleOptions {
+    fn default() -> Self {
+        ScrambleOptions { rounds: DEFAULT_ROUNDS, category: None, difficulty: None }
+    }
+}
+
+/// A game with words from the hangman word lists. Returns the message to
+/// show if no word fits `options`.
+pub fn new_scramble_game(db: &Database, settings: &Settings, options: &ScrambleOptions) -> Result<ScrambleGame, String> {
+    let (category, difficulty) = (options.category.as_deref(), options.difficulty.map(Difficulty::as_str));
+    let words = match db.random_hangman_words(category, difficulty, options.rounds) {
+        // Only fall back to the built-in words when nothing in particular was asked for
+        Ok(words) if words.is_empty() && category.is_none() && difficulty.is_none() => {
+            (0..options.rounds).map(|_| WORDS[rand::random::<usize>() % WORDS.len()].to_string()).collect()
+        }
+        Ok(words) if words.is_empty() => return Err("There are no words like that. See !hangman categories".to_string()),
+        Ok(words) => words,
+        Err(e) => return Err(format!("Error picking words: {}", e)),
+    };
+    Ok(ScrambleGame::new(&words, settings.scramble_hint_interval))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Round {
+    word: String,
+    scrambled: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScrambleGame {
+    rounds: Vec<Round>,
+    /// Which word is up, hints and scores.
+    #[serde(flatten)]
+    progress: Rounds,
+    /// The tournament round this game is played for, if any.
+    #[serde(default)]
+    tournament: Option<TournamentRound>,
+}
+
+impl ScrambleGame {
+    /// A game with one round per word, with a hint every `hint_interval`.
+    pub fn new(words: &[String], hint_interval: Duration) -> Self {
+        let mut rng = rand::thread_rng();
+        let rounds = words.iter()
+            .map(|word| Round { word: word.to_lowercase(), scrambled: scramble(&word.to_lowercase(), &mut rng) })
+            .collect();
+        ScrambleGame {
+            rounds,
+            progress: Rounds::new(hint_interval),
+            tournament: None,
+        }
+    }
+
+    fn round(&self) -> &Round {
+        &self.rounds[self.progress.current]
+    }
+
+    fn ask(&self) -> String {
+        format!("🔀 Word {}/{}: {}", self.progress.current + 1, self.rounds.len(), self.round().scrambled.to_uppercase())
+    }
+
+    /// The word with its first letters shown; each hint reveals about a third more.
+    pub fn hint(&self) -> String {
+        let word = &self.round().word;
+        let letters = word.chars().filter(|c| c.is_alphabetic()).count();
+        let shown = (letters * self.progress.hints as usize).div_ceil(MAX_HINTS as usize + 1);
+        let mut letter = 0;
+        word.chars()
+            .map(|c| {
+                if !c.is_alphabetic() {
+                    return c;
+                }
+                letter += 1;
+                if letter <= shown { c } else { '_' }
+            })
+            .collect()
+    }
+
+    /// Letters in the word, less a third for each hint used.
+    fn points(&self) -> u32 {
+        let letters = self.round().word.chars().filter(|c| c.is_alphabetic()).count() as u32;
+        (letters * (MAX_HINTS + 1 - self.progress.hints)).div_ceil(MAX_HINTS + 1)
+    }
+
+    /// Moves on after `lines`, finishing the game after the last word.
+    fn next(&mut self, mut lines: Vec<String>) -> Reply {
+        if !self.progress.advance(self.rounds.len()) {
+            lines.push(self.progress.standings("🏁 Scramble over!"));
+            return Reply::finished(lines);
+        }
+        lines.push(self.ask());
+        Reply::new(lines)
+    }
+
+    pub fn results(&self) -> Vec<ScrambleResult> {
+        self.progress.scores()
+            .map(|(username, score)| ScrambleResult {
+                username: username.clone(),
+                solved: score.won,
+                points: score.points,
+            })
+            .collect()
+    }
+}
+
+impl Game for ScrambleGame {
+    fn kind(&self) -> &'static str {
+        "scramble"
+    }
+
+    fn start(&mut self) -> Vec<String> {
+        vec![
+            format!("🎮 Scramble! Unscramble {} words; the first to type the answer in the channel scores.", self.rounds.len()),
+            self.ask(),
+        ]
+    }
+
+    fn input(&mut self, _player: &str, _args: &[String]) -> Reply {
+        Reply::refused(vec!["Type the unscrambled word in the channel. Use: start, status, quit, top".to_string()])
+    }
+
+    fn chat(&mut self, player: &str, text: &str) -> Option<Reply> {
+        // Anagrams have to be exact: a near miss is usually a different word
+        if fuzzy::normalize(text) != fuzzy::normalize(&self.round().word) {
+            return None;
+        }
+        let points = self.points();
+        let line = format!("✅ {} unscrambled it: {} (+{} points)", player, self.round().word, points);
+        self.progress.win(player, points, Event::ScrambleSolved);
+        Some(self.next(vec![line]))
+    }
+
+    fn render(&self) -> String {
+        self.ask()
+    }
+
+    fn status(&self) -> Vec<String> {
+        let mut lines = vec![self.ask()];
+        if self.progress.hints > 0 {
+            lines.push(format!("💡 Hint: {}", self.hint()));
+        }
+        lines.push(self.progress.standings("📊"));
+        lines
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).expect("scramble state is always serializable")
+    }
+
+    fn record(&self, db: &Database, channel: &str) -> Result<(), rusqlite::Error> {
+        db.record_scramble_results(channel, self.tournament, &self.results())
+    }
+
+    fn timeout(&mut self) -> Vec<String> {
+        vec![
+            format!("⏰ Scramble timed out. The word was: {}", self.round().word),
+            self.progress.standings("🏁"),
+        ]
+    }
+
+    fn turn_limit(&self) -> Option<Duration> {
+        Some(self.progress.hint_interval())
+    }
+
+    fn turn_timeout(&mut self) -> Reply {
+        if self.progress.take_hint(MAX_HINTS) {
+            return Reply::new(vec![format!("💡 Hint: {}", self.hint())]);
+        }
+        let line = format!("⌛ Time's up! The word was: {}", self.round().word);
+        self.next(vec![line])
+    }
+
+    fn payouts(&mut self) -> Vec<(String, i64)> {
+        self.progress.payouts()
+    }
+
+    fn events(&mut self) -> Vec<(String, Event)> {
+        self.progress.events()
+    }
+
+    fn tournament_round(&self) -> Option<TournamentRound> {
+        self.tournament
+    }
+
+    fn set_tournament_round(&mut self, round: Option<TournamentRound>) {
+        self.tournament = round;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn game(words: &[&str]) -> ScrambleGame {
+        let words: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+        ScrambleGame::new(&words, Duration::from_secs(20))
+    }
+
+    fn sorted(text: &str) -> Vec<char> {
+        let mut chars: Vec<char> = text.chars().collect();
+        chars.sort();
+        chars
+    }
+
+    #[test]
+    fn test_scramble_keeps_letters() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for word in ["programming", "rust", "café", "ice cream"] {
+            let scrambled = scramble(word, &mut rng);
+            assert_ne!(scrambled, word);
+            assert_eq!(sorted(&scrambled), sorted(word));
+        }
+        // Words stay in place within a phrase
+        let scrambled = scramble("ice cream", &mut rng);
+        assert_eq!(scrambled.find(' '), Some(3));
+        assert_eq!(sorted(&scrambled[..3]), sorted("ice"));
+        // Nothing to shuffle
+        assert_eq!(scramble("aaa", &mut rng), "aaa");
+    }
+
+    #[test]
+    fn test_hints() {
+        let mut game = game(&["network"]);
+        assert_eq!(game.hint(), "_______");
+        assert_eq!(game.turn_timeout().lines, vec!["💡 Hint: net____"]);
+        assert_eq!(game.turn_timeout().lines, vec!["💡 Hint: netwo__"]);
+        let reply = game.turn_timeout();
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec!["⌛ Time's up! The word was: network", "🏁 Scramble over! Nobody scored."]);
+    }
+
+    #[test]
+    fn test_first_exact_answer_scores() {
+        let mut game = game(&["rust", "server"]);
+        assert!(game.chat("alice", "rsut").is_none());
+        assert!(game.chat("alice", "rusty").is_none());
+        let reply = game.chat("bob", "RUST").unwrap();
+        assert_eq!(reply.lines[0], "✅ bob unscrambled it: rust (+4 points)");
+        assert!(reply.lines[1].starts_with("🔀 Word 2/2: "));
+
+        game.turn_timeout();
+        let reply = game.chat("alice", "server").unwrap();
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec![
+            "✅ alice unscrambled it: server (+4 points)",
+            "🏁 Scramble over! Scores: 1. alice (4), 1. bob (4)",
+        ]);
+        assert_eq!(game.results().len(), 2);
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut game = game(&["rust", "server"]);
+        game.turn_timeout();
+        game.set_tournament_round(Some(TournamentRound { tournament: 3, round: 2 }));
+        let restored: ScrambleGame = serde_json::from_value(game.save()).unwrap();
+        assert_eq!(restored.status(), game.status());
+        assert_eq!(restored.tournament_round(), Some(TournamentRound { tournament: 3, round: 2 }));
+        assert_eq!(restored.round().scrambled, game.round().scrambled);
+    }
+}
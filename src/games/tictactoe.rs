@@ -0,0 +1,134 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use super::board::{colour, Outcome, Rules, BLUE, RED};
+
+const LINES: [[usize; 3]; 8] = [
+    [0, 1, 2], [3, 4, 5], [6, 7, 8],
+    [0, 3, 6], [1, 4, 7], [2, 5, 8],
+    [0, 4, 8], [2, 4, 6],
+];
+
+/// Tic-tac-toe on a 3×3 board. Squares are named like a chessboard, `a1`
+/// to `c3` with `a1` in the bottom left.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TicTacToe {
+    /// The side on each square, row by row from `a3` to `c1`.
+    cells: [Option<usize>; 9],
+}
+
+impl TicTacToe {
+    /// The cell index of a square like "b2".
+    fn square(text: &str) -> Option<usize> {
+        let mut chars = text.chars();
+        let file = match chars.next()?.to_ascii_lowercase() {
+            c @ 'a'..='c' => c as usize - 'a' as usize,
+            _ => return None,
+        };
+        let rank = match chars.next()? {
+            c @ '1'..='3' => c as usize - '1' as usize,
+            _ => return None,
+        };
+        chars.next().is_none().then_some((2 - rank) * 3 + file)
+    }
+
+    fn mark(side: Option<usize>) -> String {
+        match side {
+            Some(0) => colour(RED, "X"),
+            Some(_) => colour(BLUE, "O"),
+            None => ".".to_string(),
+        }
+    }
+}
+
+impl Rules for TicTacToe {
+    const KIND: &'static str = "ttt";
+    const NAME: &'static str = "tic-tac-toe";
+    const MOVE_HELP: &'static str = "<a1-c3>";
+    const MOVE_LIMIT: Duration = Duration::from_secs(2 * 60);
+    const SIDES: [&'static str; 2] = ["X", "O"];
+
+    fn play(&mut self, side: usize, mv: &str) -> Result<(String, Outcome), String> {
+        let square = TicTacToe::square(mv).ok_or_else(|| format!("{} isn't a square. Use a1 to c3", mv))?;
+        if self.cells[square].is_some() {
+            return Err(format!("{} is taken", mv.to_lowercase()));
+        }
+        self.cells[square] = Some(side);
+
+        let won = LINES.iter().any(|line| line.iter().all(|&i| self.cells[i] == Some(side)));
+        let outcome = if won {
+            Outcome::Won
+        } else if self.cells.iter().all(Option::is_some) {
+            Outcome::Drawn("the board is full")
+        } else {
+            Outcome::Continue
+        };
+        Ok((mv.to_lowercase(), outcome))
+    }
+
+    fn board(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.cells.chunks(3).enumerate()
+            .map(|(row, cells)| {
+                let marks: Vec<String> = cells.iter().map(|&c| TicTacToe::mark(c)).collect();
+                format!("{} {}", 3 - row, marks.join(" "))
+            })
+            .collect();
+        lines.push("  a b c".to_string());
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game: &mut TicTacToe, moves: &[&str]) -> Outcome {
+        let mut outcome = Outcome::Continue;
+        for (i, mv) in moves.iter().enumerate() {
+            outcome = game.play(i % 2, mv).unwrap().1;
+        }
+        outcome
+    }
+
+    #[test]
+    fn test_squares() {
+        assert_eq!(TicTacToe::square("a3"), Some(0));
+        assert_eq!(TicTacToe::square("C1"), Some(8));
+        assert_eq!(TicTacToe::square("d1"), None);
+        assert_eq!(TicTacToe::square("a4"), None);
+        assert_eq!(TicTacToe::square("a11"), None);
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let mut game = TicTacToe::default();
+        assert_eq!(game.play(0, "z9").unwrap_err(), "z9 isn't a square. Use a1 to c3");
+        game.play(0, "b2").unwrap();
+        assert_eq!(game.play(1, "B2").unwrap_err(), "b2 is taken");
+    }
+
+    #[test]
+    fn test_win() {
+        let mut game = TicTacToe::default();
+        assert_eq!(play(&mut game, &["a1", "a2", "b2", "b1"]), Outcome::Continue);
+        assert_eq!(game.play(0, "c3").unwrap(), ("c3".to_string(), Outcome::Won));
+    }
+
+    #[test]
+    fn test_full_board_is_a_draw() {
+        let mut game = TicTacToe::default();
+        let outcome = play(&mut game, &["a3", "b2", "c3", "b3", "b1", "c2", "a2", "a1", "c1"]);
+        assert_eq!(outcome, Outcome::Drawn("the board is full"));
+    }
+
+    #[test]
+    fn test_board() {
+        let mut game = TicTacToe::default();
+        play(&mut game, &["a1", "b2"]);
+        assert_eq!(game.board(), vec![
+            "3 . . .".to_string(),
+            format!("2 . {} .", colour(BLUE, "O")),
+            format!("1 {} . .", colour(RED, "X")),
+            "  a b c".to_string(),
+        ]);
+    }
+}
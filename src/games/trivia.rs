@@ -0,0 +1,522 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use super::{fuzzy, Game, Reply};
+use super::rounds::Rounds;
+use crate::achievements::Event;
+use crate::database::{Database, TriviaResult, TournamentRound};
+use crate::settings::Settings;
+
+/// Hints given per question before the answer is revealed.
+const MAX_HINTS: u32 = 3;
+
+pub const DEFAULT_ROUNDS: usize = 10;
+pub const DEFAULT_HINT_INTERVAL: Duration = Duration::from_secs(15);
+pub const MAX_ROUNDS: usize = 50;
+
+/// Questions used when no pack has been loaded: question, answers, category, difficulty.
+const BUILTIN: &[(&str, &[&str], &str, &str)] = &[
+    ("Which planet is the largest in the solar system?", &["Jupiter"], "science", "easy"),
+    ("What is the chemical symbol for gold?", &["Au"], "science", "easy"),
+    ("How many bits are in a byte?", &["8", "eight"], "tech", "easy"),
+    ("Which language's compiler is called rustc?", &["Rust"], "tech", "easy"),
+    ("What does the H in HTTP stand for?", &["Hypertext", "HyperText"], "tech", "medium"),
+    ("In what year was the first IRC server started?", &["1988"], "tech", "hard"),
+    ("What is the capital of Australia?", &["Canberra"], "geography", "medium"),
+    ("Which river flows through Cairo?", &["Nile", "The Nile"], "geography", "easy"),
+    ("What is the longest river in South America?", &["Amazon", "The Amazon"], "geography", "easy"),
+    ("Which element has atomic number 1?", &["Hydrogen"], "science", "easy"),
+    ("Who wrote 'Pride and Prejudice'?", &["Jane Austen", "Austen"], "literature", "medium"),
+    ("What is the smallest prime number?", &["2", "two"], "science", "easy"),
+];
+
+/// One question from a pack.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Question {
+    pub question: String,
+    /// Accepted answers; the first is shown when revealing.
+    pub answers: Vec<String>,
+    pub category: String,
+    /// "easy", "medium" or "hard"; unrated questions count as easy.
+    #[serde(default)]
+    pub difficulty: Option<String>,
+}
+
+impl Question {
+    /// Points for a right answer: more for harder questions, fewer for each hint used.
+    fn points(&self, hints: u32) -> u32 {
+        let weight = match self.difficulty.as_deref() {
+            Some("hard") => 3,
+            Some("medium") => 2,
+            _ => 1,
+        };
+        weight * (MAX_HINTS + 1 - hints)
+    }
+
+    /// Checks a parsed question and tidies its category and difficulty.
+    fn validated(mut self) -> Result<Self, String> {
+        self.question = self.question.trim().to_string();
+        self.answers.retain(|a| !fuzzy::normalize(a).is_empty());
+        self.category = self.category.trim().to_lowercase();
+        self.difficulty = self.difficulty.map(|d| d.trim().to_lowercase()).filter(|d| !d.is_empty());
+        if self.question.is_empty() {
+            return Err("empty question".to_string());
+        }
+        if self.answers.is_empty() {
+            return Err(format!("no answers for \"{}\"", self.question));
+        }
+        if self.category.is_empty() {
+            return Err(format!("no category for \"{}\"", self.question));
+        }
+        if let Some(d) = &self.difficulty {
+            if !matches!(d.as_str(), "easy" | "medium" | "hard") {
+                return Err(format!("unknown difficulty \"{}\" for \"{}\"", d, self.question));
+            }
+        }
+        Ok(self)
+    }
+}
+
+pub fn builtin_questions() -> Vec<Question> {
+    BUILTIN.iter()
+        .map(|(question, answers, category, difficulty)| Question {
+            question: question.to_string(),
+            answers: answers.iter().map(|a| a.to_string()).collect(),
+            category: category.to_string(),
+            difficulty: Some(difficulty.to_string()),
+        })
+        .collect()
+}
+
+/// Parses a JSON pack: an array of question objects.
+pub fn parse_json(text: &str) -> Result<Vec<Question>, String> {
+    let questions: Vec<Question> = serde_json::from_str(text).map_err(|e| format!("invalid JSON: {}", e))?;
+    questions.into_iter().map(Question::validated).collect()
+}
+
+/// Parses a CSV pack with the columns `question,answers,category,difficulty`.
+/// Answers are separated by `|`, difficulty may be empty and a header row is
+/// optional. Fields containing commas are quoted, with `""` for a quote.
+pub fn parse_csv(text: &str) -> Result<Vec<Question>, String> {
+    let mut questions = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(line).ok_or_else(|| format!("line {}: unbalanced quotes", number + 1))?;
+        if number == 0 && fields.first().map(|f| f.trim().eq_ignore_ascii_case("question")) == Some(true) {
+            continue;
+        }
+        let [question, answers, category, rest @ ..] = fields.as_slice() else {
+            return Err(format!("line {}: expected question,answers,category[,difficulty]", number + 1));
+        };
+        let question = Question {
+            question: question.clone(),
+            answers: answers.split('|').map(|a| a.trim().to_string()).collect(),
+            category: category.clone(),
+            difficulty: rest.first().cloned(),
+        };
+        questions.push(question.validated().map_err(|e| format!("line {}: {}", number + 1, e))?);
+    }
+    Ok(questions)
+}
+
+/// Splits one CSV line into fields, or `None` if a quote is left open.
+fn split_csv_line(line: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+/// Loads every `.json` and `.csv` pack in `dir`. Returns the questions and a
+/// description of each file that couldn't be read. A missing directory holds no packs.
+pub fn load_packs(dir: &Path) -> (Vec<Question>, Vec<String>) {
+    let mut questions = Vec::new();
+    let mut errors = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (questions, errors);
+    };
+    let mut paths: Vec<_> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    paths.sort();
+    for path in paths {
+        let parse = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => parse_json,
+            Some("csv") => parse_csv,
+            _ => continue,
+        };
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| parse(&text)) {
+            Ok(pack) => questions.extend(pack),
+            Err(e) => errors.push(format!("{}: {}", name, e)),
+        }
+    }
+    (questions, errors)
+}
+
+/// Up to `rounds` random questions, optionally from one category.
+pub fn pick(mut pool: Vec<Question>, category: Option<&str>, rounds: usize) -> Vec<Question> {
+    if let Some(category) = category {
+        pool.retain(|q| q.category == category);
+    }
+    pool.shuffle(&mut rand::thread_rng());
+    pool.truncate(rounds);
+    pool
+}
+
+/// What `!trivia start` asks for.
+#[derive(Clone, Debug)]
+pub struct TriviaOptions {
+    pub rounds: usize,
+    pub category: Option<String>,
+}
+
+impl Default for TriviaOptions {
+    fn default() -> Self {
+        TriviaOptions { rounds: DEFAULT_ROUNDS, category: None }
+    }
+}
+
+/// A game with questions from the packs in `Settings::trivia_dir`, or the
+/// built-in ones if there are none. Returns the message to show if no
+/// question fits `options`.
+pub fn new_trivia_game(settings: &Settings, options: &TriviaOptions) -> Result<TriviaGame, String> {
+    // Packs are read on every start, so new files are picked up without a restart
+    let (mut pool, errors) = load_packs(Path::new(&settings.trivia_dir));
+    for error in errors {
+        eprintln!("Skipping trivia pack {}", error);
+    }
+    if pool.is_empty() {
+        pool = builtin_questions();
+    }
+    let mut categories: Vec<String> = pool.iter().map(|q| q.category.clone()).collect();
+    categories.sort();
+    categories.dedup();
+
+    let questions = pick(pool, options.category.as_deref(), options.rounds);
+    if questions.is_empty() {
+        return Err(format!("No questions in that category. Categories: {}", categories.join(", ")));
+    }
+    Ok(TriviaGame::new(questions, settings.trivia_hint_interval))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TriviaGame {
+    questions: Vec<Question>,
+    /// Which question is being asked, hints and scores.
+    #[serde(flatten)]
+    progress: Rounds,
+    /// The tournament round this game is played for, if any.
+    #[serde(default)]
+    tournament: Option<TournamentRound>,
+}
+
+impl TriviaGame {
+    /// A game asking `questions` in order, with a hint every `hint_interval`.
+    pub fn new(questions: Vec<Question>, hint_interval: Duration) -> Self {
+        TriviaGame {
+            questions,
+            progress: Rounds::new(hint_interval),
+            tournament: None,
+        }
+    }
+
+    fn question(&self) -> &Question {
+        &self.questions[self.progress.current]
+    }
+
+    fn ask(&self) -> String {
+        let q = self.question();
+        format!("❓ Q{}/{} [{}]: {}", self.progress.current + 1, self.questions.len(), q.category, q.question)
+    }
+
+    /// The first answer with letters hidden; each hint reveals about a quarter more.
+    pub fn hint(&self) -> String {
+        let mut letter = 0;
+        self.question().answers[0]
+            .chars()
+            .map(|c| {
+                if !c.is_alphanumeric() {
+                    return c;
+                }
+                let shown = (letter % (MAX_HINTS as usize + 1)) < self.progress.hints as usize;
+                letter += 1;
+                if shown { c } else { '_' }
+            })
+            .collect()
+    }
+
+    /// Moves on after `lines`, finishing the game after the last question.
+    fn next(&mut self, mut lines: Vec<String>) -> Reply {
+        if !self.progress.advance(self.questions.len()) {
+            lines.push(self.progress.standings("🏁 Trivia over!"));
+            return Reply::finished(lines);
+        }
+        lines.push(self.ask());
+        Reply::new(lines)
+    }
+
+    pub fn results(&self) -> Vec<TriviaResult> {
+        self.progress.scores()
+            .map(|(username, score)| TriviaResult {
+                username: username.clone(),
+                correct: score.won,
+                points: score.points,
+            })
+            .collect()
+    }
+}
+
+impl Game for TriviaGame {
+    fn kind(&self) -> &'static str {
+        "trivia"
+    }
+
+    fn start(&mut self) -> Vec<String> {
+        vec![
+            format!("🧠 Trivia! {} questions. Type your answers in the channel.", self.questions.len()),
+            self.ask(),
+        ]
+    }
+
+    fn input(&mut self, _player: &str, _args: &[String]) -> Reply {
+        Reply::refused(vec!["Type your answer in the channel. Use: start, status, quit, top".to_string()])
+    }
+
+    fn chat(&mut self, player: &str, text: &str) -> Option<Reply> {
+        let question = self.question();
+        if !question.answers.iter().any(|answer| fuzzy::is_match(text, answer)) {
+            return None;
+        }
+        let points = question.points(self.progress.hints);
+        let line = format!("✅ {} got it: {} (+{} points)", player, question.answers[0], points);
+        self.progress.win(player, points, Event::TriviaAnswered);
+        Some(self.next(vec![line]))
+    }
+
+    fn render(&self) -> String {
+        self.ask()
+    }
+
+    fn status(&self) -> Vec<String> {
+        let mut lines = vec![self.ask()];
+        if self.progress.hints > 0 {
+            lines.push(format!("💡 Hint: {}", self.hint()));
+        }
+        lines.push(self.progress.standings("📊"));
+        lines
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).expect("trivia state is always serializable")
+    }
+
+    fn record(&self, db: &Database, channel: &str) -> Result<(), rusqlite::Error> {
+        db.record_trivia_results(channel, self.tournament, &self.results())
+    }
+
+    fn timeout(&mut self) -> Vec<String> {
+        vec![
+            format!("⏰ Trivia timed out. The answer was: {}", self.question().answers[0]),
+            self.progress.standings("🏁"),
+        ]
+    }
+
+    fn turn_limit(&self) -> Option<Duration> {
+        Some(self.progress.hint_interval())
+    }
+
+    fn turn_timeout(&mut self) -> Reply {
+        if self.progress.take_hint(MAX_HINTS) {
+            return Reply::new(vec![format!("💡 Hint: {}", self.hint())]);
+        }
+        let line = format!("⌛ Time's up! The answer was: {}", self.question().answers[0]);
+        self.next(vec![line])
+    }
+
+    fn payouts(&mut self) -> Vec<(String, i64)> {
+        self.progress.payouts()
+    }
+
+    fn events(&mut self) -> Vec<(String, Event)> {
+        self.progress.events()
+    }
+
+    fn tournament_round(&self) -> Option<TournamentRound> {
+        self.tournament
+    }
+
+    fn set_tournament_round(&mut self, round: Option<TournamentRound>) {
+        self.tournament = round;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question(text: &str, answers: &[&str], difficulty: Option<&str>) -> Question {
+        Question {
+            question: text.to_string(),
+            answers: answers.iter().map(|a| a.to_string()).collect(),
+            category: "test".to_string(),
+            difficulty: difficulty.map(str::to_string),
+        }
+    }
+
+    fn game() -> TriviaGame {
+        TriviaGame::new(vec![
+            question("Largest planet?", &["Jupiter"], None),
+            question("Capital of Australia?", &["Canberra"], Some("hard")),
+        ], Duration::from_secs(15))
+    }
+
+    #[test]
+    fn test_builtin_questions_are_valid() {
+        for question in builtin_questions() {
+            assert_eq!(question.clone().validated(), Ok(question));
+        }
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let pack = r#"[
+            {"question": "2 + 2?", "answers": ["4", "four"], "category": "Maths", "difficulty": "Easy"},
+            {"question": "Pi to 2 places?", "answers": ["3.14"], "category": "maths"}
+        ]"#;
+        let questions = parse_json(pack).unwrap();
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[0].category, "maths");
+        assert_eq!(questions[0].difficulty.as_deref(), Some("easy"));
+        assert_eq!(questions[1].difficulty, None);
+
+        assert!(parse_json(r#"[{"question": "?", "answers": [], "category": "x"}]"#).unwrap_err().contains("no answers"));
+        assert!(parse_json(r#"[{"question": "?", "answers": ["a"], "category": "x", "difficulty": "brutal"}]"#).is_err());
+        assert!(parse_json("{").unwrap_err().starts_with("invalid JSON"));
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let pack = "question,answers,category,difficulty\n\
+                    \"Who said \"\"hello, world\"\"?\",K&R|Kernighan,tech,medium\n\
+                    \n\
+                    Largest ocean?,Pacific|The Pacific,geography,\n";
+        let questions = parse_csv(pack).unwrap();
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[0].question, "Who said \"hello, world\"?");
+        assert_eq!(questions[0].answers, vec!["K&R", "Kernighan"]);
+        assert_eq!(questions[1].difficulty, None);
+
+        assert_eq!(parse_csv("only,two\n").unwrap_err(), "line 1: expected question,answers,category[,difficulty]");
+        assert_eq!(parse_csv("\"open,a,b\n").unwrap_err(), "line 1: unbalanced quotes");
+    }
+
+    #[test]
+    fn test_load_packs() {
+        let dir = std::env::temp_dir().join(format!("trivia-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.json"), r#"[{"question": "Q1", "answers": ["a"], "category": "x"}]"#).unwrap();
+        fs::write(dir.join("b.csv"), "Q2,b,y,hard\n").unwrap();
+        fs::write(dir.join("c.csv"), "broken\n").unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let (questions, errors) = load_packs(&dir);
+        assert_eq!(questions.iter().map(|q| q.question.as_str()).collect::<Vec<_>>(), vec!["Q1", "Q2"]);
+        assert_eq!(errors, vec!["c.csv: line 1: expected question,answers,category[,difficulty]"]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(load_packs(&dir), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn test_pick() {
+        let pool = builtin_questions();
+        assert_eq!(pick(pool.clone(), None, 5).len(), 5);
+        let science = pick(pool.clone(), Some("science"), 50);
+        assert!(!science.is_empty());
+        assert!(science.iter().all(|q| q.category == "science"));
+        assert!(pick(pool, Some("cooking"), 5).is_empty());
+    }
+
+    #[test]
+    fn test_hints_reveal_progressively() {
+        let mut game = game();
+        assert_eq!(game.hint(), "_______");
+        assert_eq!(game.turn_timeout().lines, vec!["💡 Hint: J___t__"]);
+        assert_eq!(game.turn_timeout().lines, vec!["💡 Hint: Ju__te_"]);
+        assert_eq!(game.turn_timeout().lines, vec!["💡 Hint: Jup_ter"]);
+
+        let reply = game.turn_timeout();
+        assert!(!reply.finished);
+        assert_eq!(reply.lines, vec![
+            "⌛ Time's up! The answer was: Jupiter",
+            "❓ Q2/2 [test]: Capital of Australia?",
+        ]);
+        assert_eq!(game.progress.hints, 0);
+    }
+
+    #[test]
+    fn test_fuzzy_answers_score() {
+        let mut game = game();
+        assert!(game.chat("alice", "saturn").is_none());
+        let reply = game.chat("bob", "jupitr").unwrap();
+        assert_eq!(reply.lines[0], "✅ bob got it: Jupiter (+4 points)");
+        assert_eq!(game.payouts(), vec![("bob".to_string(), 4)]);
+        assert!(game.payouts().is_empty());
+
+        game.turn_timeout();
+        let reply = game.chat("alice", "Canbera!").unwrap();
+        assert!(reply.finished);
+        assert_eq!(reply.lines, vec![
+            "✅ alice got it: Canberra (+9 points)",
+            "🏁 Trivia over! Scores: 1. alice (9), 2. bob (4)",
+        ]);
+        assert_eq!(game.results(), vec![
+            TriviaResult { username: "alice".to_string(), correct: 1, points: 9 },
+            TriviaResult { username: "bob".to_string(), correct: 1, points: 4 },
+        ]);
+    }
+
+    #[test]
+    fn test_game_without_answers() {
+        let mut game = game();
+        for _ in 0..=MAX_HINTS {
+            game.turn_timeout();
+        }
+        for _ in 0..MAX_HINTS {
+            game.turn_timeout();
+        }
+        let reply = game.turn_timeout();
+        assert!(reply.finished);
+        assert_eq!(reply.lines[1], "🏁 Trivia over! Nobody scored.");
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut game = game();
+        game.chat("bob", "Jupiter");
+        game.turn_timeout();
+        let restored: TriviaGame = serde_json::from_value(game.save()).unwrap();
+        assert_eq!(restored.status(), game.status());
+        assert_eq!(restored.turn_limit(), Some(Duration::from_secs(15)));
+    }
+}
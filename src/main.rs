@@ -2,21 +2,65 @@ mod message;
 mod database;
 mod commands;
 mod games;
+mod settings;
+mod quota;
+mod cli;
+mod backup;
+mod scheduler;
+mod crypto;
+mod roles;
+mod dice;
+mod achievements;
+mod tournament;
 
 use irc::client::prelude::*;
 use std::default::Default;
 use futures::TryStreamExt;
 use message::ParsedMessage;
 use database::Database;
-use commands::handle_command;
+use commands::{handle_chat, handle_command};
 use games::GameManager;
+use achievements::EventBus;
+use games::hangman::{self, Difficulty};
+use settings::Settings;
+use crypto::Cipher;
+use scheduler::Scheduler;
+use std::collections::HashMap;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> irc::error::Result<()> {
-    let db = Database::new("bot_data.db")
+    let mut db = Database::new("bot_data.db")
         .expect("Failed to initialize database");
 
-    let games = GameManager::new();
+    let settings = Settings::default();
+    db.seed_roles(&settings.roles).expect("Failed to set up roles");
+    let words: Vec<(&str, &str)> = hangman::WORDS.iter().map(|w| (*w, Difficulty::of(w).as_str())).collect();
+    db.seed_hangman_words(hangman::DEFAULT_CATEGORY, &words).expect("Failed to set up hangman words");
+
+    if let Some(key) = settings.data_key() {
+        let cipher = Cipher::from_hex(&key).expect("Invalid data encryption key");
+        db.set_cipher(Some(cipher));
+    }
+
+    // Any arguments select an offline maintenance command instead of running the bot
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = cli::run(&mut db, &settings, &args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let (events, mut published) = EventBus::new();
+    let games = GameManager::new(settings.exclusive_games).with_events(events.clone());
+    // Games saved before a restart are announced once the bot is back in their channel
+    let mut resumed: HashMap<String, Vec<(&str, String)>> = HashMap::new();
+    for (channel, kind, players) in games.restore(&db).await {
+        resumed.entry(channel).or_default().push((kind, players));
+    }
+    let mut scheduler = Scheduler::new(&settings);
 
     let config = Config {
         nickname: Some("rusty".to_string()),
@@ -31,20 +75,44 @@ async fn main() -> irc::error::Result<()> {
     client.identify()?;
 
     let mut stream = client.stream()?;
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
 
-    while let Some(message) = stream.try_next().await? {
-        println!("{}", message);
+    loop {
+        tokio::select! {
+            message = stream.try_next() => {
+                let Some(message) = message? else { break };
+                println!("{}", message);
 
-        match message.command {
-            Command::PRIVMSG(ref channel, ref text) => {
-                let author = message.source_nickname().map(|s| s.to_string());
-                let parsed = ParsedMessage::parse(&text, author.clone(), channel.clone());
+                match message.command {
+                    Command::PRIVMSG(_, ref text) => {
+                        let author = message.source_nickname().map(|s| s.to_string());
+                        // Reply to the sender directly for private messages, otherwise to the channel
+                        let channel = message.response_target().unwrap_or_default().to_string();
+                        let hostmask = match &message.prefix {
+                            Some(Prefix::Nickname(nick, user, host)) => Some(format!("{}!{}@{}", nick, user, host)),
+                            _ => None,
+                        };
+                        let parsed = ParsedMessage::parse(text, author.clone(), channel).with_hostmask(hostmask);
 
-                if parsed.is_command() {
-                    handle_command(&client, &parsed, &db, &games).await?;
+                        if parsed.is_command() {
+                            handle_command(&client, &parsed, &db, &games, &events, &settings).await?;
+                        } else {
+                            handle_chat(&client, &parsed, text, &db, &games).await?;
+                        }
+                    }
+                    Command::JOIN(ref channel, _, _) if message.source_nickname() == Some(client.current_nickname()) => {
+                        for (kind, players) in resumed.remove(channel).unwrap_or_default() {
+                            client.send_privmsg(channel, format!("▶️ Resuming {} after a restart", kind))?;
+                            for line in games.status_of(&(channel.clone(), kind, players)).await.unwrap_or_default() {
+                                client.send_privmsg(channel, &line)?;
+                            }
+                        }
+                    }
+                    _ => {}
                 }
             }
-            _ => {}
+            Some(event) = published.recv() => achievements::announce(&client, &db, event)?,
+            _ = ticker.tick() => scheduler.tick(&client, &db, &games, &settings).await?,
         }
     }
 
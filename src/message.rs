@@ -4,6 +4,8 @@ pub struct ParsedMessage {
     pub args: Vec<String>,
     pub author: Option<String>,
     pub channel: String,
+    /// The sender's full `nick!user@host`, when the server provided one.
+    pub hostmask: Option<String>,
 }
 
 impl ParsedMessage {
@@ -23,9 +25,15 @@ impl ParsedMessage {
             args,
             author,
             channel,
+            hostmask: None,
         }
     }
 
+    pub fn with_hostmask(mut self, hostmask: Option<String>) -> Self {
+        self.hostmask = hostmask;
+        self
+    }
+
     pub fn is_command(&self) -> bool {
         !self.command.is_empty()
     }
@@ -109,4 +117,13 @@ mod tests {
         let msg = ParsedMessage::parse("!ping", None, "#ch".to_string());
         assert_eq!(msg.author, None);
     }
+
+    #[test]
+    fn test_with_hostmask() {
+        let msg = ParsedMessage::parse("!ping", Some("nick".to_string()), "#ch".to_string());
+        assert_eq!(msg.hostmask, None);
+
+        let msg = msg.with_hostmask(Some("nick!user@host".to_string()));
+        assert_eq!(msg.hostmask, Some("nick!user@host".to_string()));
+    }
 }
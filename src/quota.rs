@@ -0,0 +1,158 @@
+use std::fmt;
+use crate::settings::QuotaTier;
+
+/// Current storage usage for one user.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Usage {
+    pub keys: usize,
+    pub bytes: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QuotaError {
+    KeyTooLong { max: usize },
+    ValueTooLong { max: usize },
+    TooManyKeys { max: usize },
+    StorageFull { used: usize, needed: usize, max: usize },
+}
+
+impl fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaError::KeyTooLong { max } => {
+                write!(f, "Key is too long (max {} characters)", max)
+            }
+            QuotaError::ValueTooLong { max } => {
+                write!(f, "Value is too long (max {} characters)", max)
+            }
+            QuotaError::TooManyKeys { max } => {
+                write!(f, "Key limit reached ({} keys). Delete some with !del first", max)
+            }
+            QuotaError::StorageFull { used, needed, max } => {
+                write!(f, "Storage quota exceeded: {} of {} bytes used, this needs {} more", used, max, needed)
+            }
+        }
+    }
+}
+
+/// Picks the quota tier for a permission level: the one with the highest
+/// `min_level` that the level reaches. Levels below every tier get the lowest.
+pub fn tier_for(tiers: &[QuotaTier], level: i32) -> Option<&QuotaTier> {
+    tiers
+        .iter()
+        .filter(|tier| tier.min_level <= level)
+        .max_by_key(|tier| tier.min_level)
+        .or_else(|| tiers.iter().min_by_key(|tier| tier.min_level))
+}
+
+/// Bytes a single entry counts against the storage quota.
+pub fn entry_size(key: &str, value: &str) -> usize {
+    key.len() + value.len()
+}
+
+/// Checks whether storing `key = value` fits in the tier. `existing` is the
+/// value currently stored under `key`, if any, since replacing it frees its space.
+pub fn check_set(
+    tier: &QuotaTier,
+    usage: Usage,
+    key: &str,
+    value: &str,
+    existing: Option<&str>,
+) -> Result<(), QuotaError> {
+    if key.chars().count() > tier.max_key_len {
+        return Err(QuotaError::KeyTooLong { max: tier.max_key_len });
+    }
+    if value.chars().count() > tier.max_value_len {
+        return Err(QuotaError::ValueTooLong { max: tier.max_value_len });
+    }
+    if existing.is_none() && usage.keys >= tier.max_keys {
+        return Err(QuotaError::TooManyKeys { max: tier.max_keys });
+    }
+
+    let freed = existing.map(|old| entry_size(key, old)).unwrap_or(0);
+    let new_total = usage.bytes.saturating_sub(freed) + entry_size(key, value);
+    if new_total > tier.max_total_bytes {
+        return Err(QuotaError::StorageFull {
+            used: usage.bytes,
+            needed: new_total - usage.bytes.min(new_total),
+            max: tier.max_total_bytes,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier() -> QuotaTier {
+        QuotaTier {
+            min_level: 0,
+            max_keys: 2,
+            max_key_len: 5,
+            max_value_len: 10,
+            max_total_bytes: 30,
+        }
+    }
+
+    #[test]
+    fn test_check_set_within_limits() {
+        assert_eq!(check_set(&tier(), Usage::default(), "key", "value", None), Ok(()));
+    }
+
+    #[test]
+    fn test_check_set_key_too_long() {
+        let result = check_set(&tier(), Usage::default(), "toolong", "v", None);
+        assert_eq!(result, Err(QuotaError::KeyTooLong { max: 5 }));
+    }
+
+    #[test]
+    fn test_check_set_value_too_long() {
+        let result = check_set(&tier(), Usage::default(), "k", "01234567890", None);
+        assert_eq!(result, Err(QuotaError::ValueTooLong { max: 10 }));
+    }
+
+    #[test]
+    fn test_check_set_counts_characters_not_bytes() {
+        // Ten accented characters are twenty bytes but still fit the length limit
+        let result = check_set(&tier(), Usage::default(), "k", "éééééééééé", None);
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_check_set_too_many_keys() {
+        let usage = Usage { keys: 2, bytes: 4 };
+        let result = check_set(&tier(), usage, "new", "v", None);
+        assert_eq!(result, Err(QuotaError::TooManyKeys { max: 2 }));
+    }
+
+    #[test]
+    fn test_check_set_replace_at_key_limit() {
+        let usage = Usage { keys: 2, bytes: 4 };
+        assert_eq!(check_set(&tier(), usage, "a", "new", Some("o")), Ok(()));
+    }
+
+    #[test]
+    fn test_check_set_storage_full() {
+        let usage = Usage { keys: 1, bytes: 25 };
+        let result = check_set(&tier(), usage, "k", "123456", None);
+        assert_eq!(result, Err(QuotaError::StorageFull { used: 25, needed: 7, max: 30 }));
+    }
+
+    #[test]
+    fn test_check_set_replace_frees_space() {
+        let usage = Usage { keys: 1, bytes: 28 };
+        assert_eq!(check_set(&tier(), usage, "k", "short", Some("a much longer value")), Ok(()));
+    }
+
+    #[test]
+    fn test_tier_for_level() {
+        let tiers = crate::settings::Settings::default().quotas;
+        assert_eq!(tier_for(&tiers, 0).unwrap().min_level, 0);
+        assert_eq!(tier_for(&tiers, 5).unwrap().min_level, 1);
+        assert_eq!(tier_for(&tiers, 10).unwrap().min_level, 10);
+        assert_eq!(tier_for(&tiers, 50).unwrap().min_level, 10);
+        assert_eq!(tier_for(&tiers, -1).unwrap().min_level, 0);
+        assert!(tier_for(&[], 5).is_none());
+    }
+}
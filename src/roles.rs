@@ -0,0 +1,241 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use crate::settings::Role;
+
+/// Join and part channels with `!join` / `!leave`.
+pub const CHANNEL_JOIN: &str = "channel.join";
+/// Search every user's values with `!find --all`.
+pub const DATA_SEARCH_ALL: &str = "data.search_all";
+/// Request another user's `!mydata` report.
+pub const DATA_INSPECT: &str = "data.inspect";
+/// Write full exports with `!export all`.
+pub const DATA_EXPORT_ALL: &str = "data.export_all";
+/// Load exports with `!import`.
+pub const DATA_IMPORT: &str = "data.import";
+/// Delete another user's data with `!forget`.
+pub const DATA_FORGET: &str = "data.forget";
+/// List grants with `!perms`.
+pub const PERMS_VIEW: &str = "perms.view";
+/// Change grants with `!grant` / `!revoke`.
+pub const PERMS_GRANT: &str = "perms.grant";
+/// Take and inspect snapshots with `!backup`.
+pub const BACKUP_MANAGE: &str = "backup.manage";
+/// Read the audit log with `!audit`.
+pub const AUDIT_VIEW: &str = "audit.view";
+/// Edit the hangman word lists with `!hangman words`, run tournaments with
+/// `!tournament create|start|cancel`, and end any card game with `quit`.
+pub const GAMES_MANAGE: &str = "games.manage";
+/// Change role capabilities with `!roles`, and `!import` files with grants or roles.
+pub const ROLES_MANAGE: &str = "roles.manage";
+
+/// Every capability a role can hold.
+pub const CAPABILITIES: &[&str] = &[
+    CHANNEL_JOIN,
+    DATA_SEARCH_ALL,
+    DATA_INSPECT,
+    DATA_EXPORT_ALL,
+    DATA_IMPORT,
+    DATA_FORGET,
+    PERMS_VIEW,
+    PERMS_GRANT,
+    BACKUP_MANAGE,
+    AUDIT_VIEW,
+    GAMES_MANAGE,
+    ROLES_MANAGE,
+];
+
+/// Capabilities added to the default roles after roles themselves. Databases
+/// seeded before `seeded_capabilities` existed had every other default, and
+/// are given these once; see `Database::seed_roles`.
+pub const ADDED_SINCE_ROLES: &[&str] = &[GAMES_MANAGE];
+
+/// The role a permission level maps to: the highest role whose level is not
+/// above it. Levels below every role have no role.
+pub fn role_for_level(roles: &[Role], level: i32) -> Option<&Role> {
+    roles.iter()
+        .filter(|role| role.level <= level)
+        .max_by_key(|role| role.level)
+}
+
+/// Looks up a role by name, ignoring case.
+pub fn find_role<'a>(roles: &'a [Role], name: &str) -> Option<&'a Role> {
+    roles.iter().find(|role| role.name.eq_ignore_ascii_case(name))
+}
+
+/// Whether a user at `level` holds `capability`. Roles inherit the
+/// capabilities of every role below them.
+pub fn level_has_capability(roles: &[Role], level: i32, capability: &str) -> bool {
+    roles.iter()
+        .filter(|role| role.level <= level)
+        .any(|role| role.capabilities.iter().any(|c| c == capability))
+}
+
+/// Why a grant, revoke or forget was refused by [`check_grant`].
+#[derive(Debug, PartialEq)]
+pub enum GrantError {
+    OutOfRange { min: i32, max: i32 },
+    AboveOwnLevel { own: i32 },
+    TargetNotBelow,
+    LastOwner,
+}
+
+impl fmt::Display for GrantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrantError::OutOfRange { min, max } => {
+                write!(f, "Level must be between {} and {}", min, max)
+            }
+            GrantError::AboveOwnLevel { own } => {
+                write!(f, "You can't grant above your own level ({})", own)
+            }
+            GrantError::TargetNotBelow => {
+                write!(f, "You can only change the permissions of users below your own level")
+            }
+            GrantError::LastOwner => {
+                write!(f, "That would remove the last owner")
+            }
+        }
+    }
+}
+
+/// The rules for changing someone's grant, shared by `!grant`, `!revoke` and
+/// `!forget`. `new_level` is `None` when the grant is removed; `owners` counts
+/// current grants at the highest role.
+///
+/// - requested levels must fall within `range`
+/// - nobody can grant above their own level
+/// - nobody can change a user at or above their own level, including themselves
+/// - the last owner can't be removed or demoted
+pub fn check_grant(
+    roles: &[Role],
+    range: &RangeInclusive<i32>,
+    actor_level: i32,
+    target_level: i32,
+    new_level: Option<i32>,
+    owners: usize,
+) -> Result<(), GrantError> {
+    if let Some(level) = new_level {
+        if !range.contains(&level) {
+            return Err(GrantError::OutOfRange { min: *range.start(), max: *range.end() });
+        }
+        if level > actor_level {
+            return Err(GrantError::AboveOwnLevel { own: actor_level });
+        }
+    }
+
+    check_owner_remains(roles, target_level, new_level, owners)?;
+
+    if target_level >= actor_level {
+        return Err(GrantError::TargetNotBelow);
+    }
+    Ok(())
+}
+
+/// The last-owner rule on its own, for users removing their own grant with
+/// `!forgetme`, which the other rules would always refuse.
+pub fn check_owner_remains(roles: &[Role], target_level: i32, new_level: Option<i32>, owners: usize) -> Result<(), GrantError> {
+    if let Some(owner_level) = roles.iter().map(|role| role.level).max() {
+        let is_owner = target_level >= owner_level;
+        let stays_owner = new_level.is_some_and(|level| level >= owner_level);
+        if is_owner && !stays_owner && owners <= 1 {
+            return Err(GrantError::LastOwner);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    fn roles() -> Vec<Role> {
+        Settings::default().roles
+    }
+
+    #[test]
+    fn test_default_capabilities_are_known() {
+        for role in roles() {
+            for capability in &role.capabilities {
+                assert!(CAPABILITIES.contains(&capability.as_str()), "unknown capability {}", capability);
+            }
+        }
+    }
+
+    #[test]
+    fn test_role_for_level() {
+        let roles = roles();
+        assert_eq!(role_for_level(&roles, 0), None);
+        assert_eq!(role_for_level(&roles, 1).unwrap().name, "user");
+        assert_eq!(role_for_level(&roles, 7).unwrap().name, "moderator");
+        assert_eq!(role_for_level(&roles, 10).unwrap().name, "admin");
+        assert_eq!(role_for_level(&roles, 99).unwrap().name, "owner");
+    }
+
+    #[test]
+    fn test_find_role() {
+        let roles = roles();
+        assert_eq!(find_role(&roles, "Admin").unwrap().level, 10);
+        assert!(find_role(&roles, "wizard").is_none());
+    }
+
+    #[test]
+    fn test_capabilities_are_inherited() {
+        let roles = roles();
+        assert!(!level_has_capability(&roles, 0, CHANNEL_JOIN));
+        assert!(level_has_capability(&roles, 1, CHANNEL_JOIN));
+        assert!(level_has_capability(&roles, 10, CHANNEL_JOIN));
+        assert!(!level_has_capability(&roles, 9, PERMS_GRANT));
+        assert!(level_has_capability(&roles, 10, PERMS_GRANT));
+        assert!(!level_has_capability(&roles, 10, ROLES_MANAGE));
+        assert!(level_has_capability(&roles, 15, ROLES_MANAGE));
+    }
+
+    fn check(actor_level: i32, target_level: i32, new_level: Option<i32>, owners: usize) -> Result<(), GrantError> {
+        check_grant(&roles(), &(1..=15), actor_level, target_level, new_level, owners)
+    }
+
+    #[test]
+    fn test_check_grant_allows_below_own_level() {
+        assert_eq!(check(10, 0, Some(5), 1), Ok(()));
+        assert_eq!(check(10, 5, Some(10), 1), Ok(()));
+        assert_eq!(check(10, 5, None, 1), Ok(()));
+        assert_eq!(check(15, 10, None, 1), Ok(()));
+    }
+
+    #[test]
+    fn test_check_grant_range() {
+        assert_eq!(check(15, 0, Some(0), 1), Err(GrantError::OutOfRange { min: 1, max: 15 }));
+        assert_eq!(check(15, 0, Some(-3), 1), Err(GrantError::OutOfRange { min: 1, max: 15 }));
+        assert_eq!(check(15, 0, Some(16), 1), Err(GrantError::OutOfRange { min: 1, max: 15 }));
+    }
+
+    #[test]
+    fn test_check_grant_above_own_level() {
+        assert_eq!(check(10, 0, Some(15), 1), Err(GrantError::AboveOwnLevel { own: 10 }));
+        assert_eq!(check(10, 0, Some(11), 1), Err(GrantError::AboveOwnLevel { own: 10 }));
+    }
+
+    #[test]
+    fn test_check_grant_target_not_below() {
+        assert_eq!(check(10, 10, None, 1), Err(GrantError::TargetNotBelow));
+        assert_eq!(check(10, 10, Some(5), 1), Err(GrantError::TargetNotBelow));
+        assert_eq!(check(10, 15, Some(5), 2), Err(GrantError::TargetNotBelow));
+        assert_eq!(check(15, 15, None, 2), Err(GrantError::TargetNotBelow));
+    }
+
+    #[test]
+    fn test_check_grant_last_owner() {
+        assert_eq!(check(15, 15, None, 1), Err(GrantError::LastOwner));
+        assert_eq!(check(15, 15, Some(10), 1), Err(GrantError::LastOwner));
+        assert_eq!(check(15, 15, Some(15), 1), Err(GrantError::TargetNotBelow));
+    }
+
+    #[test]
+    fn test_owner_remains_when_leaving() {
+        assert_eq!(check_owner_remains(&roles(), 15, None, 1), Err(GrantError::LastOwner));
+        assert_eq!(check_owner_remains(&roles(), 15, None, 2), Ok(()));
+        assert_eq!(check_owner_remains(&roles(), 10, None, 1), Ok(()));
+        assert_eq!(check_owner_remains(&roles(), 0, None, 0), Ok(()));
+    }
+}
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use irc::client::prelude::Client;
+use crate::backup;
+use crate::database::Database;
+use crate::games::GameManager;
+use crate::settings::Settings;
+use crate::tournament;
+
+/// Runs periodic jobs. The main loop calls [`Scheduler::tick`] about once a second.
+pub struct Scheduler {
+    next_backup: Option<DateTime<Utc>>,
+}
+
+impl Scheduler {
+    pub fn new(settings: &Settings) -> Self {
+        // Continue the schedule from the newest snapshot so restarts don't skip or repeat backups
+        let next_backup = settings.backup.interval.map(|interval| {
+            let newest = backup::list_snapshots(&settings.backup)
+                .ok()
+                .and_then(|snapshots| snapshots.first().map(|s| s.taken_at));
+            match newest {
+                Some(taken_at) => taken_at + interval,
+                None => Utc::now(),
+            }
+        });
+
+        Scheduler { next_backup }
+    }
+
+    pub async fn tick(&mut self, client: &Client, db: &Database, games: &GameManager, settings: &Settings) -> irc::error::Result<()> {
+        let now = Utc::now();
+
+        let mut game_lines = games.expire_turns(db).await;
+        if let Some(idle) = settings.game_idle_timeout {
+            if let Some(lead) = settings.game_idle_warning {
+                game_lines.extend(games.warn_idle(idle, lead).await);
+            }
+            game_lines.extend(games.expire_idle(db, idle).await);
+        }
+        for (channel, lines) in game_lines {
+            for line in lines {
+                client.send_privmsg(&channel, &line)?;
+            }
+        }
+        tournament::tick(client, db, games, settings, now.timestamp()).await?;
+
+        match db.expire_permissions(now.timestamp()) {
+            Ok(expired) => {
+                for (username, level) in expired {
+                    notify_expired(client, db, settings, &username, level)?;
+                }
+            }
+            Err(e) => eprintln!("Failed to expire permissions: {}", e),
+        }
+
+        if let (Some(due), Some(interval)) = (self.next_backup, settings.backup.interval) {
+            if now >= due {
+                match backup::take_snapshot(db, &settings.backup) {
+                    Ok((snapshot, pruned)) => {
+                        println!("Scheduled backup written to {} ({} old snapshots pruned)", snapshot.name, pruned.len());
+                    }
+                    Err(e) => eprintln!("Scheduled backup failed: {}", e),
+                }
+                self.next_backup = Some(now + interval);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tells a user their temporary grant lapsed and records it in the audit log.
+fn notify_expired(client: &Client, db: &Database, settings: &Settings, username: &str, level: i32) -> irc::error::Result<()> {
+    let arguments = format!("{} {}", username, level);
+    if let Err(e) = db.record_audit("scheduler", None, "expire", &arguments, "-", "expired") {
+        eprintln!("Failed to write audit log: {}", e);
+    }
+
+    client.send_notice(username, format!("Your temporary permission level {} has expired", level))?;
+    if let Some(log_channel) = &settings.audit_channel {
+        client.send_privmsg(log_channel, format!("[audit] temporary grant of level {} to {} expired", level, username))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+use crate::crypto::KEY_ENV_VAR;
+use crate::roles;
+
+/// Tunable bot behaviour. Edit the defaults below, or override individual
+/// fields in `main.rs`, to fit your network.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Storage quotas by permission level. The tier with the highest
+    /// `min_level` not above the user's level applies.
+    pub quotas: Vec<QuotaTier>,
+    /// Directory that `!export all` writes to and `!import` reads from.
+    pub export_dir: String,
+    pub backup: BackupSettings,
+    /// Hex key (64 characters) for encrypting stored values. Falls back to the
+    /// `RUSTY_DATA_KEY` environment variable; with neither, values are stored in plaintext.
+    pub encryption_key: Option<String>,
+    /// Channel that receives a copy of every audit log entry, if any.
+    pub audit_channel: Option<String>,
+    /// Roles seeded into a new database. Once stored, roles are read from the
+    /// database and their capabilities are changed with `!roles`.
+    pub roles: Vec<Role>,
+    /// Allow only one game per channel. When false, a channel can run one game of each kind.
+    pub exclusive_games: bool,
+    /// Directory holding trivia question packs (`.json` and `.csv` files).
+    pub trivia_dir: String,
+    /// Time between trivia hints; the answer is revealed after the third.
+    pub trivia_hint_interval: Duration,
+    /// Time between scramble hints; the word is revealed after the second.
+    pub scramble_hint_interval: Duration,
+    /// Wrong guesses charged for a failed `!hangman solve`.
+    pub hangman_solve_penalty: u32,
+    /// End games with no moves for this long. `None` keeps them until someone quits.
+    pub game_idle_timeout: Option<Duration>,
+    /// Warn a channel this long before its idle game is ended. `None` disables the warning.
+    pub game_idle_warning: Option<Duration>,
+    /// Time per guess in `!hangman start speed` games, unless another is given.
+    pub hangman_speed_limit: Duration,
+    /// Chips paid by `!daily`, once per UTC day.
+    pub daily_chips: i64,
+    /// How long a wallet must have been open before it can `!give` chips, so
+    /// the `!daily` chips of freshly made nicks can't be passed straight on.
+    pub give_min_wallet_age: Duration,
+    /// Levels `!grant` accepts. Numeric levels are rounded down to a role after this check.
+    pub grant_levels: RangeInclusive<i32>,
+}
+
+impl Settings {
+    /// The configured data encryption key, if any.
+    pub fn data_key(&self) -> Option<String> {
+        self.encryption_key
+            .clone()
+            .or_else(|| std::env::var(KEY_ENV_VAR).ok())
+            .filter(|key| !key.trim().is_empty())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupSettings {
+    /// Directory snapshots are written to.
+    pub dir: String,
+    /// How often to take a snapshot automatically. `None` disables scheduled backups.
+    pub interval: Option<Duration>,
+    /// Keep the newest snapshot of each of this many recent days...
+    pub keep_daily: usize,
+    /// ...and of each of this many recent weeks.
+    pub keep_weekly: usize,
+}
+
+/// A named permission level and the capabilities it adds to the roles below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Role {
+    pub name: String,
+    pub level: i32,
+    pub capabilities: Vec<String>,
+}
+
+impl Role {
+    fn new(name: &str, level: i32, capabilities: &[&str]) -> Self {
+        Role {
+            name: name.to_string(),
+            level,
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaTier {
+    pub min_level: i32,
+    pub max_keys: usize,
+    pub max_key_len: usize,
+    pub max_value_len: usize,
+    pub max_total_bytes: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            quotas: vec![
+                QuotaTier {
+                    min_level: 0,
+                    max_keys: 20,
+                    max_key_len: 32,
+                    max_value_len: 200,
+                    max_total_bytes: 4 * 1024,
+                },
+                QuotaTier {
+                    min_level: 1,
+                    max_keys: 100,
+                    max_key_len: 64,
+                    max_value_len: 400,
+                    max_total_bytes: 32 * 1024,
+                },
+                QuotaTier {
+                    min_level: 10,
+                    max_keys: 1000,
+                    max_key_len: 64,
+                    max_value_len: 400,
+                    max_total_bytes: 256 * 1024,
+                },
+            ],
+            export_dir: "exports".to_string(),
+            backup: BackupSettings {
+                dir: "backups".to_string(),
+                interval: Some(Duration::from_secs(24 * 60 * 60)),
+                keep_daily: 7,
+                keep_weekly: 4,
+            },
+            encryption_key: None,
+            audit_channel: None,
+            roles: vec![
+                Role::new("user", 1, &[roles::CHANNEL_JOIN]),
+                Role::new("trusted", 3, &[]),
+                Role::new("moderator", 5, &[roles::PERMS_VIEW, roles::AUDIT_VIEW]),
+                Role::new("admin", 10, &[
+                    roles::PERMS_GRANT,
+                    roles::DATA_SEARCH_ALL,
+                    roles::DATA_INSPECT,
+                    roles::DATA_EXPORT_ALL,
+                    roles::DATA_IMPORT,
+                    roles::DATA_FORGET,
+                    roles::BACKUP_MANAGE,
+                    roles::GAMES_MANAGE,
+                ]),
+                Role::new("owner", 15, &[roles::ROLES_MANAGE]),
+            ],
+            grant_levels: 1..=15,
+            exclusive_games: true,
+            game_idle_timeout: Some(Duration::from_secs(30 * 60)),
+            game_idle_warning: Some(Duration::from_secs(60)),
+            hangman_speed_limit: Duration::from_secs(30),
+            hangman_solve_penalty: 2,
+            trivia_dir: "trivia".to_string(),
+            trivia_hint_interval: crate::games::trivia::DEFAULT_HINT_INTERVAL,
+            scramble_hint_interval: crate::games::scramble::DEFAULT_HINT_INTERVAL,
+            daily_chips: 100,
+            give_min_wallet_age: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use irc::client::prelude::Client;
+use crate::commands::utils::parse_duration;
+use crate::database::{Database, Tournament, TournamentRound, TournamentStatus};
+use crate::games::leaderboard::ranked;
+use crate::games::{self, GameManager};
+use crate::settings::Settings;
+
+/// Games a tournament can be played in: ones that record points per player.
+pub const KINDS: &[&str] = &["hangman", "trivia", "scramble"];
+pub const MAX_ROUNDS: u32 = 20;
+/// Seconds between the end of one round and the start of the next.
+const ROUND_BREAK: i64 = 15;
+/// Seconds to wait before trying again when another game is in the way.
+const BLOCKED_RETRY: i64 = 60;
+
+/// The table a kind's finished games are recorded in, which the
+/// tournament's scores are added up from.
+pub fn results_table(kind: &str) -> Option<&'static str> {
+    match kind {
+        "hangman" => Some("hangman_results"),
+        "trivia" => Some("trivia_results"),
+        "scramble" => Some("scramble_results"),
+        _ => None,
+    }
+}
+
+/// Parses a start time: `now`, a delay such as `30m` or `2h`, a UTC time
+/// such as `20:00`, or a weekday and time such as `fri 20:00`. Times that
+/// have passed mean the next day, or the next week with a weekday.
+pub fn parse_when(args: &[String], now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match args {
+        [word] if word == "now" => Some(now),
+        [text] => match parse_duration(text) {
+            Some(delay) => Some(now + Duration::from_std(delay).ok()?),
+            None => next_at(now, None, text),
+        },
+        [day, time] => next_at(now, Some(day.parse::<Weekday>().ok()?), time),
+        _ => None,
+    }
+}
+
+fn next_at(now: DateTime<Utc>, weekday: Option<Weekday>, time: &str) -> Option<DateTime<Utc>> {
+    let time = NaiveTime::parse_from_str(time, "%H:%M").ok()?;
+    let mut at = now.date_naive().and_time(time).and_utc();
+    if let Some(weekday) = weekday {
+        let days = (7 + weekday.num_days_from_monday() - now.weekday().num_days_from_monday()) % 7;
+        at += Duration::days(days as i64);
+    }
+    if at <= now {
+        at += Duration::days(if weekday.is_some() { 7 } else { 1 });
+    }
+    Some(at)
+}
+
+/// "Final standings: 1. alice (30), 2. bob (12)".
+pub fn standings(label: &str, scores: &[(String, i64)]) -> String {
+    if scores.is_empty() {
+        return format!("{}: nobody signed up", label);
+    }
+    let entries: Vec<String> = ranked(scores).into_iter()
+        .map(|(rank, nick, points)| format!("{}. {} ({})", rank, nick, points))
+        .collect();
+    format!("{}: {}", label, entries.join(", "))
+}
+
+/// Starts due tournaments and rounds, and wraps up rounds that have ended.
+/// The scheduler calls this every tick.
+pub async fn tick(client: &Client, db: &Database, games: &GameManager, settings: &Settings, now: i64) -> irc::error::Result<()> {
+    let tournaments = match db.active_tournaments() {
+        Ok(tournaments) => tournaments,
+        Err(e) => {
+            eprintln!("Failed to load tournaments: {}", e);
+            return Ok(());
+        }
+    };
+    for tournament in tournaments {
+        match advance(db, games, settings, &tournament, now).await {
+            Ok(lines) => {
+                for line in lines {
+                    client.send_privmsg(&tournament.channel, &line)?;
+                }
+            }
+            Err(e) => eprintln!("Failed to run tournament {} in {}: {}", tournament.id, tournament.channel, e),
+        }
+    }
+    Ok(())
+}
+
+/// Moves one tournament on as far as `now` allows, returning what to announce.
+async fn advance(db: &Database, games: &GameManager, settings: &Settings, tournament: &Tournament, now: i64) -> Result<Vec<String>, rusqlite::Error> {
+    let (id, kind) = (tournament.id, tournament.kind.as_str());
+    match (tournament.status, tournament.next_round_at) {
+        (TournamentStatus::Scheduled, _) if now >= tournament.starts_at => {
+            let players = db.tournament_players(id)?;
+            if players.is_empty() {
+                db.cancel_tournament(id)?;
+                return Ok(vec![format!("🏁 The {} tournament is cancelled: nobody signed up", kind)]);
+            }
+            db.begin_tournament(id, now)?;
+            Ok(vec![format!(
+                "🏁 The {} tournament begins! {} rounds, {} players: {}",
+                kind, tournament.rounds, players.len(), players.join(", ")
+            )])
+        }
+        // A round is being played until its game is no longer running
+        (TournamentStatus::Running, None) if !games.running(&tournament.channel, kind).await => {
+            let scores = db.tournament_scores(tournament, results_table(kind).unwrap_or_default())?;
+            if tournament.round >= tournament.rounds {
+                db.finish_tournament(id, &scores)?;
+                let mut lines = vec![standings(&format!("🏆 The {} tournament is over! Final standings", kind), &scores)];
+                let winners: Vec<&str> = ranked(&scores).into_iter()
+                    .filter(|&(rank, _, points)| rank == 1 && points > 0)
+                    .map(|(_, nick, _)| nick)
+                    .collect();
+                if !winners.is_empty() {
+                    lines.push(format!("👑 Congratulations, {}!", winners.join(" and ")));
+                }
+                return Ok(lines);
+            }
+            db.schedule_round(id, now + ROUND_BREAK)?;
+            Ok(vec![
+                standings(&format!("📊 After round {}/{}", tournament.round, tournament.rounds), &scores),
+                format!("Round {} starts in {} seconds", tournament.round + 1, ROUND_BREAK),
+            ])
+        }
+        (TournamentStatus::Running, Some(due)) if now >= due => {
+            let round = tournament.round + 1;
+            // Each round is set up like the game's own start command with no options
+            let mut game = match games::new_game(db, settings, kind) {
+                Some(Ok(game)) => game,
+                Some(Err(e)) => {
+                    eprintln!("Failed to set up round {} of tournament {}: {}", round, id, e);
+                    db.schedule_round(id, now + BLOCKED_RETRY)?;
+                    return Ok(vec![format!("⏸️ Round {} couldn't be set up and will be tried again in {} seconds", round, BLOCKED_RETRY)]);
+                }
+                None => {
+                    db.cancel_tournament(id)?;
+                    return Ok(vec![format!("🏁 The tournament is cancelled: {} can't be played in tournaments", kind)]);
+                }
+            };
+            game.set_tournament_round(Some(TournamentRound { tournament: id, round }));
+            match games.start(db, &tournament.channel, game).await {
+                Ok(lines) => {
+                    db.start_round(id)?;
+                    let mut announcement = vec![format!("🎮 Round {}/{} of the {} tournament", round, tournament.rounds, kind)];
+                    announcement.extend(lines);
+                    Ok(announcement)
+                }
+                Err(blocking) => {
+                    db.schedule_round(id, now + BLOCKED_RETRY)?;
+                    Ok(vec![format!("⏸️ Round {} will start once the {} game ends", round, blocking)])
+                }
+            }
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn create_test_db() -> Database {
+        Database::new(":memory:").expect("Failed to create in-memory database")
+    }
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // November 2023: the 13th is a Monday and the 17th a Friday
+        Utc.with_ymd_and_hms(2023, 11, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_when() {
+        let now = at(15, 18, 30);
+        assert_eq!(parse_when(&args("now"), now), Some(now));
+        assert_eq!(parse_when(&args("30m"), now), Some(at(15, 19, 0)));
+        assert_eq!(parse_when(&args("20:00"), now), Some(at(15, 20, 0)));
+        assert_eq!(parse_when(&args("18:00"), now), Some(at(16, 18, 0)));
+        assert_eq!(parse_when(&args("fri 20:00"), now), Some(at(17, 20, 0)));
+        assert_eq!(parse_when(&args("Friday 20:00"), now), Some(at(17, 20, 0)));
+        assert_eq!(parse_when(&args("wed 19:00"), now), Some(at(15, 19, 0)));
+        assert_eq!(parse_when(&args("wed 18:00"), now), Some(at(22, 18, 0)));
+        assert_eq!(parse_when(&args("mon 09:15"), now), Some(at(20, 9, 15)));
+        assert_eq!(parse_when(&args("25:00"), now), None);
+        assert_eq!(parse_when(&args("someday 20:00"), now), None);
+        assert_eq!(parse_when(&args(""), now), None);
+    }
+
+    #[test]
+    fn test_standings() {
+        let scores = vec![("alice".to_string(), 30), ("bob".to_string(), 30), ("carol".to_string(), 4)];
+        assert_eq!(standings("Final", &scores), "Final: 1. alice (30), 1. bob (30), 3. carol (4)");
+        assert_eq!(standings("Final", &[]), "Final: nobody signed up");
+    }
+
+    #[tokio::test]
+    async fn test_nobody_signed_up() {
+        let db = create_test_db();
+        let games = GameManager::new(true);
+        let settings = Settings::default();
+        db.create_tournament("#games", "hangman", 3, 100).unwrap();
+        let tournament = db.active_tournament("#games").unwrap().unwrap();
+
+        assert!(advance(&db, &games, &settings, &tournament, 99).await.unwrap().is_empty());
+        let lines = advance(&db, &games, &settings, &tournament, 100).await.unwrap();
+        assert_eq!(lines, vec!["🏁 The hangman tournament is cancelled: nobody signed up"]);
+        assert!(db.active_tournament("#games").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rounds() {
+        let db = create_test_db();
+        let games = GameManager::new(true);
+        let settings = Settings::default();
+        let id = db.create_tournament("#games", "hangman", 2, 100).unwrap();
+        db.join_tournament(id, "alice").unwrap();
+        let current = || db.active_tournament("#games").unwrap().unwrap();
+
+        let lines = advance(&db, &games, &settings, &current(), 100).await.unwrap();
+        assert_eq!(lines, vec!["🏁 The hangman tournament begins! 2 rounds, 1 players: alice"]);
+
+        // Each round is a game in the channel, and the next waits for it to end
+        let lines = advance(&db, &games, &settings, &current(), 101).await.unwrap();
+        assert_eq!(lines[0], "🎮 Round 1/2 of the hangman tournament");
+        assert!(games.running("#games", "hangman").await);
+        assert!(advance(&db, &games, &settings, &current(), 500).await.unwrap().is_empty());
+
+        // Only game managers can end a round early
+        let refused = games.quit(&db, "#games", "hangman", "alice", false).await.unwrap();
+        assert_eq!(refused.lines, vec!["This hangman game is a tournament round. Only game managers can end it"]);
+        assert!(games.running("#games", "hangman").await);
+        games.quit(&db, "#games", "hangman", "mod", true).await;
+        let lines = advance(&db, &games, &settings, &current(), 510).await.unwrap();
+        assert_eq!(lines, vec!["📊 After round 1/2: 1. alice (0)", "Round 2 starts in 15 seconds"]);
+        assert!(advance(&db, &games, &settings, &current(), 524).await.unwrap().is_empty());
+
+        // A game that is already running holds the round back
+        games.start(&db, "#games", games::new_game(&db, &settings, "scramble").unwrap().unwrap()).await.unwrap();
+        let lines = advance(&db, &games, &settings, &current(), 525).await.unwrap();
+        assert_eq!(lines, vec!["⏸️ Round 2 will start once the scramble game ends"]);
+        games.quit(&db, "#games", "scramble", "alice", false).await;
+        assert!(advance(&db, &games, &settings, &current(), 526).await.unwrap().is_empty());
+        assert_eq!(advance(&db, &games, &settings, &current(), 585).await.unwrap()[0], "🎮 Round 2/2 of the hangman tournament");
+
+        games.quit(&db, "#games", "hangman", "mod", true).await;
+        let lines = advance(&db, &games, &settings, &current(), 600).await.unwrap();
+        assert_eq!(lines, vec!["🏆 The hangman tournament is over! Final standings: 1. alice (0)"]);
+        assert!(db.active_tournament("#games").unwrap().is_none());
+        assert_eq!(db.tournament_results(id).unwrap(), vec![("alice".to_string(), 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_round_is_an_ordinary_game() {
+        let db = create_test_db();
+        let games = GameManager::new(true);
+        let settings = Settings::default();
+        let id = db.create_tournament("#games", "hangman", 2, 100).unwrap();
+        db.join_tournament(id, "alice").unwrap();
+        let current = || db.active_tournament("#games").unwrap().unwrap();
+        advance(&db, &games, &settings, &current(), 100).await.unwrap();
+        advance(&db, &games, &settings, &current(), 101).await.unwrap();
+
+        db.cancel_tournament(id).unwrap();
+        games.release_tournament(&db, id).await;
+        let reply = games.quit(&db, "#games", "hangman", "alice", false).await.unwrap();
+        assert!(reply.finished);
+        assert!(!games.running("#games", "hangman").await);
+    }
+}